@@ -11,3 +11,8 @@ pub mod dependencies {
     pub use minister;
     pub use pulpit;
 }
+
+/// The [`Persist`](persist::Persist) trait that column types must implement to
+/// snapshot and restore a datastore (with the `Serialized` backend's
+/// `persist = on` option).
+pub use pulpit::persist;
@@ -49,9 +49,34 @@ valid_tests!(
     simple {
         no_errors,
         basic_join,
+        outer_join,
+        ordered_index,
+        lookup,
+        optimise,
+        access_layout,
+        concurrent,
+        persistence,
         limited_table,
         sums,
         counts,
-        deref_some
+        deref_some,
+        references,
+        composite_types,
+        bag_params,
+        attributes,
+        update_current,
+        upsert,
+        distinct,
+        window,
+        set_operations,
+        pagination,
+        bulk_mutations,
+        fragments,
+        migrations,
+        transactions
+    },
+    sql {
+        user_details,
+        queries
     }
 );
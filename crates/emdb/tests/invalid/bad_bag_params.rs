@@ -0,0 +1,41 @@
+use emdb::macros::emql;
+
+emql! {
+    impl my_db as Serialized;
+
+    table nums {
+        value: i32,
+    }
+
+    query evens() {
+        use nums
+            |> filter(*value % 2 == 0)
+            |> map(value: i32 = *value)
+            |> collect(values as type num_bag)
+            ~> return;
+    }
+
+    query odds() {
+        use nums
+            |> filter(*value % 2 == 1)
+            |> map(odd: i32 = *value)
+            |> collect(values as type num_bag)
+            ~> return;
+    }
+
+    // the two queries collect different records as `num_bag`
+    query sum_ambiguous(items: type num_bag) {
+        use items
+            |> count(num)
+            ~> return;
+    }
+
+    // no query collects a `missing_bag`
+    query sum_missing(items: type missing_bag) {
+        use items
+            |> count(num)
+            ~> return;
+    }
+}
+
+fn main() {}
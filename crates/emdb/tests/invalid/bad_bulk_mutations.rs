@@ -0,0 +1,20 @@
+use emdb::macros::emql;
+
+emql! {
+    table users {
+        name: String,
+        age: u8,
+    }
+
+    query missing_table() {
+        delete_where(people as removed where *age < 18)
+            ~> return;
+    }
+
+    query missing_column() {
+        update_where(users as updated where *age < 18 set score = 0)
+            ~> return;
+    }
+}
+
+fn main() {}
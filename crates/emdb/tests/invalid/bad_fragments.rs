@@ -0,0 +1,50 @@
+use emdb::macros::emql;
+
+emql! {
+    table customers {
+        age: u8,
+    }
+
+    fragment adults(min_age: u8) {
+        use customers |> filter(**age >= min_age)
+    }
+
+    fragment adults() {
+        use customers
+    }
+
+    fragment filter() {
+        use customers
+    }
+
+    fragment split() {
+        use customers |> let all;
+        use all
+    }
+
+    fragment ping() {
+        pong() |> count(num)
+    }
+
+    fragment pong() {
+        ping()
+    }
+
+    query misspelled(min_age: u8) {
+        adults() |> fitler(**age > 3) |> count(num) ~> return;
+    }
+
+    query with_arguments(min_age: u8) {
+        adults(min_age) |> count(num) ~> return;
+    }
+
+    query missing_param() {
+        adults() |> count(num) ~> return;
+    }
+
+    query recursive() {
+        ping() ~> return;
+    }
+}
+
+fn main() {}
@@ -0,0 +1,44 @@
+use emdb::macros::emql;
+
+mod v0 {
+    pub struct Datastore;
+}
+
+mod v1 {
+    pub struct Datastore;
+}
+
+emql! {
+    impl my_db as Serialized;
+    impl my_interface as Interface;
+
+    table people {
+        name: String,
+    }
+
+    table friends {
+        name: String,
+    }
+
+    migration from super::v1::Datastore to missing_db {}
+
+    migration from super::v1::Datastore to my_interface {}
+
+    migration from super::v1::Datastore to my_db {
+        table people {
+            nickname = None,
+            name = String::new(),
+            name = String::from("bob"),
+        }
+        table events empty;
+        table people empty;
+    }
+
+    migration from super::v1::Datastore to my_db {}
+
+    migration from super::v0::Datastore to my_db {
+        table friends from people {}
+    }
+}
+
+fn main() {}
@@ -0,0 +1,22 @@
+use emdb::macros::emql;
+
+emql! {
+    table purchases {
+        customer: String,
+        product: String,
+    }
+
+    query different_types() {
+        use purchases |> map(customer: String = customer.clone()) |> let customers;
+        use purchases |> map(product: String = product.clone()) |> let products;
+        intersect(use customers, products) |> count(num) ~> return;
+    }
+
+    query not_streams() {
+        use purchases |> map(customer: String = customer.clone()) |> let customers;
+        use purchases |> map(customer: String = customer.clone()) |> count(customer) ~> let total;
+        except(all use customers, total) |> count(num) ~> return;
+    }
+}
+
+fn main() {}
@@ -0,0 +1,25 @@
+use emdb::macros::emql;
+
+emql! {
+    table scores {
+        name: String,
+        score: i32,
+    }
+
+    query not_sorted(last: &str) {
+        use scores
+            |> map(name: String = name.clone())
+            |> skip(after last.to_owned())
+            |> count(num)
+            ~> return;
+    }
+
+    query missing_field(last: ref scores) {
+        ref scores as entry
+            |> skip(after ref missing = last)
+            |> count(num)
+            ~> return;
+    }
+}
+
+fn main() {}
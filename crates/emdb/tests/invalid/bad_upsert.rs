@@ -0,0 +1,40 @@
+use emdb::macros::emql;
+
+emql! {
+    impl my_db as Serialized;
+
+    table words {
+        word: String,
+        count: i64,
+    } @ [unique(word) as unique_words]
+
+    table tags {
+        tag: String,
+    } @ [unique(tag) as unique_tags]
+
+    query not_unique() {
+        row(word: String = String::new(), count: i64 = 1)
+            ~> upsert(words as ref entry on count set count = count)
+            ~> return;
+    }
+
+    query ref_is_column() {
+        row(word: String = String::new(), count: i64 = 1)
+            ~> upsert(words as ref count on word set count = count.count + 1)
+            ~> return;
+    }
+
+    query missing_column() {
+        row(word: String = String::new(), count: i64 = 1)
+            ~> upsert(words as ref entry on word set total = count)
+            ~> return;
+    }
+
+    query wrong_record() {
+        row(name: String = String::new())
+            ~> upsert(tags as ref entry on tag set tag = name)
+            ~> return;
+    }
+}
+
+fn main() {}
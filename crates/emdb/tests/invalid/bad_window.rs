@@ -0,0 +1,34 @@
+use emdb::macros::emql;
+
+emql! {
+    table readings {
+        sensor: String,
+        level: i32,
+    }
+
+    query unknown_function() {
+        use readings
+            |> window(order by level compute avg: i32 = running_avg(level))
+            ~> return;
+    }
+
+    query wrong_arguments() {
+        use readings
+            |> window(partition by sensor order by level compute pos: usize = rank(level), sum: i32 = running_sum())
+            ~> return;
+    }
+
+    query missing_fields() {
+        use readings
+            |> window(partition by place order by height compute pos: usize = row_number())
+            ~> return;
+    }
+
+    query existing_field() {
+        use readings
+            |> window(order by level compute level: usize = row_number())
+            ~> return;
+    }
+}
+
+fn main() {}
@@ -0,0 +1,19 @@
+use emdb::macros::emql;
+
+emql! {
+    impl my_interface as Interface;
+    impl my_db as Serialized {
+        interface = my_interface,
+        concurrent = on,
+    };
+
+    table values {
+        value: i32,
+    }
+
+    query add(value: i32) {
+        row(value: i32 = value) ~> insert(values as ref it);
+    }
+}
+
+fn main() {}
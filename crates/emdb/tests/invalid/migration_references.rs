@@ -0,0 +1,21 @@
+use emdb::macros::emql;
+
+mod v1 {
+    pub struct Datastore;
+}
+
+emql! {
+    impl my_db as Serialized;
+
+    table people {
+        name: String,
+    }
+
+    table friends {
+        person: ref people,
+    }
+
+    migration from super::v1::Datastore to my_db {}
+}
+
+fn main() {}
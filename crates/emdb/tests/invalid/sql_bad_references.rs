@@ -0,0 +1,18 @@
+use emdb::macros::sql;
+
+sql! {
+    impl my_db as Serialized;
+
+    CREATE TABLE users (
+        name TEXT,
+        credits INTEGER
+    );
+
+    PREPARE rich_users(threshold INTEGER) AS
+        SELECT name FROM users WHERE balance > :threshold;
+
+    PREPARE get_user(id INTEGER) AS
+        SELECT name FROM users WHERE ROWID = :id;
+}
+
+fn main() {}
@@ -0,0 +1,17 @@
+use emdb::macros::emql;
+
+emql! {
+    impl my_db as Serialized;
+
+    table people {
+        name: String,
+        employer: ref companies,
+        manager: ref people,
+    } @ [
+        valid_ref(name) as name_is_ref,
+        valid_ref(manager, set_none) as manager_exists,
+        valid_ref(mentor) as mentor_exists,
+    ]
+}
+
+fn main() {}
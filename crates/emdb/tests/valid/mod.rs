@@ -11,4 +11,5 @@ pub mod complex;
 pub mod context;
 pub mod extreme;
 pub mod simple;
+pub mod sql;
 pub mod fixme;
\ No newline at end of file
@@ -0,0 +1,83 @@
+use emdb::macros::emql;
+
+emql! {
+    impl my_db as Serialized{
+        table_select = Access,
+    };
+
+    table accounts {
+        owner: String,
+        bio: String,
+        balance: i64,
+        logins: u32,
+    } @ [unique(owner) as unique_owners]
+
+    query open(owner: String, bio: String) {
+        row(owner: String = owner, bio: String = bio, balance: i64 = 0, logins: u32 = 0)
+            ~> insert(accounts as ref account)
+            ~> return;
+    }
+
+    query deposit(owner: String, amount: i64) {
+        row(owner: String = owner)
+            ~> unique(owner for accounts.owner as ref account)
+            ~> deref(account as data)
+            ~> update(account use balance = data.balance + amount)
+            ~> map(balance: i64 = data.balance + amount)
+            ~> return;
+    }
+
+    query login(account: ref accounts) {
+        row(account: ref accounts = account)
+            ~> deref(account as data)
+            ~> update(account use logins = data.logins + 1);
+    }
+
+    query total() {
+        use accounts
+            |> fold(sum: i64 = 0 -> sum + balance)
+            ~> return;
+    }
+
+    query profile(account: ref accounts) {
+        row(account: ref accounts = account)
+            ~> deref(account as data)
+            ~> return;
+    }
+
+    query close(account: ref accounts) {
+        row(account: ref accounts = account)
+            ~> delete(account);
+    }
+}
+
+pub fn test() {
+    let mut ds = my_db::Datastore::new();
+    let mut db = ds.db();
+
+    let alice = db
+        .open(String::from("alice"), String::from("likes rust"))
+        .unwrap()
+        .account;
+    let bob = db
+        .open(String::from("bob"), String::from("likes c"))
+        .unwrap()
+        .account;
+
+    assert_eq!(db.deposit(String::from("alice"), 10).unwrap().balance, 10);
+    assert_eq!(db.deposit(String::from("bob"), 5).unwrap().balance, 5);
+    assert_eq!(db.deposit(String::from("alice"), 3).unwrap().balance, 13);
+    db.login(alice).unwrap();
+    db.login(alice).unwrap();
+    assert_eq!(db.total().sum, 18);
+
+    let profile = db.profile(alice).unwrap().data;
+    assert_eq!(profile.owner, "alice");
+    assert_eq!(profile.bio, "likes rust");
+    assert_eq!(profile.balance, 13);
+    assert_eq!(profile.logins, 2);
+
+    db.close(bob).unwrap();
+    assert_eq!(db.total().sum, 13);
+    assert!(db.profile(bob).is_err());
+}
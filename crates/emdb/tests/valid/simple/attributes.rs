@@ -0,0 +1,61 @@
+use emdb::macros::emql;
+use notes_interface::{Database, Datastore};
+
+emql! {
+    impl notes_interface as Interface;
+    impl my_db as Serialized;
+    impl notes_impl as Serialized {
+        interface = notes_interface,
+    };
+
+    /// Short notes, with a priority
+    table notes {
+        text: String,
+        priority: u8,
+    }
+
+    /// Add a new note
+    #[must_use]
+    query add_note(text: String, priority: u8) {
+        row(text: String = text, priority: u8 = priority)
+            ~> insert(notes as ref note)
+            ~> return;
+    }
+
+    /// The number of notes
+    #[deprecated = "use `count_urgent` instead"]
+    query count_notes() {
+        use notes |> count(num) ~> return;
+    }
+
+    /// The number of notes with a priority of at least 5
+    query count_urgent() {
+        use notes
+            |> filter(**priority >= 5)
+            |> count(num)
+            ~> return;
+    }
+
+    #[cfg(any())]
+    query never_generated() {
+        use notes |> count(num) ~> return;
+    }
+}
+
+pub fn test() {
+    let mut ds = my_db::Datastore::new();
+    let mut db = ds.db();
+
+    let _ = db.add_note(String::from("write docs"), 3);
+    let _ = db.add_note(String::from("fix bug"), 8);
+    assert_eq!(db.count_urgent().num, 1);
+
+    #[allow(deprecated)]
+    let num = db.count_notes().num;
+    assert_eq!(num, 2);
+
+    let mut ds = notes_impl::Datastore::new();
+    let mut db = ds.db();
+    let _ = db.add_note(String::from("from the interface"), 5);
+    let _ = db.count_urgent();
+}
@@ -0,0 +1,66 @@
+use emdb::macros::emql;
+
+emql! {
+    impl my_db as Serialized{
+        concurrent = on,
+    };
+
+    table staged {
+        name: String,
+        score: i64,
+    }
+
+    table scores {
+        name: String,
+        score: i64,
+    }
+
+    query stage(name: String, score: i64) {
+        row(name: String = name, score: i64 = score)
+            ~> insert(staged as ref entry)
+            ~> return;
+    }
+
+    query staged_batch() {
+        use staged
+            |> map(name: String = name.clone(), score: i64 = *score)
+            |> collect(entries as type batch)
+            ~> return;
+    }
+
+    query batch_total(items: type batch) {
+        use items
+            |> fold(total: i64 = 0 -> total + score)
+            ~> return;
+    }
+
+    query add_all(items: type batch) {
+        use items
+            |> insert(scores as ref added)
+            |> count(num)
+            ~> return;
+    }
+
+    query num_scores() {
+        use scores
+            |> count(num)
+            ~> return;
+    }
+}
+
+pub fn test() {
+    let mut ds = my_db::Datastore::new();
+    let db = ds.db();
+
+    db.stage(String::from("a"), 3);
+    db.stage(String::from("b"), 4);
+
+    let batch = db.staged_batch().entries;
+    assert_eq!(batch.len(), 2);
+    assert_eq!(db.batch_total(batch.clone()).total, 7);
+    assert_eq!(db.batch_total(Vec::new()).total, 0);
+
+    assert_eq!(db.add_all(batch.clone()).num, 2);
+    assert_eq!(db.add_all(batch).num, 2);
+    assert_eq!(db.num_scores().num, 4);
+}
@@ -0,0 +1,89 @@
+use emdb::macros::emql;
+
+emql! {
+    impl my_db as Serialized;
+
+    table users {
+        name: String,
+        age: u8,
+        score: i64,
+    }
+
+    table posts {
+        author: ref users,
+        text: String,
+    } @ [valid_ref(author, cascade) as post_author]
+
+    query add_user(name: &str, age: u8) {
+        row(name: String = name.to_owned(), age: u8 = age, score: i64 = 0)
+            ~> insert(users as ref user)
+            ~> return;
+    }
+
+    query add_post(author: ref users, text: &str) {
+        row(author: ref users = author, text: String = text.to_owned())
+            ~> insert(posts as ref post)
+            ~> return;
+    }
+
+    query reward(min_age: u8, bonus: i64) {
+        update_where(users as rewarded where *age >= min_age set score = score + bonus)
+            ~> return;
+    }
+
+    // the parameter `score` shadows the column
+    query set_scores(score: i64) {
+        update_where(users as updated where true set score = score.abs())
+            ~> return;
+    }
+
+    query remove_younger(min_age: u8) {
+        delete_where(users as removed where *age < min_age)
+            ~> return;
+    }
+
+    query clear_posts() {
+        delete_where(posts as removed where true)
+            ~> return;
+    }
+
+    query total_score() {
+        use users
+            |> fold(total: i64 = 0 -> total + score)
+            ~> return;
+    }
+
+    query post_count() {
+        use posts |> count(num) ~> return;
+    }
+}
+
+pub fn test() {
+    let mut ds = my_db::Datastore::new();
+    let mut db = ds.db();
+
+    let alice = db.add_user("alice", 30).user;
+    let bob = db.add_user("bob", 12).user;
+    db.add_user("carol", 15);
+    db.add_user("dave", 45);
+    db.add_post(alice, "hello").unwrap();
+    db.add_post(bob, "hi").unwrap();
+    db.add_post(bob, "bye").unwrap();
+
+    assert_eq!(db.reward(18, 10).unwrap().rewarded, 2);
+    assert_eq!(db.reward(40, 5).unwrap().rewarded, 1);
+    assert_eq!(db.reward(100, 5).unwrap().rewarded, 0);
+    assert_eq!(db.total_score().total, 25);
+
+    // deleting bob cascades to his posts
+    assert_eq!(db.remove_younger(18).unwrap().removed, 2);
+    assert_eq!(db.remove_younger(18).unwrap().removed, 0);
+    assert_eq!(db.post_count().num, 1);
+    assert_eq!(db.total_score().total, 25);
+
+    assert_eq!(db.clear_posts().removed, 1);
+    assert_eq!(db.post_count().num, 0);
+
+    assert_eq!(db.set_scores(-3).unwrap().updated, 2);
+    assert_eq!(db.total_score().total, 6);
+}
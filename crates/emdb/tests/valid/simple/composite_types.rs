@@ -0,0 +1,67 @@
+use emdb::macros::emql;
+
+emql! {
+    impl my_db as Serialized;
+
+    table tasks {
+        title: String,
+        done: bool,
+    }
+
+    query add_task(title: &str) {
+        row(title: String = String::from(title), done: bool = false)
+            ~> insert(tasks as ref task)
+            ~> return;
+    }
+
+    query complete(task: ref tasks) {
+        row(task: ref tasks = task)
+            ~> update(task use done = true);
+    }
+
+    query open_tasks() {
+        ref tasks as task
+            |> deref(task as data)
+            |> filter(!data.done)
+            |> map(task: ref tasks = task)
+            |> collect(open as type open_tasks)
+            ~> map(
+                keys: Vec<ref tasks> = open.iter().map(|t| t.task).collect(),
+                summary: (usize, type open_tasks) = (open.len(), open.clone()),
+            )
+            ~> return;
+    }
+
+    query first_of(candidates: Vec<ref tasks>, fallback: (ref tasks, bool)) {
+        row(
+            first: Option<ref tasks> = candidates.first().copied(),
+            fallback: (ref tasks, bool) = fallback,
+        )
+            ~> return;
+    }
+}
+
+pub fn test() {
+    let mut ds = my_db::Datastore::new();
+    let mut db = ds.db();
+
+    let write: my_db::tables::tasks::Key = db.add_task("write").task;
+    let test = db.add_task("test").task;
+    let ship = db.add_task("ship").task;
+
+    db.complete(test).unwrap();
+
+    let open = db.open_tasks().unwrap();
+    let keys: Vec<my_db::tables::tasks::Key> = open.keys;
+    assert_eq!(keys.len(), 2);
+    assert!(keys.contains(&write) && keys.contains(&ship));
+
+    let (num_open, open_tasks) = open.summary;
+    assert_eq!(num_open, 2);
+    assert!(open_tasks.iter().all(|t| keys.contains(&t.task)));
+
+    let first = db.first_of(keys.clone(), (test, true));
+    assert!(first.first.is_some_and(|k| keys.contains(&k)));
+    assert_eq!(first.fallback, (test, true));
+    assert!(db.first_of(Vec::new(), (test, false)).first.is_none());
+}
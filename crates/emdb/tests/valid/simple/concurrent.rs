@@ -0,0 +1,112 @@
+use emdb::macros::emql;
+use std::{error::Error, panic};
+
+emql! {
+    impl my_db as Serialized{
+        concurrent = on,
+    };
+
+    table counters {
+        name: String,
+        value: i64,
+    } @ [unique(name) as unique_names]
+
+    table events {
+        description: String,
+    }
+
+    query new_counter(name: String) {
+        row(name: String = name, value: i64 = 0)
+            ~> insert(counters as ref counter)
+            ~> return;
+    }
+
+    query increment(name: String) {
+        row(name: String = name)
+            ~> unique(name for counters.name as ref counter)
+            ~> deref(counter as data)
+            ~> update(counter use value = data.value + 1);
+    }
+
+    query value_of(name: String) {
+        row(name: String = name)
+            ~> unique(name for counters.name as ref counter)
+            ~> deref(counter as data)
+            ~> map(value: i64 = data.value)
+            ~> return;
+    }
+
+    query log(description: String) {
+        row(description: String = description)
+            ~> insert(events as ref event)
+            ~> return;
+    }
+
+    query forget(event: ref events) {
+        row(event: ref events = event)
+            ~> delete(event);
+    }
+
+    query num_events() {
+        use events
+            |> count(num)
+            ~> return;
+    }
+}
+
+pub fn test() {
+    let mut ds = my_db::Datastore::new();
+    let mut db = ds.db();
+
+    db.new_counter(String::from("a")).unwrap();
+    db.new_counter(String::from("b")).unwrap();
+
+    std::thread::scope(|s| {
+        for name in ["a", "b", "a", "b"] {
+            let db = &db;
+            s.spawn(move || {
+                for _ in 0..100 {
+                    db.increment(String::from(name)).unwrap();
+                    db.log(format!("incremented {name}"));
+                }
+            });
+        }
+        for _ in 0..2 {
+            let db = &db;
+            s.spawn(move || {
+                for _ in 0..100 {
+                    assert!(db.value_of(String::from("a")).unwrap().value <= 200);
+                    assert!(db.num_events().num <= 400);
+                }
+            });
+        }
+    });
+
+    assert_eq!(db.value_of(String::from("a")).unwrap().value, 200);
+    assert_eq!(db.value_of(String::from("b")).unwrap().value, 200);
+    assert_eq!(db.num_events().num, 400);
+
+    let event = db.log(String::from("to forget")).event;
+    assert_eq!(db.num_events().num, 401);
+    db.forget(event).unwrap();
+    assert_eq!(db.num_events().num, 400);
+
+    // transactions roll back the tables without their locks
+    assert!(db
+        .transaction(|tx| -> Result<(), Box<dyn Error>> {
+            tx.increment(String::from("a"))?;
+            tx.new_counter(String::from("b"))?;
+            Ok(())
+        })
+        .is_err());
+    assert_eq!(db.value_of(String::from("a")).unwrap().value, 200);
+
+    let unwound = panic::catch_unwind(panic::AssertUnwindSafe(|| {
+        db.transaction(|tx| -> Result<(), Box<dyn Error>> {
+            tx.increment(String::from("a"))?;
+            panic!("failed mid-transaction")
+        })
+    }));
+    assert!(unwound.is_err());
+    assert_eq!(db.value_of(String::from("a")).unwrap().value, 200);
+}
@@ -0,0 +1,55 @@
+use emdb::macros::emql;
+
+emql! {
+    impl my_db as Serialized;
+
+    table readings {
+        sensor: String,
+        level: i32,
+    }
+
+    query add(sensor: &str, level: i32) {
+        row(sensor: String = sensor.to_owned(), level: i32 = level)
+            ~> insert(readings as ref reading);
+    }
+
+    // compares the whole record
+    query sensors() {
+        use readings
+            |> map(sensor: String = sensor.clone())
+            |> distinct()
+            |> count(num)
+            ~> return;
+    }
+
+    // sorted by the distinct field first, so the highest level of each sensor is kept
+    query peaks() {
+        use readings
+            |> map(sensor: String = sensor.clone(), level: i32 = *level)
+            |> sort(sensor asc, level desc)
+            |> distinct(sensor)
+            |> fold(total: i32 = 0 -> total + level)
+            ~> return;
+    }
+
+    query unique_readings() {
+        use readings
+            |> distinct(sensor, level)
+            |> count(num)
+            ~> return;
+    }
+}
+
+pub fn test() {
+    let mut ds = my_db::Datastore::new();
+    let mut db = ds.db();
+
+    for (sensor, level) in [("b", 3), ("a", 1), ("b", 7), ("a", 1), ("c", 2), ("a", 4), ("b", 3)] {
+        db.add(sensor, level);
+    }
+
+    assert_eq!(db.sensors().num, 3);
+    assert_eq!(db.peaks().total, 4 + 7 + 2);
+
+    assert_eq!(db.unique_readings().num, 5);
+}
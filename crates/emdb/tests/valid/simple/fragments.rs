@@ -0,0 +1,72 @@
+use emdb::macros::emql;
+
+emql! {
+    impl my_db as Serialized;
+
+    table customers {
+        name: String,
+        age: u8,
+    }
+
+    // a fragment starting a stream, using the invoking query's parameter
+    fragment adults(min_age: u8) {
+        use customers |> filter(**age >= min_age)
+    }
+
+    // a fragment continuing a stream
+    fragment names() {
+        map(name: String = name.clone())
+    }
+
+    // fragments can invoke other fragments
+    fragment adult_names(min_age: u8) {
+        adults() |> names()
+    }
+
+    query add_customer(name: &str, age: u8) {
+        row(name: String = name.to_owned(), age: u8 = age)
+            ~> insert(customers as ref customer);
+    }
+
+    query get_adult_names(min_age: u8) {
+        adult_names()
+            |> sort(name asc)
+            |> fold(names: String = String::new() -> format!("{names}{name},"))
+            ~> return;
+    }
+
+    query count_adults(min_age: u8) {
+        adults() |> count(num) ~> return;
+    }
+
+    query names_by_age() {
+        use customers
+            |> groupby(age for let same_age in {
+                use same_age
+                    |> names()
+                    |> count(num)
+                    ~> map(age: u8 = *age, num: usize = num)
+                    ~> return;
+            })
+            |> sort(age asc)
+            |> fold(groups: String = String::new() -> format!("{groups}{age}:{num},"))
+            ~> return;
+    }
+}
+
+pub fn test() {
+    let mut ds = my_db::Datastore::new();
+    let mut db = ds.db();
+
+    db.add_customer("alice", 30);
+    db.add_customer("bob", 12);
+    db.add_customer("carol", 30);
+
+    assert_eq!(db.get_adult_names(18).names, "alice,carol,");
+    assert_eq!(db.get_adult_names(40).names, "");
+
+    assert_eq!(db.count_adults(18).num, 2);
+    assert_eq!(db.count_adults(10).num, 3);
+
+    assert_eq!(db.names_by_age().groups, "12:1,30:2,");
+}
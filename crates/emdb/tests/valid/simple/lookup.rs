@@ -0,0 +1,80 @@
+use emdb::macros::emql;
+
+emql! {
+    impl my_db as Serialized;
+
+    table products {
+        serial: usize,
+        name: String,
+    } @ [unique(serial) as unique_serials]
+
+    table purchases {
+        customer: String,
+        product: usize,
+    } @ [index(product) as by_product]
+
+    query new_product(serial: usize, name: &str) {
+        row(serial: usize = serial, name: String = String::from(name))
+            ~> insert(products as ref product);
+    }
+
+    query new_purchase(customer: &str, product: usize) {
+        row(customer: String = String::from(customer), product: usize = product)
+            ~> insert(purchases as ref purchase);
+    }
+
+    query customers_of(serial: usize) {
+        row(serial: usize = serial)
+            ~> lookup(serial for purchases.product as ref purchase)
+            |> deref(purchase as bought)
+            |> map(customer: &'db String = bought.customer)
+            |> collect(customers)
+            ~> return;
+    }
+
+    query purchase_counts() {
+        use products
+            |> lookup(serial for purchases.product as ref purchase)
+            |> groupby(serial for let purchased in {
+                use purchased
+                    |> count(num)
+                    ~> map(serial: usize = *serial, num: usize = num)
+                    ~> return;
+            })
+            |> collect(counts)
+            ~> return;
+    }
+}
+
+pub fn test() {
+    let mut ds = my_db::Datastore::new();
+    let mut db = ds.db();
+
+    db.new_product(1, "apple").unwrap();
+    db.new_product(2, "pear").unwrap();
+    db.new_product(3, "plum").unwrap();
+
+    db.new_purchase("Alice", 1);
+    db.new_purchase("Bob", 1);
+    db.new_purchase("Alice", 2);
+
+    let mut apple_customers: Vec<&String> = db
+        .customers_of(1)
+        .unwrap()
+        .customers
+        .into_iter()
+        .map(|v| v.customer)
+        .collect();
+    apple_customers.sort();
+    assert_eq!(apple_customers, vec!["Alice", "Bob"]);
+    assert!(db.customers_of(3).unwrap().customers.is_empty());
+
+    let mut counts: Vec<(usize, usize)> = db
+        .purchase_counts()
+        .counts
+        .into_iter()
+        .map(|v| (v.serial, v.num))
+        .collect();
+    counts.sort();
+    assert_eq!(counts, vec![(1, 2), (2, 1)]);
+}
@@ -0,0 +1,209 @@
+pub mod v1 {
+    use emdb::macros::emql;
+
+    emql! {
+        impl my_db as Serialized {
+            pub = on,
+            persist = on,
+        };
+
+        table people {
+            name: String,
+            age: u8,
+        } @ [unique(name) as unique_names]
+
+        table events {
+            description: String,
+        }
+
+        query add_person(name: &str, age: u8) {
+            row(name: String = name.to_owned(), age: u8 = age)
+                ~> insert(people as ref person)
+                ~> return;
+        }
+
+        query log(description: &str) {
+            row(description: String = description.to_owned())
+                ~> insert(events as ref event);
+        }
+    }
+}
+
+/// A previous version deleting rows, so the rows migrated are taken from a
+/// table with deletions
+pub mod v1_deletions {
+    use emdb::macros::emql;
+
+    emql! {
+        impl my_db as Serialized { pub = on, };
+
+        table events {
+            description: String,
+            seen: u32,
+        }
+
+        query log(description: &str) {
+            row(description: String = description.to_owned(), seen: u32 = 0)
+                ~> insert(events as ref event)
+                ~> return;
+        }
+
+        query see(event: ref events) {
+            row(event: ref events = event)
+                ~> update(event use seen = seen + 1);
+        }
+
+        query forget(event: ref events) {
+            row(event: ref events = event)
+                ~> delete(event);
+        }
+    }
+}
+
+pub mod v2 {
+    use emdb::macros::emql;
+
+    emql! {
+        impl my_db as Serialized { pub = on, };
+        impl concurrent_db as Serialized {
+            pub = on,
+            concurrent = on,
+        };
+
+        table people {
+            name: String,
+            birth_year: u16,
+            nickname: Option<String>,
+        } @ [
+            unique(name) as unique_names,
+            pred(*birth_year >= 1900) as born_after_1900,
+        ]
+
+        table logs {
+            description: String,
+        }
+
+        table audit {
+            entry: String,
+        }
+
+        migration from crate::valid::simple::migrations::v1::my_db::Datastore to my_db {
+            table people as person {
+                birth_year = 2024 - *person.age as u16,
+                nickname = None,
+            }
+            table logs from events {}
+            table audit empty;
+        }
+
+        migration from crate::valid::simple::migrations::v1::my_db::Datastore to concurrent_db {
+            table people as person {
+                birth_year = 2024 - *person.age as u16,
+                nickname = Some(person.name.to_uppercase()),
+            }
+            table logs from events {}
+            table audit empty;
+        }
+
+        migration from crate::valid::simple::migrations::v1_deletions::my_db::Datastore to my_db {
+            table people empty;
+            table logs from events as event {
+                description = format!("{} (seen {})", event.description, event.seen),
+            }
+            table audit empty;
+        }
+
+        query get_person(name: &str) {
+            row(name: String = name.to_owned())
+                ~> unique(name for people.name as ref person)
+                ~> deref(person as data)
+                ~> map(birth_year: u16 = *data.birth_year, nickname: Option<String> = data.nickname.clone())
+                ~> return;
+        }
+
+        query count_logs() {
+            use logs |> count(num) ~> return;
+        }
+
+        query log_descriptions() {
+            use logs
+                |> map(description: String = description.clone())
+                |> sort(description asc)
+                |> collect(descriptions)
+                ~> return;
+        }
+
+        query count_audit() {
+            use audit |> count(num) ~> return;
+        }
+    }
+}
+
+fn old_datastore() -> v1::my_db::Datastore {
+    let mut ds = v1::my_db::Datastore::new();
+    let mut db = ds.db();
+    db.add_person("alice", 30).unwrap();
+    db.add_person("bob", 4).unwrap();
+    db.log("first");
+    db.log("second");
+    ds
+}
+
+pub fn test() {
+    let mut ds = v2::my_db::Datastore::try_from(old_datastore()).unwrap();
+    let db = ds.db();
+    let alice = db.get_person("alice").unwrap();
+    assert_eq!(alice.birth_year, 1994);
+    assert_eq!(alice.nickname, None);
+    assert_eq!(db.get_person("bob").unwrap().birth_year, 2020);
+    assert_eq!(db.count_logs().num, 2);
+    assert_eq!(db.count_audit().num, 0);
+
+    let mut concurrent = v2::concurrent_db::Datastore::try_from(old_datastore()).unwrap();
+    let concurrent_db = concurrent.db();
+    assert_eq!(
+        concurrent_db.get_person("bob").unwrap().nickname.as_deref(),
+        Some("BOB")
+    );
+
+    // snapshots of the previous version can be restored, then migrated
+    let mut snapshot = Vec::new();
+    old_datastore().db().snapshot(&mut snapshot).unwrap();
+    let restored = v1::my_db::Datastore::restore(&mut snapshot.as_slice()).unwrap();
+    let mut ds = v2::my_db::Datastore::try_from(restored).unwrap();
+    assert_eq!(ds.db().count_logs().num, 2);
+
+    // rows violating the new constraints fail the migration
+    let mut old = old_datastore();
+    old.db().add_person("methuselah", 200).unwrap();
+    let Err(err) = v2::my_db::Datastore::try_from(old) else {
+        panic!("The migration should fail")
+    };
+    assert_eq!(err.table, "people");
+    assert_eq!(
+        err.to_string(),
+        "A row moved by a migration violates the constraints of table `people`"
+    );
+    assert_eq!(
+        std::error::Error::source(&err).unwrap().to_string(),
+        "Cannot insert into `people`, the row does not satisfy the predicate `born_after_1900`"
+    );
+
+    // deleted rows are not moved
+    let mut old = v1_deletions::my_db::Datastore::new();
+    let mut db = old.db();
+    let first = db.log("first").event;
+    let second = db.log("second").event;
+    db.see(second).unwrap();
+    db.forget(first).unwrap();
+    db.log("third");
+    let mut ds = v2::my_db::Datastore::try_from(old).unwrap();
+    let descriptions = ds
+        .db()
+        .log_descriptions()
+        .descriptions
+        .into_iter()
+        .map(|row| row.description)
+        .collect::<Vec<_>>();
+    assert_eq!(descriptions, ["second (seen 1)", "third (seen 0)"]);
+}
@@ -8,4 +8,25 @@ pub mod sums;
 pub mod counts;
 pub mod filter;
 pub mod deref_some;
-pub mod mutable_string;
\ No newline at end of file
+pub mod mutable_string;
+pub mod outer_join;
+pub mod ordered_index;
+pub mod lookup;
+pub mod optimise;
+pub mod access_layout;
+pub mod concurrent;
+pub mod persistence;
+pub mod references;
+pub mod composite_types;
+pub mod bag_params;
+pub mod attributes;
+pub mod update_current;
+pub mod upsert;
+pub mod distinct;
+pub mod window;
+pub mod set_operations;
+pub mod pagination;
+pub mod bulk_mutations;
+pub mod fragments;
+pub mod migrations;
+pub mod transactions;
@@ -0,0 +1,112 @@
+use emdb::macros::emql;
+
+emql! {
+    impl my_db as Serialized;
+
+    table values {
+        value: i32,
+        name: String,
+    }
+
+    query add_value(value: i32, name: &str) {
+        row(value: i32 = value, name: String = String::from(name))
+            ~> insert(values as ref new_key);
+    }
+
+    // filter pushed below the map, then merged
+    query doubled_above(min: i32) {
+        use values
+            |> map(value: i32 = *value)
+            |> map(value: i32 = value, doubled: i32 = value * 2)
+            |> filter(*value > min)
+            |> filter(*value < 100)
+            |> collect(results)
+            ~> return;
+    }
+
+    // filter kept after the checked deref (an invalid reference is still an error)
+    query names_if(enabled: bool) {
+        ref values as value_ref
+            |> deref(value_ref as row)
+            |> filter(enabled)
+            |> map(name: &'db String = row.name)
+            |> collect(names)
+            ~> return;
+    }
+
+    // maps merged, with the literal's type taken from the first map
+    query offsets() {
+        use values
+            |> map(base: i64 = 1000, value: i32 = *value)
+            |> map(offset: i64 = base + value as i64)
+            |> collect(results)
+            ~> return;
+    }
+
+    // unused field removed from the first map
+    query pairs() {
+        use values
+            |> map(value: i32 = *value, name_len: usize = name.len())
+            |> map(left: i32 = value, right: i32 = value)
+            |> collect(results)
+            ~> return;
+    }
+
+    // deref and map removed before the count
+    query count_values() {
+        use values
+            |> map(value: i32 = *value)
+            |> count(num)
+            ~> return;
+    }
+
+    // sort and take replaced with top-k
+    query top(n: usize) {
+        use values
+            |> map(value: i32 = *value)
+            |> sort(value desc)
+            |> take(n)
+            |> collect(results)
+            ~> return;
+    }
+}
+
+pub fn test() {
+    let mut ds = my_db::Datastore::new();
+    let mut db = ds.db();
+
+    for (value, name) in [
+        (1, "one"),
+        (5, "five"),
+        (3, "three"),
+        (200, "lots"),
+        (4, "four"),
+    ] {
+        let _: () = db.add_value(value, name);
+    }
+
+    let mut doubled: Vec<(i32, i32)> = db
+        .doubled_above(2)
+        .results
+        .into_iter()
+        .map(|v| (v.value, v.doubled))
+        .collect();
+    doubled.sort();
+    assert_eq!(doubled, vec![(3, 6), (4, 8), (5, 10)]);
+
+    assert_eq!(db.names_if(true).unwrap().names.len(), 5);
+    assert!(db.names_if(false).unwrap().names.is_empty());
+
+    let mut offsets: Vec<i64> = db.offsets().results.into_iter().map(|v| v.offset).collect();
+    offsets.sort();
+    assert_eq!(offsets, vec![1001, 1003, 1004, 1005, 1200]);
+
+    assert!(db.pairs().results.into_iter().all(|v| v.left == v.right));
+
+    assert_eq!(db.count_values().num, 5);
+
+    let top: Vec<i32> = db.top(3).results.into_iter().map(|v| v.value).collect();
+    assert_eq!(top, vec![200, 5, 4]);
+    let all: Vec<i32> = db.top(10).results.into_iter().map(|v| v.value).collect();
+    assert_eq!(all, vec![200, 5, 4, 3, 1]);
+}
@@ -0,0 +1,64 @@
+use emdb::macros::emql;
+
+emql! {
+    impl my_db as Serialized;
+
+    table readings {
+        time: u64,
+        value: i32,
+    } @ [ordered(time) as by_time]
+
+    query add_reading(time: u64, value: i32) {
+        row(time: u64 = time, value: i32 = value)
+            ~> insert(readings as ref reading);
+    }
+
+    query between(start: u64, end: u64) {
+        use readings
+            |> filter(**time >= start && **time < end)
+            |> map(value: i32 = *value)
+            |> collect(values)
+            ~> return;
+    }
+
+    query after(start: u64) {
+        use readings
+            |> filter(start < **time)
+            |> count(num)
+            ~> return;
+    }
+
+    query at(instant: u64) {
+        use readings
+            |> filter(**time == instant && **value > 0)
+            |> count(num)
+            ~> return;
+    }
+}
+
+pub fn test() {
+    let mut ds = my_db::Datastore::new();
+    let mut db = ds.db();
+
+    for (time, value) in [(5, 1), (1, 2), (3, 3), (5, -4), (9, 5)] {
+        db.add_reading(time, value);
+    }
+
+    let mut values: Vec<i32> = db
+        .between(3, 9)
+        .values
+        .into_iter()
+        .map(|v| v.value)
+        .collect();
+    values.sort();
+    assert_eq!(values, vec![-4, 1, 3]);
+
+    // inverted and empty (excluded) bounds scan nothing
+    assert!(db.between(10, 5).values.is_empty());
+    assert!(db.between(5, 5).values.is_empty());
+
+    assert_eq!(db.after(5).num, 1);
+    assert_eq!(db.after(0).num, 5);
+    assert_eq!(db.at(5).num, 1);
+    assert_eq!(db.at(2).num, 0);
+}
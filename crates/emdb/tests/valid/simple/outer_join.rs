@@ -0,0 +1,85 @@
+use emdb::macros::emql;
+
+emql! {
+    impl my_db as Serialized;
+
+    table owners {
+        name: String,
+    }
+
+    table pets {
+        owner: String,
+        pet: String,
+    }
+
+    query new_owner(name: &str) {
+        row(name: String = String::from(name))
+            ~> insert(owners as ref owner);
+    }
+
+    query new_pet(owner: &str, pet: &str) {
+        row(owner: String = String::from(owner), pet: String = String::from(pet))
+            ~> insert(pets as ref pet);
+    }
+
+    query owners_pets() {
+        use owners |> let all_owners;
+        use pets |> let all_pets;
+
+        join(use all_owners [left equi(name = owner)] use all_pets)
+            |> map(name: &'db String = all_owners.name, pet: Option<&'db String> = all_pets.map(|p| p.pet))
+            |> collect(pets as type owner_pet)
+            ~> return;
+    }
+
+    query pets_owners() {
+        use owners |> let all_owners;
+        use pets |> let all_pets;
+
+        join(use all_owners [right equi(name = owner)] use all_pets)
+            |> map(pet: &'db String = all_pets.pet, owned: bool = all_owners.is_some())
+            |> collect(pets as type pet_owner)
+            ~> return;
+    }
+
+    query all_pairs() {
+        use owners |> let all_owners;
+        use pets |> let all_pets;
+
+        join(use all_owners [full pred { left.name == right.owner }] use all_pets)
+            |> count(pairs)
+            ~> return;
+    }
+}
+
+pub fn test() {
+    let mut ds = my_db::Datastore::new();
+    let mut db = ds.db();
+
+    db.new_owner("Alice");
+    db.new_owner("Bob");
+    db.new_pet("Alice", "Rex");
+    db.new_pet("Charlie", "Tom");
+
+    let mut owner_pets: Vec<(&String, Option<&String>)> = db
+        .owners_pets()
+        .pets
+        .into_iter()
+        .map(|v| (v.name, v.pet))
+        .collect();
+    owner_pets.sort();
+    assert_eq!(owner_pets.len(), 2);
+    assert_eq!(owner_pets[0].1.map(String::as_str), Some("Rex"));
+    assert_eq!(owner_pets[1].1, None);
+
+    let unowned = db
+        .pets_owners()
+        .pets
+        .into_iter()
+        .filter(|v| !v.owned)
+        .count();
+    assert_eq!(unowned, 1);
+
+    // Alice & Rex, Bob & nothing, nothing & Tom
+    assert_eq!(db.all_pairs().pairs, 3);
+}
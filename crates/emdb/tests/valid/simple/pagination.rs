@@ -0,0 +1,77 @@
+use emdb::macros::emql;
+
+emql! {
+    impl my_db as Serialized;
+
+    table scores {
+        name: String,
+        score: i32,
+    }
+
+    query add(name: &str, score: i32) {
+        row(name: String = name.to_owned(), score: i32 = score)
+            ~> insert(scores as ref entry)
+            ~> return;
+    }
+
+    query page(offset: usize, size: usize) {
+        use scores
+            |> map(name: String = name.clone(), score: i32 = *score)
+            |> sort(score desc, name asc)
+            |> skip(offset)
+            |> take(size)
+            |> fold(names: String = String::new() -> format!("{names}{name},"))
+            ~> return;
+    }
+
+    query page_after_key(score: i32, name: &str, size: usize) {
+        use scores
+            |> map(name: String = name.clone(), score: i32 = *score)
+            |> sort(score desc, name asc)
+            |> skip(after (score, name.to_owned()))
+            |> take(size)
+            |> fold(names: String = String::new() -> format!("{names}{name},"))
+            ~> return;
+    }
+
+    query page_after_row(last: ref scores, size: usize) {
+        ref scores as entry
+            |> deref(entry as row)
+            |> map(entry: ref scores = entry, name: String = row.name.clone(), score: i32 = *row.score)
+            |> sort(score desc, name asc)
+            |> skip(after ref entry = last)
+            |> take(size)
+            |> fold(names: String = String::new() -> format!("{names}{name},"))
+            ~> return;
+    }
+}
+
+pub fn test() {
+    let mut ds = my_db::Datastore::new();
+    let mut db = ds.db();
+
+    let mut refs = Vec::new();
+    for (name, score) in [("a", 5), ("b", 9), ("c", 7), ("d", 9), ("e", 1)] {
+        refs.push(db.add(name, score).entry);
+    }
+
+    // ordered as b, d, c, a, e
+    assert_eq!(db.page(0, 2).names, "b,d,");
+    assert_eq!(db.page(2, 2).names, "c,a,");
+    assert_eq!(db.page(4, 2).names, "e,");
+    assert_eq!(db.page(10, 2).names, "");
+
+    assert_eq!(db.page_after_key(9, "b", 2).names, "d,c,");
+    assert_eq!(db.page_after_key(9, "d", 2).names, "c,a,");
+    assert_eq!(db.page_after_key(7, "c", 10).names, "a,e,");
+    assert_eq!(db.page_after_key(100, "z", 1).names, "b,");
+
+    // pages after a key are stable across inserts before it
+    db.add("aa", 9);
+    assert_eq!(db.page_after_key(9, "d", 2).names, "c,a,");
+
+    let d = refs[3];
+    let e = refs[4];
+    assert_eq!(db.page_after_row(d, 2).unwrap().names, "c,a,");
+    assert_eq!(db.page_after_row(e, 2).unwrap().names, "");
+}
@@ -0,0 +1,160 @@
+use emdb::macros::emql;
+use emdb::persist::Persist;
+use persistence_interface::{Database as _, Datastore as _};
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Colour(u8, u8, u8);
+
+impl Persist for Colour {
+    fn persist<W: std::io::Write>(&self, writer: &mut W) -> std::io::Result<()> {
+        (self.0, self.1, self.2).persist(writer)
+    }
+
+    fn restore<R: std::io::Read>(reader: &mut R) -> std::io::Result<Self> {
+        let (r, g, b) = Persist::restore(reader)?;
+        Ok(Colour(r, g, b))
+    }
+}
+
+emql! {
+    impl my_db as Serialized{
+        persist = on,
+    };
+    impl concurrent_db as Serialized{
+        persist = on,
+        concurrent = on,
+    };
+    impl persistence_interface as Interface;
+    impl interface_db as Serialized{
+        interface = persistence_interface,
+        persist = on,
+    };
+
+    table people {
+        name: String,
+        favourite: crate::valid::simple::persistence::Colour,
+        age: Option<u8>,
+    } @ [unique(name) as unique_names]
+
+    table events {
+        description: String,
+        by: Option<ref people>,
+    }
+
+    query add_person(name: String, favourite: super::Colour, age: Option<u8>) {
+        row(name: String = name, favourite: super::Colour = favourite, age: Option<u8> = age)
+            ~> insert(people as ref person)
+            ~> return;
+    }
+
+    query get_person(person: ref people) {
+        row(person: ref people = person)
+            ~> deref(person as data)
+            ~> map(name: String = data.name.clone())
+            ~> return;
+    }
+
+    query people_named(name: String) {
+        row(name: String = name)
+            ~> unique(name for people.name as ref person)
+            ~> deref(person as data)
+            ~> map(favourite: super::Colour = data.favourite.clone())
+            ~> return;
+    }
+
+    query log(description: String, by: Option<ref people>) {
+        row(description: String = description, by: Option<ref people> = by)
+            ~> insert(events as ref event)
+            ~> return;
+    }
+
+    query get_event(event: ref events) {
+        row(event: ref events = event)
+            ~> deref(event as data)
+            ~> map(description: String = data.description.clone(), by: Option<ref people> = data.by)
+            ~> return;
+    }
+
+    query forget(event: ref events) {
+        row(event: ref events = event) ~> delete(event);
+    }
+
+    query num_people() {
+        use people
+            |> count(num)
+            ~> return;
+    }
+}
+
+pub fn test() {
+    let mut ds = my_db::Datastore::new();
+    let mut snapshot = Vec::new();
+    let (alice, bob, first, second, third) = {
+        let mut db = ds.db();
+        let alice = db
+            .add_person(String::from("alice"), Colour(255, 0, 0), Some(30))
+            .unwrap()
+            .person;
+        let bob = db
+            .add_person(String::from("bob"), Colour(0, 0, 255), None)
+            .unwrap()
+            .person;
+        // a failed insert leaves no gap in the keys
+        assert!(db
+            .add_person(String::from("alice"), Colour(0, 0, 0), None)
+            .is_err());
+        db.add_person(String::from("carol"), Colour(0, 255, 0), Some(4))
+            .unwrap();
+        let first = db.log(String::from("first"), Some(bob)).event;
+        let second = db.log(String::from("second"), None).event;
+        let third = db.log(String::from("third"), Some(alice)).event;
+        // the deleted row leaves a hole that is kept by the restored table
+        db.forget(second).unwrap();
+        db.snapshot(&mut snapshot).unwrap();
+        (alice, bob, first, second, third)
+    };
+
+    let mut restored = my_db::Datastore::restore(&mut snapshot.as_slice()).unwrap();
+    let mut db = restored.db();
+    assert_eq!(db.num_people().num, 3);
+    assert_eq!(
+        db.people_named(String::from("carol")).unwrap().favourite,
+        Colour(0, 255, 0)
+    );
+
+    // rows keep the same keys, so references are still valid
+    assert_eq!(db.get_person(alice).unwrap().name, "alice");
+    assert_eq!(db.get_person(bob).unwrap().name, "bob");
+    let first = db.get_event(first).unwrap();
+    assert_eq!(first.description, "first");
+    assert_eq!(db.get_person(first.by.unwrap()).unwrap().name, "bob");
+    assert!(db.get_event(second).is_err());
+    assert_eq!(db.get_event(third).unwrap().description, "third");
+
+    // new rows do not replace the restored rows
+    let fourth = db.log(String::from("fourth"), None).event;
+    assert_eq!(db.get_event(fourth).unwrap().description, "fourth");
+    assert_eq!(db.get_event(third).unwrap().description, "third");
+    db.forget(third).unwrap();
+    assert!(db.get_event(third).is_err());
+
+    // constraints still apply to the restored data
+    assert!(db
+        .add_person(String::from("bob"), Colour(0, 0, 0), None)
+        .is_err());
+
+    // snapshots can be restored into any datastore with the same tables
+    let mut concurrent = concurrent_db::Datastore::restore(&mut snapshot.as_slice()).unwrap();
+    let concurrent_db = concurrent.db();
+    assert_eq!(concurrent_db.num_people().num, 3);
+    let mut resnapshot = Vec::new();
+    concurrent_db.snapshot(&mut resnapshot).unwrap();
+    assert_eq!(resnapshot, snapshot);
+
+    // datastores implementing an interface are restored the same way
+    let mut interfaced = interface_db::Datastore::restore(&mut snapshot.as_slice()).unwrap();
+    assert_eq!(interfaced.db().num_people().num, 3);
+
+    assert!(my_db::Datastore::restore(&mut &snapshot[..snapshot.len() - 1]).is_err());
+    assert!(my_db::Datastore::restore(&mut &b"not a snapshot"[..]).is_err());
+}
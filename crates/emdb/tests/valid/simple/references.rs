@@ -0,0 +1,110 @@
+use emdb::macros::emql;
+
+emql! {
+    impl my_db as Serialized;
+
+    table people {
+        name: String,
+        best_friend: Option<ref people>,
+    } @ [valid_ref(best_friend, set_none) as friend_exists]
+
+    table posts {
+        author: ref people,
+        text: String,
+    } @ [valid_ref(author, cascade) as author_exists]
+
+    table likes {
+        post: ref posts,
+        liker: ref people,
+    } @ [
+        valid_ref(post, cascade) as post_exists,
+        valid_ref(liker) as liker_exists,
+    ]
+
+    query new_person(name: String) {
+        row(name: String = name, best_friend: Option<ref people> = None)
+            ~> insert(people as ref person)
+            ~> return;
+    }
+
+    query befriend(person: ref people, friend: ref people) {
+        row(person: ref people = person, friend: ref people = friend)
+            ~> update(person use best_friend = Some(friend));
+    }
+
+    query has_friend(person: ref people) {
+        row(person: ref people = person)
+            ~> deref(person as data)
+            ~> map(has_friend: bool = data.best_friend.is_some())
+            ~> return;
+    }
+
+    query new_post(author: ref people, text: String) {
+        row(author: ref people = author, text: String = text)
+            ~> insert(posts as ref post)
+            ~> return;
+    }
+
+    query like(post: ref posts, liker: ref people) {
+        row(post: ref posts = post, liker: ref people = liker)
+            ~> insert(likes as ref like)
+            ~> return;
+    }
+
+    query remove_person(person: ref people) {
+        row(person: ref people = person)
+            ~> delete(person);
+    }
+
+    query num_posts() {
+        use posts |> count(num) ~> return;
+    }
+
+    query num_likes() {
+        use likes |> count(num) ~> return;
+    }
+}
+
+pub fn test() {
+    let mut ds = my_db::Datastore::new();
+    let mut db = ds.db();
+
+    let alice = db.new_person(String::from("alice")).unwrap().person;
+    let bob = db.new_person(String::from("bob")).unwrap().person;
+    let carol = db.new_person(String::from("carol")).unwrap().person;
+
+    db.befriend(alice, bob).unwrap();
+    let post = db.new_post(bob, String::from("hello")).unwrap().post;
+    db.like(post, alice).unwrap();
+
+    // references to a deleted row are rejected
+    db.remove_person(carol).unwrap();
+    assert_eq!(
+        db.new_post(carol, String::from("ghost"))
+            .err()
+            .unwrap()
+            .to_string(),
+        "`posts.author` references a row of `people` that does not exist (`author_exists`)"
+    );
+    assert!(db.befriend(alice, carol).is_err());
+    assert!(db.like(post, carol).is_err());
+    assert_eq!(db.num_posts().num, 1);
+
+    // alice liked a post, so cannot be removed, and nothing is changed
+    assert_eq!(
+        db.remove_person(alice).err().unwrap().to_string(),
+        "The deleted row of `people` is still referenced by a row of `likes` (`liker_exists`)"
+    );
+    assert!(db.has_friend(alice).unwrap().has_friend);
+    assert_eq!(db.num_likes().num, 1);
+
+    // removing bob cascades to his posts (and their likes), and clears alice's
+    // best friend
+    db.remove_person(bob).unwrap();
+    assert_eq!(db.num_posts().num, 0);
+    assert_eq!(db.num_likes().num, 0);
+    assert!(!db.has_friend(alice).unwrap().has_friend);
+
+    // with no likes left, alice can be removed
+    db.remove_person(alice).unwrap();
+}
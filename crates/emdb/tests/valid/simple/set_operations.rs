@@ -0,0 +1,67 @@
+use emdb::macros::emql;
+
+emql! {
+    impl my_db as Serialized;
+
+    table purchases {
+        customer: String,
+        product: String,
+    }
+
+    query buy(customer: &str, product: &str) {
+        row(customer: String = customer.to_owned(), product: String = product.to_owned())
+            ~> insert(purchases as ref purchase);
+    }
+
+    query bought_both(x: &str, y: &str) {
+        use purchases |> filter(**product == x) |> map(customer: String = customer.clone()) |> let bought_x;
+        use purchases |> filter(**product == y) |> map(customer: String = customer.clone()) |> let bought_y;
+        intersect(use bought_x, bought_y) |> count(num) ~> return;
+    }
+
+    query bought_both_all(x: &str, y: &str) {
+        use purchases |> filter(**product == x) |> map(customer: String = customer.clone()) |> let bought_x;
+        use purchases |> filter(**product == y) |> map(customer: String = customer.clone()) |> let bought_y;
+        intersect(all use bought_x, bought_y) |> count(num) ~> return;
+    }
+
+    // customers who bought x but not y
+    query bought_only(x: &str, y: &str) {
+        use purchases |> filter(**product == x) |> map(customer: String = customer.clone()) |> let bought_x;
+        use purchases |> filter(**product == y) |> map(customer: String = customer.clone()) |> let bought_y;
+        except(use bought_x, bought_y) |> count(num) ~> return;
+    }
+
+    query bought_only_all(x: &str, y: &str) {
+        use purchases |> filter(**product == x) |> map(customer: String = customer.clone()) |> let bought_x;
+        use purchases |> filter(**product == y) |> map(customer: String = customer.clone()) |> let bought_y;
+        except(all use bought_x, bought_y) |> count(num) ~> return;
+    }
+}
+
+pub fn test() {
+    let mut ds = my_db::Datastore::new();
+    let mut db = ds.db();
+
+    for (customer, product) in [
+        ("alice", "apple"),
+        ("alice", "pear"),
+        ("bob", "apple"),
+        ("bob", "apple"),
+        ("carol", "pear"),
+        ("dave", "apple"),
+    ] {
+        db.buy(customer, product);
+    }
+
+    // apple: [alice, bob, bob, dave], pear: [alice, carol]
+    assert_eq!(db.bought_both("apple", "pear").num, 1);
+    assert_eq!(db.bought_both("apple", "apple").num, 3);
+    assert_eq!(db.bought_both_all("apple", "pear").num, 1);
+    assert_eq!(db.bought_both_all("apple", "apple").num, 4);
+
+    assert_eq!(db.bought_only("apple", "pear").num, 2);
+    assert_eq!(db.bought_only("apple", "apple").num, 0);
+    assert_eq!(db.bought_only_all("apple", "pear").num, 3);
+    assert_eq!(db.bought_only_all("pear", "apple").num, 1);
+}
@@ -0,0 +1,118 @@
+use emdb::macros::emql;
+use std::{error::Error, panic};
+
+emql! {
+    impl my_db as Serialized;
+
+    table accounts {
+        name: String,
+        balance: i64,
+    } @ [unique(name) as unique_names, pred(*balance >= 0) as no_overdraft]
+
+    query open(name: &str, balance: i64) {
+        row(name: String = name.to_owned(), balance: i64 = balance)
+            ~> insert(accounts as ref account)
+            ~> return;
+    }
+
+    query deposit(account: ref accounts, amount: i64) {
+        row(account: ref accounts = account, amount: i64 = amount)
+            ~> update(account use balance = balance + amount);
+    }
+
+    query withdraw(account: ref accounts, amount: i64) {
+        row(account: ref accounts = account, amount: i64 = amount)
+            ~> update(account use balance = balance - amount);
+    }
+
+    query balance(account: ref accounts) {
+        row(account: ref accounts = account)
+            ~> deref(account as data)
+            ~> map(balance: i64 = data.balance)
+            ~> return;
+    }
+
+    query num_accounts() {
+        use accounts |> count(num) ~> return;
+    }
+}
+
+pub fn test() {
+    let mut ds = my_db::Datastore::new();
+    let mut db = ds.db();
+
+    let alice = db.open("alice", 100).unwrap().account;
+    let bob = db.open("bob", 0).unwrap().account;
+
+    // a transfer is committed when all queries succeed
+    db.transaction(|tx| -> Result<(), Box<dyn Error>> {
+        tx.withdraw(alice, 60)?;
+        tx.deposit(bob, 60)?;
+        Ok(())
+    })
+    .unwrap();
+    assert_eq!(db.balance(alice).unwrap().balance, 40);
+    assert_eq!(db.balance(bob).unwrap().balance, 60);
+
+    // the deposit is rolled back when the withdrawal overdraws
+    let err = db
+        .transaction(|tx| -> Result<(), Box<dyn Error>> {
+            tx.deposit(bob, 50)?;
+            tx.withdraw(alice, 50)?;
+            Ok(())
+        })
+        .unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "Cannot update `accounts`, the updated row does not satisfy the predicate `no_overdraft`"
+    );
+    assert_eq!(db.balance(alice).unwrap().balance, 40);
+    assert_eq!(db.balance(bob).unwrap().balance, 60);
+
+    // a failed query only aborts its own changes, and a nested transaction
+    // only rolls back to where it started
+    db.transaction(|tx| -> Result<(), Box<dyn Error>> {
+        tx.open("carol", 10)?;
+        assert!(tx.open("alice", 10).is_err());
+        let nested = tx.transaction(|tx| -> Result<(), Box<dyn Error>> {
+            tx.open("dave", 10)?;
+            tx.withdraw(bob, 100)?;
+            Ok(())
+        });
+        assert!(nested.is_err());
+        assert_eq!(tx.num_accounts().num, 3);
+        Ok(())
+    })
+    .unwrap();
+    assert_eq!(db.num_accounts().num, 3);
+
+    // rolling back to a savepoint keeps the changes before it
+    db.transaction(|tx| -> Result<(), Box<dyn Error>> {
+        tx.deposit(alice, 5)?;
+        let savepoint = tx.savepoint();
+        tx.deposit(alice, 100)?;
+        tx.open("erin", 10)?;
+        tx.rollback_to(savepoint);
+        Ok(())
+    })
+    .unwrap();
+    assert_eq!(db.balance(alice).unwrap().balance, 45);
+    assert_eq!(db.num_accounts().num, 3);
+
+    // a panicking transaction is rolled back as it unwinds, and later
+    // queries commit again
+    let unwound = panic::catch_unwind(panic::AssertUnwindSafe(|| {
+        db.transaction(|tx| -> Result<(), Box<dyn Error>> {
+            tx.deposit(alice, 1000)?;
+            tx.open("frank", 10)?;
+            panic!("failed mid-transaction")
+        })
+    }));
+    assert!(unwound.is_err());
+    assert_eq!(db.balance(alice).unwrap().balance, 45);
+    assert_eq!(db.num_accounts().num, 3);
+    let savepoint = db.savepoint();
+    db.deposit(alice, 5).unwrap();
+    db.rollback_to(savepoint);
+    assert_eq!(db.balance(alice).unwrap().balance, 50);
+}
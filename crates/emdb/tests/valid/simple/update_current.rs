@@ -0,0 +1,94 @@
+use emdb::macros::emql;
+
+emql! {
+    impl my_db as Serialized;
+
+    table counters {
+        value: i64,
+        hits: u32,
+        parent: Option<ref counters>,
+    } @ [valid_ref(parent, set_none) as parent_exists]
+
+    query new_counter() {
+        row(value: i64 = 0, hits: u32 = 0, parent: Option<ref counters> = None)
+            ~> insert(counters as ref counter)
+            ~> return;
+    }
+
+    query increment(counter: ref counters) {
+        row(counter: ref counters = counter)
+            ~> update(counter use value = value + 1, hits = hits + 1);
+    }
+
+    query add(counter: ref counters, amount: i64) {
+        row(counter: ref counters = counter, amount: i64 = amount)
+            ~> update(counter use value = value + amount);
+    }
+
+    // the input's `value` shadows the column
+    query set(counter: ref counters, value: i64) {
+        row(counter: ref counters = counter, value: i64 = value)
+            ~> update(counter use value = value);
+    }
+
+    // the parameter `value` shadows the column
+    query reset(counter: ref counters, value: i64) {
+        row(counter: ref counters = counter)
+            ~> update(counter use value = value.abs());
+    }
+
+    query double_hit() {
+        ref counters as counter
+            |> deref(counter as data)
+            |> filter(data.hits > 0)
+            |> update(counter use value = value * 2);
+    }
+
+    query adopt(counter: ref counters, new_parent: ref counters) {
+        row(counter: ref counters = counter, new_parent: ref counters = new_parent)
+            ~> update(counter use parent = parent.or(Some(new_parent)));
+    }
+
+    query remove(counter: ref counters) {
+        row(counter: ref counters = counter)
+            ~> delete(counter);
+    }
+
+    query get(counter: ref counters) {
+        row(counter: ref counters = counter)
+            ~> deref(counter as data)
+            ~> map(value: i64 = data.value, hits: u32 = data.hits, has_parent: bool = data.parent.is_some())
+            ~> return;
+    }
+}
+
+pub fn test() {
+    let mut ds = my_db::Datastore::new();
+    let mut db = ds.db();
+
+    let a = db.new_counter().unwrap().counter;
+    let b = db.new_counter().unwrap().counter;
+
+    db.increment(a).unwrap();
+    db.increment(a).unwrap();
+    db.add(a, 5).unwrap();
+    let a_data = db.get(a).unwrap();
+    assert_eq!((a_data.value, a_data.hits), (7, 2));
+
+    db.set(b, 3).unwrap();
+    db.double_hit().unwrap();
+    assert_eq!(db.get(a).unwrap().value, 14);
+    assert_eq!(db.get(b).unwrap().value, 3);
+
+    // adopting a deleted counter fails, and the update is undone
+    let c = db.new_counter().unwrap().counter;
+    db.remove(c).unwrap();
+    assert!(db.adopt(a, c).is_err());
+    assert!(!db.get(a).unwrap().has_parent);
+
+    db.adopt(a, b).unwrap();
+    assert!(db.get(a).unwrap().has_parent);
+
+    db.reset(b, -42).unwrap();
+    assert_eq!(db.get(b).unwrap().value, 42);
+}
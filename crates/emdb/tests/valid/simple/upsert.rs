@@ -0,0 +1,70 @@
+use emdb::macros::emql;
+
+emql! {
+    impl my_db as Serialized;
+
+    table words {
+        word: String,
+        count: i64,
+        length: usize,
+    } @ [unique(word) as unique_words, pred(*count < 100) as not_too_common]
+
+    // the existing row is borrowed as `entry`
+    query see(word: &str, times: i64) {
+        row(word: String = word.to_owned(), count: i64 = times, length: usize = word.len())
+            ~> upsert(words as ref entry on word set count = entry.count + count)
+            ~> return;
+    }
+
+    query reset(word: &str) {
+        row(word: String = word.to_owned(), count: i64 = 0, length: usize = word.len())
+            ~> upsert(words as ref entry on word set count = count)
+            ~> return;
+    }
+
+    // every word is seen again with an exclamation
+    query exclaim_all() {
+        ref words as entry
+            |> deref(entry as data)
+            |> map(word: String = format!("{}!", data.word), count: i64 = 1, length: usize = data.length + 1)
+            |> upsert(words as ref entry on word set count = entry.count + 1)
+            |> count(num)
+            ~> return;
+    }
+
+    query get(word: &str) {
+        row(word: String = word.to_owned())
+            ~> unique(word for words.word as ref entry)
+            ~> deref(entry as data)
+            ~> map(count: i64 = data.count, length: usize = *data.length)
+            ~> return;
+    }
+}
+
+pub fn test() {
+    let mut ds = my_db::Datastore::new();
+    let mut db = ds.db();
+
+    let a = db.see("hello", 1).unwrap().entry;
+    let b = db.see("hello", 2).unwrap().entry;
+    assert_eq!(a, b);
+    assert_eq!(db.get("hello").unwrap().count, 3);
+
+    db.see("world", 4).unwrap();
+    db.reset("hello").unwrap();
+    assert_eq!(db.get("hello").unwrap().count, 0);
+    assert_eq!(db.get("world").unwrap().count, 4);
+
+    // an update failing the predicate is an error, and leaves the row unchanged
+    assert_eq!(
+        db.see("world", 100).err().unwrap().to_string(),
+        "Cannot update `words`, the updated row does not satisfy the predicate `not_too_common`"
+    );
+    assert_eq!(db.get("world").unwrap().count, 4);
+
+    assert_eq!(db.exclaim_all().unwrap().num, 2);
+    assert_eq!(db.get("hello!").unwrap().length, 6);
+    assert_eq!(db.exclaim_all().unwrap().num, 4);
+    assert_eq!(db.get("hello!").unwrap().count, 2);
+    assert_eq!(db.get("world!!").unwrap().count, 1);
+}
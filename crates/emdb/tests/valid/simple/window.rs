@@ -0,0 +1,76 @@
+use emdb::macros::emql;
+
+emql! {
+    impl my_db as Serialized;
+
+    table readings {
+        sensor: String,
+        level: i32,
+    }
+
+    query add(sensor: &str, level: i32) {
+        row(sensor: String = sensor.to_owned(), level: i32 = level)
+            ~> insert(readings as ref reading);
+    }
+
+    query ranks() {
+        use readings
+            |> map(sensor: String = sensor.clone(), level: i32 = *level)
+            |> window(
+                partition by sensor
+                order by level asc
+                compute pos: usize = rank(), dense: usize = dense_rank(), row: usize = row_number()
+            )
+            |> fold(
+                pos_total: usize = 0 -> pos_total + pos,
+                dense_total: usize = 0 -> dense_total + dense,
+                row_total: usize = 0 -> row_total + row
+            )
+            ~> return;
+    }
+
+    query running() {
+        use readings
+            |> map(sensor: String = sensor.clone(), level: i32 = *level)
+            |> window(
+                partition by sensor
+                order by level
+                compute sum: i32 = running_sum(level), low: i32 = running_min(level), high: i32 = running_max(level)
+            )
+            |> fold(
+                sum_total: i32 = 0 -> sum_total + sum,
+                low_total: i32 = 0 -> low_total + low,
+                high_total: i32 = 0 -> high_total + high
+            )
+            ~> return;
+    }
+
+    // without a partition the whole stream is one window
+    query numbered() {
+        use readings
+            |> window(order by level desc compute num: usize = row_number())
+            |> fold(total: usize = 0 -> total + num)
+            ~> return;
+    }
+}
+
+pub fn test() {
+    let mut ds = my_db::Datastore::new();
+    let mut db = ds.db();
+
+    for (sensor, level) in [("b", 3), ("a", 1), ("b", 7), ("a", 1), ("c", 2), ("a", 4), ("b", 3)] {
+        db.add(sensor, level);
+    }
+
+    let ranks = db.ranks();
+    assert_eq!(ranks.pos_total, (1 + 1 + 3) * 2 + 1);
+    assert_eq!(ranks.dense_total, (1 + 1 + 2) * 2 + 1);
+    assert_eq!(ranks.row_total, (1 + 2 + 3) * 2 + 1);
+
+    let running = db.running();
+    assert_eq!(running.sum_total, (3 + 6 + 13) + (1 + 2 + 6) + 2);
+    assert_eq!(running.low_total, (3 + 3 + 3) + (1 + 1 + 1) + 2);
+    assert_eq!(running.high_total, (3 + 3 + 7) + (1 + 1 + 4) + 2);
+
+    assert_eq!(db.numbered().total, 1 + 2 + 3 + 4 + 5 + 6 + 7);
+}
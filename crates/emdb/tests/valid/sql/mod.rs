@@ -0,0 +1,6 @@
+//! # Tests for the SQL frontend
+//! Each uses [`emdb::macros::sql`], lowered to the same plan as the equivalent
+//! emQL.
+
+pub mod user_details;
+pub mod queries;
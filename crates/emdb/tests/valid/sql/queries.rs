@@ -0,0 +1,71 @@
+use emdb::macros::sql;
+
+sql! {
+    impl my_db as Serialized;
+
+    CREATE TABLE products (
+        name TEXT UNIQUE,
+        category TEXT,
+        price INTEGER,
+        stock SMALLINT,
+        CHECK (price > 0),
+        CHECK (stock >= 0)
+    );
+
+    PREPARE add_product(product_name TEXT, product_category TEXT, product_price INTEGER) AS
+        INSERT INTO products (name, category, price, stock)
+        VALUES (:product_name, :product_category, :product_price, 0);
+
+    PREPARE cheapest(cat TEXT, n usize) AS
+        SELECT name, price FROM products
+        WHERE category = :cat
+        ORDER BY price ASC, name DESC
+        LIMIT :n;
+
+    PREPARE by_stock() AS
+        SELECT name FROM products ORDER BY stock DESC;
+
+    PREPARE restock(cat TEXT, amount SMALLINT) AS
+        UPDATE products SET stock = stock + :amount WHERE category = :cat;
+
+    PREPARE discontinue(max_price INTEGER) AS
+        DELETE FROM products WHERE price <= :max_price AND NOT (category = "essentials");
+
+    PREPARE stats() AS
+        SELECT COUNT(*) AS num, MIN(price) AS cheapest, MAX(price) AS dearest FROM products;
+
+    PREPARE num_products() AS
+        SELECT COUNT(*) FROM products;
+}
+
+pub fn test() {
+    let mut ds = my_db::Datastore::new();
+    let mut db = ds.db();
+
+    db.add_product(String::from("bread"), String::from("essentials"), 2).unwrap();
+    db.add_product(String::from("milk"), String::from("essentials"), 1).unwrap();
+    db.add_product(String::from("cake"), String::from("treats"), 5).unwrap();
+    db.add_product(String::from("candle"), String::from("gifts"), 3).unwrap();
+    assert!(db.add_product(String::from("milk"), String::from("treats"), 1).is_err());
+    assert!(db.add_product(String::from("nothing"), String::from("treats"), 0).is_err());
+
+    let cheapest = db
+        .cheapest(String::from("essentials"), 1)
+        .rows
+        .into_iter()
+        .map(|row| (row.name, row.price))
+        .collect::<Vec<_>>();
+    assert_eq!(cheapest, vec![(String::from("milk"), 1)]);
+
+    db.restock(String::from("treats"), 4).unwrap();
+    let by_stock = db.by_stock().rows.into_iter().map(|row| row.name).collect::<Vec<_>>();
+    assert_eq!(by_stock[0], "cake");
+
+    let stats = db.stats();
+    assert_eq!(stats.num, 4);
+    assert_eq!(stats.cheapest, Some(1));
+    assert_eq!(stats.dearest, Some(5));
+
+    db.discontinue(3).unwrap();
+    assert_eq!(db.num_products().count, 3);
+}
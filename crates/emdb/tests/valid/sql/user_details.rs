@@ -0,0 +1,62 @@
+use emdb::macros::sql;
+use user_deets::{Database, Datastore};
+
+// The sqlite implementation from the `user_details` benchmark
+sql! {
+    impl user_deets as Interface{
+        pub = on,
+    };
+    impl my_db as Serialized{
+        interface = user_deets,
+    };
+
+    CREATE TABLE users (
+        name VARCHAR NOT NULL,
+        premium BOOLEAN NOT NULL,
+        credits MEDIUMINT NOT NULL,
+
+        CONSTRAINT premcredits CHECK (premium OR credits >= 0)
+    );
+
+    PREPARE new_user(username VARCHAR, prem BOOLEAN, start_creds MEDIUMINT) AS
+        INSERT INTO users (name, premium, credits) VALUES (:username, :prem, :start_creds)
+        RETURNING ROWID AS user_id;
+
+    PREPARE get_info(user_id REF users) AS
+        SELECT name, premium, credits FROM users WHERE ROWID = :user_id;
+
+    PREPARE get_snapshot() AS
+        SELECT ROWID AS id, name, premium, credits FROM users;
+
+    PREPARE add_credits(user_id REF users, creds MEDIUMINT) AS
+        UPDATE users SET credits = credits + :creds WHERE ROWID = :user_id;
+
+    PREPARE reward_premium(bonus MEDIUMINT) AS
+        UPDATE users SET credits = credits + :bonus WHERE premium = TRUE;
+
+    PREPARE total_premium_credits() AS
+        SELECT SUM(credits) AS total FROM users WHERE premium = TRUE;
+}
+
+pub fn test() {
+    let mut ds = my_db::Datastore::new();
+    let mut db = ds.db();
+
+    let bob = db.new_user(String::from("bob"), true, 10).unwrap().user_id;
+    let alice = db.new_user(String::from("alice"), false, 5).unwrap().user_id;
+    assert!(db.new_user(String::from("eve"), false, -1).is_err());
+
+    let info = db.get_info(bob).unwrap();
+    assert_eq!(info.name, "bob");
+    assert!(info.premium);
+    assert_eq!(info.credits, 10);
+
+    db.add_credits(alice, 3).unwrap();
+    db.reward_premium(5).unwrap();
+    assert_eq!(db.get_info(alice).unwrap().credits, 8);
+    assert_eq!(db.total_premium_credits().total, 15);
+
+    let snapshot = db.get_snapshot().unwrap().rows.into_iter().collect::<Vec<_>>();
+    assert_eq!(snapshot.len(), 2);
+    assert!(snapshot.iter().any(|row| row.id == alice && row.name == "alice"));
+}
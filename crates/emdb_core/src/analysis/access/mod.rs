@@ -1,3 +1,543 @@
 //! ## Analysing Access Patterns
 //! - Determining which columns of which tablesa are accessed together
 //! - Determining the access patterns of associated reads & writes
+//!
+//! For each table we record:
+//! - The columns read by each [`plan::DeRef`], found by following the
+//!   dereferenced row to the operators that use its columns.
+//! - Whether each dereference is done while scanning the whole table (from a
+//!   [`plan::ScanRefs`] or [`plan::RangeRefs`]), or for individual rows.
+//! - The columns updated together by each [`plan::Update`] and
+//!   [`plan::Upsert`], and the current values they read.
+//! - The columns read while scanning by each [`plan::DeleteWhere`] and
+//!   [`plan::UpdateWhere`], and the columns the latter updates.
+//!
+//! Expressions are inspected syntactically and conservatively, as with the
+//! optimiser. If the row escapes the query (e.g. is collected, returned or
+//! joined) then all of its columns are considered read.
+
+use std::collections::{HashMap, HashSet};
+
+use proc_macro2::{TokenStream, TokenTree};
+use quote::ToTokens;
+use syn::Expr;
+
+use crate::{
+    optimise::graph::{idents, next},
+    plan,
+};
+
+/// The columns read from a table by a single dereference
+pub struct Read {
+    pub op: plan::Key<plan::Operator>,
+    pub columns: HashSet<plan::RecordField>,
+
+    /// The rows dereferenced come from a scan of the table
+    pub scan: bool,
+}
+
+/// The columns updated together by a single update
+pub struct Write {
+    pub op: plan::Key<plan::Operator>,
+    pub columns: HashSet<plan::RecordField>,
+}
+
+#[derive(Default)]
+pub struct TableAccess {
+    pub reads: Vec<Read>,
+    pub updates: Vec<Write>,
+}
+
+impl TableAccess {
+    /// Columns that are only read while scanning the table
+    pub fn only_scanned(&self) -> HashSet<&plan::RecordField> {
+        let point = self
+            .reads
+            .iter()
+            .filter(|read| !read.scan)
+            .flat_map(|read| read.columns.iter())
+            .collect::<HashSet<_>>();
+        self.reads
+            .iter()
+            .filter(|read| read.scan)
+            .flat_map(|read| read.columns.iter())
+            .filter(|col| !point.contains(col))
+            .collect()
+    }
+}
+
+/// The access patterns of every table in the plan
+pub struct AccessPatterns {
+    tables: HashMap<plan::Key<plan::Table>, TableAccess>,
+}
+
+impl AccessPatterns {
+    pub fn analyse(lp: &plan::Plan) -> Self {
+        let mut tables = lp
+            .tables
+            .iter()
+            .map(|(key, _)| (key, TableAccess::default()))
+            .collect::<HashMap<_, _>>();
+        for (key, op) in lp.operators.iter() {
+            match op {
+                plan::Operator::DeRef(deref) => {
+                    tables.get_mut(&deref.table).unwrap().reads.push(Read {
+                        op: key,
+                        columns: deref_reads(lp, deref),
+                        scan: scanned(lp, deref.input, &deref.reference),
+                    })
+                }
+                plan::Operator::Update(update) => {
+                    let table = tables.get_mut(&update.table).unwrap();
+                    if !update.current.is_empty() {
+                        table.reads.push(Read {
+                            op: key,
+                            columns: update.current.iter().cloned().collect(),
+                            scan: scanned(lp, update.input, &update.reference),
+                        })
+                    }
+                    table.updates.push(Write {
+                        op: key,
+                        columns: update.mapping.keys().cloned().collect(),
+                    })
+                }
+                plan::Operator::Upsert(upsert) => {
+                    let table = tables.get_mut(&upsert.table).unwrap();
+                    if upsert.current {
+                        // the whole existing row is borrowed by the expressions
+                        table.reads.push(Read {
+                            op: key,
+                            columns: lp.get_table(upsert.table).columns.keys().cloned().collect(),
+                            scan: false,
+                        })
+                    }
+                    table.updates.push(Write {
+                        op: key,
+                        columns: upsert.mapping.keys().cloned().collect(),
+                    })
+                }
+                plan::Operator::DeleteWhere(delete_where) => {
+                    if !delete_where.columns.is_empty() {
+                        tables
+                            .get_mut(&delete_where.table)
+                            .unwrap()
+                            .reads
+                            .push(Read {
+                                op: key,
+                                columns: delete_where.columns.iter().cloned().collect(),
+                                scan: true,
+                            })
+                    }
+                }
+                plan::Operator::UpdateWhere(update_where) => {
+                    let table = tables.get_mut(&update_where.table).unwrap();
+                    if !update_where.columns.is_empty() {
+                        table.reads.push(Read {
+                            op: key,
+                            columns: update_where.columns.iter().cloned().collect(),
+                            scan: true,
+                        })
+                    }
+                    table.updates.push(Write {
+                        op: key,
+                        columns: update_where.mapping.keys().cloned().collect(),
+                    })
+                }
+                _ => (),
+            }
+        }
+        AccessPatterns { tables }
+    }
+
+    pub fn get(&self, table: plan::Key<plan::Table>) -> &TableAccess {
+        self.tables.get(&table).unwrap()
+    }
+}
+
+/// Determine if the references in a field of a dataflow were produced by a
+/// scan of a table.
+fn scanned(lp: &plan::Plan, df: plan::Key<plan::DataFlow>, field: &plan::RecordField) -> bool {
+    let input = match lp.get_operator(lp.get_dataflow(df).get_conn().from) {
+        plan::Operator::ScanRefs(plan::ScanRefs { out_ref, .. })
+        | plan::Operator::RangeRefs(plan::RangeRefs { out_ref, .. }) => return out_ref == field,
+        plan::Operator::UniqueRef(plan::UniqueRef { out, input, .. })
+        | plan::Operator::LookupRefs(plan::LookupRefs { out, input, .. }) => {
+            if out == field {
+                return false;
+            }
+            *input
+        }
+        plan::Operator::Filter(plan::Filter { input, .. })
+        | plan::Operator::Sort(plan::Sort { input, .. })
+        | plan::Operator::Distinct(plan::Distinct { input, .. })
+        | plan::Operator::Window(plan::Window { input, .. })
+        | plan::Operator::Take(plan::Take { input, .. })
+        | plan::Operator::Skip(plan::Skip { input, .. })
+        | plan::Operator::TopK(plan::TopK { input, .. })
+        | plan::Operator::Assert(plan::Assert { input, .. })
+        | plan::Operator::DeRef(plan::DeRef { input, .. })
+        | plan::Operator::Expand(plan::Expand { input, .. })
+        | plan::Operator::Update(plan::Update { input, .. })
+        | plan::Operator::Delete(plan::Delete { input, .. })
+        | plan::Operator::Fork(plan::Fork { input, .. }) => *input,
+        _ => return false,
+    };
+    scanned(lp, input, field)
+}
+
+/// Where the columns of the dereferenced row can be found in a dataflow
+#[derive(Clone)]
+struct Tracked {
+    /// The field containing the whole row
+    row: Option<plan::RecordField>,
+
+    /// Columns expanded from the row into fields of the dataflow
+    expanded: bool,
+}
+
+struct Uses {
+    columns: HashSet<plan::RecordField>,
+    read: HashSet<plan::RecordField>,
+}
+
+impl Uses {
+    fn all(&mut self) {
+        self.read.extend(self.columns.iter().cloned());
+    }
+
+    fn field(&mut self, tracked: &Tracked, field: &plan::RecordField) {
+        if tracked.row.as_ref() == Some(field) {
+            self.all();
+        } else if tracked.expanded && self.columns.contains(field) {
+            self.read.insert(field.clone());
+        }
+    }
+
+    fn expr(&mut self, tracked: &Tracked, expr: &Expr) {
+        if tracked.expanded {
+            for id in idents(expr) {
+                let field = plan::RecordField::User(id);
+                if self.columns.contains(&field) {
+                    self.read.insert(field);
+                }
+            }
+        }
+        if let Some(plan::RecordField::User(row)) = &tracked.row {
+            self.row_accesses(row, expr.to_token_stream());
+        }
+    }
+
+    /// Find columns accessed as `row.column`, any other use of the row uses
+    /// all columns.
+    fn row_accesses(&mut self, row: &syn::Ident, tks: TokenStream) {
+        let mut tks = tks.into_iter();
+        while let Some(tt) = tks.next() {
+            match tt {
+                TokenTree::Group(g) => self.row_accesses(row, g.stream()),
+                TokenTree::Ident(id) if id == *row => match (tks.next(), tks.next()) {
+                    (Some(TokenTree::Punct(p)), Some(TokenTree::Ident(col)))
+                        if p.as_char() == '.'
+                            && self.columns.contains(&plan::RecordField::User(col.clone())) =>
+                    {
+                        self.read.insert(plan::RecordField::User(col));
+                    }
+                    _ => self.all(),
+                },
+                _ => (),
+            }
+        }
+    }
+}
+
+/// The columns of a dereferenced row that are used by the operators after
+/// the [`plan::DeRef`].
+pub fn deref_reads(lp: &plan::Plan, deref: &plan::DeRef) -> HashSet<plan::RecordField> {
+    let mut uses = Uses {
+        columns: lp
+            .get_record_type_conc(deref.named_type)
+            .fields
+            .keys()
+            .cloned()
+            .collect(),
+        read: HashSet::new(),
+    };
+    let mut visit = vec![(
+        deref.output,
+        Tracked {
+            row: Some(deref.named.clone()),
+            expanded: false,
+        },
+    )];
+
+    while let Some((df, mut tracked)) = visit.pop() {
+        let outputs = match lp.get_operator(next(lp, df)) {
+            plan::Operator::Filter(plan::Filter {
+                predicate: expr,
+                output,
+                ..
+            })
+            | plan::Operator::Assert(plan::Assert {
+                assert: expr,
+                output,
+                ..
+            })
+            | plan::Operator::Take(plan::Take {
+                limit: expr,
+                output,
+                ..
+            }) => {
+                uses.expr(&tracked, expr);
+                vec![*output]
+            }
+            plan::Operator::Sort(plan::Sort {
+                sort_order, output, ..
+            }) => {
+                for (field, _) in sort_order {
+                    uses.field(&tracked, field);
+                }
+                vec![*output]
+            }
+            plan::Operator::Skip(plan::Skip { from, output, .. }) => {
+                match from {
+                    plan::SkipFrom::Offset(offset) => uses.expr(&tracked, offset),
+                    plan::SkipFrom::Key { sort_order, key } => {
+                        for (field, _) in sort_order {
+                            uses.field(&tracked, field);
+                        }
+                        uses.expr(&tracked, key);
+                    }
+                    plan::SkipFrom::Row { field, row } => {
+                        uses.field(&tracked, field);
+                        uses.expr(&tracked, row);
+                    }
+                }
+                vec![*output]
+            }
+            plan::Operator::Distinct(plan::Distinct { fields, output, .. }) => {
+                for field in fields {
+                    uses.field(&tracked, field);
+                }
+                vec![*output]
+            }
+            plan::Operator::Window(plan::Window {
+                partition,
+                order,
+                compute,
+                output,
+                ..
+            }) => {
+                for field in partition.iter().chain(order.iter().map(|(field, _)| field)) {
+                    uses.field(&tracked, field);
+                }
+                for (_, function) in compute {
+                    if let plan::WindowFunction::RunningSum(expr)
+                    | plan::WindowFunction::RunningMin(expr)
+                    | plan::WindowFunction::RunningMax(expr) = function
+                    {
+                        uses.expr(&tracked, expr);
+                    }
+                }
+                vec![*output]
+            }
+            plan::Operator::TopK(plan::TopK {
+                sort_order,
+                limit,
+                output,
+                ..
+            }) => {
+                for (field, _) in sort_order {
+                    uses.field(&tracked, field);
+                }
+                uses.expr(&tracked, limit);
+                vec![*output]
+            }
+            plan::Operator::Update(plan::Update {
+                reference,
+                mapping,
+                output,
+                ..
+            }) => {
+                uses.field(&tracked, reference);
+                for expr in mapping.values() {
+                    uses.expr(&tracked, expr);
+                }
+                vec![*output]
+            }
+            plan::Operator::Delete(plan::Delete {
+                reference: field,
+                output,
+                ..
+            })
+            | plan::Operator::DeRef(plan::DeRef {
+                reference: field,
+                output,
+                ..
+            })
+            | plan::Operator::UniqueRef(plan::UniqueRef {
+                from: field,
+                output,
+                ..
+            })
+            | plan::Operator::LookupRefs(plan::LookupRefs {
+                from: field,
+                output,
+                ..
+            }) => {
+                uses.field(&tracked, field);
+                vec![*output]
+            }
+            plan::Operator::Expand(plan::Expand { field, output, .. }) => {
+                if tracked.row.as_ref() == Some(field) {
+                    tracked = Tracked {
+                        row: None,
+                        expanded: true,
+                    };
+                }
+                vec![*output]
+            }
+            plan::Operator::Fork(plan::Fork { outputs, .. }) => outputs.clone(),
+            plan::Operator::Union(plan::Union { output, .. }) => vec![*output],
+
+            // the row is replaced by the results of expressions
+            plan::Operator::Map(plan::Map { mapping, .. }) => {
+                for (_, expr) in mapping {
+                    uses.expr(&tracked, expr);
+                }
+                vec![]
+            }
+            plan::Operator::Fold(plan::Fold { fold_fields, .. })
+            | plan::Operator::Combine(plan::Combine {
+                update_fields: fold_fields,
+                ..
+            }) => {
+                for (_, plan::FoldField { initial, update }) in fold_fields {
+                    uses.expr(&tracked, initial);
+                    uses.expr(&tracked, update);
+                }
+                vec![]
+            }
+            plan::Operator::Count(_) | plan::Operator::Discard(_) => vec![],
+
+            // every field is compared
+            plan::Operator::Intersect(_) | plan::Operator::Except(_) => {
+                if tracked.row.is_some() || tracked.expanded {
+                    uses.all();
+                }
+                vec![]
+            }
+
+            // the row escapes the operators we can track it through
+            plan::Operator::Insert(_)
+            | plan::Operator::Upsert(_)
+            | plan::Operator::Collect(_)
+            | plan::Operator::Return(_)
+            | plan::Operator::Join(_)
+            | plan::Operator::GroupBy(_)
+            | plan::Operator::Lift(_) => {
+                if tracked.row.is_some() || tracked.expanded {
+                    uses.all();
+                }
+                vec![]
+            }
+            plan::Operator::ScanRefs(_)
+            | plan::Operator::RangeRefs(_)
+            | plan::Operator::DeleteWhere(_)
+            | plan::Operator::UpdateWhere(_)
+            | plan::Operator::Row(_)
+            | plan::Operator::ScanBag(_) => {
+                unreachable!("Operator has no input dataflow")
+            }
+        };
+        visit.extend(outputs.into_iter().map(|output| (output, tracked.clone())));
+    }
+    uses.read
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::frontend::{Emql, Frontend};
+    use proc_macro2::TokenStream;
+    use quote::quote;
+
+    /// Get the access patterns of the only table in a schema
+    fn access(tks: TokenStream) -> (plan::Plan, TableAccess) {
+        let Ok((lp, _)) = Emql::from_tokens(tks) else {
+            panic!("Invalid emql in test")
+        };
+        let mut access = AccessPatterns::analyse(&lp);
+        let (table, _) = lp.tables.iter().next().unwrap();
+        let table_access = access.tables.remove(&table).unwrap();
+        (lp, table_access)
+    }
+
+    fn columns(cols: &[&str]) -> HashSet<plan::RecordField> {
+        cols.iter()
+            .map(|c| plan::RecordField::User(syn::Ident::new(c, proc_macro2::Span::call_site())))
+            .collect()
+    }
+
+    #[test]
+    fn scanned_columns() {
+        let (_, access) = access(quote! {
+            table people { name: String, age: u8, bio: String }
+            query adults() {
+                use people
+                    |> filter(**age >= 18)
+                    |> map(name: String = name.clone())
+                    |> collect(names)
+                    ~> return;
+            }
+        });
+        assert_eq!(access.reads.len(), 1);
+        assert!(access.reads[0].scan);
+        assert!(access.reads[0].columns == columns(&["name", "age"]));
+    }
+
+    #[test]
+    fn point_reads() {
+        let (_, access) = access(quote! {
+            table people { name: String, age: u8, bio: String }
+            query age_of(id: ref people) {
+                row(id: ref people = id)
+                    ~> deref(id as person)
+                    ~> map(age: u8 = person.age)
+                    ~> return;
+            }
+            query total_age() {
+                use people
+                    |> fold(sum: u64 = 0 -> sum + **age as u64)
+                    ~> return;
+            }
+            query everything(id: ref people) {
+                row(id: ref people = id)
+                    ~> deref(id as person)
+                    ~> return;
+            }
+        });
+        let point = access
+            .reads
+            .iter()
+            .filter(|read| !read.scan)
+            .map(|read| read.columns.clone())
+            .collect::<Vec<_>>();
+        assert!(point.contains(&columns(&["age"])));
+        assert!(point.contains(&columns(&["name", "age", "bio"])));
+        assert_eq!(access.only_scanned().len(), 0);
+    }
+
+    #[test]
+    fn updates() {
+        let (_, access) = access(quote! {
+            table people { name: String, age: u8, bio: String }
+            query birthday(id: ref people) {
+                row(id: ref people = id)
+                    ~> deref(id as person)
+                    ~> update(id use age = person.age + 1);
+            }
+        });
+        assert_eq!(access.updates.len(), 1);
+        assert!(access.updates[0].columns == columns(&["age"]));
+        assert!(access.reads[0].columns == columns(&["age"]));
+    }
+}
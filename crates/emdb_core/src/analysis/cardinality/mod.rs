@@ -2,3 +2,454 @@
 //! Determining cardinality constraints of dataflows.
 //! - Static (fixed numnber) and runtime (i.e. passing sizes around), values and
 //!   bounds.
+//!
+//! Each [`plan::DataFlow`] is given a bound on the number of rows it carries:
+//! - Singles always carry exactly one row.
+//! - Streams are bounded by a [`plan::Take`] (or [`plan::TopK`]), or by the
+//!   `limit` constraint of the table they were scanned from.
+//! - Bounds are propagated through operators that do not increase the number
+//!   of rows (e.g. `map`, `filter`), and combined through joins, unions and
+//!   lookups when they are constant.
+//!
+//! Backends can use this to choose fixed-size buffers, avoid allocating for
+//! single row streams, and [`unbounded_collects`] warns on `collect`s of
+//! unbounded streams (e.g. the `Serialized` backend's `warn_unbounded` option).
+
+use std::collections::{HashMap, LinkedList};
+
+use proc_macro2::Span;
+use proc_macro_error2::{Diagnostic, Level};
+use syn::{Expr, ExprLit, Lit};
+
+use crate::plan;
+
+/// An upper bound on the number of rows
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Size {
+    /// Known when generating code
+    Const(usize),
+
+    /// A `usize` expression evaluated in the context that bounds the stream
+    /// (e.g. the limit of a `take`).
+    Expr(Expr),
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Cardinality {
+    /// Exactly one row, a single
+    Single,
+
+    /// A stream of at most the given number of rows
+    AtMost(Size),
+
+    /// A stream with no bound
+    Unbounded,
+}
+
+impl Size {
+    fn from_expr(expr: &Expr) -> Self {
+        match expr {
+            Expr::Lit(ExprLit {
+                lit: Lit::Int(i), ..
+            }) => match i.base10_parse() {
+                Ok(n) => Size::Const(n),
+                Err(_) => Size::Expr(expr.clone()),
+            },
+            Expr::Paren(p) => Size::from_expr(&p.expr),
+            _ => Size::Expr(expr.clone()),
+        }
+    }
+
+    /// The tightest bound we can statically choose from two bounds, where
+    /// `self` is the newer one
+    fn min(self, other: Size) -> Size {
+        match (self, other) {
+            (Size::Const(a), Size::Const(b)) => Size::Const(a.min(b)),
+            (Size::Expr(_), Size::Const(c)) => Size::Const(c),
+            (s, _) => s,
+        }
+    }
+
+    fn combine(self, other: Size, op: fn(usize, usize) -> Option<usize>) -> Option<Size> {
+        match (self, other) {
+            (Size::Const(a), Size::Const(b)) => op(a, b).map(Size::Const),
+            _ => None,
+        }
+    }
+}
+
+impl Cardinality {
+    /// The maximum number of rows, [`None`] if unbounded
+    pub fn bound(&self) -> Option<Size> {
+        match self {
+            Cardinality::Single => Some(Size::Const(1)),
+            Cardinality::AtMost(s) => Some(s.clone()),
+            Cardinality::Unbounded => None,
+        }
+    }
+
+    fn stream(bound: Option<Size>) -> Self {
+        match bound {
+            Some(s) => Cardinality::AtMost(s),
+            None => Cardinality::Unbounded,
+        }
+    }
+
+    fn limit(self, limit: &Expr) -> Self {
+        let limit = Size::from_expr(limit);
+        Cardinality::stream(Some(match self.bound() {
+            Some(bound) => limit.min(bound),
+            None => limit,
+        }))
+    }
+
+    fn product(self, other: Self) -> Self {
+        Cardinality::stream(
+            self.bound()
+                .zip(other.bound())
+                .and_then(|(a, b)| a.combine(b, usize::checked_mul)),
+        )
+    }
+
+    fn sum(self, other: Self) -> Self {
+        Cardinality::stream(
+            self.bound()
+                .zip(other.bound())
+                .and_then(|(a, b)| a.combine(b, usize::checked_add)),
+        )
+    }
+
+    fn smallest(self, other: Self) -> Self {
+        Cardinality::stream(match (self.bound(), other.bound()) {
+            (Some(a), Some(b)) => Some(a.min(b)),
+            (a, b) => a.or(b),
+        })
+    }
+
+    /// The cardinality of a stream, where an empty input still produces one row
+    /// (e.g. the left side of a left join)
+    fn at_least_one(self) -> Self {
+        match self {
+            Cardinality::AtMost(Size::Const(0)) => Cardinality::AtMost(Size::Const(1)),
+            other => other,
+        }
+    }
+}
+
+/// The cardinality of every dataflow in the plan
+pub struct Cardinalities {
+    flows: HashMap<plan::Key<plan::DataFlow>, Cardinality>,
+}
+
+impl Cardinalities {
+    pub fn analyse(lp: &plan::Plan) -> Self {
+        let mut cards = Cardinalities {
+            flows: HashMap::new(),
+        };
+        for (key, _) in lp.dataflow.iter() {
+            cards.compute(lp, key);
+        }
+        cards
+    }
+
+    pub fn get(&self, df: plan::Key<plan::DataFlow>) -> &Cardinality {
+        self.flows.get(&df).unwrap()
+    }
+
+    fn compute(&mut self, lp: &plan::Plan, df: plan::Key<plan::DataFlow>) -> Cardinality {
+        if let Some(card) = self.flows.get(&df) {
+            return card.clone();
+        }
+        let plan::DataFlowConn { from, with, .. } = lp.get_dataflow(df).get_conn();
+        let card = if with.stream {
+            lp.get_operator(*from).cardinality(lp, self)
+        } else {
+            Cardinality::Single
+        };
+        self.flows.insert(df, card.clone());
+        card
+    }
+}
+
+/// Warnings for every `collect` of an unbounded stream, as the bag collected
+/// can grow with the size of the tables scanned.
+pub fn unbounded_collects(lp: &plan::Plan) -> LinkedList<Diagnostic> {
+    let cards = Cardinalities::analyse(lp);
+    lp.operators
+        .iter()
+        .filter_map(|(_, op)| match op {
+            plan::Operator::Collect(plan::Collect { input, into, .. })
+                if *cards.get(*input) == Cardinality::Unbounded =>
+            {
+                let span = match into {
+                    plan::RecordField::User(id) => id.span(),
+                    plan::RecordField::Internal(_) => Span::call_site(),
+                };
+                Some(
+                    Diagnostic::spanned(
+                        span,
+                        Level::Warning,
+                        format!("`collect` into `{into}` of a stream with no bound on its size"),
+                    )
+                    .help(String::from(
+                        "Bound the stream with a `take`, or the table scanned with a `limit` constraint",
+                    )),
+                )
+            }
+            _ => None,
+        })
+        .collect()
+}
+
+fn table_limit(lp: &plan::Plan, table: plan::Key<plan::Table>) -> Cardinality {
+    Cardinality::stream(
+        lp.get_table(table)
+            .row_cons
+            .limit
+            .as_ref()
+            .map(|limit| Size::from_expr(&limit.cons.0)),
+    )
+}
+
+#[enumtrait::store(trait_get_cardinality)]
+trait GetCardinality {
+    /// The cardinality of the stream output by the operator
+    fn cardinality(&self, lp: &plan::Plan, cards: &mut Cardinalities) -> Cardinality {
+        unreachable!("Operator does not output a stream")
+    }
+}
+
+#[enumtrait::impl_trait(trait_get_cardinality for plan::operator_enum)]
+impl GetCardinality for plan::Operator {}
+
+/// Operators that output at most one row for every input row
+macro_rules! same_as_input {
+    ($($op:ident),*) => {
+        $(
+            impl GetCardinality for plan::$op {
+                fn cardinality(&self, lp: &plan::Plan, cards: &mut Cardinalities) -> Cardinality {
+                    cards.compute(lp, self.input)
+                }
+            }
+        )*
+    };
+}
+
+same_as_input!(
+    Update, Insert, Upsert, Delete, UniqueRef, DeRef, Map, Expand, Filter, Sort, Assert, GroupBy,
+    Lift, Fork, Distinct, Window, Skip
+);
+
+// Operators that only output singles, or have no outputs
+impl GetCardinality for plan::Fold {}
+impl GetCardinality for plan::Combine {}
+impl GetCardinality for plan::Count {}
+impl GetCardinality for plan::Collect {}
+impl GetCardinality for plan::Row {}
+impl GetCardinality for plan::DeleteWhere {}
+impl GetCardinality for plan::UpdateWhere {}
+impl GetCardinality for plan::Return {}
+impl GetCardinality for plan::Discard {}
+
+impl GetCardinality for plan::LookupRefs {
+    fn cardinality(&self, lp: &plan::Plan, cards: &mut Cardinalities) -> Cardinality {
+        cards
+            .compute(lp, self.input)
+            .product(table_limit(lp, self.table))
+    }
+}
+
+impl GetCardinality for plan::ScanRefs {
+    fn cardinality(&self, lp: &plan::Plan, cards: &mut Cardinalities) -> Cardinality {
+        table_limit(lp, self.table)
+    }
+}
+
+impl GetCardinality for plan::RangeRefs {
+    fn cardinality(&self, lp: &plan::Plan, cards: &mut Cardinalities) -> Cardinality {
+        table_limit(lp, self.table)
+    }
+}
+
+impl GetCardinality for plan::Take {
+    fn cardinality(&self, lp: &plan::Plan, cards: &mut Cardinalities) -> Cardinality {
+        cards.compute(lp, self.input).limit(&self.limit)
+    }
+}
+
+impl GetCardinality for plan::TopK {
+    fn cardinality(&self, lp: &plan::Plan, cards: &mut Cardinalities) -> Cardinality {
+        cards.compute(lp, self.input).limit(&self.limit)
+    }
+}
+
+impl GetCardinality for plan::Join {
+    fn cardinality(&self, lp: &plan::Plan, cards: &mut Cardinalities) -> Cardinality {
+        let left = cards.compute(lp, self.left.dataflow);
+        let right = cards.compute(lp, self.right.dataflow);
+        match self.join_kind {
+            plan::JoinKind::Inner => left.product(right),
+            plan::JoinKind::Left => left.product(right.at_least_one()),
+            plan::JoinKind::Right => left.at_least_one().product(right),
+            plan::JoinKind::Full => left.clone().product(right.clone()).sum(left).sum(right),
+        }
+    }
+}
+
+impl GetCardinality for plan::ScanBag {
+    fn cardinality(&self, lp: &plan::Plan, cards: &mut Cardinalities) -> Cardinality {
+        Cardinality::Unbounded
+    }
+}
+
+impl GetCardinality for plan::Union {
+    fn cardinality(&self, lp: &plan::Plan, cards: &mut Cardinalities) -> Cardinality {
+        self.inputs
+            .iter()
+            .map(|df| cards.compute(lp, *df))
+            .reduce(Cardinality::sum)
+            .unwrap_or(Cardinality::AtMost(Size::Const(0)))
+    }
+}
+
+impl GetCardinality for plan::Intersect {
+    fn cardinality(&self, lp: &plan::Plan, cards: &mut Cardinalities) -> Cardinality {
+        self.inputs
+            .iter()
+            .map(|df| cards.compute(lp, *df))
+            .reduce(Cardinality::smallest)
+            .unwrap_or(Cardinality::AtMost(Size::Const(0)))
+    }
+}
+
+impl GetCardinality for plan::Except {
+    fn cardinality(&self, lp: &plan::Plan, cards: &mut Cardinalities) -> Cardinality {
+        self.inputs
+            .first()
+            .map(|df| cards.compute(lp, *df))
+            .unwrap_or(Cardinality::AtMost(Size::Const(0)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::frontend::{Emql, Frontend};
+    use proc_macro2::TokenStream;
+    use quote::quote;
+
+    /// Get the cardinality of the stream collected in a query
+    fn collected(tks: TokenStream) -> Cardinality {
+        let Ok((lp, _)) = Emql::from_tokens(tks) else {
+            panic!("Invalid emql in test")
+        };
+        let cards = Cardinalities::analyse(&lp);
+        let collect = lp
+            .operators
+            .iter()
+            .find_map(|(_, op)| match op {
+                plan::Operator::Collect(c) => Some(c.input),
+                _ => None,
+            })
+            .unwrap();
+        cards.get(collect).clone()
+    }
+
+    #[test]
+    fn scans() {
+        assert_eq!(
+            collected(quote! {
+                table values { x: i32 }
+                query q() { use values |> collect(it) ~> return; }
+            }),
+            Cardinality::Unbounded
+        );
+        assert_eq!(
+            collected(quote! {
+                table values { x: i32 } @ [limit(10) as max_values]
+                query q() { use values |> filter(**x > 3) |> collect(it) ~> return; }
+            }),
+            Cardinality::AtMost(Size::Const(10))
+        );
+    }
+
+    #[test]
+    fn takes() {
+        assert_eq!(
+            collected(quote! {
+                table values { x: i32 } @ [limit(10) as max_values]
+                query q() { use values |> take(3) |> collect(it) ~> return; }
+            }),
+            Cardinality::AtMost(Size::Const(3))
+        );
+        assert!(matches!(
+            collected(quote! {
+                table values { x: i32 }
+                query q(n: usize) { use values |> take(n) |> collect(it) ~> return; }
+            }),
+            Cardinality::AtMost(Size::Expr(_))
+        ));
+    }
+
+    #[test]
+    fn joins() {
+        assert_eq!(
+            collected(quote! {
+                table lefts { x: i32 } @ [limit(3) as max_lefts]
+                table rights { y: i32 } @ [limit(4) as max_rights]
+                query q() {
+                    use lefts |> let all_lefts;
+                    use rights |> let all_rights;
+                    join(use all_lefts [inner cross] use all_rights)
+                        |> collect(it)
+                        ~> return;
+                }
+            }),
+            Cardinality::AtMost(Size::Const(12))
+        );
+    }
+
+    #[test]
+    fn set_operations() {
+        assert_eq!(
+            collected(quote! {
+                table lefts { x: i32 } @ [limit(3) as max_lefts]
+                table rights { x: i32 } @ [limit(4) as max_rights]
+                query q() {
+                    use lefts |> map(x: i32 = *x) |> let all_lefts;
+                    use rights |> map(x: i32 = *x) |> let all_rights;
+                    intersect(use all_rights, all_lefts) |> collect(it) ~> return;
+                }
+            }),
+            Cardinality::AtMost(Size::Const(3))
+        );
+        assert_eq!(
+            collected(quote! {
+                table lefts { x: i32 }
+                table rights { x: i32 } @ [limit(4) as max_rights]
+                query q() {
+                    use lefts |> map(x: i32 = *x) |> let all_lefts;
+                    use rights |> map(x: i32 = *x) |> let all_rights;
+                    except(all use all_lefts, all_rights) |> collect(it) ~> return;
+                }
+            }),
+            Cardinality::Unbounded
+        );
+    }
+
+    #[test]
+    fn warns_on_unbounded_collects() {
+        let Ok((lp, _)) = Emql::from_tokens(quote! {
+            table values { x: i32 }
+            table limited { x: i32 } @ [limit(10) as max_values]
+            query all() { use values |> collect(everything) ~> return; }
+            query some() { use values |> take(5) |> collect(first) ~> return; }
+            query bounded() { use limited |> collect(it) ~> return; }
+        }) else {
+            panic!("Invalid emql in test")
+        };
+        let warnings = unbounded_collects(&lp);
+        assert_eq!(warnings.len(), 1);
+        assert!(warnings.front().unwrap().message().contains("`everything`"));
+    }
+}
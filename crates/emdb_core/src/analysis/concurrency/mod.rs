@@ -4,3 +4,447 @@
 //! Each query can have a conflict with all others (including itself).
 //! - Conflicts occur when one query writes to the same data
 //! - Reading the same data, or reading different sets results in no conflict.
+//!
+//! The read and write sets of each query are determined per table and column:
+//! - Derefs read the columns used after them (see [`super::access`]).
+//! - Scans, range scans and index lookups read the set of rows (and the
+//!   indexed column).
+//! - Updates write the columns they assign.
+//! - `delete_where` and `update_where` scan the table, reading the columns
+//!   used by their predicate and expressions, then delete or update rows.
+//! - Inserts and deletes write the set of rows, conflicting with any other
+//!   access to the table.
+//! - Upserts read the unique column, write the set of rows and the columns
+//!   they assign, and read the whole existing row if they use its values.
+//! - Inserts and updates of `valid_ref` columns read the set of rows of the
+//!   referenced table, deletes read (restrict), delete (cascade) or update
+//!   (set_none) the referencing rows.
+
+use std::collections::{HashMap, HashSet};
+
+use super::access::deref_reads;
+use crate::plan;
+
+/// The parts of a single table accessed by a query
+#[derive(Default)]
+pub struct TableAccess {
+    pub read_cols: HashSet<plan::RecordField>,
+
+    /// The set of rows is read (e.g. by a scan or index lookup)
+    pub read_rows: bool,
+
+    pub write_cols: HashSet<plan::RecordField>,
+
+    /// Rows are inserted or deleted
+    pub write_rows: bool,
+}
+
+impl TableAccess {
+    pub fn writes(&self) -> bool {
+        self.write_rows || !self.write_cols.is_empty()
+    }
+
+    /// Check if a write from this access conflicts with another access
+    fn write_conflicts(&self, other: &TableAccess) -> bool {
+        self.write_rows
+            || self
+                .write_cols
+                .iter()
+                .any(|col| other.read_cols.contains(col) || other.write_cols.contains(col))
+    }
+
+    fn conflicts(&self, other: &TableAccess) -> bool {
+        (self.writes() && self.write_conflicts(other))
+            || (other.writes() && other.write_conflicts(self))
+    }
+}
+
+/// The tables (and parts of tables) accessed by a query
+#[derive(Default)]
+pub struct QueryAccess {
+    pub tables: HashMap<plan::Key<plan::Table>, TableAccess>,
+}
+
+impl QueryAccess {
+    fn table(&mut self, table: plan::Key<plan::Table>) -> &mut TableAccess {
+        self.tables.entry(table).or_default()
+    }
+
+    fn context(&mut self, lp: &plan::Plan, ctx: plan::Key<plan::Context>) {
+        for op in &lp.get_context(ctx).ordering {
+            lp.get_operator(*op).accesses(lp, self);
+        }
+    }
+}
+
+/// The read & write sets of all queries in the plan
+pub struct Conflicts {
+    queries: HashMap<plan::Key<plan::Query>, QueryAccess>,
+}
+
+impl Conflicts {
+    pub fn analyse(lp: &plan::Plan) -> Self {
+        Conflicts {
+            queries: lp
+                .queries
+                .iter()
+                .map(|(key, query)| {
+                    let mut access = QueryAccess::default();
+                    access.context(lp, query.ctx);
+                    (key, access)
+                })
+                .collect(),
+        }
+    }
+
+    pub fn get(&self, query: plan::Key<plan::Query>) -> &QueryAccess {
+        self.queries.get(&query).unwrap()
+    }
+
+    /// The tables on which two queries conflict (sorted by table), the same
+    /// query can be used twice to check if it conflicts with itself.
+    pub fn conflicts(
+        &self,
+        a: plan::Key<plan::Query>,
+        b: plan::Key<plan::Query>,
+    ) -> Vec<plan::Key<plan::Table>> {
+        let (a, b) = (self.get(a), self.get(b));
+        let mut tables = a
+            .tables
+            .iter()
+            .filter_map(|(table, a_access)| {
+                b.tables
+                    .get(table)
+                    .is_some_and(|b_access| a_access.conflicts(b_access))
+                    .then_some(*table)
+            })
+            .collect::<Vec<_>>();
+        tables.sort_by_key(|table| table.arr_idx());
+        tables
+    }
+}
+
+#[enumtrait::store(trait_get_accesses)]
+trait GetAccesses {
+    /// Add the reads and writes of the operator to the query's access
+    fn accesses(&self, lp: &plan::Plan, access: &mut QueryAccess) {}
+}
+
+#[enumtrait::impl_trait(trait_get_accesses for plan::operator_enum)]
+impl GetAccesses for plan::Operator {}
+
+impl GetAccesses for plan::Insert {
+    fn accesses(&self, lp: &plan::Plan, access: &mut QueryAccess) {
+        access.table(self.table).write_rows = true;
+        for r in lp.references_from(self.table) {
+            access.table(r.cons.cons.table).read_rows = true;
+        }
+    }
+}
+
+impl GetAccesses for plan::Upsert {
+    fn accesses(&self, lp: &plan::Plan, access: &mut QueryAccess) {
+        let table = access.table(self.table);
+        table.write_rows = true;
+        table.read_rows = true;
+        table.read_cols.insert(self.field.clone());
+        table.write_cols.extend(self.mapping.keys().cloned());
+        if self.current {
+            table
+                .read_cols
+                .extend(lp.get_table(self.table).columns.keys().cloned());
+        }
+        for r in lp.references_from(self.table) {
+            access.table(r.cons.cons.table).read_rows = true;
+        }
+    }
+}
+
+/// Deleting rows from a table, and applying the on delete actions to the
+/// rows referencing them.
+fn delete_accesses(lp: &plan::Plan, table: plan::Key<plan::Table>, access: &mut QueryAccess) {
+    access.table(table).write_rows = true;
+    for r in lp.delete_references(table) {
+        let table = access.table(r.table);
+        table.read_rows = true;
+        table.read_cols.insert(r.field.clone());
+        match r.cons.cons.on_delete {
+            plan::OnDelete::Restrict => (),
+            plan::OnDelete::Cascade => table.write_rows = true,
+            plan::OnDelete::SetNone => {
+                table.write_cols.insert(r.field.clone());
+            }
+        }
+    }
+}
+
+impl GetAccesses for plan::Delete {
+    fn accesses(&self, lp: &plan::Plan, access: &mut QueryAccess) {
+        delete_accesses(lp, self.table, access);
+    }
+}
+
+impl GetAccesses for plan::DeleteWhere {
+    fn accesses(&self, lp: &plan::Plan, access: &mut QueryAccess) {
+        let table = access.table(self.table);
+        table.read_rows = true;
+        table.read_cols.extend(self.columns.iter().cloned());
+        delete_accesses(lp, self.table, access);
+    }
+}
+
+impl GetAccesses for plan::Update {
+    fn accesses(&self, lp: &plan::Plan, access: &mut QueryAccess) {
+        let table = access.table(self.table);
+        table.write_cols.extend(self.mapping.keys().cloned());
+        table.read_cols.extend(self.current.iter().cloned());
+        for r in lp.references_from(self.table) {
+            if self.mapping.contains_key(r.field) {
+                access.table(r.cons.cons.table).read_rows = true;
+            }
+        }
+    }
+}
+
+impl GetAccesses for plan::UpdateWhere {
+    fn accesses(&self, lp: &plan::Plan, access: &mut QueryAccess) {
+        let table = access.table(self.table);
+        table.read_rows = true;
+        table.read_cols.extend(self.columns.iter().cloned());
+        table.write_cols.extend(self.mapping.keys().cloned());
+        for r in lp.references_from(self.table) {
+            if self.mapping.contains_key(r.field) {
+                access.table(r.cons.cons.table).read_rows = true;
+            }
+        }
+    }
+}
+
+impl GetAccesses for plan::UniqueRef {
+    fn accesses(&self, lp: &plan::Plan, access: &mut QueryAccess) {
+        let table = access.table(self.table);
+        table.read_rows = true;
+        table.read_cols.insert(self.field.clone());
+    }
+}
+
+impl GetAccesses for plan::LookupRefs {
+    fn accesses(&self, lp: &plan::Plan, access: &mut QueryAccess) {
+        let table = access.table(self.table);
+        table.read_rows = true;
+        table.read_cols.insert(self.field.clone());
+    }
+}
+
+impl GetAccesses for plan::ScanRefs {
+    fn accesses(&self, lp: &plan::Plan, access: &mut QueryAccess) {
+        access.table(self.table).read_rows = true;
+    }
+}
+
+impl GetAccesses for plan::RangeRefs {
+    fn accesses(&self, lp: &plan::Plan, access: &mut QueryAccess) {
+        let table = access.table(self.table);
+        table.read_rows = true;
+        table.read_cols.insert(self.field.clone());
+    }
+}
+
+impl GetAccesses for plan::DeRef {
+    fn accesses(&self, lp: &plan::Plan, access: &mut QueryAccess) {
+        access
+            .table(self.table)
+            .read_cols
+            .extend(deref_reads(lp, self));
+    }
+}
+
+impl GetAccesses for plan::GroupBy {
+    fn accesses(&self, lp: &plan::Plan, access: &mut QueryAccess) {
+        access.context(lp, self.inner_ctx);
+    }
+}
+
+impl GetAccesses for plan::Lift {
+    fn accesses(&self, lp: &plan::Plan, access: &mut QueryAccess) {
+        access.context(lp, self.inner_ctx);
+    }
+}
+
+impl GetAccesses for plan::Map {}
+impl GetAccesses for plan::Expand {}
+impl GetAccesses for plan::Fold {}
+impl GetAccesses for plan::Filter {}
+impl GetAccesses for plan::Combine {}
+impl GetAccesses for plan::Sort {}
+impl GetAccesses for plan::Distinct {}
+impl GetAccesses for plan::Window {}
+impl GetAccesses for plan::Assert {}
+impl GetAccesses for plan::Take {}
+impl GetAccesses for plan::Skip {}
+impl GetAccesses for plan::TopK {}
+impl GetAccesses for plan::Collect {}
+impl GetAccesses for plan::Count {}
+impl GetAccesses for plan::Join {}
+impl GetAccesses for plan::Fork {}
+impl GetAccesses for plan::Union {}
+impl GetAccesses for plan::Intersect {}
+impl GetAccesses for plan::Except {}
+impl GetAccesses for plan::Row {}
+impl GetAccesses for plan::ScanBag {}
+impl GetAccesses for plan::Return {}
+impl GetAccesses for plan::Discard {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::frontend::{Emql, Frontend};
+    use quote::quote;
+
+    /// The names of queries that conflict with each query (including itself)
+    fn conflicting(lp: &plan::Plan) -> HashMap<String, HashSet<String>> {
+        let conflicts = Conflicts::analyse(lp);
+        lp.queries
+            .iter()
+            .map(|(a, qa)| {
+                (
+                    qa.name.to_string(),
+                    lp.queries
+                        .iter()
+                        .filter(|(b, _)| !conflicts.conflicts(a, *b).is_empty())
+                        .map(|(_, qb)| qb.name.to_string())
+                        .collect(),
+                )
+            })
+            .collect()
+    }
+
+    fn names(names: &[&str]) -> HashSet<String> {
+        names.iter().map(|n| n.to_string()).collect()
+    }
+
+    #[test]
+    fn reads_and_writes() {
+        let Ok((lp, _)) = Emql::from_tokens(quote! {
+            table people { name: String, age: u8, bio: String }
+            table notes { text: String }
+
+            query name_of(id: ref people) {
+                row(id: ref people = id)
+                    ~> deref(id as person)
+                    ~> map(name: String = person.name.clone())
+                    ~> return;
+            }
+            query age_of(id: ref people) {
+                row(id: ref people = id)
+                    ~> deref(id as person)
+                    ~> map(age: u8 = person.age)
+                    ~> return;
+            }
+            query birthday(id: ref people) {
+                row(id: ref people = id)
+                    ~> deref(id as person)
+                    ~> update(id use age = person.age + 1);
+            }
+            query add_note(text: String) {
+                row(text: String = text) ~> insert(notes as ref note);
+            }
+        }) else {
+            panic!("Invalid emql in test")
+        };
+
+        let conflicts = conflicting(&lp);
+        assert_eq!(conflicts["name_of"], names(&[]));
+        assert_eq!(conflicts["age_of"], names(&["birthday"]));
+        assert_eq!(conflicts["birthday"], names(&["age_of", "birthday"]));
+        assert_eq!(conflicts["add_note"], names(&["add_note"]));
+    }
+
+    #[test]
+    fn row_writes() {
+        let Ok((lp, _)) = Emql::from_tokens(quote! {
+            table people { name: String, age: u8 }
+
+            query count_people() {
+                use people |> count(num) ~> return;
+            }
+            query remove(id: ref people) {
+                row(id: ref people = id) ~> delete(id);
+            }
+            query ages() {
+                use people
+                    |> map(age: u8 = *age)
+                    |> collect(ages)
+                    ~> return;
+            }
+        }) else {
+            panic!("Invalid emql in test")
+        };
+
+        let conflicts = conflicting(&lp);
+        assert_eq!(conflicts["count_people"], names(&["remove"]));
+        assert_eq!(conflicts["ages"], names(&["remove"]));
+        assert_eq!(
+            conflicts["remove"],
+            names(&["count_people", "remove", "ages"])
+        );
+    }
+
+    #[test]
+    fn bulk_writes() {
+        let Ok((lp, _)) = Emql::from_tokens(quote! {
+            table people { name: String, age: u8, score: i64 }
+
+            query names() {
+                use people
+                    |> map(name: String = name.clone())
+                    |> collect(names)
+                    ~> return;
+            }
+            query reward() {
+                update_where(people as rewarded where *age >= 18 set score = score + 1)
+                    ~> return;
+            }
+            query purge() {
+                delete_where(people as removed where *age < 18)
+                    ~> return;
+            }
+        }) else {
+            panic!("Invalid emql in test")
+        };
+
+        let conflicts = conflicting(&lp);
+        assert_eq!(conflicts["names"], names(&["purge"]));
+        assert_eq!(conflicts["reward"], names(&["reward", "purge"]));
+        assert_eq!(conflicts["purge"], names(&["names", "reward", "purge"]));
+    }
+
+    #[test]
+    fn references() {
+        let Ok((lp, _)) = Emql::from_tokens(quote! {
+            table people { name: String }
+            table posts { author: ref people, text: String } @ [valid_ref(author, cascade) as post_author]
+            table notes { about: ref people } @ [valid_ref(about) as note_about]
+
+            query remove(id: ref people) {
+                row(id: ref people = id) ~> delete(id);
+            }
+            query count_posts() {
+                use posts |> count(num) ~> return;
+            }
+            query add_note(about: ref people) {
+                row(about: ref people = about) ~> insert(notes as ref note);
+            }
+        }) else {
+            panic!("Invalid emql in test")
+        };
+
+        let conflicts = conflicting(&lp);
+        assert_eq!(
+            conflicts["remove"],
+            names(&["remove", "count_posts", "add_note"])
+        );
+        assert_eq!(conflicts["count_posts"], names(&["remove"]));
+        assert_eq!(conflicts["add_note"], names(&["remove", "add_note"]));
+    }
+}
@@ -37,11 +37,26 @@ impl GetMuts for plan::Update {
         true
     }
 }
+impl GetMuts for plan::Upsert {
+    fn mutates(&self, lp: &plan::Plan) -> bool {
+        true
+    }
+}
 impl GetMuts for plan::Delete {
     fn mutates(&self, lp: &plan::Plan) -> bool {
         true
     }
 }
+impl GetMuts for plan::DeleteWhere {
+    fn mutates(&self, lp: &plan::Plan) -> bool {
+        true
+    }
+}
+impl GetMuts for plan::UpdateWhere {
+    fn mutates(&self, lp: &plan::Plan) -> bool {
+        true
+    }
+}
 
 impl GetMuts for plan::GroupBy {
     fn mutates(&self, lp: &plan::Plan) -> bool {
@@ -55,7 +70,9 @@ impl GetMuts for plan::Lift {
 }
 
 impl GetMuts for plan::UniqueRef {}
+impl GetMuts for plan::LookupRefs {}
 impl GetMuts for plan::ScanRefs {}
+impl GetMuts for plan::RangeRefs {}
 impl GetMuts for plan::DeRef {}
 impl GetMuts for plan::Map {}
 impl GetMuts for plan::Expand {}
@@ -63,13 +80,20 @@ impl GetMuts for plan::Fold {}
 impl GetMuts for plan::Filter {}
 impl GetMuts for plan::Combine {}
 impl GetMuts for plan::Sort {}
+impl GetMuts for plan::Distinct {}
+impl GetMuts for plan::Window {}
 impl GetMuts for plan::Assert {}
 impl GetMuts for plan::Take {}
+impl GetMuts for plan::Skip {}
+impl GetMuts for plan::TopK {}
 impl GetMuts for plan::Collect {}
 impl GetMuts for plan::Count {}
 impl GetMuts for plan::Join {}
 impl GetMuts for plan::Fork {}
 impl GetMuts for plan::Union {}
+impl GetMuts for plan::Intersect {}
+impl GetMuts for plan::Except {}
 impl GetMuts for plan::Row {}
+impl GetMuts for plan::ScanBag {}
 impl GetMuts for plan::Return {}
 impl GetMuts for plan::Discard {}
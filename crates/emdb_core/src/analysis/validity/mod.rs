@@ -1,7 +1,1140 @@
 //! Checks the validity of plans.
 //! Runs assertions on each.
+//! - Checks the `INV` invariants of [`plan`] for the graph structure, contexts
+//!   and each operator's dataflow.
+//! - Any violation is a bug in a frontend or optimisation, rather than the
+//!   user's code, so errors are reported without spans.
+//! - Expressions are not checked (fields used in expressions are only known to
+//!   rustc).
+use std::collections::{HashMap, LinkedList};
+
+use proc_macro_error2::{Diagnostic, Level};
+
 use crate::plan;
 
-pub fn check_valid(lp: &plan::Plan) {
-    todo!()
+type Errors = LinkedList<Diagnostic>;
+
+pub fn check_valid(lp: &plan::Plan) -> Result<(), Errors> {
+    let mut errors = LinkedList::new();
+    check_graph(lp, &mut errors);
+    for (key, op) in lp.operators.iter() {
+        op.check(key, lp, &mut errors);
+    }
+    if errors.is_empty() {
+        Ok(())
+    } else {
+        Err(errors)
+    }
+}
+
+fn invalid(errors: &mut Errors, message: String) {
+    errors.push_back(
+        Diagnostic::new(Level::Error, format!("Invalid plan: {message}"))
+            .note("This is a bug in emDB, please report it".to_owned()),
+    );
+}
+
+/// Check the dataflow connects operators, and the ordering of operators in each
+/// context respects the dataflow.
+/// - Discards are not in the ordering of a context, and consume after all
+///   ordered operators.
+fn check_graph(lp: &plan::Plan, errors: &mut Errors) {
+    let mut positions = HashMap::new();
+    for (ctx_key, ctx) in lp.contexts.iter() {
+        for (pos, op) in ctx.ordering.iter().chain(ctx.discards.iter()).enumerate() {
+            if lp.operators.get(*op).is_none() {
+                invalid(
+                    errors,
+                    format!("context {} orders a removed operator", ctx_key.arr_idx()),
+                );
+            } else if positions.insert(*op, (ctx_key, pos)).is_some() {
+                invalid(
+                    errors,
+                    format!("operator {} is ordered more than once", op.arr_idx()),
+                );
+            }
+        }
+
+        if let Some(ret) = ctx.returnflow {
+            if !matches!(lp.operators.get(ret), Some(plan::Operator::Return(_))) {
+                invalid(
+                    errors,
+                    format!(
+                        "context {} returns from a non-return operator",
+                        ctx_key.arr_idx()
+                    ),
+                );
+            }
+        }
+        for discard in &ctx.discards {
+            if !matches!(lp.operators.get(*discard), Some(plan::Operator::Discard(_))) {
+                invalid(
+                    errors,
+                    format!(
+                        "context {} has a non-discard operator as a discard",
+                        ctx_key.arr_idx()
+                    ),
+                );
+            }
+        }
+    }
+
+    for (key, _) in lp.operators.iter() {
+        if !positions.contains_key(&key) {
+            invalid(
+                errors,
+                format!("operator {} is not in any context", key.arr_idx()),
+            );
+        }
+    }
+
+    for (df_key, df) in lp.dataflow.iter() {
+        let plan::DataFlow::Conn(plan::DataFlowConn { from, to, .. }) = df else {
+            invalid(
+                errors,
+                format!("dataflow {} is incomplete", df_key.arr_idx()),
+            );
+            continue;
+        };
+        match (positions.get(from), positions.get(to)) {
+            (Some((from_ctx, from_pos)), Some((to_ctx, to_pos))) => {
+                if from_ctx == to_ctx {
+                    if from_pos >= to_pos {
+                        invalid(
+                            errors,
+                            format!(
+                                "dataflow {} goes from operator {} to operator {}, which is ordered before it",
+                                df_key.arr_idx(),
+                                from.arr_idx(),
+                                to.arr_idx()
+                            ),
+                        );
+                    }
+                } else if !lp.get_context(*to_ctx).inflows.contains(&df_key) {
+                    invalid(
+                        errors,
+                        format!(
+                            "dataflow {} connects two contexts, but is not an inflow",
+                            df_key.arr_idx()
+                        ),
+                    );
+                }
+            }
+            _ => invalid(
+                errors,
+                format!("dataflow {} connects a removed operator", df_key.arr_idx()),
+            ),
+        }
+    }
+}
+
+/// Get the data for an input to the operator, checking it is connected
+fn input<'a>(
+    lp: &'a plan::Plan,
+    key: plan::Key<plan::Operator>,
+    df: plan::Key<plan::DataFlow>,
+    errors: &mut Errors,
+) -> Option<&'a plan::Data> {
+    match lp.dataflow.get(df) {
+        Some(plan::DataFlow::Conn(conn)) if conn.to == key => Some(&conn.with),
+        Some(plan::DataFlow::Conn(_)) => {
+            invalid(
+                errors,
+                format!(
+                    "operator {} has input {} that does not flow to it",
+                    key.arr_idx(),
+                    df.arr_idx()
+                ),
+            );
+            None
+        }
+        _ => {
+            invalid(
+                errors,
+                format!(
+                    "operator {} has a missing or incomplete input",
+                    key.arr_idx()
+                ),
+            );
+            None
+        }
+    }
+}
+
+/// Get the data for an output of the operator, checking it is connected
+fn output<'a>(
+    lp: &'a plan::Plan,
+    key: plan::Key<plan::Operator>,
+    df: plan::Key<plan::DataFlow>,
+    errors: &mut Errors,
+) -> Option<&'a plan::Data> {
+    match lp.dataflow.get(df) {
+        Some(plan::DataFlow::Conn(conn)) if conn.from == key => Some(&conn.with),
+        Some(plan::DataFlow::Conn(_)) => {
+            invalid(
+                errors,
+                format!(
+                    "operator {} has output {} that does not flow from it",
+                    key.arr_idx(),
+                    df.arr_idx()
+                ),
+            );
+            None
+        }
+        _ => {
+            invalid(
+                errors,
+                format!(
+                    "operator {} has a missing or incomplete output",
+                    key.arr_idx()
+                ),
+            );
+            None
+        }
+    }
+}
+
+/// A checker for the invariants of a single operator, with its inputs and outputs
+struct OpCheck<'a, 'e> {
+    lp: &'a plan::Plan,
+    key: plan::Key<plan::Operator>,
+    name: &'static str,
+    errors: &'e mut Errors,
+}
+
+impl<'a> OpCheck<'a, '_> {
+    fn error(&mut self, message: &str) {
+        invalid(
+            self.errors,
+            format!("{} (operator {}) {message}", self.name, self.key.arr_idx()),
+        );
+    }
+
+    fn input(&mut self, df: plan::Key<plan::DataFlow>) -> Option<&'a plan::Data> {
+        input(self.lp, self.key, df, self.errors)
+    }
+
+    fn output(&mut self, df: plan::Key<plan::DataFlow>) -> Option<&'a plan::Data> {
+        output(self.lp, self.key, df, self.errors)
+    }
+
+    fn fields(
+        &self,
+        data: &plan::Data,
+    ) -> &'a HashMap<plan::RecordField, plan::Key<plan::ScalarType>> {
+        &self.lp.get_record_type_conc(data.fields).fields
+    }
+
+    fn stream(&mut self, data: &plan::Data, stream: bool, which: &str) {
+        if data.stream != stream {
+            let expected = if stream { "a stream" } else { "a single" };
+            self.error(&format!("{which} must be {expected}"));
+        }
+    }
+
+    fn has_field(&mut self, data: &plan::Data, field: &plan::RecordField, which: &str) {
+        if !self.fields(data).contains_key(field) {
+            self.error(&format!("{which} is missing field `{field}`"));
+        }
+    }
+
+    /// The input and output must be of the same type
+    fn same(&mut self, input: &plan::Data, output: &plan::Data) {
+        if input.stream != output.stream
+            || !plan::record_type_eq(self.lp, &input.fields, &output.fields)
+        {
+            self.error("must have the same input and output type");
+        }
+    }
+
+    /// The output has exactly the fields of the input, and the `added` field
+    fn extends(&mut self, input: &plan::Data, output: &plan::Data, added: &plan::RecordField) {
+        let in_fields = self.fields(input);
+        let out_fields = self.fields(output);
+        if in_fields.contains_key(added) {
+            self.error(&format!("input already has the field `{added}`"));
+        }
+        if !out_fields.contains_key(added)
+            || out_fields.len() != in_fields.len() + 1
+            || in_fields.iter().any(|(field, ty)| {
+                !out_fields
+                    .get(field)
+                    .is_some_and(|out_ty| plan::scalar_type_eq(self.lp, ty, out_ty))
+            })
+        {
+            self.error(&format!(
+                "output must be the input with the field `{added}`"
+            ));
+        }
+    }
+
+    /// The output record contains exactly the fields provided
+    fn exact_fields<'f>(
+        &mut self,
+        data: &plan::Data,
+        fields: impl Iterator<Item = &'f plan::RecordField>,
+    ) {
+        let record = self.fields(data);
+        let mut count = 0;
+        for field in fields {
+            count += 1;
+            if !record.contains_key(field) {
+                self.error(&format!("output is missing field `{field}`"));
+            }
+        }
+        if count != record.len() {
+            self.error("output has fields not produced by the operator");
+        }
+    }
+
+    fn table_ref(
+        &mut self,
+        data: &plan::Data,
+        field: &plan::RecordField,
+        table: plan::Key<plan::Table>,
+    ) {
+        if let Some(ty) = self.fields(data).get(field) {
+            if !matches!(self.lp.get_scalar_type_conc(*ty), plan::ScalarTypeConc::TableRef(t) if *t == table)
+            {
+                self.error(&format!("field `{field}` must be a reference to the table"));
+            }
+        }
+    }
+
+    fn column(
+        &mut self,
+        table: plan::Key<plan::Table>,
+        field: &plan::RecordField,
+    ) -> Option<&'a plan::Column> {
+        let column = self.lp.get_table(table).columns.get(field);
+        if column.is_none() {
+            self.error(&format!("table has no column `{field}`"));
+        }
+        column
+    }
+
+    /// The single field of table references output by a scan
+    fn scanned(
+        &mut self,
+        output: plan::Key<plan::DataFlow>,
+        out_ref: &plan::RecordField,
+        table: plan::Key<plan::Table>,
+    ) {
+        if let Some(out) = self.output(output) {
+            self.stream(out, true, "output");
+            self.exact_fields(out, std::iter::once(out_ref));
+            self.table_ref(out, out_ref, table);
+        }
+    }
+}
+
+#[enumtrait::store(trait_check_valid)]
+pub trait CheckValid {
+    /// Add errors for any invariants of the operator that do not hold
+    fn check(&self, key: plan::Key<plan::Operator>, lp: &plan::Plan, errors: &mut Errors) {}
+}
+
+#[enumtrait::impl_trait(trait_check_valid for plan::operator_enum)]
+impl CheckValid for plan::Operator {}
+
+impl CheckValid for plan::Update {
+    fn check(&self, key: plan::Key<plan::Operator>, lp: &plan::Plan, errors: &mut Errors) {
+        let mut c = OpCheck {
+            lp,
+            key,
+            name: "update",
+            errors,
+        };
+        if let (Some(input), Some(output)) = (c.input(self.input), c.output(self.output)) {
+            c.has_field(input, &self.reference, "input");
+            c.table_ref(input, &self.reference, self.table);
+            c.same(input, output);
+        }
+        for field in self.mapping.keys().chain(&self.current) {
+            c.column(self.table, field);
+        }
+        for field in lp.get_record_type_conc(self.update_type).fields.keys() {
+            c.column(self.table, field);
+        }
+    }
+}
+
+impl CheckValid for plan::Insert {
+    fn check(&self, key: plan::Key<plan::Operator>, lp: &plan::Plan, errors: &mut Errors) {
+        let mut c = OpCheck {
+            lp,
+            key,
+            name: "insert",
+            errors,
+        };
+        if let (Some(input), Some(output)) = (c.input(self.input), c.output(self.output)) {
+            c.stream(output, input.stream, "output");
+            c.exact_fields(output, std::iter::once(&self.out_ref));
+            c.table_ref(output, &self.out_ref, self.table);
+        }
+    }
+}
+
+impl CheckValid for plan::Upsert {
+    fn check(&self, key: plan::Key<plan::Operator>, lp: &plan::Plan, errors: &mut Errors) {
+        let mut c = OpCheck {
+            lp,
+            key,
+            name: "upsert",
+            errors,
+        };
+        if let (Some(input), Some(output)) = (c.input(self.input), c.output(self.output)) {
+            c.stream(output, input.stream, "output");
+            c.exact_fields(output, std::iter::once(&self.out_ref));
+            c.table_ref(output, &self.out_ref, self.table);
+        }
+        c.column(self.table, &self.field);
+        for field in self.mapping.keys() {
+            c.column(self.table, field);
+        }
+        for field in lp.get_record_type_conc(self.update_type).fields.keys() {
+            c.column(self.table, field);
+        }
+    }
+}
+
+impl CheckValid for plan::Delete {
+    fn check(&self, key: plan::Key<plan::Operator>, lp: &plan::Plan, errors: &mut Errors) {
+        let mut c = OpCheck {
+            lp,
+            key,
+            name: "delete",
+            errors,
+        };
+        if let (Some(input), Some(output)) = (c.input(self.input), c.output(self.output)) {
+            c.has_field(input, &self.reference, "input");
+            c.table_ref(input, &self.reference, self.table);
+            c.same(input, output);
+        }
+    }
+}
+
+impl CheckValid for plan::DeleteWhere {
+    fn check(&self, key: plan::Key<plan::Operator>, lp: &plan::Plan, errors: &mut Errors) {
+        let mut c = OpCheck {
+            lp,
+            key,
+            name: "delete_where",
+            errors,
+        };
+        if let Some(output) = c.output(self.output) {
+            c.stream(output, false, "output");
+            c.exact_fields(output, std::iter::once(&self.count));
+        }
+        for field in &self.columns {
+            c.column(self.table, field);
+        }
+    }
+}
+
+impl CheckValid for plan::UpdateWhere {
+    fn check(&self, key: plan::Key<plan::Operator>, lp: &plan::Plan, errors: &mut Errors) {
+        let mut c = OpCheck {
+            lp,
+            key,
+            name: "update_where",
+            errors,
+        };
+        if let Some(output) = c.output(self.output) {
+            c.stream(output, false, "output");
+            c.exact_fields(output, std::iter::once(&self.count));
+        }
+        for field in self.mapping.keys().chain(&self.columns) {
+            c.column(self.table, field);
+        }
+        for field in lp.get_record_type_conc(self.update_type).fields.keys() {
+            c.column(self.table, field);
+        }
+    }
+}
+
+impl CheckValid for plan::UniqueRef {
+    fn check(&self, key: plan::Key<plan::Operator>, lp: &plan::Plan, errors: &mut Errors) {
+        let mut c = OpCheck {
+            lp,
+            key,
+            name: "unique_ref",
+            errors,
+        };
+        if let (Some(input), Some(output)) = (c.input(self.input), c.output(self.output)) {
+            c.has_field(input, &self.from, "input");
+            c.stream(output, input.stream, "output");
+            c.extends(input, output, &self.out);
+            c.table_ref(output, &self.out, self.table);
+        }
+        if let Some(column) = c.column(self.table, &self.field) {
+            if column.cons.unique.is_none() {
+                c.error(&format!("column `{}` must be unique", self.field));
+            }
+        }
+    }
+}
+
+impl CheckValid for plan::LookupRefs {
+    fn check(&self, key: plan::Key<plan::Operator>, lp: &plan::Plan, errors: &mut Errors) {
+        let mut c = OpCheck {
+            lp,
+            key,
+            name: "lookup_refs",
+            errors,
+        };
+        if let (Some(input), Some(output)) = (c.input(self.input), c.output(self.output)) {
+            c.has_field(input, &self.from, "input");
+            c.stream(output, true, "output");
+            c.extends(input, output, &self.out);
+            c.table_ref(output, &self.out, self.table);
+        }
+        if let Some(column) = c.column(self.table, &self.field) {
+            if column.cons.hashed.is_none() {
+                c.error(&format!("column `{}` must be indexed", self.field));
+            }
+        }
+    }
+}
+
+impl CheckValid for plan::ScanRefs {
+    fn check(&self, key: plan::Key<plan::Operator>, lp: &plan::Plan, errors: &mut Errors) {
+        let mut c = OpCheck {
+            lp,
+            key,
+            name: "scan_refs",
+            errors,
+        };
+        c.scanned(self.output, &self.out_ref, self.table);
+    }
+}
+
+impl CheckValid for plan::RangeRefs {
+    fn check(&self, key: plan::Key<plan::Operator>, lp: &plan::Plan, errors: &mut Errors) {
+        let mut c = OpCheck {
+            lp,
+            key,
+            name: "range_refs",
+            errors,
+        };
+        c.scanned(self.output, &self.out_ref, self.table);
+        if let Some(column) = c.column(self.table, &self.field) {
+            if column.cons.ordered.is_none() {
+                c.error(&format!("column `{}` must be ordered", self.field));
+            }
+        }
+    }
+}
+
+impl CheckValid for plan::DeRef {
+    fn check(&self, key: plan::Key<plan::Operator>, lp: &plan::Plan, errors: &mut Errors) {
+        let mut c = OpCheck {
+            lp,
+            key,
+            name: "deref",
+            errors,
+        };
+        if let (Some(input), Some(output)) = (c.input(self.input), c.output(self.output)) {
+            c.has_field(input, &self.reference, "input");
+            c.table_ref(input, &self.reference, self.table);
+            c.stream(output, input.stream, "output");
+            c.extends(input, output, &self.named);
+        }
+    }
+}
+
+impl CheckValid for plan::Map {
+    fn check(&self, key: plan::Key<plan::Operator>, lp: &plan::Plan, errors: &mut Errors) {
+        let mut c = OpCheck {
+            lp,
+            key,
+            name: "map",
+            errors,
+        };
+        if let (Some(input), Some(output)) = (c.input(self.input), c.output(self.output)) {
+            c.stream(output, input.stream, "output");
+            c.exact_fields(output, self.mapping.iter().map(|(field, _)| field));
+        }
+    }
+}
+
+impl CheckValid for plan::Expand {
+    fn check(&self, key: plan::Key<plan::Operator>, lp: &plan::Plan, errors: &mut Errors) {
+        let mut c = OpCheck {
+            lp,
+            key,
+            name: "expand",
+            errors,
+        };
+        if let (Some(input), Some(output)) = (c.input(self.input), c.output(self.output)) {
+            c.stream(output, input.stream, "output");
+            match c
+                .fields(input)
+                .get(&self.field)
+                .map(|ty| lp.get_scalar_type_conc(*ty))
+            {
+                Some(plan::ScalarTypeConc::Record(record)) => {
+                    if !plan::record_type_eq(lp, record, &output.fields) {
+                        c.error(&format!(
+                            "output must be the type of field `{}`",
+                            self.field
+                        ));
+                    }
+                }
+                Some(_) => c.error(&format!("field `{}` must be a record", self.field)),
+                None => c.has_field(input, &self.field, "input"),
+            }
+        }
+    }
+}
+
+impl CheckValid for plan::Fold {
+    fn check(&self, key: plan::Key<plan::Operator>, lp: &plan::Plan, errors: &mut Errors) {
+        let mut c = OpCheck {
+            lp,
+            key,
+            name: "fold",
+            errors,
+        };
+        if let (Some(input), Some(output)) = (c.input(self.input), c.output(self.output)) {
+            c.stream(input, true, "input");
+            c.stream(output, false, "output");
+            c.exact_fields(output, self.fold_fields.iter().map(|(field, _)| field));
+        }
+    }
+}
+
+impl CheckValid for plan::Filter {
+    fn check(&self, key: plan::Key<plan::Operator>, lp: &plan::Plan, errors: &mut Errors) {
+        let mut c = OpCheck {
+            lp,
+            key,
+            name: "filter",
+            errors,
+        };
+        if let (Some(input), Some(output)) = (c.input(self.input), c.output(self.output)) {
+            c.same(input, output);
+        }
+    }
+}
+
+impl CheckValid for plan::Sort {
+    fn check(&self, key: plan::Key<plan::Operator>, lp: &plan::Plan, errors: &mut Errors) {
+        let mut c = OpCheck {
+            lp,
+            key,
+            name: "sort",
+            errors,
+        };
+        if let (Some(input), Some(output)) = (c.input(self.input), c.output(self.output)) {
+            c.stream(input, true, "input");
+            c.same(input, output);
+            for (field, _) in &self.sort_order {
+                c.has_field(input, field, "input");
+            }
+        }
+    }
+}
+
+impl CheckValid for plan::Distinct {
+    fn check(&self, key: plan::Key<plan::Operator>, lp: &plan::Plan, errors: &mut Errors) {
+        let mut c = OpCheck {
+            lp,
+            key,
+            name: "distinct",
+            errors,
+        };
+        if let (Some(input), Some(output)) = (c.input(self.input), c.output(self.output)) {
+            c.stream(input, true, "input");
+            c.same(input, output);
+            for field in &self.fields {
+                c.has_field(input, field, "input");
+            }
+        }
+    }
+}
+
+impl CheckValid for plan::Window {
+    fn check(&self, key: plan::Key<plan::Operator>, lp: &plan::Plan, errors: &mut Errors) {
+        let mut c = OpCheck {
+            lp,
+            key,
+            name: "window",
+            errors,
+        };
+        if let (Some(input), Some(output)) = (c.input(self.input), c.output(self.output)) {
+            c.stream(input, true, "input");
+            c.stream(output, true, "output");
+            for field in self
+                .partition
+                .iter()
+                .chain(self.order.iter().map(|(field, _)| field))
+            {
+                c.has_field(input, field, "input");
+            }
+            for (field, _) in &self.compute {
+                c.has_field(output, field, "output");
+            }
+        }
+    }
+}
+
+impl CheckValid for plan::Assert {
+    fn check(&self, key: plan::Key<plan::Operator>, lp: &plan::Plan, errors: &mut Errors) {
+        let mut c = OpCheck {
+            lp,
+            key,
+            name: "assert",
+            errors,
+        };
+        if let (Some(input), Some(output)) = (c.input(self.input), c.output(self.output)) {
+            c.same(input, output);
+        }
+    }
+}
+
+impl CheckValid for plan::Collect {
+    fn check(&self, key: plan::Key<plan::Operator>, lp: &plan::Plan, errors: &mut Errors) {
+        let mut c = OpCheck {
+            lp,
+            key,
+            name: "collect",
+            errors,
+        };
+        if let (Some(input), Some(output)) = (c.input(self.input), c.output(self.output)) {
+            c.stream(input, true, "input");
+            c.stream(output, false, "output");
+            c.exact_fields(output, std::iter::once(&self.into));
+            if let Some(ty) = c.fields(output).get(&self.into) {
+                let is_bag = matches!(
+                    lp.get_scalar_type_conc(*ty),
+                    plan::ScalarTypeConc::Bag(record) if plan::record_type_eq(lp, record, &input.fields)
+                );
+                if !is_bag {
+                    c.error(&format!("field `{}` must be a bag of the input", self.into));
+                }
+            }
+        }
+    }
+}
+
+impl CheckValid for plan::Skip {
+    fn check(&self, key: plan::Key<plan::Operator>, lp: &plan::Plan, errors: &mut Errors) {
+        let mut c = OpCheck {
+            lp,
+            key,
+            name: "skip",
+            errors,
+        };
+        if let (Some(input), Some(output)) = (c.input(self.input), c.output(self.output)) {
+            c.stream(input, true, "input");
+            c.same(input, output);
+            match &self.from {
+                plan::SkipFrom::Offset(_) => (),
+                plan::SkipFrom::Key { sort_order, .. } => {
+                    for (field, _) in sort_order {
+                        c.has_field(input, field, "input");
+                    }
+                }
+                plan::SkipFrom::Row { field, .. } => c.has_field(input, field, "input"),
+            }
+        }
+    }
+}
+
+impl CheckValid for plan::Take {
+    fn check(&self, key: plan::Key<plan::Operator>, lp: &plan::Plan, errors: &mut Errors) {
+        let mut c = OpCheck {
+            lp,
+            key,
+            name: "take",
+            errors,
+        };
+        if let (Some(input), Some(output)) = (c.input(self.input), c.output(self.output)) {
+            c.stream(input, true, "input");
+            c.same(input, output);
+        }
+    }
+}
+
+impl CheckValid for plan::TopK {
+    fn check(&self, key: plan::Key<plan::Operator>, lp: &plan::Plan, errors: &mut Errors) {
+        let mut c = OpCheck {
+            lp,
+            key,
+            name: "top_k",
+            errors,
+        };
+        if let (Some(input), Some(output)) = (c.input(self.input), c.output(self.output)) {
+            c.stream(input, true, "input");
+            c.same(input, output);
+            for (field, _) in &self.sort_order {
+                c.has_field(input, field, "input");
+            }
+        }
+    }
+}
+
+impl CheckValid for plan::Count {
+    fn check(&self, key: plan::Key<plan::Operator>, lp: &plan::Plan, errors: &mut Errors) {
+        let mut c = OpCheck {
+            lp,
+            key,
+            name: "count",
+            errors,
+        };
+        if let (Some(input), Some(output)) = (c.input(self.input), c.output(self.output)) {
+            c.stream(input, true, "input");
+            c.stream(output, false, "output");
+            c.exact_fields(output, std::iter::once(&self.out_field));
+        }
+    }
+}
+
+impl CheckValid for plan::Join {
+    fn check(&self, key: plan::Key<plan::Operator>, lp: &plan::Plan, errors: &mut Errors) {
+        let mut c = OpCheck {
+            lp,
+            key,
+            name: "join",
+            errors,
+        };
+        let left = c.input(self.left.dataflow);
+        let right = c.input(self.right.dataflow);
+        if let (Some(left), Some(right), Some(output)) = (left, right, c.output(self.output)) {
+            c.stream(left, true, "left input");
+            c.stream(right, true, "right input");
+            c.stream(output, true, "output");
+            c.exact_fields(
+                output,
+                [&self.left.identifier, &self.right.identifier].into_iter(),
+            );
+            if let plan::MatchKind::Equi {
+                left_field,
+                right_field,
+            } = &self.match_kind
+            {
+                c.has_field(left, left_field, "left input");
+                c.has_field(right, right_field, "right input");
+            }
+        }
+    }
+}
+
+impl CheckValid for plan::Combine {
+    fn check(&self, key: plan::Key<plan::Operator>, lp: &plan::Plan, errors: &mut Errors) {
+        let mut c = OpCheck {
+            lp,
+            key,
+            name: "combine",
+            errors,
+        };
+        if let (Some(input), Some(output)) = (c.input(self.input), c.output(self.output)) {
+            c.stream(input, true, "input");
+            c.stream(output, false, "output");
+        }
+    }
+}
+
+impl CheckValid for plan::GroupBy {
+    fn check(&self, key: plan::Key<plan::Operator>, lp: &plan::Plan, errors: &mut Errors) {
+        let mut c = OpCheck {
+            lp,
+            key,
+            name: "groupby",
+            errors,
+        };
+        if let (Some(input), Some(_)) = (c.input(self.input), c.output(self.output)) {
+            c.stream(input, true, "input");
+            c.has_field(input, &self.group_by, "input");
+        }
+        c.output(self.stream_in);
+        if !lp
+            .get_context(self.inner_ctx)
+            .inflows
+            .contains(&self.stream_in)
+        {
+            c.error("inner context must take the grouped stream as an inflow");
+        }
+    }
+}
+
+impl CheckValid for plan::Lift {
+    fn check(&self, key: plan::Key<plan::Operator>, lp: &plan::Plan, errors: &mut Errors) {
+        let mut c = OpCheck {
+            lp,
+            key,
+            name: "lift",
+            errors,
+        };
+        c.input(self.input);
+        c.output(self.output);
+    }
+}
+
+impl CheckValid for plan::Fork {
+    fn check(&self, key: plan::Key<plan::Operator>, lp: &plan::Plan, errors: &mut Errors) {
+        let mut c = OpCheck {
+            lp,
+            key,
+            name: "fork",
+            errors,
+        };
+        if let Some(input) = c.input(self.input) {
+            for out in &self.outputs {
+                if let Some(output) = c.output(*out) {
+                    c.same(input, output);
+                }
+            }
+        }
+    }
+}
+
+impl CheckValid for plan::Union {
+    fn check(&self, key: plan::Key<plan::Operator>, lp: &plan::Plan, errors: &mut Errors) {
+        let mut c = OpCheck {
+            lp,
+            key,
+            name: "union",
+            errors,
+        };
+        if let Some(output) = c.output(self.output) {
+            c.stream(output, true, "output");
+            for df in &self.inputs {
+                if let Some(input) = c.input(*df) {
+                    c.same(input, output);
+                }
+            }
+        }
+    }
+}
+
+impl CheckValid for plan::Intersect {
+    fn check(&self, key: plan::Key<plan::Operator>, lp: &plan::Plan, errors: &mut Errors) {
+        let mut c = OpCheck {
+            lp,
+            key,
+            name: "intersect",
+            errors,
+        };
+        if let Some(output) = c.output(self.output) {
+            c.stream(output, true, "output");
+            for df in &self.inputs {
+                if let Some(input) = c.input(*df) {
+                    c.same(input, output);
+                }
+            }
+        }
+    }
+}
+
+impl CheckValid for plan::Except {
+    fn check(&self, key: plan::Key<plan::Operator>, lp: &plan::Plan, errors: &mut Errors) {
+        let mut c = OpCheck {
+            lp,
+            key,
+            name: "except",
+            errors,
+        };
+        if let Some(output) = c.output(self.output) {
+            c.stream(output, true, "output");
+            for df in &self.inputs {
+                if let Some(input) = c.input(*df) {
+                    c.same(input, output);
+                }
+            }
+        }
+    }
+}
+
+impl CheckValid for plan::Row {
+    fn check(&self, key: plan::Key<plan::Operator>, lp: &plan::Plan, errors: &mut Errors) {
+        let mut c = OpCheck {
+            lp,
+            key,
+            name: "row",
+            errors,
+        };
+        if let Some(output) = c.output(self.output) {
+            c.stream(output, false, "output");
+            c.exact_fields(output, self.fields.iter().map(|(field, _)| field));
+        }
+    }
+}
+
+impl CheckValid for plan::ScanBag {
+    fn check(&self, key: plan::Key<plan::Operator>, lp: &plan::Plan, errors: &mut Errors) {
+        let mut c = OpCheck {
+            lp,
+            key,
+            name: "scan bag",
+            errors,
+        };
+        if let Some(output) = c.output(self.output) {
+            c.stream(output, true, "output");
+        }
+    }
+}
+
+impl CheckValid for plan::Return {
+    fn check(&self, key: plan::Key<plan::Operator>, lp: &plan::Plan, errors: &mut Errors) {
+        input(lp, key, self.input, errors);
+    }
+}
+
+impl CheckValid for plan::Discard {
+    fn check(&self, key: plan::Key<plan::Operator>, lp: &plan::Plan, errors: &mut Errors) {
+        input(lp, key, self.input, errors);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::frontend::{Emql, Frontend};
+    use proc_macro2::{Ident, Span};
+    use quote::quote;
+
+    fn valid_plan() -> plan::Plan {
+        let Ok((lp, _)) = Emql::from_tokens(quote! {
+            table values { x: i32 }
+            query q() {
+                use values
+                    |> map(y: i32 = *x)
+                    |> filter(*y > 3)
+                    |> collect(it)
+                    ~> return;
+            }
+        }) else {
+            panic!("Invalid emql in test")
+        };
+        lp
+    }
+
+    fn find_op(
+        lp: &plan::Plan,
+        pred: impl Fn(&plan::Operator) -> bool,
+    ) -> plan::Key<plan::Operator> {
+        lp.operators
+            .iter()
+            .find_map(|(key, op)| pred(op).then_some(key))
+            .unwrap()
+    }
+
+    /// Check the plan is invalid, and get the messages of its diagnostics
+    fn messages(lp: &plan::Plan) -> Vec<String> {
+        let Err(errors) = check_valid(lp) else {
+            panic!("Plan is valid")
+        };
+        errors.iter().map(|e| e.message().to_owned()).collect()
+    }
+
+    fn reported(messages: &[String], message: &str) -> bool {
+        messages
+            .iter()
+            .any(|m| m == &format!("Invalid plan: {message}"))
+    }
+
+    #[test]
+    fn frontend_plan_is_valid() {
+        assert!(check_valid(&valid_plan()).is_ok());
+    }
+
+    #[test]
+    fn mismatched_dataflow_type() {
+        let mut lp = valid_plan();
+        let map = find_op(&lp, |op| matches!(op, plan::Operator::Map(_)));
+        let filter = find_op(&lp, |op| matches!(op, plan::Operator::Filter(_)));
+        let plan::Operator::Map(plan::Map { input, .. }) = lp.get_operator(map) else {
+            unreachable!()
+        };
+        let plan::Operator::Filter(plan::Filter { output, .. }) = lp.get_operator(filter) else {
+            unreachable!()
+        };
+        let (input, output) = (*input, *output);
+
+        // the filter outputs the fields of the map's input
+        let scanned = lp.get_dataflow(input).get_conn().with.fields;
+        let plan::DataFlow::Conn(conn) = lp.get_mut_dataflow(output) else {
+            unreachable!()
+        };
+        conn.with.fields = scanned;
+
+        assert!(reported(
+            &messages(&lp),
+            &format!(
+                "filter (operator {}) must have the same input and output type",
+                filter.arr_idx()
+            )
+        ));
+    }
+
+    #[test]
+    fn scan_with_extra_fields() {
+        let mut lp = valid_plan();
+        let scan = find_op(&lp, |op| matches!(op, plan::Operator::ScanRefs(_)));
+        let plan::Operator::ScanRefs(plan::ScanRefs { output, .. }) = lp.get_operator(scan) else {
+            unreachable!()
+        };
+        let output = *output;
+
+        // the reference output by the scan is no longer its only field
+        let mut record = lp
+            .get_record_type_conc(lp.get_dataflow(output).get_conn().with.fields)
+            .clone();
+        let ref_type = *record.fields.values().next().unwrap();
+        record.fields.insert(
+            plan::RecordField::User(Ident::new("extra", Span::call_site())),
+            ref_type,
+        );
+        let extended = lp.record_types.insert(plan::ConcRef::Conc(record));
+        let plan::DataFlow::Conn(conn) = lp.get_mut_dataflow(output) else {
+            unreachable!()
+        };
+        conn.with.fields = extended;
+
+        assert!(reported(
+            &messages(&lp),
+            &format!(
+                "scan_refs (operator {}) output has fields not produced by the operator",
+                scan.arr_idx()
+            )
+        ));
+    }
+
+    #[test]
+    fn misordered_context() {
+        let mut lp = valid_plan();
+        let (_, query) = lp.queries.iter().next().unwrap();
+        let ctx = query.ctx;
+        let map = find_op(&lp, |op| matches!(op, plan::Operator::Map(_)));
+        let filter = find_op(&lp, |op| matches!(op, plan::Operator::Filter(_)));
+        let plan::Operator::Map(plan::Map { output, .. }) = lp.get_operator(map) else {
+            unreachable!()
+        };
+        let between = *output;
+
+        // the filter is ordered before the map it consumes from
+        let ordering = &mut lp.get_mut_context(ctx).ordering;
+        let map_pos = ordering.iter().position(|op| *op == map).unwrap();
+        let filter_pos = ordering.iter().position(|op| *op == filter).unwrap();
+        ordering.swap(map_pos, filter_pos);
+        let errors = messages(&lp);
+        assert!(reported(
+            &errors,
+            &format!(
+                "dataflow {} goes from operator {} to operator {}, which is ordered before it",
+                between.arr_idx(),
+                map.arr_idx(),
+                filter.arr_idx()
+            )
+        ));
+
+        // the map is no longer ordered in any context
+        lp.get_mut_context(ctx).ordering.retain(|op| *op != map);
+        assert!(reported(
+            &messages(&lp),
+            &format!("operator {} is not in any context", map.arr_idx())
+        ));
+    }
 }
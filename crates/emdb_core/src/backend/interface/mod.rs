@@ -2,7 +2,11 @@
 //! - Allows for any return types, any table key types.
 //! - Assumes a window-like (`datastore`, `database` wraps `&mut datastore`) pattern.
 
-use crate::{analysis::mutability::GetMuts, plan, utils::on_off::on_off};
+use crate::{
+    analysis::mutability::GetMuts,
+    plan,
+    utils::{misc::singlelist, on_off::on_off},
+};
 use combi::{
     tokens::{
         basic::{collectuntil, peekpunct, recovgroup},
@@ -14,6 +18,7 @@ use combi::{
 };
 use namer::InterfaceNamer;
 use proc_macro2::{Delimiter, TokenStream};
+use proc_macro_error2::{Diagnostic, Level};
 use quote::{quote, ToTokens};
 use quote_debug::Tokens;
 use syn::{Ident, Type};
@@ -84,10 +89,27 @@ impl super::EMDBBackend for Interface {
         let db_lifetime = quote! {'db};
         let qy_lifetime = quote! {'qy};
 
+        // bags are records chosen by each implementation, so cannot be named
+        // in the trait
+        for (_, plan::Query { name, ctx, .. }) in &plan.queries {
+            if let Some((param, _)) = plan
+                .get_context(*ctx)
+                .params
+                .iter()
+                .find(|(_, ty)| contains_bag(plan, *ty))
+            {
+                return Err(singlelist(Diagnostic::spanned(
+                    param.span(),
+                    Level::Error,
+                    format!("Query `{name}` has a bag parameter `{param}`, which cannot be used in an interface"),
+                )));
+            }
+        }
+
         let query_code = plan
             .queries
             .iter()
-            .map(|(_, plan::Query { name, ctx })| {
+            .map(|(_, plan::Query { attrs, name, ctx })| {
                 let mut_tk = if plan.get_context(*ctx).mutates(plan) {
                     quote!(mut)
                 } else {
@@ -100,6 +122,7 @@ impl super::EMDBBackend for Interface {
                 });
 
                 quote! {
+                    #(#attrs)*
                     fn #name<#qy_lifetime>(&#qy_lifetime #mut_tk self, #(#params),* ) -> impl #trait_any;
                 }
             });
@@ -159,6 +182,17 @@ impl super::EMDBBackend for Interface {
 pub mod namer;
 pub mod public; 
 
+fn contains_bag(lp: &plan::Plan, key: plan::Key<plan::ScalarType>) -> bool {
+    match lp.get_scalar_type_conc(key) {
+        plan::ScalarTypeConc::Bag(_) => true,
+        plan::ScalarTypeConc::Option(s) => contains_bag(lp, *s),
+        plan::ScalarTypeConc::Composite { inner, .. } => {
+            inner.iter().any(|(_, s)| contains_bag(lp, *s))
+        }
+        _ => false,
+    }
+}
+
 fn generate_parameter_type(
     lp: &plan::Plan,
     key: plan::Key<plan::ScalarType>,
@@ -180,6 +214,13 @@ fn generate_parameter_type(
             type_context: plan::TypeContext::Query,
             ty,
         } => ty.to_token_stream(),
+        plan::ScalarTypeConc::Option(s) => {
+            let inner = generate_parameter_type(lp, *s, namer);
+            quote!(Option<#inner>)
+        }
+        plan::ScalarTypeConc::Composite { ty, inner } => plan::substitute_composite(ty, inner, |s| {
+            generate_parameter_type(lp, s, namer).into_token_stream()
+        }),
         _ => unreachable!("Only rust types and table references are allowed in query parameters"),
     }
     .into()
@@ -11,6 +11,12 @@ fn get_exposed_keys_scalar<'imm>(lp: &'imm plan::Plan, key: plan::Key<plan::Scal
     match lp.get_scalar_type_conc(key) {
         plan::ScalarTypeConc::TableRef(t) => {tableset.insert(plan::ImmKey::new(*t, lp));},
         plan::ScalarTypeConc::Bag(r) | plan::ScalarTypeConc::Record(r) => get_exposed_keys_record(lp, *r, tableset),
+        plan::ScalarTypeConc::Option(s) => get_exposed_keys_scalar(lp, *s, tableset),
+        plan::ScalarTypeConc::Composite { inner, .. } => {
+            for (_, s) in inner {
+                get_exposed_keys_scalar(lp, *s, tableset)
+            }
+        }
         _ => (),
     }
 }
@@ -216,6 +216,10 @@ impl GetFeature<PlanEdge> for plan::ScalarType {
                     plan::ScalarTypeConc::TableRef(t) => edges.push(ScalarToTable {scalar: self_key, table: *t}.into()),
                     plan::ScalarTypeConc::TableGet { table, field } => edges.push(ScalarGetTable { scalar: self_key, table: *table, field: field.to_string() }.into()),
                     plan::ScalarTypeConc::Bag(r) | plan::ScalarTypeConc::Record(r) => edges.push(ScalarToRecord {scalar: self_key, record: *r}.into()),
+                    plan::ScalarTypeConc::Option(s) => edges.push(ScalarToScalar {from: self_key, to: *s}.into()),
+                    plan::ScalarTypeConc::Composite { inner, .. } => for (_, s) in inner {
+                        edges.push(ScalarToScalar {from: self_key, to: *s}.into())
+                    },
                     plan::ScalarTypeConc::Rust{..} => (),
                 },
             }
@@ -289,24 +293,54 @@ impl GetExtraNodeEdges for plan::Insert {
     }
 }
 
+impl GetExtraNodeEdges for plan::Upsert {
+    fn get_extra_features(&self, self_key: plan::Key<plan::Operator>, edges: &mut Vec<PlanEdge>, config: &DisplayConfig) {
+        edges.push(TableAccess { op: self_key, table: self.table }.into());
+    }
+}
+
 impl GetExtraNodeEdges for plan::Delete {
     fn get_extra_features(&self, self_key: plan::Key<plan::Operator>, edges: &mut Vec<PlanEdge>, config: &DisplayConfig) {
         edges.push(TableAccess { op: self_key, table: self.table }.into());
     }
 }
 
+impl GetExtraNodeEdges for plan::DeleteWhere {
+    fn get_extra_features(&self, self_key: plan::Key<plan::Operator>, edges: &mut Vec<PlanEdge>, config: &DisplayConfig) {
+        edges.push(TableAccess { op: self_key, table: self.table }.into());
+    }
+}
+
+impl GetExtraNodeEdges for plan::UpdateWhere {
+    fn get_extra_features(&self, self_key: plan::Key<plan::Operator>, edges: &mut Vec<PlanEdge>, config: &DisplayConfig) {
+        edges.push(TableAccess { op: self_key, table: self.table }.into());
+    }
+}
+
 impl GetExtraNodeEdges for plan::UniqueRef {
     fn get_extra_features(&self, self_key: plan::Key<plan::Operator>, edges: &mut Vec<PlanEdge>, config: &DisplayConfig) {
         edges.push(TableAccess { op: self_key, table: self.table }.into());
     }
 }
 
+impl GetExtraNodeEdges for plan::LookupRefs {
+    fn get_extra_features(&self, self_key: plan::Key<plan::Operator>, edges: &mut Vec<PlanEdge>, config: &DisplayConfig) {
+        edges.push(TableAccess { op: self_key, table: self.table }.into());
+    }
+}
+
 impl GetExtraNodeEdges for plan::ScanRefs {
     fn get_extra_features(&self, self_key: plan::Key<plan::Operator>, edges: &mut Vec<PlanEdge>, config: &DisplayConfig) {
         edges.push(TableAccess { op: self_key, table: self.table }.into());
     }
 }
 
+impl GetExtraNodeEdges for plan::RangeRefs {
+    fn get_extra_features(&self, self_key: plan::Key<plan::Operator>, edges: &mut Vec<PlanEdge>, config: &DisplayConfig) {
+        edges.push(TableAccess { op: self_key, table: self.table }.into());
+    }
+}
+
 impl GetExtraNodeEdges for plan::DeRef {
     fn get_extra_features(&self, self_key: plan::Key<plan::Operator>, edges: &mut Vec<PlanEdge>, config: &DisplayConfig) {
         edges.push(TableAccess { op: self_key, table: self.table }.into());
@@ -331,14 +365,21 @@ impl GetExtraNodeEdges for plan::Fold {}
 impl GetExtraNodeEdges for plan::Filter {}
 impl GetExtraNodeEdges for plan::Combine {}
 impl GetExtraNodeEdges for plan::Sort {}
+impl GetExtraNodeEdges for plan::Distinct {}
+impl GetExtraNodeEdges for plan::Window {}
 impl GetExtraNodeEdges for plan::Count {}
 impl GetExtraNodeEdges for plan::Assert {}
 impl GetExtraNodeEdges for plan::Collect {}
 impl GetExtraNodeEdges for plan::Take {}
+impl GetExtraNodeEdges for plan::Skip {}
+impl GetExtraNodeEdges for plan::TopK {}
 impl GetExtraNodeEdges for plan::Join {}
 impl GetExtraNodeEdges for plan::Fork {}
 impl GetExtraNodeEdges for plan::Union {}
+impl GetExtraNodeEdges for plan::Intersect {}
+impl GetExtraNodeEdges for plan::Except {}
 impl GetExtraNodeEdges for plan::Row {}
+impl GetExtraNodeEdges for plan::ScanBag {}
 impl GetExtraNodeEdges for plan::Return {}
 impl GetExtraNodeEdges for plan::Discard {}
 
@@ -138,7 +138,9 @@ impl StyleableNode for plan::ScalarType {
                 plan::ScalarTypeConc::TableRef(r) => "ref".to_owned(),
                 plan::ScalarTypeConc::Bag(_) => "bag".to_owned(),
                 plan::ScalarTypeConc::Record(_) => "rec".to_owned(),
+                plan::ScalarTypeConc::Option(_) => "option".to_owned(),
                 plan::ScalarTypeConc::Rust{ type_context: query_context, ty } => format!("{}", ty.to_token_stream()),
+                plan::ScalarTypeConc::Composite { ty, .. } => format!("{}", ty.to_token_stream()),
                 plan::ScalarTypeConc::TableGet { table, field } => format!("get {}.{field}", &plan.get_table(*table).name),
             }),
             plan::ConcRef::Ref(_) => dot::LabelText::label(""),
@@ -295,24 +297,54 @@ impl OperatorDescription for plan::Insert {
     }
 }
 
+impl OperatorDescription for plan::Upsert {
+    fn description(&self,plan: &plan::Plan) -> String {
+        format!("Upsert")
+    }
+}
+
 impl OperatorDescription for plan::Delete {
     fn description(&self,plan: &plan::Plan) -> String {
         format!("Delete")
     }
 }
 
+impl OperatorDescription for plan::DeleteWhere {
+    fn description(&self,plan: &plan::Plan) -> String {
+        format!("Delete where {} as {}", self.predicate.to_token_stream(), self.count)
+    }
+}
+
+impl OperatorDescription for plan::UpdateWhere {
+    fn description(&self,plan: &plan::Plan) -> String {
+        format!("Update where {} as {}", self.predicate.to_token_stream(), self.count)
+    }
+}
+
 impl OperatorDescription for plan::UniqueRef {
     fn description(&self,plan: &plan::Plan) -> String {
         format!("UniqueRef")
     }
 }
 
+impl OperatorDescription for plan::LookupRefs {
+    fn description(&self,plan: &plan::Plan) -> String {
+        format!("LookupRef")
+    }
+}
+
 impl OperatorDescription for plan::ScanRefs {
     fn description(&self,plan: &plan::Plan) -> String {
         format!("ScanRef")
     }
 }
 
+impl OperatorDescription for plan::RangeRefs {
+    fn description(&self,plan: &plan::Plan) -> String {
+        format!("RangeRef")
+    }
+}
+
 impl OperatorDescription for plan::DeRef {
     fn description(&self,plan: &plan::Plan) -> String {
         format!("DeRef")
@@ -361,6 +393,18 @@ impl OperatorDescription for plan::Sort {
     }
 }
 
+impl OperatorDescription for plan::Distinct {
+    fn description(&self,plan: &plan::Plan) -> String {
+        format!("Distinct")
+    }
+}
+
+impl OperatorDescription for plan::Window {
+    fn description(&self,plan: &plan::Plan) -> String {
+        format!("Window")
+    }
+}
+
 impl OperatorDescription for plan::Assert {
     fn description(&self,plan: &plan::Plan) -> String {
         format!("Assert")
@@ -379,6 +423,22 @@ impl OperatorDescription for plan::Take {
     }
 }
 
+impl OperatorDescription for plan::Skip {
+    fn description(&self,plan: &plan::Plan) -> String {
+        match &self.from {
+            plan::SkipFrom::Offset(_) => format!("Skip"),
+            plan::SkipFrom::Key { .. } => format!("Skip After Key"),
+            plan::SkipFrom::Row { field, .. } => format!("Skip After Row with {field}"),
+        }
+    }
+}
+
+impl OperatorDescription for plan::TopK {
+    fn description(&self,plan: &plan::Plan) -> String {
+        format!("TopK")
+    }
+}
+
 impl OperatorDescription for plan::Join {
     fn description(&self,plan: &plan::Plan) -> String {
         format!("Join")
@@ -409,12 +469,36 @@ impl OperatorDescription for plan::Union {
     }
 }
 
+impl OperatorDescription for plan::Intersect {
+    fn description(&self,plan: &plan::Plan) -> String {
+        match self.semantics {
+            plan::SetSemantics::Set => format!("Intersect"),
+            plan::SetSemantics::Bag => format!("Intersect All"),
+        }
+    }
+}
+
+impl OperatorDescription for plan::Except {
+    fn description(&self,plan: &plan::Plan) -> String {
+        match self.semantics {
+            plan::SetSemantics::Set => format!("Except"),
+            plan::SetSemantics::Bag => format!("Except All"),
+        }
+    }
+}
+
 impl OperatorDescription for plan::Row {
     fn description(&self,plan: &plan::Plan) -> String {
         format!("Row")
     }
 }
 
+impl OperatorDescription for plan::ScanBag {
+    fn description(&self,plan: &plan::Plan) -> String {
+        format!("Scan bag {}", self.bag)
+    }
+}
+
 impl OperatorDescription for plan::Return {
     fn description(&self,plan: &plan::Plan) -> String {
         format!("Return")
@@ -0,0 +1,169 @@
+//! # Migrations
+//! A `migration from <previous datastore> to <impl>` generates an
+//! `impl TryFrom<previous datastore> for Datastore`, which moves the rows of
+//! each table of the previous datastore (another version of the schema,
+//! generated by this backend) into a new datastore.
+//! - Each previous table is consumed, moving out its rows by value (see
+//!   [`pulpit::column::ColumnTake`]) to insert new rows through the new table's
+//!   window, so column data is never copied.
+//! - Migrated columns are computed by the given expressions, from a borrow of
+//!   the previous row, the other columns are moved from the previous row's
+//!   column of the same name.
+//! - Inserts that violate the new table's constraints fail the migration with a
+//!   `MigrationError`, with the insert's error as its source.
+//!
+//! References are keys, which are not kept when rows are moved, so tables with
+//! reference columns cannot be migrated.
+
+use super::{namer::SerializedNamer, tables::GeneratedInfo};
+use crate::{
+    backend::interface::{namer::InterfaceNamer, InterfaceTrait},
+    plan,
+};
+use proc_macro2::{Ident, TokenStream};
+use pulpit::gen::namer::CodeNamer;
+use quote::quote;
+
+pub fn generate_migrations(
+    lp: &plan::Plan,
+    gen_info: &GeneratedInfo,
+    interface_trait: &Option<InterfaceTrait>,
+    namer: &SerializedNamer,
+    impl_name: &Ident,
+) -> TokenStream {
+    let SerializedNamer {
+        pulpit:
+            CodeNamer {
+                mod_insert,
+                mod_insert_struct_insert,
+                struct_window_method_insert,
+                struct_window_method_commit,
+                struct_table_method_take,
+                mod_insert_struct_insert_method_borrow,
+                ..
+            },
+        struct_datastore,
+        mod_tables,
+        struct_migration_error,
+        migration_row,
+        migration_taken,
+        interface:
+            InterfaceNamer {
+                trait_datastore,
+                trait_datastore_method_new,
+                ..
+            },
+        ..
+    } = namer;
+
+    let new_datastore = if let Some(InterfaceTrait { name }) = interface_trait {
+        quote!(<Self as super::#name::#trait_datastore>::#trait_datastore_method_new())
+    } else {
+        quote!(Self::#trait_datastore_method_new())
+    };
+
+    let migrations = lp
+        .migrations
+        .iter()
+        .filter(|(_, migration)| &migration.to == impl_name)
+        .map(|(_, plan::Migration { from, tables, .. })| {
+            let moves = lp.tables.iter().filter_map(|(key, table)| {
+                let (from_table, row, columns) = match tables.get(&key) {
+                    Some(plan::TableMigration::Empty) => return None,
+                    Some(plan::TableMigration::Rows { from, row, columns }) => {
+                        (from, row.as_ref().unwrap_or(migration_row), Some(columns))
+                    }
+                    None => (&table.name, migration_row, None),
+                };
+
+                let table_name = namer.table_internal_name(lp, key);
+                let table_str = table.name.to_string();
+                let mut fields = table.columns.keys().collect::<Vec<_>>();
+                fields.sort_by_key(|field| field.to_string());
+                // migrated columns are computed first, so the borrow of the
+                // previous row ends before the other columns are moved out
+                let (migrated, moved): (Vec<_>, Vec<_>) = fields
+                    .into_iter()
+                    .partition(|field| columns.is_some_and(|columns| columns.contains_key(*field)));
+                let migrated = migrated.into_iter().map(|field| {
+                    let name = namer.transform_field_name(field);
+                    let expr = &columns.unwrap()[field];
+                    quote!(#name: #expr)
+                });
+                let moved = moved.into_iter().map(|field| {
+                    let name = namer.transform_field_name(field);
+                    quote!(#name: #migration_taken.#name)
+                });
+
+                let insert = quote! {
+                    window.#struct_window_method_insert({
+                        let #row = #migration_taken.#mod_insert_struct_insert_method_borrow();
+                        #mod_tables::#table_name::#mod_insert::#mod_insert_struct_insert {
+                            #(#migrated,)*
+                            #(#moved,)*
+                        }
+                    })
+                };
+                let insert = if gen_info.insert_can_error[&plan::Idx::new(key, lp)] {
+                    quote! {
+                        #insert.map_err(|err| #struct_migration_error { table: #table_str, source: Box::new(err) })?;
+                    }
+                } else {
+                    quote!(#insert;)
+                };
+
+                Some(quote! {
+                    {
+                        let mut window = datastore.#table_name.window();
+                        for #migration_taken in old.#from_table.#struct_table_method_take() {
+                            #insert
+                        }
+                        window.#struct_window_method_commit();
+                    }
+                })
+            });
+
+            quote! {
+                impl TryFrom<#from> for #struct_datastore {
+                    type Error = #struct_migration_error;
+
+                    /// Move the rows of a previous version's datastore into a new datastore.
+                    #[allow(unused_variables)]
+                    fn try_from(old: #from) -> Result<Self, Self::Error> {
+                        let mut datastore = #new_datastore;
+                        #(#moves)*
+                        Ok(datastore)
+                    }
+                }
+            }
+        })
+        .collect::<Vec<_>>();
+
+    if migrations.is_empty() {
+        quote!()
+    } else {
+        quote! {
+            /// A row moved by a migration violates the constraints of its new table
+            #[derive(Debug)]
+            pub struct #struct_migration_error {
+                pub table: &'static str,
+                /// The error from inserting the row into the new table
+                pub source: Box<dyn std::error::Error>,
+            }
+
+            impl std::fmt::Display for #struct_migration_error {
+                fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+                    write!(f, "A row moved by a migration violates the constraints of table `{}`", self.table)
+                }
+            }
+
+            impl std::error::Error for #struct_migration_error {
+                fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+                    Some(self.source.as_ref())
+                }
+            }
+
+            #(#migrations)*
+        }
+    }
+}
@@ -6,6 +6,20 @@
 //! - Generates a table object that uses parallelism internally, but only allows
 //!   queries to execute in parallel if they are read only (normal borrow checker
 //!   rules apply)
+//! - With `concurrent = on` the database's tables are each locked separately,
+//!   all queries take `&self`, and queries that do not conflict (see
+//!   [`crate::analysis::concurrency`]) can execute in parallel.
+//! - With `persist = on` the database can be snapshot and restored (see [`persist`]).
+//! - With `warn_unbounded = on` a warning is emitted for each `collect` of a
+//!   stream with no bound on its size (see [`crate::analysis::cardinality`]).
+//! - Migrations generate conversions from the datastores of previous versions
+//!   of the schema (see [`migrations`]).
+//! - `valid_ref` constraints are enforced by the generated operators (see
+//!   [`references`]).
+//! - Each query's error implements [`std::error::Error`], and errors from
+//!   constraints carry the keys and values that violated them.
+//! - Several queries can be run atomically in a transaction (see
+//!   [`transactions`]).
 
 use combi::{
     core::{choice, mapsuc},
@@ -22,7 +36,8 @@ use prettyplease::unparse;
 use proc_macro2::TokenStream;
 use proc_macro_error2::{Diagnostic, Level};
 use pulpit::gen::selector::{
-    ColumnarSelector, CopySelector, MutabilitySelector, TableSelectors, ThunderdomeSelector,
+    AccessSelector, ColumnarSelector, CopySelector, MutabilitySelector, TableSelectors,
+    ThunderdomeSelector,
 };
 use queries::QueriesInfo;
 use quote::quote;
@@ -34,10 +49,14 @@ use crate::utils::{misc::singlelist, on_off::on_off};
 use operators::OperatorImpls;
 
 mod closures;
+mod migrations;
 pub mod namer;
 mod operators;
+mod persist;
 mod queries;
+mod references;
 mod tables;
+mod transactions;
 mod types;
 mod stats;
 
@@ -49,6 +68,9 @@ pub struct Serialized {
     aggressive_inlining: bool,
     operator_impl: OperatorImpls,
     table_selector: TableSelectors,
+    concurrent: bool,
+    persist: bool,
+    warn_unbounded: bool,
 }
 
 fn operator_impl_parse() -> impl TokenParser<OperatorImpls> {
@@ -66,6 +88,7 @@ fn table_select_parse() -> impl TokenParser<TableSelectors> {
         peekident("Mutability") => mapsuc(matchident("Mutability"), |_| MutabilitySelector.into()),
         peekident("Thunderdome") => mapsuc(matchident("Thunderdome"), |_| ThunderdomeSelector.into()),
         peekident("Columnar") => mapsuc(matchident("Columnar"), |_| ColumnarSelector.into()),
+        peekident("Access") => mapsuc(matchident("Access"), |_| AccessSelector.into()),
         peekident("Copy") => mapsuc(matchident("Copy"), |_| CopySelector.into()),
         otherwise => error(gettoken, |t| Diagnostic::spanned(t.span(), Level::Error, "Invalid Table Selector Choice".to_owned()))
     )
@@ -96,7 +119,19 @@ impl EMDBBackend for Serialized {
                                 OptField::new("aggressive_inlining", on_off),
                                 (
                                     OptField::new("op_impl", operator_impl_parse),
-                                    (OptField::new("table_select", table_select_parse), OptEnd),
+                                    (
+                                        OptField::new("table_select", table_select_parse),
+                                        (
+                                            OptField::new("concurrent", on_off),
+                                            (
+                                                OptField::new("persist", on_off),
+                                                (
+                                                    OptField::new("warn_unbounded", on_off),
+                                                    OptEnd,
+                                                ),
+                                            ),
+                                        ),
+                                    ),
                                 ),
                             ),
                         ),
@@ -105,14 +140,26 @@ impl EMDBBackend for Serialized {
             )
                 .gen('=');
             let (_, res) = parser.comp(TokenIter::from(opts, backend_name.span()));
-            res.to_result().map_err(TokenDiagnostic::into_list).map(
+            let backend = res.to_result().map_err(TokenDiagnostic::into_list).map(
                 |(
                     debug,
                     (
                         interface,
                         (
                             public,
-                            (ds_name, (inline_queries, (operator_impl, (table_selector, ())))),
+                            (
+                                ds_name,
+                                (
+                                    inline_queries,
+                                    (
+                                        operator_impl,
+                                        (
+                                            table_selector,
+                                            (concurrent, (persist, (warn_unbounded, ()))),
+                                        ),
+                                    ),
+                                ),
+                            ),
                         ),
                     ),
                 )| Serialized {
@@ -123,8 +170,25 @@ impl EMDBBackend for Serialized {
                     aggressive_inlining: inline_queries.unwrap_or(false),
                     operator_impl: operator_impl.unwrap_or(DEFAULT_OP_IMPL),
                     table_selector: table_selector.unwrap_or(DEFAULT_TABLE_SELECTOR),
+                    concurrent: concurrent.unwrap_or(false),
+                    persist: persist.unwrap_or(false),
+                    warn_unbounded: warn_unbounded.unwrap_or(false),
                 },
-            )
+            )?;
+            if backend.concurrent && backend.interface.is_some() {
+                Err(singlelist(
+                    Diagnostic::spanned(
+                        backend_name.span(),
+                        Level::Error,
+                        String::from("`concurrent` cannot be used with an `interface`"),
+                    )
+                    .help(String::from(
+                        "Mutating queries in an interface take `&mut self`, so cannot be run concurrently",
+                    )),
+                ))
+            } else {
+                Ok(backend)
+            }
         } else {
             Ok(Self {
                 debug: None,
@@ -134,6 +198,9 @@ impl EMDBBackend for Serialized {
                 aggressive_inlining: false,
                 operator_impl: DEFAULT_OP_IMPL,
                 table_selector: DEFAULT_TABLE_SELECTOR,
+                concurrent: false,
+                persist: false,
+                warn_unbounded: false,
             })
         }
     }
@@ -144,11 +211,41 @@ impl EMDBBackend for Serialized {
         plan: &crate::plan::Plan,
     ) -> Result<proc_macro2::TokenStream, std::collections::LinkedList<proc_macro_error2::Diagnostic>>
     {
+        if self.warn_unbounded {
+            for warning in crate::analysis::cardinality::unbounded_collects(plan) {
+                warning.emit();
+            }
+        }
+
         let mut namer = namer::SerializedNamer::new();
         if let Some(name) = self.ds_name {
             namer.struct_datastore = name;
         }
 
+        // references are keys, which are not kept when rows are migrated
+        for (_, migration) in plan.migrations.iter().filter(|(_, m)| m.to == impl_name) {
+            if let Some((_, table)) = plan.tables.iter().find(|(key, _)| {
+                !matches!(
+                    migration.tables.get(key),
+                    Some(crate::plan::TableMigration::Empty)
+                ) && !references::reference_columns(plan, *key, &namer).is_empty()
+            }) {
+                return Err(singlelist(
+                    Diagnostic::spanned(
+                        table.name.span(),
+                        Level::Error,
+                        format!(
+                            "Table `{}` has reference columns, so its rows cannot be migrated to `{impl_name}`",
+                            table.name
+                        ),
+                    )
+                    .help(String::from(
+                        "Tables with reference columns can only start empty (`table <name> empty;`) in a migration",
+                    )),
+                ));
+            }
+        }
+
         let tables::TableWindow {
             table_defs,
             datastore,
@@ -161,6 +258,8 @@ impl EMDBBackend for Serialized {
             &namer,
             &self.table_selector,
             self.aggressive_inlining,
+            self.concurrent,
+            self.persist,
         );
 
         let record_defs =
@@ -179,8 +278,40 @@ impl EMDBBackend for Serialized {
             &namer,
             &operator_impl,
             self.aggressive_inlining,
+            self.concurrent,
         );
 
+        let references = references::generate_references(plan, &namer);
+
+        let persistence = if self.persist {
+            let persist::Persistence {
+                datastore_restore,
+                database_snapshot,
+            } = persist::generate_persistence(
+                plan,
+                &table_generated_info,
+                &self.interface,
+                &namer,
+                self.concurrent,
+            );
+            quote! {
+                #datastore_restore
+                #database_snapshot
+            }
+        } else {
+            quote!()
+        };
+
+        let migrations = migrations::generate_migrations(
+            plan,
+            &table_generated_info,
+            &self.interface,
+            &namer,
+            &impl_name,
+        );
+
+        let transactions = transactions::generate_transactions(plan, &namer, self.concurrent);
+
         let namer::SerializedNamer { mod_tables, .. } = &namer;
 
         let public_tk = if self.public { quote!(pub) } else { quote!() };
@@ -207,6 +338,10 @@ impl EMDBBackend for Serialized {
                 #database
                 #query_impls
                 #stats_struct
+                #references
+                #persistence
+                #migrations
+                #transactions
             }
         };
 
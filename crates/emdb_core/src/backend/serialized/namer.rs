@@ -29,6 +29,17 @@ pub struct SerializedNamer {
     pub struct_database_member_stats: Ident,
     pub struct_stats: Ident,
     pub closure_stats_param: Ident,
+    pub mod_references: Ident,
+    pub mod_references_enum_error: Ident,
+    pub struct_migration_error: Ident,
+    pub migration_row: Ident,
+    pub migration_taken: Ident,
+    pub struct_database_member_transaction: Ident,
+    pub struct_database_method_transaction: Ident,
+    pub struct_database_method_savepoint: Ident,
+    pub struct_database_method_rollback_to: Ident,
+    pub struct_savepoint: Ident,
+    pub struct_transaction_guard: Ident,
 }
 
 impl SerializedNamer {
@@ -53,6 +64,19 @@ impl SerializedNamer {
             struct_database_member_stats: new_id(&format!("{INTERNAL_FIELD_PREFIX}stats")),
             struct_stats: new_id("Stats"),
             closure_stats_param: new_id(&format!("{INTERNAL_FIELD_PREFIX}stats")),
+            mod_references: new_id("references"),
+            mod_references_enum_error: new_id("Error"),
+            struct_migration_error: new_id("MigrationError"),
+            migration_row: new_id(&format!("{INTERNAL_FIELD_PREFIX}row")),
+            migration_taken: new_id(&format!("{INTERNAL_FIELD_PREFIX}taken")),
+            struct_database_member_transaction: new_id(&format!(
+                "{INTERNAL_FIELD_PREFIX}transaction"
+            )),
+            struct_database_method_transaction: new_id("transaction"),
+            struct_database_method_savepoint: new_id("savepoint"),
+            struct_database_method_rollback_to: new_id("rollback_to"),
+            struct_savepoint: new_id("Savepoint"),
+            struct_transaction_guard: new_id("TransactionGuard"),
         }
     }
 
@@ -100,10 +124,6 @@ impl SerializedNamer {
         new_id(&format!("return_value_{}", key.arr_idx()))
     }
 
-    pub fn operator_error_value_name(&self, key: plan::Key<plan::Operator>) -> Ident {
-        new_id(&format!("Error{}", key.arr_idx()))
-    }
-
     pub fn dataflow_value_name(&self, key: plan::Key<plan::DataFlow>) -> Ident {
         new_id(&format!("dataflow_value_{}", key.arr_idx()))
     }
@@ -112,6 +132,36 @@ impl SerializedNamer {
         new_id(&format!("Error{}", key.arr_idx()))
     }
 
+    pub fn operator_reference_error_variant_name(&self, key: plan::Key<plan::Operator>) -> Ident {
+        new_id(&format!("ReferenceError{}", key.arr_idx()))
+    }
+
+    /// The variant for errors from the update of an upsert (the insert uses
+    /// [`SerializedNamer::operator_error_variant_name`])
+    pub fn operator_update_error_variant_name(&self, key: plan::Key<plan::Operator>) -> Ident {
+        new_id(&format!("UpdateError{}", key.arr_idx()))
+    }
+
+    /// The variant of the references error for a `valid_ref` constraint
+    pub fn reference_error_name(&self, lp: &plan::Plan, reference: &plan::Reference) -> Ident {
+        new_id(&format!(
+            "{}_{}",
+            lp.get_table(reference.table).name,
+            reference.cons.alias
+        ))
+    }
+
+    /// The function that checks and updates the references to a deleted row
+    pub fn reference_delete_fn_name(&self, lp: &plan::Plan, key: plan::Key<plan::Table>) -> Ident {
+        new_id(&format!("delete_references_{}", lp.get_table(key).name))
+    }
+
+    /// The update used to set a reference to `None` when the referenced row is
+    /// deleted
+    pub fn reference_set_none_update(&self, field: &plan::RecordField) -> Ident {
+        new_id(&format!("pulpit_reference_none_{}", self.transform_field_name(field)))
+    }
+
     pub fn name_stat_member(&self, stats_ind: usize) -> Ident {
         Ident::new(&format!("stat_{stats_ind}"), Span::call_site())
     }
@@ -135,6 +185,11 @@ impl SerializedNamer {
     pub fn table_param_name(&self, lp: &plan::Plan, key: plan::Key<plan::Table>) -> Ident {
         new_id(&format!("internal_table_use_{}", lp.get_table(key).name))
     }
+
+    /// The savepoint of a table taken before a query, to abort to on error
+    pub fn table_savepoint_name(&self, lp: &plan::Plan, key: plan::Key<plan::Table>) -> Ident {
+        new_id(&format!("savepoint_{}", lp.get_table(key).name))
+    }
 }
 
 pub struct DataFlowNaming<'plan> {
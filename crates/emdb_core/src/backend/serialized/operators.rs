@@ -1,6 +1,7 @@
 
 use std::iter::{empty, once};
 
+use proc_macro2::TokenStream;
 use quote::{quote, ToTokens};
 use quote_debug::Tokens;
 use syn::{Expr, Ident, Path, Stmt};
@@ -9,12 +10,12 @@ use super::{
     closures::{generate_closure_usage, ContextGen}, namer::{
         boolean_predicate, dataflow_fields, expose_user_fields, new_error, transfer_fields,
         DataFlowNaming, SerializedNamer,
-    }, stats::{RequiredStats, StatKind}, tables::GeneratedInfo, types::generate_record_name
+    }, references::{check_reference, delete_references_call, delete_tables, reference_error_path}, stats::{RequiredStats, StatKind}, tables::GeneratedInfo, types::{generate_record_name, generate_scalar_type}
 };
 use crate::{
     backend::serialized::closures::generate_application,
     plan::{self, operator_enum, FoldField},
-    utils::{misc::{new_id, PushMap}, mut_scope::ScopeHandle},
+    utils::{misc::{new_id, PushMap}, mut_scope::{Mutability, ScopeHandle}},
 };
 
 
@@ -176,6 +177,85 @@ impl OperatorGen for plan::UniqueRef {
     }
 }
 
+impl OperatorGen for plan::LookupRefs {
+    fn apply<'imm>(
+        &self,
+        _self_key: plan::Key<plan::Operator>,
+        lp: &'imm plan::Plan,
+        namer: &SerializedNamer,
+        _error_path: &Tokens<Path>,
+        _errors: &mut PushMap<'_, Ident, Option<Tokens<Path>>>,
+        parent_scope: &mut ScopeHandle<'_, plan::ImmKey<'imm, plan::Table>>,
+        _gen_info: &GeneratedInfo<'imm>,
+        _context_vals: &mut Vec<(Ident, Tokens<Expr>)>,
+        OperatorImpl { impl_alias, .. }: &OperatorImpl,
+        _required_stats: &mut RequiredStats,
+    ) -> Tokens<Stmt> {
+        let SerializedNamer { phantom_field, .. } = namer;
+
+        let DataFlowNaming {
+            holding_var: input_holding_var,
+            record_type: input_record_type,
+            stream: input_stream,
+            ..
+        } = dataflow_fields(lp, self.input, namer);
+        let DataFlowNaming {
+            holding_var,
+            data_constructor,
+            ..
+        } = dataflow_fields(lp, self.output, namer);
+
+        let lookup_reference = namer.transform_field_name(&self.from);
+        let new_field = namer.transform_field_name(&self.out);
+
+        parent_scope.add_imm(plan::ImmKey::new(self.table, lp));
+        let table_param = namer.table_param_name(lp, self.table);
+        let table = lp.get_table(self.table);
+
+        // TODO: integrate this into the namer somehow?
+        let index_access = &table.columns[&self.field]
+            .cons
+            .hashed
+            .as_ref()
+            .unwrap()
+            .alias;
+
+        // each input record is copied for every matching row
+        let copy_fields = input_record_type.fields.keys().map(|id| {
+            let field_name = namer.transform_field_name(id);
+            quote!(#field_name: Clone::clone(&#input_holding_var.#field_name))
+        });
+
+        let input_values = if input_stream {
+            quote!(#impl_alias::export_buffer(#input_holding_var).into_iter())
+        } else {
+            quote!(std::iter::once(#impl_alias::export_single(#input_holding_var)))
+        };
+
+        // keys are collected so the stream does not borrow the table
+        quote! {
+            let #holding_var = {
+                let lookups = #input_values
+                    .flat_map(|#input_holding_var| {
+                        #table_param
+                            .#index_access()
+                            .lookup(std::borrow::Borrow::borrow(&#input_holding_var.#lookup_reference))
+                            .collect::<Vec<_>>()
+                            .into_iter()
+                            .map(move |data| #data_constructor {
+                                #new_field: data,
+                                #(#copy_fields,)*
+                                #phantom_field: std::marker::PhantomData
+                            })
+                    })
+                    .collect::<Vec<_>>();
+                #impl_alias::consume_stream(lookups.into_iter())
+            };
+        }
+        .into()
+    }
+}
+
 impl OperatorGen for plan::ScanRefs {
     fn apply<'imm>(
         &self,
@@ -228,6 +308,87 @@ impl OperatorGen for plan::ScanRefs {
         .into()
     }
 }
+impl OperatorGen for plan::RangeRefs {
+    fn apply<'imm>(
+        &self,
+        _self_key: plan::Key<plan::Operator>,
+        lp: &'imm plan::Plan,
+        namer: &SerializedNamer,
+        _error_path: &Tokens<Path>,
+        _errors: &mut PushMap<'_, Ident, Option<Tokens<Path>>>,
+        parent_scope: &mut ScopeHandle<'_, plan::ImmKey<'imm, plan::Table>>,
+        _gen_info: &GeneratedInfo<'imm>,
+        _context_vals: &mut Vec<(Ident, Tokens<Expr>)>,
+        OperatorImpl { impl_alias, .. }: &OperatorImpl,
+        required_stats: &mut RequiredStats,
+    ) -> Tokens<Stmt> {
+        let SerializedNamer { phantom_field, .. } = namer;
+
+        parent_scope.add_imm(plan::ImmKey::new(self.table, lp));
+        let table_param = namer.table_param_name(lp, self.table);
+        let table = lp.get_table(self.table);
+
+        // TODO: integrate this into the namer somehow?
+        let ordered_access = &table.columns[&self.field]
+            .cons
+            .ordered
+            .as_ref()
+            .unwrap()
+            .alias;
+
+        // The bounds are evaluated before the scan, and borrowed as the column
+        // type (the expressions may be values or references to values).
+        let bound_value = |name: &str, bound: &std::ops::Bound<Expr>| {
+            let var = new_id(name);
+            match bound {
+                std::ops::Bound::Included(e) => (
+                    quote!(let #var = #e;),
+                    quote!(std::ops::Bound::Included(std::borrow::Borrow::borrow(&#var))),
+                ),
+                std::ops::Bound::Excluded(e) => (
+                    quote!(let #var = #e;),
+                    quote!(std::ops::Bound::Excluded(std::borrow::Borrow::borrow(&#var))),
+                ),
+                std::ops::Bound::Unbounded => (quote!(), quote!(std::ops::Bound::Unbounded)),
+            }
+        };
+        let (lower_def, lower_bound) = bound_value("lower_bound", &self.lower);
+        let (upper_def, upper_bound) = bound_value("upper_bound", &self.upper);
+
+        let DataFlowNaming {
+            holding_var,
+            data_constructor,
+            ..
+        } = dataflow_fields(lp, self.output, namer);
+        let out_ref_name = namer.transform_field_name(&self.out_ref);
+        let map_stats = namer.access_stat_member(required_stats.add_stat(StatKind::Map));
+
+        // keys are collected so the stream does not borrow the table
+        quote! {
+            let #holding_var = {
+                #lower_def
+                #upper_def
+                let stream_values = #impl_alias::consume_stream(
+                    #table_param
+                        .#ordered_access()
+                        .range_bounds(#lower_bound, #upper_bound)
+                        .collect::<Vec<_>>()
+                        .into_iter()
+                    );
+                #impl_alias::map(
+                    stream_values,
+                    |value| #data_constructor {
+                        #out_ref_name : value,
+                        #phantom_field: std::marker::PhantomData
+                    },
+                    #map_stats
+                )
+            };
+        }
+        .into()
+    }
+}
+
 impl OperatorGen for plan::DeRef {
     fn apply<'imm>(
         &self,
@@ -243,6 +404,8 @@ impl OperatorGen for plan::DeRef {
         required_stats: &mut RequiredStats,
     ) -> Tokens<Stmt> {
         let SerializedNamer {
+            pulpit: pulpit::gen::namer::CodeNamer { type_key_error, .. },
+            mod_tables,
             operator_error_parameter,
             ..
         } = namer;
@@ -262,7 +425,7 @@ impl OperatorGen for plan::DeRef {
         let table_param = namer.table_param_name(lp, self.table);
         let deref_field = namer.transform_field_name(&self.reference);
         let new_field = namer.transform_field_name(&self.named);
-        let error_variant = namer.operator_error_value_name(self_key);
+        let table_mod = namer.table_internal_name(lp, self.table);
         let inner_type = generate_record_name(lp, self.named_type, namer);
         let get_value_id = new_id("get_value");
         let get_op_name = namer.pulpit_table_interaction(self_key);
@@ -304,7 +467,7 @@ impl OperatorGen for plan::DeRef {
                 };
             }
         } else {
-            let error_construct = new_error(self_key, error_path, None, errors, namer);
+            let error_construct = new_error(self_key, error_path, Some(quote!(super::super::#mod_tables::#table_mod::#type_key_error).into()), errors, namer);
             quote!{
                 let #holding_var = {
                     let result = #impl_alias::#map_kind(
@@ -317,7 +480,7 @@ impl OperatorGen for plan::DeRef {
                                     },
                                     #(#transfer_fields_input_append,)*
                                 }),
-                                Err(_) => return Err( #error_path::#error_variant )
+                                Err(#operator_error_parameter) => return Err(#operator_error_parameter)
                             }
                         },
                         #map_stats_access
@@ -363,10 +526,13 @@ impl OperatorGen for plan::Update {
                     mod_update,
                     mod_update_struct_update,
                     mod_update_enum_error,
+                    mod_borrow,
+                    mod_borrow_struct_borrow,
                     ..
                 },
             ..
         } = namer;
+        let table_mod = namer.table_internal_name(lp, self.table);
         let DataFlowNaming {
             data_constructor: input_data_constructor,
             record_type: input_record_type,
@@ -395,8 +561,17 @@ impl OperatorGen for plan::Update {
                 })
                 .collect::<Vec<_>>();
 
+            // the current values of the row are borrowed from the table
+            let current_arg = if self.current.is_empty() {
+                quote!()
+            } else {
+                let current_names = self.current.iter().map(|k| namer.transform_field_name(k));
+                let borrow_struct = quote!(#mod_tables::#table_mod::#mod_borrow::#mod_borrow_struct_borrow);
+                quote!(, #borrow_struct { #(#current_names,)* .. }: #borrow_struct<'_>)
+            };
+
             context_vals.push((closure_val.clone(), quote! {
-                |#input_data_constructor { #(#args_names,)* .. } | {
+                |#input_data_constructor { #(#args_names,)* .. } #current_arg | {
                     (
                         #update_type { #(#update_exprs,)* #phantom_field: std::marker::PhantomData },
                         #input_data_constructor { #(#args_names,)* #phantom_field: std::marker::PhantomData }
@@ -409,7 +584,6 @@ impl OperatorGen for plan::Update {
         let update_method = namer.pulpit_table_interaction(self_key);
         parent_scope.add_mut(plan::ImmKey::new(self.table, lp));
         let table_param = namer.table_param_name(lp, self.table);
-        let table_mod = namer.table_internal_name(lp, self.table);
 
         let key_member = namer.transform_field_name(&self.reference);
 
@@ -434,25 +608,80 @@ impl OperatorGen for plan::Update {
             (StatKind::MapSingle, quote!(map_single), quote!(export_single), quote!(consume_single), quote!(error_single))
         };
 
+        let reference_error = namer.operator_reference_error_variant_name(self_key);
+        let reference_fields = lp
+            .references_from(self.table)
+            .filter(|r| self.mapping.contains_key(r.field))
+            .map(|r| (r, namer.transform_field_name(r.field)))
+            .collect::<Vec<_>>();
+        let checks = reference_fields
+            .iter()
+            .map(|(r, field)| {
+                parent_scope.add_imm(plan::ImmKey::new(r.cons.cons.table, lp));
+                // when reading current values the update struct is computed
+                // inside the table's update, so the (copied) references are
+                // checked after, and any changes are undone by the abort
+                let value = if self.current.is_empty() {
+                    quote!(update_struct.#field)
+                } else {
+                    quote!(#field)
+                };
+                check_reference(lp, r, value.into(), &quote!(#error_path::#reference_error), namer)
+            })
+            .collect::<Vec<_>>();
+        if !checks.is_empty() {
+            errors.push(reference_error, Some(reference_error_path(namer)));
+        }
+
+        let apply_update = if self.current.is_empty() {
+            quote! {
+                // NOTE: need to clone to avoid borrow issues
+                // TODO: determine how closure cloning affects cloning of internals 
+                let (update_struct, continue_struct) = #closure_val.clone()(#input_holding);
+                #(#checks)*
+
+                match #table_param.#update_method(
+                    #mod_tables::#table_mod::#mod_update::#update_method::#mod_update_struct_update {
+                        #(#transfer_update_struct,)*
+                    },
+                    continue_struct.#key_member
+                ) {
+                    Ok(()) => Ok(continue_struct),
+                    Err(#operator_error_parameter) => #error_construct,
+                }
+            }
+        } else {
+            let reference_names = reference_fields.iter().map(|(_, field)| field).collect::<Vec<_>>();
+            quote! {
+                let update_key = #input_holding.#key_member;
+                let mut continue_data = None;
+                match #table_param.#update_method(
+                    |current| {
+                        let (update_struct, continue_struct) = #closure_val.clone()(#input_holding, current);
+                        continue_data = Some((continue_struct, (#(update_struct.#reference_names,)*)));
+                        #mod_tables::#table_mod::#mod_update::#update_method::#mod_update_struct_update {
+                            #(#transfer_update_struct,)*
+                        }
+                    },
+                    update_key
+                ) {
+                    Ok(()) => {
+                        let (continue_struct, (#(#reference_names,)*)) = continue_data.unwrap();
+                        #(#checks)*
+                        Ok(continue_struct)
+                    }
+                    Err(#operator_error_parameter) => #error_construct,
+                }
+            }
+        };
+
         let map_stats_access = namer.access_stat_member(required_stats.add_stat(map_stats));
         quote! {
             let #holding_var = {
                 let results = #impl_alias::#map_kind(
                     #input_holding,
                     |#input_holding| {
-                        // NOTE: need to clone to avoid borrow issues
-                        // TODO: determine how closure cloning affects cloning of internals 
-                        let (update_struct, continue_struct) = #closure_val.clone()(#input_holding);
-
-                        match #table_param.#update_method(
-                            #mod_tables::#table_mod::#mod_update::#update_method::#mod_update_struct_update {
-                                #(#transfer_update_struct,)*
-                            },
-                            continue_struct.#key_member
-                        ) {
-                            Ok(()) => Ok(continue_struct),
-                            Err(#operator_error_parameter) => #error_construct,
-                        }
+                        #apply_update
                     },
                     #map_stats_access
                 );
@@ -519,7 +748,51 @@ impl OperatorGen for plan::Insert {
             quote!(#field_name: #input_holding.#field_name)
         });
 
-        let results_internal = if gen_info.insert_can_error[&plan::Idx::new(self.table, lp)] {
+        let reference_error = namer.operator_reference_error_variant_name(self_key);
+        let checks = lp
+            .references_from(self.table)
+            .map(|r| {
+                parent_scope.add_imm(plan::ImmKey::new(r.cons.cons.table, lp));
+                let field = namer.transform_field_name(r.field);
+                check_reference(lp, &r, quote!(#input_holding.#field).into(), &quote!(#error_path::#reference_error), namer)
+            })
+            .collect::<Vec<_>>();
+
+        let results_internal = if !checks.is_empty() {
+            errors.push(reference_error, Some(reference_error_path(namer)));
+            let insert = quote! {
+                #table_param.insert(#mod_tables::#table_mod::#mod_insert::#mod_insert_struct_insert {
+                    #(#insert_fields,)*
+                })
+            };
+            let insert = if gen_info.insert_can_error[&plan::Idx::new(self.table, lp)] {
+                let error_construct = new_error(self_key, error_path, Some(quote!(super::super::#mod_tables::#table_mod::#mod_insert::#mod_insert_enum_error).into()), errors, namer);
+                quote! {
+                    match #insert {
+                        Ok(key) => key,
+                        Err(#operator_error_parameter) => return #error_construct,
+                    }
+                }
+            } else {
+                insert
+            };
+            quote! {
+                {
+                    let result = #impl_alias::#map_kind(
+                        #input_holding,
+                        |#input_holding| {
+                            #(#checks)*
+                            Ok(#data_constructor {
+                                #ref_name: #insert,
+                                #phantom_field: std::marker::PhantomData
+                            })
+                        },
+                        #map_stats_access
+                    );
+                    #impl_alias::#error_kind(result)?
+                }
+            }
+        } else if gen_info.insert_can_error[&plan::Idx::new(self.table, lp)] {
             let error_construct = new_error(self_key, error_path, Some(quote!(super::super::#mod_tables::#table_mod::#mod_insert::#mod_insert_enum_error).into()), errors, namer);
             quote! {
                 {
@@ -564,7 +837,7 @@ impl OperatorGen for plan::Insert {
         .into()
     }
 }
-impl OperatorGen for plan::Delete {
+impl OperatorGen for plan::Upsert {
     fn apply<'imm>(
         &self,
         self_key: plan::Key<plan::Operator>,
@@ -573,103 +846,554 @@ impl OperatorGen for plan::Delete {
         error_path: &Tokens<Path>,
         errors: &mut PushMap<'_, Ident, Option<Tokens<Path>>>,
         parent_scope: &mut ScopeHandle<'_, plan::ImmKey<'imm, plan::Table>>,
-        _gen_info: &GeneratedInfo<'imm>,
-        _context_vals: &mut Vec<(Ident, Tokens<Expr>)>,
+        gen_info: &GeneratedInfo<'imm>,
+        context_vals: &mut Vec<(Ident, Tokens<Expr>)>,
         OperatorImpl { impl_alias, .. }: &OperatorImpl,
         required_stats: &mut RequiredStats,
     ) -> Tokens<Stmt> {
+        let closure_val = namer.operator_closure_value_name(self_key);
         let SerializedNamer {
             mod_tables,
             operator_error_parameter,
+            phantom_field,
             pulpit:
                 pulpit::gen::namer::CodeNamer {
-                    type_key_error,
-                    struct_window_method_delete,
+                    mod_insert,
+                    mod_insert_enum_error,
+                    mod_insert_struct_insert,
+                    mod_update,
+                    mod_update_struct_update,
+                    mod_update_enum_error,
+                    mod_borrow,
+                    mod_borrow_struct_borrow,
                     ..
                 },
             ..
         } = namer;
         let DataFlowNaming {
             holding_var: input_holding,
+            data_constructor: input_data_constructor,
+            record_type,
             ..
         } = dataflow_fields(lp, self.input, namer);
         let DataFlowNaming {
             holding_var,
             stream,
+            data_constructor,
             ..
         } = dataflow_fields(lp, self.output, namer);
 
-        let (map_stats, map_kind, buffer, consume, error_kind) = if stream {
-            (StatKind::MapSeq, quote!(map_seq), quote!(export_buffer), quote!(consume_buffer), quote!(error_stream))
-        } else {
-            (StatKind::MapSingle, quote!(map_single), quote!(export_single), quote!(consume_single), quote!(error_single))
-        };
-
-        let map_stats_access = namer.access_stat_member(required_stats.add_stat(map_stats));
-
         parent_scope.add_mut(plan::ImmKey::new(self.table, lp));
         let table_param = namer.table_param_name(lp, self.table);
         let table_mod = namer.table_internal_name(lp, self.table);
-        let key_member = namer.transform_field_name(&self.reference);
-        let error_construct = new_error(
-            self_key,
-            error_path,
-            Some(quote!(super::super::#mod_tables::#table_mod::#type_key_error).into()),
-            errors,
-            namer,
-        );
+        let table = lp.get_table(self.table);
+        let ref_name = namer.transform_field_name(&self.out_ref);
 
-        quote!{
-            let #holding_var = {
-                let result = #impl_alias::#map_kind(
-                    #input_holding,
-                    |#input_holding| {
-                        match #table_param.#struct_window_method_delete(#input_holding.#key_member) {
-                            Ok(()) => Ok(#input_holding),
-                            Err(#operator_error_parameter) => #error_construct,
-                        }
-                    },
-                    #map_stats_access
-                );
-                #impl_alias::#consume(#impl_alias::#buffer(#impl_alias::#error_kind(result)?))
+        {
+            // the update expression closure, the existing row is borrowed as the
+            // returned reference's name
+            let update_type = generate_record_name(lp, self.update_type, namer);
+            let update_exprs = self.mapping.iter().map(|(name, expr)| {
+                let field_name = namer.transform_field_name(name);
+                quote!(#field_name: #expr)
+            });
+            let args_names = record_type.fields.keys().map(|k| namer.transform_field_name(k));
+            let current_arg = if self.current {
+                let borrow_struct = quote!(#mod_tables::#table_mod::#mod_borrow::#mod_borrow_struct_borrow);
+                quote!(, #ref_name: #borrow_struct<'_>)
+            } else {
+                quote!()
             };
-        }.into()
-    }
-}
-
-// Errors
-impl OperatorGen for plan::Assert {
-    fn apply<'imm>(
-        &self,
-        self_key: plan::Key<plan::Operator>,
-        lp: &'imm plan::Plan,
-        namer: &SerializedNamer,
-        error_path: &Tokens<Path>,
-        errors: &mut PushMap<'_, Ident, Option<Tokens<Path>>>,
-        _parent_scope: &mut ScopeHandle<'_, plan::ImmKey<'imm, plan::Table>>,
-        _gen_info: &GeneratedInfo<'imm>,
-        context_vals: &mut Vec<(Ident, Tokens<Expr>)>,
-        OperatorImpl { impl_alias, .. }: &OperatorImpl,
-        required_stats: &mut RequiredStats,
-    ) -> Tokens<Stmt> {
-        let DataFlowNaming {
-            holding_var: input_holding,
-            ..
-        } = dataflow_fields(lp, self.input, namer);
-        let DataFlowNaming {
-            holding_var,
-            stream,
-            ..
-        } = dataflow_fields(lp, self.output, namer);
 
-        let closure_data = namer.operator_closure_value_name(self_key);
-
-        context_vals.push((
-            closure_data.clone(),
-            (boolean_predicate(lp, &self.assert, self.input, namer).into_token_stream()).into(),
-        ));
+            context_vals.push((closure_val.clone(), quote! {
+                |#input_data_constructor { #(#args_names,)* .. } #current_arg | {
+                    #update_type { #(#update_exprs,)* #phantom_field: std::marker::PhantomData }
+                }
+            }
+            .into()));
+        }
 
-        let error_construct = new_error(self_key, error_path, None, errors, namer);
+        // TODO: integrate this into the namer somehow?
+        let unique_field_access = &table.columns[&self.field]
+            .cons
+            .unique
+            .as_ref()
+            .unwrap()
+            .alias;
+        let unique_field = namer.transform_field_name(&self.field);
+
+        let reference_error = namer.operator_reference_error_variant_name(self_key);
+        let insert_checks = lp
+            .references_from(self.table)
+            .map(|r| {
+                parent_scope.add_imm(plan::ImmKey::new(r.cons.cons.table, lp));
+                let field = namer.transform_field_name(r.field);
+                check_reference(lp, &r, quote!(#input_holding.#field).into(), &quote!(#error_path::#reference_error), namer)
+            })
+            .collect::<Vec<_>>();
+        let reference_names = lp
+            .references_from(self.table)
+            .filter(|r| self.mapping.contains_key(r.field))
+            .map(|r| namer.transform_field_name(r.field))
+            .collect::<Vec<_>>();
+        let update_checks = lp
+            .references_from(self.table)
+            .filter(|r| self.mapping.contains_key(r.field))
+            .map(|r| {
+                let field = namer.transform_field_name(r.field);
+                let value = if self.current {
+                    quote!(#field)
+                } else {
+                    quote!(update_struct.#field)
+                };
+                check_reference(lp, &r, value.into(), &quote!(#error_path::#reference_error), namer)
+            })
+            .collect::<Vec<_>>();
+        if !insert_checks.is_empty() {
+            errors.push(reference_error, Some(reference_error_path(namer)));
+        }
+
+        let insert_fields = record_type.fields.keys().map(|name| {
+            let field_name = namer.transform_field_name(name);
+            quote!(#field_name: #input_holding.#field_name)
+        });
+        let insert = quote! {
+            #table_param.insert(#mod_tables::#table_mod::#mod_insert::#mod_insert_struct_insert {
+                #(#insert_fields,)*
+            })
+        };
+        let insert = if gen_info.insert_can_error[&plan::Idx::new(self.table, lp)] {
+            let error_construct = new_error(self_key, error_path, Some(quote!(super::super::#mod_tables::#table_mod::#mod_insert::#mod_insert_enum_error).into()), errors, namer);
+            quote! {
+                match #insert {
+                    Ok(key) => key,
+                    Err(#operator_error_parameter) => return #error_construct,
+                }
+            }
+        } else {
+            insert
+        };
+
+        let update_method = namer.pulpit_table_interaction(self_key);
+        let update_error = namer.operator_update_error_variant_name(self_key);
+        errors.push(
+            update_error.clone(),
+            Some(quote!(super::super::#mod_tables::#table_mod::#mod_update::#update_method::#mod_update_enum_error).into()),
+        );
+        let transfer_update_struct = self.mapping.keys().map(|name| {
+            let field_name = namer.transform_field_name(name);
+            quote!(#field_name: update_struct.#field_name)
+        });
+        let update_struct = quote!(#mod_tables::#table_mod::#mod_update::#update_method::#mod_update_struct_update);
+
+        let update = if self.current {
+            // the update struct is computed inside the table's update, so the
+            // (copied) references are checked after, and any changes are undone
+            // by the abort
+            quote! {
+                let mut references = None;
+                match #table_param.#update_method(
+                    |current| {
+                        let update_struct = #closure_val.clone()(#input_holding, current);
+                        references = Some((#(update_struct.#reference_names,)*));
+                        #update_struct { #(#transfer_update_struct,)* }
+                    },
+                    key
+                ) {
+                    Ok(()) => {
+                        let (#(#reference_names,)*) = references.unwrap();
+                        #(#update_checks)*
+                        key
+                    }
+                    Err(#operator_error_parameter) => return Err(#error_path::#update_error(#operator_error_parameter)),
+                }
+            }
+        } else {
+            quote! {
+                let update_struct = #closure_val.clone()(#input_holding);
+                #(#update_checks)*
+                match #table_param.#update_method(
+                    #update_struct { #(#transfer_update_struct,)* },
+                    key
+                ) {
+                    Ok(()) => key,
+                    Err(#operator_error_parameter) => return Err(#error_path::#update_error(#operator_error_parameter)),
+                }
+            }
+        };
+
+        let (map_stats, map_kind, buffer, consume, error_kind) = if stream {
+            (StatKind::Map, quote!(map_seq), quote!(export_buffer), quote!(consume_buffer), quote!(error_stream))
+        } else {
+            (StatKind::MapSeq, quote!(map_single), quote!(export_single), quote!(consume_single), quote!(error_single))
+        };
+        let map_stats_access = namer.access_stat_member(required_stats.add_stat(map_stats));
+
+        quote! {
+            let #holding_var = {
+                let result = #impl_alias::#map_kind(
+                    #input_holding,
+                    |#input_holding| {
+                        // the unique index determines if the row already exists
+                        let #ref_name = match #table_param.#unique_field_access(&#input_holding.#unique_field) {
+                            Ok(key) => {
+                                #update
+                            }
+                            Err(_) => {
+                                #(#insert_checks)*
+                                #insert
+                            }
+                        };
+                        Ok(#data_constructor {
+                            #ref_name,
+                            #phantom_field: std::marker::PhantomData
+                        })
+                    },
+                    #map_stats_access
+                );
+                #impl_alias::#consume(#impl_alias::#buffer(#impl_alias::#error_kind(result)?))
+            };
+        }
+        .into()
+    }
+}
+impl OperatorGen for plan::Delete {
+    fn apply<'imm>(
+        &self,
+        self_key: plan::Key<plan::Operator>,
+        lp: &'imm plan::Plan,
+        namer: &SerializedNamer,
+        error_path: &Tokens<Path>,
+        errors: &mut PushMap<'_, Ident, Option<Tokens<Path>>>,
+        parent_scope: &mut ScopeHandle<'_, plan::ImmKey<'imm, plan::Table>>,
+        _gen_info: &GeneratedInfo<'imm>,
+        _context_vals: &mut Vec<(Ident, Tokens<Expr>)>,
+        OperatorImpl { impl_alias, .. }: &OperatorImpl,
+        required_stats: &mut RequiredStats,
+    ) -> Tokens<Stmt> {
+        let SerializedNamer {
+            mod_tables,
+            operator_error_parameter,
+            pulpit:
+                pulpit::gen::namer::CodeNamer {
+                    type_key_error,
+                    struct_window_method_delete,
+                    ..
+                },
+            ..
+        } = namer;
+        let DataFlowNaming {
+            holding_var: input_holding,
+            ..
+        } = dataflow_fields(lp, self.input, namer);
+        let DataFlowNaming {
+            holding_var,
+            stream,
+            ..
+        } = dataflow_fields(lp, self.output, namer);
+
+        let (map_stats, map_kind, buffer, consume, error_kind) = if stream {
+            (StatKind::MapSeq, quote!(map_seq), quote!(export_buffer), quote!(consume_buffer), quote!(error_stream))
+        } else {
+            (StatKind::MapSingle, quote!(map_single), quote!(export_single), quote!(consume_single), quote!(error_single))
+        };
+
+        let map_stats_access = namer.access_stat_member(required_stats.add_stat(map_stats));
+
+        parent_scope.add_mut(plan::ImmKey::new(self.table, lp));
+        let table_param = namer.table_param_name(lp, self.table);
+        let table_mod = namer.table_internal_name(lp, self.table);
+        let key_member = namer.transform_field_name(&self.reference);
+        let error_construct = new_error(
+            self_key,
+            error_path,
+            Some(quote!(super::super::#mod_tables::#table_mod::#type_key_error).into()),
+            errors,
+            namer,
+        );
+
+        // the references to the deleted row are checked & updated after it is deleted
+        let references = delete_references_call(lp, self.table, quote!(#input_holding.#key_member), namer).map(|call| {
+            for (table, mutability) in delete_tables(lp, self.table) {
+                match mutability {
+                    Mutability::Mut => parent_scope.add_mut(plan::ImmKey::new(table, lp)),
+                    Mutability::Imm => parent_scope.add_imm(plan::ImmKey::new(table, lp)),
+                }
+            }
+            let reference_error = namer.operator_reference_error_variant_name(self_key);
+            errors.push(reference_error.clone(), Some(reference_error_path(namer)));
+            quote! {
+                if let Err(#operator_error_parameter) = #call {
+                    return Err(#error_path::#reference_error(#operator_error_parameter));
+                }
+            }
+        });
+
+        quote!{
+            let #holding_var = {
+                let result = #impl_alias::#map_kind(
+                    #input_holding,
+                    |#input_holding| {
+                        match #table_param.#struct_window_method_delete(#input_holding.#key_member) {
+                            Ok(()) => {
+                                #references
+                                Ok(#input_holding)
+                            },
+                            Err(#operator_error_parameter) => #error_construct,
+                        }
+                    },
+                    #map_stats_access
+                );
+                #impl_alias::#consume(#impl_alias::#buffer(#impl_alias::#error_kind(result)?))
+            };
+        }.into()
+    }
+}
+
+impl OperatorGen for plan::DeleteWhere {
+    fn apply<'imm>(
+        &self,
+        self_key: plan::Key<plan::Operator>,
+        lp: &'imm plan::Plan,
+        namer: &SerializedNamer,
+        error_path: &Tokens<Path>,
+        errors: &mut PushMap<'_, Ident, Option<Tokens<Path>>>,
+        parent_scope: &mut ScopeHandle<'_, plan::ImmKey<'imm, plan::Table>>,
+        _gen_info: &GeneratedInfo<'imm>,
+        context_vals: &mut Vec<(Ident, Tokens<Expr>)>,
+        OperatorImpl { impl_alias, .. }: &OperatorImpl,
+        _required_stats: &mut RequiredStats,
+    ) -> Tokens<Stmt> {
+        let SerializedNamer {
+            mod_tables,
+            operator_error_parameter,
+            phantom_field,
+            pulpit:
+                pulpit::gen::namer::CodeNamer {
+                    mod_borrow,
+                    mod_borrow_struct_borrow,
+                    struct_window_method_scan_get,
+                    struct_window_method_borrow,
+                    struct_window_method_delete,
+                    ..
+                },
+            ..
+        } = namer;
+        let DataFlowNaming {
+            holding_var,
+            data_constructor,
+            ..
+        } = dataflow_fields(lp, self.output, namer);
+
+        parent_scope.add_mut(plan::ImmKey::new(self.table, lp));
+        let table_param = namer.table_param_name(lp, self.table);
+        let table_mod = namer.table_internal_name(lp, self.table);
+
+        let closure_val = namer.operator_closure_value_name(self_key);
+        let predicate = &self.predicate;
+        let column_names = self.columns.iter().map(|k| namer.transform_field_name(k));
+        let borrow_struct = quote!(#mod_tables::#table_mod::#mod_borrow::#mod_borrow_struct_borrow);
+        context_vals.push((closure_val.clone(), quote! {
+            |#borrow_struct { #(#column_names,)* .. }: #borrow_struct<'_>| -> bool {
+                #predicate
+            }
+        }
+        .into()));
+
+        let references = delete_references_call(lp, self.table, quote!(key), namer).map(|call| {
+            for (table, mutability) in delete_tables(lp, self.table) {
+                match mutability {
+                    Mutability::Mut => parent_scope.add_mut(plan::ImmKey::new(table, lp)),
+                    Mutability::Imm => parent_scope.add_imm(plan::ImmKey::new(table, lp)),
+                }
+            }
+            let reference_error = namer.operator_reference_error_variant_name(self_key);
+            errors.push(reference_error.clone(), Some(reference_error_path(namer)));
+            quote! {
+                if let Err(#operator_error_parameter) = #call {
+                    return Err(#error_path::#reference_error(#operator_error_parameter));
+                }
+            }
+        });
+
+        let count_field = namer.transform_field_name(&self.count);
+        quote!{
+            let #holding_var = {
+                let mut affected: usize = 0;
+                // the keys are collected so rows can be deleted during the scan
+                for key in #table_param.#struct_window_method_scan_get().collect::<Vec<_>>() {
+                    // rows can already be deleted by a cascade from an earlier row
+                    let matches = #table_param.#struct_window_method_borrow(key).is_ok_and(|row| #closure_val(row));
+                    if matches && #table_param.#struct_window_method_delete(key).is_ok() {
+                        #references
+                        affected += 1;
+                    }
+                }
+                #impl_alias::consume_single(#data_constructor {
+                    #count_field: affected,
+                    #phantom_field: std::marker::PhantomData
+                })
+            };
+        }.into()
+    }
+}
+
+impl OperatorGen for plan::UpdateWhere {
+    fn apply<'imm>(
+        &self,
+        self_key: plan::Key<plan::Operator>,
+        lp: &'imm plan::Plan,
+        namer: &SerializedNamer,
+        error_path: &Tokens<Path>,
+        errors: &mut PushMap<'_, Ident, Option<Tokens<Path>>>,
+        parent_scope: &mut ScopeHandle<'_, plan::ImmKey<'imm, plan::Table>>,
+        _gen_info: &GeneratedInfo<'imm>,
+        context_vals: &mut Vec<(Ident, Tokens<Expr>)>,
+        OperatorImpl { impl_alias, .. }: &OperatorImpl,
+        _required_stats: &mut RequiredStats,
+    ) -> Tokens<Stmt> {
+        let SerializedNamer {
+            mod_tables,
+            operator_error_parameter,
+            phantom_field,
+            pulpit:
+                pulpit::gen::namer::CodeNamer {
+                    mod_update,
+                    mod_update_struct_update,
+                    mod_update_enum_error,
+                    mod_borrow,
+                    mod_borrow_struct_borrow,
+                    struct_window_method_scan_get,
+                    struct_window_method_borrow,
+                    ..
+                },
+            ..
+        } = namer;
+        let DataFlowNaming {
+            holding_var,
+            data_constructor,
+            ..
+        } = dataflow_fields(lp, self.output, namer);
+
+        parent_scope.add_mut(plan::ImmKey::new(self.table, lp));
+        let table_param = namer.table_param_name(lp, self.table);
+        let table_mod = namer.table_internal_name(lp, self.table);
+
+        let closure_val = namer.operator_closure_value_name(self_key);
+        {
+            // the values to update are only computed for matching rows
+            let predicate = &self.predicate;
+            let update_type = generate_record_name(lp, self.update_type, namer);
+            let update_exprs = self.mapping.iter().map(|(name, expr)| {
+                let field_name = namer.transform_field_name(name);
+                quote!(#field_name: #expr)
+            });
+            let column_names = self.columns.iter().map(|k| namer.transform_field_name(k));
+            let borrow_struct = quote!(#mod_tables::#table_mod::#mod_borrow::#mod_borrow_struct_borrow);
+            context_vals.push((closure_val.clone(), quote! {
+                |#borrow_struct { #(#column_names,)* .. }: #borrow_struct<'_>| {
+                    if #predicate {
+                        Some(#update_type { #(#update_exprs,)* #phantom_field: std::marker::PhantomData })
+                    } else {
+                        None
+                    }
+                }
+            }
+            .into()));
+        }
+
+        let update_method = namer.pulpit_table_interaction(self_key);
+        let transfer_update_struct = self.mapping.keys().map(|name| {
+            let field_name = namer.transform_field_name(name);
+            quote!(#field_name: update_struct.#field_name)
+        });
+
+        let error_construct = new_error(
+            self_key,
+            error_path,
+            Some(quote!(
+                super::super::#mod_tables::#table_mod::#mod_update::#update_method::#mod_update_enum_error
+            ).into()),
+            errors,
+            namer
+        );
+
+        let reference_error = namer.operator_reference_error_variant_name(self_key);
+        let checks = lp
+            .references_from(self.table)
+            .filter(|r| self.mapping.contains_key(r.field))
+            .map(|r| {
+                parent_scope.add_imm(plan::ImmKey::new(r.cons.cons.table, lp));
+                let field = namer.transform_field_name(r.field);
+                check_reference(lp, &r, quote!(update_struct.#field).into(), &quote!(#error_path::#reference_error), namer)
+            })
+            .collect::<Vec<_>>();
+        if !checks.is_empty() {
+            errors.push(reference_error, Some(reference_error_path(namer)));
+        }
+
+        let count_field = namer.transform_field_name(&self.count);
+        quote!{
+            let #holding_var = {
+                let mut affected: usize = 0;
+                // the keys are collected so rows can be updated during the scan
+                for key in #table_param.#struct_window_method_scan_get().collect::<Vec<_>>() {
+                    let update = #table_param.#struct_window_method_borrow(key).ok().and_then(|row| #closure_val(row));
+                    if let Some(update_struct) = update {
+                        #(#checks)*
+                        match #table_param.#update_method(
+                            #mod_tables::#table_mod::#mod_update::#update_method::#mod_update_struct_update {
+                                #(#transfer_update_struct,)*
+                            },
+                            key
+                        ) {
+                            Ok(()) => affected += 1,
+                            Err(#operator_error_parameter) => return #error_construct,
+                        }
+                    }
+                }
+                #impl_alias::consume_single(#data_constructor {
+                    #count_field: affected,
+                    #phantom_field: std::marker::PhantomData
+                })
+            };
+        }.into()
+    }
+}
+
+// Errors
+impl OperatorGen for plan::Assert {
+    fn apply<'imm>(
+        &self,
+        self_key: plan::Key<plan::Operator>,
+        lp: &'imm plan::Plan,
+        namer: &SerializedNamer,
+        error_path: &Tokens<Path>,
+        errors: &mut PushMap<'_, Ident, Option<Tokens<Path>>>,
+        _parent_scope: &mut ScopeHandle<'_, plan::ImmKey<'imm, plan::Table>>,
+        _gen_info: &GeneratedInfo<'imm>,
+        context_vals: &mut Vec<(Ident, Tokens<Expr>)>,
+        OperatorImpl { impl_alias, .. }: &OperatorImpl,
+        required_stats: &mut RequiredStats,
+    ) -> Tokens<Stmt> {
+        let DataFlowNaming {
+            holding_var: input_holding,
+            ..
+        } = dataflow_fields(lp, self.input, namer);
+        let DataFlowNaming {
+            holding_var,
+            stream,
+            ..
+        } = dataflow_fields(lp, self.output, namer);
+
+        let closure_data = namer.operator_closure_value_name(self_key);
+
+        context_vals.push((
+            closure_data.clone(),
+            (boolean_predicate(lp, &self.assert, self.input, namer).into_token_stream()).into(),
+        ));
+
+        let error_construct = new_error(self_key, error_path, None, errors, namer);
 
         let (stats, all_kind) = if stream {
             (StatKind::All, quote!(all))
@@ -677,27 +1401,254 @@ impl OperatorGen for plan::Assert {
             (StatKind::Is, quote!(is))
         };
 
-        let stats_access = namer.access_stat_member(required_stats.add_stat(stats));
+        let stats_access = namer.access_stat_member(required_stats.add_stat(stats));
+
+        quote! {
+            let #holding_var = {
+                let (result, pass_on) = #impl_alias::#all_kind(
+                    #input_holding,
+                    #closure_data,
+                    #stats_access
+                )
+                if result {
+                    pass_on
+                } else {
+                    return #error_construct;
+                }
+            };
+        }
+        .into()
+    }
+}
+
+impl OperatorGen for plan::Map {
+    fn apply<'imm>(
+        &self,
+        self_key: plan::Key<plan::Operator>,
+        lp: &'imm plan::Plan,
+        namer: &SerializedNamer,
+        _error_path: &Tokens<Path>,
+        _errors: &mut PushMap<'_, Ident, Option<Tokens<Path>>>,
+        _parent_scope: &mut ScopeHandle<'_, plan::ImmKey<'imm, plan::Table>>,
+        _gen_info: &GeneratedInfo<'imm>,
+        context_vals: &mut Vec<(Ident, Tokens<Expr>)>,
+        OperatorImpl { impl_alias, .. }: &OperatorImpl,
+        required_stats: &mut RequiredStats,
+    ) -> Tokens<Stmt> {
+        let SerializedNamer {
+            phantom_field,
+            ..
+        } = namer;
+        let DataFlowNaming {
+            holding_var: input_holding,
+            data_constructor: input_constructor,
+            record_type: input_record_type,
+            ..
+        } = dataflow_fields(lp, self.input, namer);
+        let DataFlowNaming {
+            holding_var,
+            data_constructor,
+            stream,
+            ..
+        } = dataflow_fields(lp, self.output, namer);
+
+        let closure_data = namer.operator_closure_value_name(self_key);
+
+        let input_fields = expose_user_fields(input_record_type, namer);
+
+        let mapping_fields = self.mapping.iter().map(|(rf, e)| {
+            let field_name = namer.transform_field_name(rf);
+            quote! {#field_name: #e}
+        });
+
+        context_vals.push((
+            closure_data.clone(),
+            quote! {
+                |#input_constructor { #(#input_fields,)* }| {
+                    #data_constructor {
+                        #(#mapping_fields,)*
+                        #phantom_field: std::marker::PhantomData
+                    }
+                }
+            }
+            .into(),
+        ));
+
+        let (map_stats, map_fn) = if stream {
+            (StatKind::Map, quote!(map))
+        } else {
+            (StatKind::MapSingle, quote!(map_single))
+        };
+
+        let map_stats_access = namer.access_stat_member(required_stats.add_stat(map_stats));
+
+        quote! {
+            let #holding_var = #impl_alias::#map_fn(
+                #input_holding,
+                #closure_data,
+                #map_stats_access
+            );
+        }
+        .into()
+    }
+}
+impl OperatorGen for plan::Expand {
+    fn apply<'imm>(
+        &self,
+        _self_key: plan::Key<plan::Operator>,
+        lp: &'imm plan::Plan,
+        namer: &SerializedNamer,
+        _error_path: &Tokens<Path>,
+        _errors: &mut PushMap<'_, Ident, Option<Tokens<Path>>>,
+        _parent_scope: &mut ScopeHandle<'_, plan::ImmKey<'imm, plan::Table>>,
+        _gen_info: &GeneratedInfo<'imm>,
+        _context_vals: &mut Vec<(Ident, Tokens<Expr>)>,
+        OperatorImpl { impl_alias, .. }: &OperatorImpl,
+        required_stats: &mut RequiredStats,
+    ) -> Tokens<Stmt> {
+        let DataFlowNaming {
+            holding_var: input_holding,
+            ..
+        } = dataflow_fields(lp, self.input, namer);
+        let DataFlowNaming {
+            holding_var,
+            stream,
+            ..
+        } = dataflow_fields(lp, self.output, namer);
+
+        let (map_stats, map_fn) = if stream {
+            (StatKind::Map, quote!(map))
+        } else {
+            (StatKind::MapSingle, quote!(map_single))
+        };
+
+        let map_stats_access = namer.access_stat_member(required_stats.add_stat(map_stats));
+
+        let expand_field = namer.transform_field_name(&self.field);
+
+        quote! {
+            let #holding_var = #impl_alias::#map_fn(
+                #input_holding,
+                | #input_holding | #input_holding.#expand_field,
+                #map_stats_access
+            );
+        }
+        .into()
+    }
+}
+impl OperatorGen for plan::Fold {
+    fn apply<'imm>(
+        &self,
+        self_key: plan::Key<plan::Operator>,
+        lp: &'imm plan::Plan,
+        namer: &SerializedNamer,
+        _error_path: &Tokens<Path>,
+        _errors: &mut PushMap<'_, Ident, Option<Tokens<Path>>>,
+        _parent_scope: &mut ScopeHandle<'_, plan::ImmKey<'imm, plan::Table>>,
+        _gen_info: &GeneratedInfo<'imm>,
+        context_vals: &mut Vec<(Ident, Tokens<Expr>)>,
+        OperatorImpl { impl_alias, .. }: &OperatorImpl,
+        required_stats: &mut RequiredStats,
+    ) -> Tokens<Stmt> {
+        let SerializedNamer {
+            phantom_field,
+            ..
+        } = namer;
+        let DataFlowNaming {
+            data_constructor: input_data_constructor,
+            record_type: input_record_type,
+            holding_var: input_holding,
+            ..
+        } = dataflow_fields(lp, self.input, namer);
+        let DataFlowNaming {
+            holding_var,
+            data_constructor: acc_constructor,
+            data_type: acc_data_type,
+            record_type: acc_record_type,
+            ..
+        } = dataflow_fields(lp, self.output, namer);
+        let closure_value = namer.operator_closure_value_name(self_key);
+
+        let (init_fields, update_fields): (Vec<_>, Vec<_>) = self
+            .fold_fields
+            .iter()
+            .map(|(rf, plan::FoldField { initial, update })| {
+                let field_name = namer.transform_field_name(rf);
+                (
+                    quote!(#field_name: #initial),
+                    quote!(#field_name: { #update }),
+                )
+            })
+            .unzip();
+
+        let acc_fields = expose_user_fields(acc_record_type, namer);
+        let input_fields = expose_user_fields(input_record_type, namer);
+
+        context_vals.push((closure_value.clone(), quote! {
+            (
+                #acc_constructor {
+                    #(#init_fields,)*
+                    #phantom_field: std::marker::PhantomData
+                },
+                |#acc_constructor { #(#acc_fields,)* } : #acc_data_type, #input_data_constructor { #(#input_fields,)* }  | {
+                    #acc_constructor {
+                        #(#update_fields,)*
+                        #phantom_field: std::marker::PhantomData
+                    }
+                }
+            )
+        }
+        .into()));
+
+        let fold_access_member = namer.access_stat_member(required_stats.add_stat(StatKind::Fold));
 
         quote! {
             let #holding_var = {
-                let (result, pass_on) = #impl_alias::#all_kind(
-                    #input_holding,
-                    #closure_data,
-                    #stats_access
-                )
-                if result {
-                    pass_on
-                } else {
-                    return #error_construct;
-                }
+                let (init, update) = #closure_value;
+                #impl_alias::fold(#input_holding, init, update, #fold_access_member)
             };
         }
         .into()
     }
 }
+impl OperatorGen for plan::Filter {
+    fn apply<'imm>(
+        &self,
+        self_key: plan::Key<plan::Operator>,
+        lp: &'imm plan::Plan,
+        namer: &SerializedNamer,
+        _error_path: &Tokens<Path>,
+        _errors: &mut PushMap<'_, Ident, Option<Tokens<Path>>>,
+        _parent_scope: &mut ScopeHandle<'_, plan::ImmKey<'imm, plan::Table>>,
+        _gen_info: &GeneratedInfo<'imm>,
+        context_vals: &mut Vec<(Ident, Tokens<Expr>)>,
+        OperatorImpl { impl_alias, .. }: &OperatorImpl,
+        required_stats: &mut RequiredStats,
+    ) -> Tokens<Stmt> {
+        let DataFlowNaming {
+            holding_var: input_holding,
+            ..
+        } = dataflow_fields(lp, self.input, namer);
+        let DataFlowNaming {
+            holding_var,
+            ..
+        } = dataflow_fields(lp, self.output, namer);
+        let closure_value = namer.operator_closure_value_name(self_key);
+
+        context_vals.push((
+            closure_value.clone(),
+            (boolean_predicate(lp, &self.predicate, self.input, namer).into_token_stream()).into(),
+        ));
+
+        let filter_access_member = namer.access_stat_member(required_stats.add_stat(StatKind::Filter));
+
+        quote!{
+            let #holding_var = #impl_alias::filter(#input_holding, #closure_value, #filter_access_member);
+        }.into()
+    }
+}
 
-impl OperatorGen for plan::Map {
+impl OperatorGen for plan::Combine {
     fn apply<'imm>(
         &self,
         self_key: plan::Key<plan::Operator>,
@@ -717,58 +1668,56 @@ impl OperatorGen for plan::Map {
         } = namer;
         let DataFlowNaming {
             holding_var: input_holding,
-            data_constructor: input_constructor,
-            record_type: input_record_type,
+            data_type,
+            data_constructor,
             ..
         } = dataflow_fields(lp, self.input, namer);
         let DataFlowNaming {
             holding_var,
-            data_constructor,
-            stream,
             ..
         } = dataflow_fields(lp, self.output, namer);
+        let Self {
+            left_name,
+            right_name,
+            ..
+        } = self;
+        let closure_value = namer.operator_closure_value_name(self_key);
 
-        let closure_data = namer.operator_closure_value_name(self_key);
-
-        let input_fields = expose_user_fields(input_record_type, namer);
-
-        let mapping_fields = self.mapping.iter().map(|(rf, e)| {
-            let field_name = namer.transform_field_name(rf);
-            quote! {#field_name: #e}
-        });
+        let (field_defaults, field_sets): (Vec<_>, Vec<_>) = self.update_fields.iter().map(|(field, FoldField { initial, update })| {
+            let field_name = namer.transform_field_name(field);
+            (quote!(#field_name: #initial), quote!(#field_name: #update))
+        }).unzip();
 
         context_vals.push((
-            closure_data.clone(),
+            closure_value.clone(),
             quote! {
-                |#input_constructor { #(#input_fields,)* }| {
+                (
                     #data_constructor {
-                        #(#mapping_fields,)*
+                        #(#field_defaults,)*
                         #phantom_field: std::marker::PhantomData
+                    },
+                    |#left_name: #data_type, #right_name: #data_type|
+                        #data_constructor {
+                            #(#field_sets,)*
+                            #phantom_field: std::marker::PhantomData
                     }
-                }
+                )
             }
             .into(),
         ));
 
-        let (map_stats, map_fn) = if stream {
-            (StatKind::Map, quote!(map))
-        } else {
-            (StatKind::MapSingle, quote!(map_single))
-        };
-
-        let map_stats_access = namer.access_stat_member(required_stats.add_stat(map_stats));
+        let combine_access_member = namer.access_stat_member(required_stats.add_stat(StatKind::Combine));
 
-        quote! {
-            let #holding_var = #impl_alias::#map_fn(
-                #input_holding,
-                #closure_data,
-                #map_stats_access
-            );
-        }
-        .into()
+        quote!{
+            let #holding_var = {
+                let (alternative, update) = #closure_value;
+                #impl_alias::combine(#input_holding, alternative, update, #combine_access_member)   
+            };
+        }.into()
     }
 }
-impl OperatorGen for plan::Expand {
+
+impl OperatorGen for plan::Sort {
     fn apply<'imm>(
         &self,
         _self_key: plan::Key<plan::Operator>,
@@ -788,31 +1737,95 @@ impl OperatorGen for plan::Expand {
         } = dataflow_fields(lp, self.input, namer);
         let DataFlowNaming {
             holding_var,
-            stream,
             ..
         } = dataflow_fields(lp, self.output, namer);
 
-        let (map_stats, map_fn) = if stream {
-            (StatKind::Map, quote!(map))
-        } else {
-            (StatKind::MapSingle, quote!(map_single))
-        };
+        let order_greater = quote!(std::cmp::Ordering::Greater);
+        let order_equal = quote!(std::cmp::Ordering::Equal);
+        let order_less = quote!(std::cmp::Ordering::Less);
 
-        let map_stats_access = namer.access_stat_member(required_stats.add_stat(map_stats));
+        let comparisons = self.sort_order.iter().map(|(rf, order)| {
+            let (gt_result, lt_result) = match order {
+                plan::SortOrder::Asc => (&order_greater, &order_less),
+                plan::SortOrder::Desc => (&order_less, &order_greater),
+            };
+            let field_name = namer.transform_field_name(rf);
+            quote! {
+                match left.#field_name.cmp(&right.#field_name) {
+                    std::cmp::Ordering::Greater => return #gt_result,
+                    std::cmp::Ordering::Less => return #lt_result,
+                    std::cmp::Ordering::Equal => (),
+                }
+            }
+        });
 
-        let expand_field = namer.transform_field_name(&self.field);
+        let sort_access_member = namer.access_stat_member(required_stats.add_stat(StatKind::Sort));
 
-        quote! {
-            let #holding_var = #impl_alias::#map_fn(
-                #input_holding,
-                | #input_holding | #input_holding.#expand_field,
-                #map_stats_access
-            );
+        quote!{
+            let #holding_var = #impl_alias::sort(#input_holding, |left, right| {
+                #(#comparisons)*
+                #order_equal
+            }, #sort_access_member);
+        }.into()
+    }
+}
+impl OperatorGen for plan::Distinct {
+    fn apply<'imm>(
+        &self,
+        _self_key: plan::Key<plan::Operator>,
+        lp: &'imm plan::Plan,
+        namer: &SerializedNamer,
+        _error_path: &Tokens<Path>,
+        _errors: &mut PushMap<'_, Ident, Option<Tokens<Path>>>,
+        _parent_scope: &mut ScopeHandle<'_, plan::ImmKey<'imm, plan::Table>>,
+        _gen_info: &GeneratedInfo<'imm>,
+        _context_vals: &mut Vec<(Ident, Tokens<Expr>)>,
+        OperatorImpl { impl_alias, .. }: &OperatorImpl,
+        required_stats: &mut RequiredStats,
+    ) -> Tokens<Stmt> {
+        let DataFlowNaming {
+            holding_var: input_holding,
+            ..
+        } = dataflow_fields(lp, self.input, namer);
+        let DataFlowNaming {
+            holding_var,
+            ..
+        } = dataflow_fields(lp, self.output, namer);
+
+        let field_names = self.fields.iter().map(|rf| namer.transform_field_name(rf)).collect::<Vec<_>>();
+
+        // if the input is sorted by the fields first, the same records are
+        // adjacent and do not need to be hashed
+        let sort_order = match lp.get_operator(lp.get_dataflow(self.input).get_conn().from) {
+            plan::Operator::Sort(plan::Sort { sort_order, .. })
+            | plan::Operator::TopK(plan::TopK { sort_order, .. }) => Some(sort_order),
+            _ => None,
+        };
+        let sorted = sort_order.is_some_and(|sort_order| {
+            sort_order.len() >= self.fields.len()
+                && sort_order[..self.fields.len()]
+                    .iter()
+                    .all(|(field, _)| self.fields.contains(field))
+        });
+
+        if sorted {
+            let distinct_access_member = namer.access_stat_member(required_stats.add_stat(StatKind::DistinctSorted));
+            quote!{
+                let #holding_var = #impl_alias::distinct_sorted(#input_holding, |left, right| {
+                    #(left.#field_names == right.#field_names)&&*
+                }, #distinct_access_member);
+            }.into()
+        } else {
+            let distinct_access_member = namer.access_stat_member(required_stats.add_stat(StatKind::Distinct));
+            quote!{
+                let #holding_var = #impl_alias::distinct(#input_holding, |data| {
+                    (#(data.#field_names.clone(),)*)
+                }, #distinct_access_member);
+            }.into()
         }
-        .into()
     }
 }
-impl OperatorGen for plan::Fold {
+impl OperatorGen for plan::Window {
     fn apply<'imm>(
         &self,
         self_key: plan::Key<plan::Operator>,
@@ -821,7 +1834,7 @@ impl OperatorGen for plan::Fold {
         _error_path: &Tokens<Path>,
         _errors: &mut PushMap<'_, Ident, Option<Tokens<Path>>>,
         _parent_scope: &mut ScopeHandle<'_, plan::ImmKey<'imm, plan::Table>>,
-        _gen_info: &GeneratedInfo<'imm>,
+        gen_info: &GeneratedInfo<'imm>,
         context_vals: &mut Vec<(Ident, Tokens<Expr>)>,
         OperatorImpl { impl_alias, .. }: &OperatorImpl,
         required_stats: &mut RequiredStats,
@@ -831,63 +1844,177 @@ impl OperatorGen for plan::Fold {
             ..
         } = namer;
         let DataFlowNaming {
-            data_constructor: input_data_constructor,
-            record_type: input_record_type,
             holding_var: input_holding,
+            data_constructor: input_constructor,
+            record_type: input_record_type,
             ..
         } = dataflow_fields(lp, self.input, namer);
         let DataFlowNaming {
             holding_var,
-            data_constructor: acc_constructor,
-            data_type: acc_data_type,
-            record_type: acc_record_type,
+            data_constructor,
+            record_type,
             ..
         } = dataflow_fields(lp, self.output, namer);
         let closure_value = namer.operator_closure_value_name(self_key);
 
-        let (init_fields, update_fields): (Vec<_>, Vec<_>) = self
-            .fold_fields
-            .iter()
-            .map(|(rf, plan::FoldField { initial, update })| {
-                let field_name = namer.transform_field_name(rf);
-                (
-                    quote!(#field_name: #initial),
-                    quote!(#field_name: { #update }),
-                )
-            })
-            .unzip();
+        // all input fields are moved to the output, so internal fields are
+        // bound to their own names, rather than ignored
+        let (input_fields, output_fields): (Vec<_>, Vec<_>) = input_record_type.fields.keys().map(|rf| {
+            let field_name = namer.transform_field_name(rf);
+            let alias = match rf {
+                plan::RecordField::User(id) => id.clone(),
+                plan::RecordField::Internal(_) => field_name.clone(),
+            };
+            (quote!(#field_name: #alias), quote!(#field_name: #alias))
+        }).unzip();
 
-        let acc_fields = expose_user_fields(acc_record_type, namer);
-        let input_fields = expose_user_fields(input_record_type, namer);
+        let mut state_names = Vec::new();
+        let mut state_types = Vec::new();
+        let mut state_inits = Vec::new();
+        let mut computes = Vec::new();
+        let mut computed_fields = Vec::new();
+        let mut uses_peer = false;
+        for (rf, function) in &self.compute {
+            let field_name = namer.transform_field_name(rf);
+            let state = new_id(&format!("window_state_{field_name}"));
+            let value = new_id(&format!("window_value_{field_name}"));
+            let ty = generate_scalar_type(lp, &gen_info.get_types, record_type.fields[rf], namer);
+            let (state_type, state_init, compute) = match function {
+                plan::WindowFunction::RowNumber => (
+                    quote!(#ty),
+                    quote!(0),
+                    quote!{
+                        *#state += 1;
+                        let #value = *#state;
+                    },
+                ),
+                plan::WindowFunction::Rank => {
+                    uses_peer = true;
+                    (
+                        quote!((#ty, #ty)),
+                        quote!((0, 0)),
+                        quote!{
+                            #state.0 += 1;
+                            if !peer {
+                                #state.1 = #state.0;
+                            }
+                            let #value = #state.1;
+                        },
+                    )
+                }
+                plan::WindowFunction::DenseRank => {
+                    uses_peer = true;
+                    (
+                        quote!(#ty),
+                        quote!(0),
+                        quote!{
+                            if !peer {
+                                *#state += 1;
+                            }
+                            let #value = *#state;
+                        },
+                    )
+                }
+                plan::WindowFunction::RunningSum(expr) => (
+                    quote!(#ty),
+                    quote!(Default::default()),
+                    quote!{
+                        let #value: #ty = #expr;
+                        *#state += #value;
+                        let #value = #state.clone();
+                    },
+                ),
+                plan::WindowFunction::RunningMin(expr) | plan::WindowFunction::RunningMax(expr) => {
+                    let keep = if matches!(function, plan::WindowFunction::RunningMin(_)) {
+                        quote!(current <= #value)
+                    } else {
+                        quote!(current >= #value)
+                    };
+                    (
+                        quote!(Option<#ty>),
+                        quote!(None),
+                        quote!{
+                            let #value: #ty = #expr;
+                            let #value = match #state.take() {
+                                Some(current) if #keep => current,
+                                _ => #value,
+                            };
+                            *#state = Some(#value.clone());
+                        },
+                    )
+                }
+            };
+            state_names.push(state);
+            state_types.push(state_type);
+            state_inits.push(state_init);
+            computes.push(compute);
+            computed_fields.push(quote!(#field_name: #value));
+        }
+        let peer = if uses_peer { quote!(peer) } else { quote!(_) };
 
-        context_vals.push((closure_value.clone(), quote! {
-            (
-                #acc_constructor {
-                    #(#init_fields,)*
-                    #phantom_field: std::marker::PhantomData
-                },
-                |#acc_constructor { #(#acc_fields,)* } : #acc_data_type, #input_data_constructor { #(#input_fields,)* }  | {
-                    #acc_constructor {
-                        #(#update_fields,)*
-                        #phantom_field: std::marker::PhantomData
+        context_vals.push((
+            closure_value.clone(),
+            quote! {
+                (
+                    {
+                        let initial: (#(#state_types,)*) = (#(#state_inits,)*);
+                        initial
+                    },
+                    |(#(#state_names,)*): &mut (#(#state_types,)*), #peer: bool, #input_constructor { #(#input_fields,)* #phantom_field: _ }| {
+                        #(#computes)*
+                        #data_constructor {
+                            #(#output_fields,)*
+                            #(#computed_fields,)*
+                            #phantom_field: std::marker::PhantomData
+                        }
                     }
+                )
+            }
+            .into(),
+        ));
+
+        let order_greater = quote!(std::cmp::Ordering::Greater);
+        let order_equal = quote!(std::cmp::Ordering::Equal);
+        let order_less = quote!(std::cmp::Ordering::Less);
+
+        let comparisons = self.order.iter().map(|(rf, order)| {
+            let (gt_result, lt_result) = match order {
+                plan::SortOrder::Asc => (&order_greater, &order_less),
+                plan::SortOrder::Desc => (&order_less, &order_greater),
+            };
+            let field_name = namer.transform_field_name(rf);
+            quote! {
+                match left.#field_name.cmp(&right.#field_name) {
+                    std::cmp::Ordering::Greater => return #gt_result,
+                    std::cmp::Ordering::Less => return #lt_result,
+                    std::cmp::Ordering::Equal => (),
                 }
-            )
-        }
-        .into()));
+            }
+        });
 
-        let fold_access_member = namer.access_stat_member(required_stats.add_stat(StatKind::Fold));
+        let partition_names = self.partition.iter().map(|rf| namer.transform_field_name(rf));
 
-        quote! {
+        let window_access_member = namer.access_stat_member(required_stats.add_stat(StatKind::Window));
+
+        quote!{
             let #holding_var = {
-                let (init, update) = #closure_value;
-                #impl_alias::fold(#input_holding, init, update, #fold_access_member)
+                let (initial, compute) = #closure_value;
+                #impl_alias::window(
+                    #input_holding,
+                    |data| (#(data.#partition_names.clone(),)*),
+                    |left, right| {
+                        #(#comparisons)*
+                        #order_equal
+                    },
+                    initial,
+                    compute,
+                    #window_access_member
+                )
             };
-        }
-        .into()
+        }.into()
     }
 }
-impl OperatorGen for plan::Filter {
+impl OperatorGen for plan::Take {
     fn apply<'imm>(
         &self,
         self_key: plan::Key<plan::Operator>,
@@ -909,22 +2036,24 @@ impl OperatorGen for plan::Filter {
             holding_var,
             ..
         } = dataflow_fields(lp, self.output, namer);
+
         let closure_value = namer.operator_closure_value_name(self_key);
 
+        let take_expr = &self.limit;
+
         context_vals.push((
             closure_value.clone(),
-            (boolean_predicate(lp, &self.predicate, self.input, namer).into_token_stream()).into(),
+            quote! { {let limit: usize = #take_expr; limit} }.into(),
         ));
 
-        let filter_access_member = namer.access_stat_member(required_stats.add_stat(StatKind::Filter));
+        let stats = namer.access_stat_member(required_stats.add_stat(StatKind::Take));
 
         quote!{
-            let #holding_var = #impl_alias::filter(#input_holding, #closure_value, #filter_access_member);
+            let #holding_var = #impl_alias::take(#input_holding, #closure_value, #stats);
         }.into()
     }
 }
-
-impl OperatorGen for plan::Combine {
+impl OperatorGen for plan::Skip {
     fn apply<'imm>(
         &self,
         self_key: plan::Key<plan::Operator>,
@@ -938,72 +2067,94 @@ impl OperatorGen for plan::Combine {
         OperatorImpl { impl_alias, .. }: &OperatorImpl,
         required_stats: &mut RequiredStats,
     ) -> Tokens<Stmt> {
-        let SerializedNamer {
-            phantom_field,
-            ..
-        } = namer;
         let DataFlowNaming {
             holding_var: input_holding,
-            data_type,
-            data_constructor,
             ..
         } = dataflow_fields(lp, self.input, namer);
         let DataFlowNaming {
             holding_var,
             ..
         } = dataflow_fields(lp, self.output, namer);
-        let Self {
-            left_name,
-            right_name,
-            ..
-        } = self;
+
         let closure_value = namer.operator_closure_value_name(self_key);
 
-        let (field_defaults, field_sets): (Vec<_>, Vec<_>) = self.update_fields.iter().map(|(field, FoldField { initial, update })| {
-            let field_name = namer.transform_field_name(field);
-            (quote!(#field_name: #initial), quote!(#field_name: #update))
-        }).unzip();
+        match &self.from {
+            plan::SkipFrom::Offset(offset) => {
+                context_vals.push((
+                    closure_value.clone(),
+                    quote! { {let offset: usize = #offset; offset} }.into(),
+                ));
 
-        context_vals.push((
-            closure_value.clone(),
-            quote! {
-                (
-                    #data_constructor {
-                        #(#field_defaults,)*
-                        #phantom_field: std::marker::PhantomData
-                    },
-                    |#left_name: #data_type, #right_name: #data_type|
-                        #data_constructor {
-                            #(#field_sets,)*
-                            #phantom_field: std::marker::PhantomData
-                    }
-                )
+                let stats = namer.access_stat_member(required_stats.add_stat(StatKind::Skip));
+
+                quote!{
+                    let #holding_var = #impl_alias::skip(#input_holding, #closure_value, #stats);
+                }.into()
             }
-            .into(),
-        ));
+            plan::SkipFrom::Key { sort_order, key } => {
+                // a single sort field's key does not need to be a tuple
+                let key = if sort_order.len() == 1 {
+                    quote!((#key,))
+                } else {
+                    quote!(#key)
+                };
+                context_vals.push((closure_value.clone(), key.into()));
+
+                // skip rows ordered before, or equal to the key
+                let comparisons = sort_order.iter().enumerate().map(|(index, (rf, order))| {
+                    let (before, after) = match order {
+                        plan::SortOrder::Asc => (quote!(Less), quote!(Greater)),
+                        plan::SortOrder::Desc => (quote!(Greater), quote!(Less)),
+                    };
+                    let field_name = namer.transform_field_name(rf);
+                    let index = syn::Index::from(index);
+                    quote! {
+                        match data.#field_name.cmp(&#closure_value.#index) {
+                            std::cmp::Ordering::#before => return true,
+                            std::cmp::Ordering::#after => return false,
+                            std::cmp::Ordering::Equal => (),
+                        }
+                    }
+                });
 
-        let combine_access_member = namer.access_stat_member(required_stats.add_stat(StatKind::Combine));
+                let stats = namer.access_stat_member(required_stats.add_stat(StatKind::SkipWhile));
 
-        quote!{
-            let #holding_var = {
-                let (alternative, update) = #closure_value;
-                #impl_alias::combine(#input_holding, alternative, update, #combine_access_member)   
-            };
-        }.into()
+                quote!{
+                    let #holding_var = #impl_alias::skip_while(#input_holding, |data| {
+                        #(#comparisons)*
+                        true
+                    }, #stats);
+                }.into()
+            }
+            plan::SkipFrom::Row { field, row } => {
+                context_vals.push((closure_value.clone(), quote!(#row).into()));
+
+                let field_name = namer.transform_field_name(field);
+                let skip_while_stats = namer.access_stat_member(required_stats.add_stat(StatKind::SkipWhile));
+                let skip_stats = namer.access_stat_member(required_stats.add_stat(StatKind::Skip));
+
+                quote!{
+                    let #holding_var = #impl_alias::skip(
+                        #impl_alias::skip_while(#input_holding, |data| data.#field_name != #closure_value, #skip_while_stats),
+                        1,
+                        #skip_stats
+                    );
+                }.into()
+            }
+        }
     }
 }
-
-impl OperatorGen for plan::Sort {
+impl OperatorGen for plan::TopK {
     fn apply<'imm>(
         &self,
-        _self_key: plan::Key<plan::Operator>,
+        self_key: plan::Key<plan::Operator>,
         lp: &'imm plan::Plan,
         namer: &SerializedNamer,
         _error_path: &Tokens<Path>,
         _errors: &mut PushMap<'_, Ident, Option<Tokens<Path>>>,
         _parent_scope: &mut ScopeHandle<'_, plan::ImmKey<'imm, plan::Table>>,
         _gen_info: &GeneratedInfo<'imm>,
-        _context_vals: &mut Vec<(Ident, Tokens<Expr>)>,
+        context_vals: &mut Vec<(Ident, Tokens<Expr>)>,
         OperatorImpl { impl_alias, .. }: &OperatorImpl,
         required_stats: &mut RequiredStats,
     ) -> Tokens<Stmt> {
@@ -1035,52 +2186,20 @@ impl OperatorGen for plan::Sort {
             }
         });
 
-        let sort_access_member = namer.access_stat_member(required_stats.add_stat(StatKind::Sort));
-
-        quote!{
-            let #holding_var = #impl_alias::sort(#input_holding, |left, right| {
-                #(#comparisons)*
-                #order_equal
-            }, #sort_access_member);
-        }.into()
-    }
-}
-impl OperatorGen for plan::Take {
-    fn apply<'imm>(
-        &self,
-        self_key: plan::Key<plan::Operator>,
-        lp: &'imm plan::Plan,
-        namer: &SerializedNamer,
-        _error_path: &Tokens<Path>,
-        _errors: &mut PushMap<'_, Ident, Option<Tokens<Path>>>,
-        _parent_scope: &mut ScopeHandle<'_, plan::ImmKey<'imm, plan::Table>>,
-        _gen_info: &GeneratedInfo<'imm>,
-        context_vals: &mut Vec<(Ident, Tokens<Expr>)>,
-        OperatorImpl { impl_alias, .. }: &OperatorImpl,
-        required_stats: &mut RequiredStats,
-    ) -> Tokens<Stmt> {
-        let DataFlowNaming {
-            holding_var: input_holding,
-            ..
-        } = dataflow_fields(lp, self.input, namer);
-        let DataFlowNaming {
-            holding_var,
-            ..
-        } = dataflow_fields(lp, self.output, namer);
-
         let closure_value = namer.operator_closure_value_name(self_key);
-
-        let take_expr = &self.limit;
-
+        let limit_expr = &self.limit;
         context_vals.push((
             closure_value.clone(),
-            quote! { {let limit: usize = #take_expr; limit} }.into(),
+            quote! { {let limit: usize = #limit_expr; limit} }.into(),
         ));
 
-        let stats = namer.access_stat_member(required_stats.add_stat(StatKind::Take));
+        let stats = namer.access_stat_member(required_stats.add_stat(StatKind::TopK));
 
         quote!{
-            let #holding_var = #impl_alias::take(#input_holding, #closure_value, #stats);
+            let #holding_var = #impl_alias::top_k(#input_holding, |left, right| {
+                #(#comparisons)*
+                #order_equal
+            }, #closure_value, #stats);
         }.into()
     }
 }
@@ -1214,47 +2333,93 @@ impl OperatorGen for plan::Join {
         let left_field = namer.transform_field_name(&self.left.identifier);
         let right_field = namer.transform_field_name(&self.right.identifier);
 
-        let joined = match &self.join_kind {
-            plan::JoinKind::Inner => match &self.match_kind {
-                plan::MatchKind::Cross => {
-                    let cross_stats = namer.access_stat_member(required_stats.add_stat(StatKind::CrossJoin));
-                    quote! {#impl_alias::join_cross(#left_hold_var, #right_hold_var, #cross_stats)}
-                }
-                plan::MatchKind::Pred(predicate) => {
-                    let join_pred_stats = namer.access_stat_member(required_stats.add_stat(StatKind::PredJoin));
-                    let join_pred = namer.operator_closure_value_name(self_key);
-
-                    context_vals.push((
-                        join_pred.clone(),
-                        quote! {
-                            |left: &#data_left, right: &#data_right| -> bool {
-                                #predicate
-                            }
-                        }
-                        .into(),
-                    ));
+        let (cross_fn, cross_stat, pred_fn, pred_stat, equi_fn, equi_stat) = match &self.join_kind {
+            plan::JoinKind::Inner => (
+                quote!(cross_join), StatKind::CrossJoin,
+                quote!(predicate_join), StatKind::PredJoin,
+                quote!(equi_join), StatKind::EquiJoin,
+            ),
+            plan::JoinKind::Left | plan::JoinKind::Right => (
+                quote!(left_cross_join), StatKind::LeftCrossJoin,
+                quote!(left_predicate_join), StatKind::LeftPredJoin,
+                quote!(left_equi_join), StatKind::LeftEquiJoin,
+            ),
+            plan::JoinKind::Full => (
+                quote!(full_cross_join), StatKind::FullCrossJoin,
+                quote!(full_predicate_join), StatKind::FullPredJoin,
+                quote!(full_equi_join), StatKind::FullEquiJoin,
+            ),
+        };
 
-                    quote! {#impl_alias::predicate_join(#left_hold_var, #right_hold_var, #join_pred, #join_pred_stats)}
-                }
-                plan::MatchKind::Equi {
-                    left_field,
-                    right_field,
-                } => {
-                    let join_equi_stats = namer.access_stat_member(required_stats.add_stat(StatKind::EquiJoin));
-                    let left_select = namer.transform_field_name(left_field);
-                    let right_select = namer.transform_field_name(right_field);
+        // NOTE: Right outer joins are implemented as left outer joins with the 
+        //       inputs swapped, so all arguments are ordered through this.
+        let swap_inputs = matches!(self.join_kind, plan::JoinKind::Right);
+        let order = |left: proc_macro2::TokenStream, right: proc_macro2::TokenStream| {
+            if swap_inputs {
+                quote!(#right, #left)
+            } else {
+                quote!(#left, #right)
+            }
+        };
+        let inputs = order(quote!(#left_hold_var), quote!(#right_hold_var));
+
+        let joined = match &self.match_kind {
+            plan::MatchKind::Cross => {
+                let cross_stats = namer.access_stat_member(required_stats.add_stat(cross_stat));
+                quote! {#impl_alias::#cross_fn(#inputs, #cross_stats)}
+            }
+            plan::MatchKind::Pred(predicate) => {
+                let join_pred_stats = namer.access_stat_member(required_stats.add_stat(pred_stat));
+                let join_pred = namer.operator_closure_value_name(self_key);
+                let pred_args = order(quote!(left: &#data_left), quote!(right: &#data_right));
+
+                context_vals.push((
+                    join_pred.clone(),
                     quote! {
-                        {
-                            #impl_alias::equi_join(#left_hold_var, #right_hold_var, |left: &#data_left| &left.#left_select, |right: &#data_right| &right.#right_select, #join_equi_stats)
+                        |#pred_args| -> bool {
+                            #predicate
                         }
                     }
+                    .into(),
+                ));
+
+                quote! {#impl_alias::#pred_fn(#inputs, #join_pred, #join_pred_stats)}
+            }
+            plan::MatchKind::Equi {
+                left_field,
+                right_field,
+            } => {
+                let join_equi_stats = namer.access_stat_member(required_stats.add_stat(equi_stat));
+                let left_select = namer.transform_field_name(left_field);
+                let right_select = namer.transform_field_name(right_field);
+                let splits = order(
+                    quote!(|left: &#data_left| &left.#left_select),
+                    quote!(|right: &#data_right| &right.#right_select),
+                );
+                quote! {
+                    {
+                        #impl_alias::#equi_fn(#inputs, #splits, #join_equi_stats)
+                    }
                 }
-            },
+            }
+        };
+
+        let left_type = if self.join_kind.left_optional() {
+            quote!(Option<#data_left>)
+        } else {
+            quote!(#data_left)
         };
+        let right_type = if self.join_kind.right_optional() {
+            quote!(Option<#data_right>)
+        } else {
+            quote!(#data_right)
+        };
+        let joined_pattern = order(quote!(left), quote!(right));
+        let joined_type = order(left_type, right_type);
 
         let map_stats = namer.access_stat_member(required_stats.add_stat(StatKind::Map));
         quote! {
-            let #holding_var = #impl_alias::map(#joined, |(left, right): (#data_left, #data_right)| {
+            let #holding_var = #impl_alias::map(#joined, |(#joined_pattern): (#joined_type)| {
                 #data_constructor {
                     #left_field: left,
                     #right_field: right,
@@ -1356,6 +2521,119 @@ impl OperatorGen for plan::Union {
         .into()
     }
 }
+impl OperatorGen for plan::Intersect {
+    fn apply<'imm>(
+        &self,
+        _self_key: plan::Key<plan::Operator>,
+        lp: &'imm plan::Plan,
+        namer: &SerializedNamer,
+        _error_path: &Tokens<Path>,
+        _errors: &mut PushMap<'_, Ident, Option<Tokens<Path>>>,
+        _parent_scope: &mut ScopeHandle<'_, plan::ImmKey<'imm, plan::Table>>,
+        _gen_info: &GeneratedInfo<'imm>,
+        _context_vals: &mut Vec<(Ident, Tokens<Expr>)>,
+        OperatorImpl { impl_alias, .. }: &OperatorImpl,
+        required_stats: &mut RequiredStats,
+    ) -> Tokens<Stmt> {
+        let DataFlowNaming {
+            holding_var,
+            data_type,
+            record_type,
+            ..
+        } = dataflow_fields(lp, self.output, namer);
+        let key = set_operation_key(&data_type, record_type, namer);
+
+        let mut inflows = self.inputs.iter();
+        let first_input = inflows.next().expect("Cannot generate intersect of no data");
+        let first_holding_in = dataflow_fields(lp, *first_input, namer).holding_var;
+        let body = inflows.fold(quote! {#first_holding_in}, |prev, df| {
+            let var = dataflow_fields(lp, *df, namer).holding_var;
+            let intersect_stats = namer.access_stat_member(required_stats.add_stat(StatKind::Intersect));
+            quote! {
+                #impl_alias::intersect(#prev, #var, key, #intersect_stats)
+            }
+        });
+        let body = match self.semantics {
+            plan::SetSemantics::Set => {
+                let distinct_stats = namer.access_stat_member(required_stats.add_stat(StatKind::Distinct));
+                quote! { #impl_alias::distinct(#body, key, #distinct_stats) }
+            }
+            plan::SetSemantics::Bag => body,
+        };
+
+        quote! {
+            let #holding_var = {
+                let key = #key;
+                #body
+            };
+        }
+        .into()
+    }
+}
+impl OperatorGen for plan::Except {
+    fn apply<'imm>(
+        &self,
+        _self_key: plan::Key<plan::Operator>,
+        lp: &'imm plan::Plan,
+        namer: &SerializedNamer,
+        _error_path: &Tokens<Path>,
+        _errors: &mut PushMap<'_, Ident, Option<Tokens<Path>>>,
+        _parent_scope: &mut ScopeHandle<'_, plan::ImmKey<'imm, plan::Table>>,
+        _gen_info: &GeneratedInfo<'imm>,
+        _context_vals: &mut Vec<(Ident, Tokens<Expr>)>,
+        OperatorImpl { impl_alias, .. }: &OperatorImpl,
+        required_stats: &mut RequiredStats,
+    ) -> Tokens<Stmt> {
+        let DataFlowNaming {
+            holding_var,
+            data_type,
+            record_type,
+            ..
+        } = dataflow_fields(lp, self.output, namer);
+        let key = set_operation_key(&data_type, record_type, namer);
+
+        let mut inflows = self.inputs.iter();
+        let first_input = inflows.next().expect("Cannot generate except of no data");
+        let first_holding_in = dataflow_fields(lp, *first_input, namer).holding_var;
+
+        // removing duplicates from the first input is enough, as each record
+        // removed from it is also removed from the output
+        let first = match self.semantics {
+            plan::SetSemantics::Set => {
+                let distinct_stats = namer.access_stat_member(required_stats.add_stat(StatKind::Distinct));
+                quote! { #impl_alias::distinct(#first_holding_in, key, #distinct_stats) }
+            }
+            plan::SetSemantics::Bag => quote! {#first_holding_in},
+        };
+        let body = inflows.fold(first, |prev, df| {
+            let var = dataflow_fields(lp, *df, namer).holding_var;
+            let except_stats = namer.access_stat_member(required_stats.add_stat(StatKind::Except));
+            quote! {
+                #impl_alias::except(#prev, #var, key, #except_stats)
+            }
+        });
+
+        quote! {
+            let #holding_var = {
+                let key = #key;
+                #body
+            };
+        }
+        .into()
+    }
+}
+
+/// The key compared by set operations, containing every field of the record
+fn set_operation_key(
+    data_type: &Tokens<syn::Type>,
+    record_type: &plan::RecordConc,
+    namer: &SerializedNamer,
+) -> TokenStream {
+    let field_names = record_type.fields.keys().map(|rf| namer.transform_field_name(rf));
+    quote! {
+        |data: &#data_type| (#(data.#field_names.clone(),)*)
+    }
+}
 impl OperatorGen for plan::Row {
     fn apply<'imm>(
         &self,
@@ -1405,6 +2683,29 @@ impl OperatorGen for plan::Row {
     }
 }
 
+impl OperatorGen for plan::ScanBag {
+    fn apply<'imm>(
+        &self,
+        _self_key: plan::Key<plan::Operator>,
+        lp: &'imm plan::Plan,
+        namer: &SerializedNamer,
+        _error_path: &Tokens<Path>,
+        _errors: &mut PushMap<'_, Ident, Option<Tokens<Path>>>,
+        _parent_scope: &mut ScopeHandle<'_, plan::ImmKey<'imm, plan::Table>>,
+        _gen_info: &GeneratedInfo<'imm>,
+        _context_vals: &mut Vec<(Ident, Tokens<Expr>)>,
+        OperatorImpl { impl_alias, .. }: &OperatorImpl,
+        _required_stats: &mut RequiredStats,
+    ) -> Tokens<Stmt> {
+        let DataFlowNaming { holding_var, .. } = dataflow_fields(lp, self.output, namer);
+        let bag = &self.bag;
+        quote! {
+            let #holding_var = #impl_alias::consume_buffer(#bag);
+        }
+        .into()
+    }
+}
+
 impl OperatorGen for plan::Return {
     fn apply<'imm>(
         &self,
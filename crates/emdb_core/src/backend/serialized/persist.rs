@@ -0,0 +1,182 @@
+//! # Persistence
+//! With `persist = on` the datastore can be snapshot to, and restored from the
+//! format described in [`pulpit::persist`].
+//! - `Database::snapshot(&self, writer)` writes every table, scanning through
+//!   the database's windows (so it can be called between queries).
+//! - `Datastore::restore(reader)` creates a new datastore, restoring every row
+//!   with the key it had when snapshot.
+//!
+//! As keys are kept, keys held by the user and reference columns are still
+//! valid for the restored datastore.
+//! - Tables without deletions are restored by inserting rows in the order they
+//!   were written.
+//! - Tables with deletions always use the `Thunderdome` table selector, so each
+//!   row's key can be written and the row restored at it (see
+//!   [`pulpit::persist`] for the caveats).
+//!
+//! Tables are written in declaration order, and each table's columns ordered
+//! by name.
+//!
+//! All table column types must implement [`pulpit::persist::Persist`].
+
+use super::namer::SerializedNamer;
+use crate::{
+    backend::interface::{namer::InterfaceNamer, InterfaceTrait},
+    plan,
+};
+use pulpit::gen::namer::CodeNamer;
+use quote::quote;
+use quote_debug::Tokens;
+use syn::ItemImpl;
+
+use super::tables::GeneratedInfo;
+
+pub struct Persistence {
+    pub datastore_restore: Tokens<ItemImpl>,
+    pub database_snapshot: Tokens<ItemImpl>,
+}
+
+pub fn generate_persistence(
+    lp: &plan::Plan,
+    gen_info: &GeneratedInfo,
+    interface_trait: &Option<InterfaceTrait>,
+    namer: &SerializedNamer,
+    concurrent: bool,
+) -> Persistence {
+    let SerializedNamer {
+        pulpit:
+            CodeNamer {
+                pulpit_path,
+                mod_insert,
+                mod_insert_struct_insert,
+                struct_window_method_insert,
+                struct_window_method_restore,
+                struct_window_method_commit,
+                struct_window_method_count,
+                struct_window_method_scan_brw,
+                struct_window_method_borrow,
+                ..
+            },
+        struct_datastore,
+        struct_database,
+        db_lifetime,
+        mod_tables,
+        interface:
+            InterfaceNamer {
+                trait_datastore,
+                trait_datastore_method_new,
+                ..
+            },
+        ..
+    } = namer;
+
+    let (restores, snapshots): (Vec<_>, Vec<_>) = lp
+        .tables
+        .iter()
+        .map(|(key, table)| {
+            let table_name = namer.table_internal_name(lp, key);
+            let table_str = table.name.to_string();
+            // columns are ordered by name, so the format does not depend on
+            // hashmap iteration order
+            let mut fields = table
+                .columns
+                .keys()
+                .map(|field| namer.transform_field_name(field))
+                .collect::<Vec<_>>();
+            fields.sort();
+            let field_strs = fields.iter().map(|f| f.to_string()).collect::<Vec<_>>();
+
+            let row = quote! {
+                #mod_tables::#table_name::#mod_insert::#mod_insert_struct_insert {
+                    #(#fields: #pulpit_path::persist::Persist::restore(reader)?,)*
+                }
+            };
+
+            // rows of tables with deletions are written after their keys
+            let (insert, persist_key) = if gen_info.restores[&plan::Idx::new(key, lp)] {
+                (
+                    quote! {
+                        window.#struct_window_method_restore(#pulpit_path::persist::Persist::restore(reader)?, #row)
+                            .map_err(|_| #pulpit_path::persist::invalid_data(
+                                concat!("A row in table `", #table_str, "` violates its constraints, or has the key of another row")
+                            ))?;
+                    },
+                    quote!(#pulpit_path::persist::Persist::persist(&key, writer)?;),
+                )
+            } else if gen_info.insert_can_error[&plan::Idx::new(key, lp)] {
+                (
+                    quote! {
+                        window.#struct_window_method_insert(#row).map_err(|_| #pulpit_path::persist::invalid_data(
+                            concat!("A row in table `", #table_str, "` violates its constraints")
+                        ))?;
+                    },
+                    quote!(),
+                )
+            } else {
+                (quote!(window.#struct_window_method_insert(#row);), quote!())
+            };
+
+            let window = if concurrent {
+                quote!(self.#table_name.read().unwrap())
+            } else {
+                quote!(&self.#table_name)
+            };
+
+            (
+                quote! {
+                    {
+                        let mut window = datastore.#table_name.window();
+                        let rows = #pulpit_path::persist::read_table_header(reader, #table_str, &[#(#field_strs),*])?;
+                        for _ in 0..rows {
+                            #insert
+                        }
+                        window.#struct_window_method_commit();
+                    }
+                },
+                quote! {
+                    {
+                        let window = #window;
+                        #pulpit_path::persist::write_table_header(writer, #table_str, &[#(#field_strs),*], window.#struct_window_method_count())?;
+                        for key in window.#struct_window_method_scan_brw() {
+                            let row = window.#struct_window_method_borrow(key).expect("Scanned keys are valid");
+                            #persist_key
+                            #(#pulpit_path::persist::Persist::persist(row.#fields, writer)?;)*
+                        }
+                    }
+                },
+            )
+        })
+        .unzip();
+
+    let new_datastore = if let Some(InterfaceTrait { name }) = interface_trait {
+        quote!(<Self as super::#name::#trait_datastore>::#trait_datastore_method_new())
+    } else {
+        quote!(Self::#trait_datastore_method_new())
+    };
+
+    Persistence {
+        datastore_restore: quote! {
+            impl #struct_datastore {
+                /// Create a new datastore from a snapshot written by `snapshot`.
+                pub fn restore<R: std::io::Read>(reader: &mut R) -> std::io::Result<Self> {
+                    #pulpit_path::persist::read_header(reader)?;
+                    let mut datastore = #new_datastore;
+                    #(#restores)*
+                    Ok(datastore)
+                }
+            }
+        }
+        .into(),
+        database_snapshot: quote! {
+            impl <#db_lifetime> #struct_database<#db_lifetime> {
+                /// Write all tables to a snapshot, that can be restored with `restore`.
+                pub fn snapshot<W: std::io::Write>(&self, writer: &mut W) -> std::io::Result<()> {
+                    #pulpit_path::persist::write_header(writer)?;
+                    #(#snapshots)*
+                    Ok(())
+                }
+            }
+        }
+        .into(),
+    }
+}
@@ -1,12 +1,14 @@
 use std::{collections::HashMap, iter::once};
 
+use itertools::Itertools;
+use proc_macro2::TokenStream;
 use pulpit::gen::namer::CodeNamer;
 use quote::quote;
 use quote_debug::Tokens;
-use syn::{ExprBlock, Ident, ImplItemFn, ItemEnum, ItemImpl, ItemMod, Path};
+use syn::{ExprBlock, Ident, ImplItemFn, ItemImpl, ItemMod, Path};
 
 use crate::{
-    backend::interface::{namer::InterfaceNamer, InterfaceTrait}, plan, utils::{misc::PushMap, mut_scope::{Mutability, ScopeData, ScopeHandle}}
+    analysis::concurrency::Conflicts, backend::interface::{namer::InterfaceNamer, InterfaceTrait}, plan, utils::{misc::PushMap, mut_scope::{Mutability, ScopeData, ScopeHandle}}
 };
 
 use super::{
@@ -19,7 +21,7 @@ fn generate_errors(
         mod_queries_mod_query_enum_error,
         ..
     }: &SerializedNamer,
-) -> Option<Tokens<ItemEnum>> {
+) -> Option<TokenStream> {
     if errors.is_empty() {
         None
     } else {
@@ -30,19 +32,36 @@ fn generate_errors(
                 quote!(#name)
             }
         });
-        Some(
-            quote! {
-                #[derive(Debug)]
-                pub enum #mod_queries_mod_query_enum_error {
-                    #(#variants),*
+        // errors from tables and references carry their context, the only
+        // variants without are from `assert`
+        let displays = errors.iter().map(|(name, inner)| {
+            if inner.is_some() {
+                quote!(Self::#name(err) => std::fmt::Display::fmt(err, f))
+            } else {
+                quote!(Self::#name => f.write_str("An assertion in the query failed"))
+            }
+        });
+        Some(quote! {
+            #[derive(Debug)]
+            pub enum #mod_queries_mod_query_enum_error {
+                #(#variants),*
+            }
+
+            impl std::fmt::Display for #mod_queries_mod_query_enum_error {
+                fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+                    match self {
+                        #(#displays,)*
+                    }
                 }
             }
-            .into(),
-        )
+
+            impl std::error::Error for #mod_queries_mod_query_enum_error {}
+        })
     }
 }
 
 struct CommitInfo {
+    savepoints: TokenStream,
     commits: Tokens<ExprBlock>,
     aborts: Tokens<ExprBlock>,
 }
@@ -51,36 +70,58 @@ fn generate_commits<'imm>(
     lp: &'imm plan::Plan,
     mutated_tables: ScopeHandle<'_, plan::ImmKey<'imm, plan::Table>>,
     namer: &SerializedNamer,
+    concurrent: bool,
 ) -> Option<CommitInfo> {
     let SerializedNamer {
         pulpit:
             CodeNamer {
                 struct_window_method_commit,
-                struct_window_method_abort,
+                struct_window_method_savepoint,
+                struct_window_method_abort_to,
                 ..
             },
+        struct_database_member_transaction,
         ..
     } = namer;
 
     if !mutated_tables.mutates() {
         None
     } else {
-        let (commits, aborts): (Vec<_>, Vec<_>) = mutated_tables
+        let (savepoints, (commits, aborts)): (Vec<_>, (Vec<_>, Vec<_>)) = mutated_tables
             .mutabilities()
+            .filter(|(_, mutable)| !concurrent || matches!(mutable, Mutability::Mut))
             .map(|(key, _)| {
                 let table_name = namer.table_internal_name(lp, **key);
+                let savepoint = namer.table_savepoint_name(lp, **key);
+                // concurrent queries commit through the locks they hold
+                let table = if concurrent {
+                    quote!(#table_name)
+                } else {
+                    quote!(self.#table_name)
+                };
                 (
                     quote! {
-                        self.#table_name.#struct_window_method_commit();
-                    },
-                    quote! {
-                        self.#table_name.#struct_window_method_abort();
+                        let #savepoint = #table.#struct_window_method_savepoint();
                     },
+                    (
+                        quote! {
+                            #table.#struct_window_method_commit();
+                        },
+                        // only the query's changes are aborted, so the
+                        // transaction it is part of can continue
+                        quote! {
+                            #table.#struct_window_method_abort_to(#savepoint);
+                        },
+                    ),
                 )
             })
             .unzip();
         Some(CommitInfo {
-            commits: quote! { { #(#commits;)* } }.into(),
+            savepoints: quote! { #(#savepoints)* },
+            // queries in a transaction are committed at the end of the
+            // transaction
+            commits: quote! { { if !self.#struct_database_member_transaction { #(#commits;)* } } }
+                .into(),
             aborts: quote! { { #(#aborts;)*  } }.into(),
         })
     }
@@ -89,21 +130,74 @@ fn generate_commits<'imm>(
 struct QueryMod {
     query_mod: Tokens<ItemMod>,
     query_impl: Tokens<ImplItemFn>,
+    query_docs: TokenStream,
 }
 
 impl QueryMod {
-    fn extract(self) -> (Tokens<ItemMod>, Tokens<ImplItemFn>) {
-        (self.query_mod, self.query_impl)
+    fn extract(self) -> (Tokens<ItemMod>, TokenStream, Tokens<ImplItemFn>) {
+        (self.query_mod, self.query_docs, self.query_impl)
+    }
+}
+
+/// Documents the queries that conflict with a concurrent query, and so cannot
+/// be run in parallel with it.
+fn conflict_docs(
+    lp: &plan::Plan,
+    conflicts: &Conflicts,
+    query: plan::Key<plan::Query>,
+) -> TokenStream {
+    let conflicting = lp
+        .queries
+        .iter()
+        .filter_map(|(other, plan::Query { name, .. })| {
+            let tables = conflicts.conflicts(query, other);
+            if tables.is_empty() {
+                None
+            } else {
+                let tables = tables
+                    .iter()
+                    .map(|table| format!("`{}`", lp.get_table(*table).name))
+                    .join(", ");
+                let doc = format!(" - `{name}` on {tables}");
+                Some(quote!(#[doc = #doc]))
+            }
+        })
+        .collect::<Vec<_>>();
+    if conflicting.is_empty() {
+        quote!(#[doc = " Does not conflict with any queries."])
+    } else {
+        quote! {
+            #[doc = " Conflicts with:"]
+            #(#conflicting)*
+        }
+    }
+}
+
+/// The user's attributes for a query's method, followed by the generated docs.
+/// - `#[must_use]` and `#[deprecated]` cannot be applied to the methods
+///   implementing an interface trait, so are only used on the trait's method.
+fn query_attrs(query: &plan::Query, implements_trait: bool, docs: TokenStream) -> TokenStream {
+    let attrs = query.attrs.iter().filter(|attr| {
+        !(implements_trait
+            && (attr.path().is_ident("must_use") || attr.path().is_ident("deprecated")))
+    });
+    if docs.is_empty() || !query.attrs.iter().any(|attr| attr.path().is_ident("doc")) {
+        quote!(#(#attrs)* #docs)
+    } else {
+        quote!(#(#attrs)* #[doc = ""] #docs)
     }
 }
 
+#[allow(clippy::too_many_arguments)]
 fn generate_query<'imm>(
     lp: &'imm plan::Plan,
     gen_info: &GeneratedInfo<'imm>,
     namer: &SerializedNamer,
-    plan::Query { name, ctx }: &'imm plan::Query,
+    query_key: plan::Key<plan::Query>,
+    plan::Query { name, ctx, .. }: &'imm plan::Query,
     operator_impl: &OperatorImpl,
     required_stats: &mut RequiredStats,
+    conflicts: Option<&Conflicts>,
 ) -> QueryMod {
     let OperatorImpl { impl_alias, .. } = operator_impl;
     let SerializedNamer {
@@ -156,7 +250,11 @@ fn generate_query<'imm>(
                     Mutability::Imm => quote!(&),
                 };
                 let name = namer.table_internal_name(lp, **k);
-                quote!(#reference self.#name)
+                if conflicts.is_some() {
+                    quote!(#reference *#name)
+                } else {
+                    quote!(#reference self.#name)
+                }
             })
         ).chain(
             params_use
@@ -164,22 +262,48 @@ fn generate_query<'imm>(
     };
     let run_query = quote!((#code)(#(#toplevel_closure_args),* ));
 
+    // Concurrent queries lock all tables before starting, in the same order to
+    // avoid deadlocks.
+    let (locks, docs) = if let Some(conflicts) = conflicts {
+        let locks = scope
+            .mutabilities()
+            .sorted_by_key(|(k, _)| k.arr_idx())
+            .map(|(k, mutable)| {
+                let name = namer.table_internal_name(lp, **k);
+                match mutable {
+                    Mutability::Mut => quote!(let mut #name = self.#name.write().unwrap();),
+                    Mutability::Imm => quote!(let #name = self.#name.read().unwrap();),
+                }
+            })
+            .collect::<TokenStream>();
+        (locks, conflict_docs(lp, conflicts, query_key))
+    } else {
+        (quote!(), quote!())
+    };
+    let mut_self = if conflicts.is_some() {
+        quote!()
+    } else {
+        quote!(mut)
+    };
+
     match (
         generate_errors(errors, namer),
-        generate_commits(lp, scope, namer)
+        generate_commits(lp, scope, namer, conflicts.is_some())
     ) {
         (None, None) => {
             QueryMod {
                 query_mod: quote! { mod #name {} }.into(),
                 query_impl: quote! {
                     fn #name<#qy_lifetime>(&#qy_lifetime self, #(#params),* ) -> #return_type {
+                        #locks
                         #run_query
                     }
                 }
                 .into(),
+                query_docs: docs,
             }
         },
-        (None, Some(CommitInfo { commits, aborts:_ } )) => {
+        (None, Some(CommitInfo { commits, .. } )) => {
 
             // NOTE: This case is possible when many inserts (that do not throw errors) occur on a table, 
             //       but nothing else does. In this case we are not optimal - we could avoid transactions
@@ -189,13 +313,15 @@ fn generate_query<'imm>(
             QueryMod {
                 query_mod: quote! { mod #name {} }.into(),
                 query_impl: quote! {
-                    fn #name<#qy_lifetime>(&#qy_lifetime mut self, #(#params),* ) -> #return_type {
+                    fn #name<#qy_lifetime>(&#qy_lifetime #mut_self self, #(#params),* ) -> #return_type {
+                        #locks
                         let result = #run_query;
                         #commits
                         result
                     }
                 }
                 .into(),
+                query_docs: docs,
             }
          },
         (Some(error_enum), None) => {
@@ -205,18 +331,22 @@ fn generate_query<'imm>(
                 } }.into(),
                 query_impl: quote!{
                     fn #name<#qy_lifetime>(&#qy_lifetime self, #(#params),* ) -> Result<#return_type, #mod_queries::#name::#mod_queries_mod_query_enum_error> {
+                        #locks
                         #run_query.map(#impl_alias::export_single)
                     }
                 }.into(),
+                query_docs: docs,
             }
         }
-        (Some(error_enum), Some(CommitInfo { commits, aborts })) => {
+        (Some(error_enum), Some(CommitInfo { savepoints, commits, aborts })) => {
             QueryMod {
                 query_mod: quote!{ pub mod #name {
                     #error_enum
                 } }.into(),
                 query_impl: quote!{
-                    fn #name<#qy_lifetime>(&#qy_lifetime mut self, #(#params),* ) -> Result<#return_type, #mod_queries::#name::#mod_queries_mod_query_enum_error> {
+                    fn #name<#qy_lifetime>(&#qy_lifetime #mut_self self, #(#params),* ) -> Result<#return_type, #mod_queries::#name::#mod_queries_mod_query_enum_error> {
+                        #locks
+                        #savepoints
                         match #run_query {
                             Ok(result) => {
                                 #commits
@@ -229,6 +359,7 @@ fn generate_query<'imm>(
                         }
                     }
                 }.into(),
+                query_docs: docs,
             }
         }
     }
@@ -257,6 +388,7 @@ pub fn generate_queries<'imm>(
     namer: &'imm SerializedNamer,
     operator_impl: &OperatorImpl,
     inline_queries: bool,
+    concurrent: bool,
 ) -> QueriesInfo {
     let SerializedNamer {
         db_lifetime,
@@ -267,11 +399,15 @@ pub fn generate_queries<'imm>(
         ..
     } = namer;
     let mut required_stats = RequiredStats::new();
-    let (mods, impls): (Vec<Tokens<ItemMod>>, Vec<Tokens<ImplItemFn>>) = lp
+    let conflicts = concurrent.then(|| Conflicts::analyse(lp));
+    let (mods, docs, impls): (Vec<Tokens<ItemMod>>, Vec<TokenStream>, Vec<Tokens<ImplItemFn>>) = lp
         .queries
         .iter()
-        .map(|(_, query)| generate_query(lp, gen_info, namer, query, operator_impl, &mut required_stats).extract())
-        .unzip();
+        .map(|(key, query)| {
+            let (query_mod, docs, query_impl) = generate_query(lp, gen_info, namer, key, query, operator_impl, &mut required_stats, conflicts.as_ref()).extract();
+            (query_mod, query_attrs(query, interface_trait.is_some(), docs), query_impl)
+        })
+        .multiunzip();
 
     QueriesInfo {
         query_mod: quote! {
@@ -299,7 +435,7 @@ pub fn generate_queries<'imm>(
                 quote! {
                     impl <#db_lifetime> #impl_database #struct_database<#db_lifetime> {
                         #type_ds
-                        #(#inline_tks #modifier #impls)*
+                        #(#docs #inline_tks #modifier #impls)*
                     }
                 }
                 .into(),
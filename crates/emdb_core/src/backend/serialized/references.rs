@@ -0,0 +1,376 @@
+//! # Referential Integrity
+//! Columns constrained by `valid_ref` must reference an existing row. As each
+//! [`pulpit`] table only has access to its own rows, the checks are generated
+//! here and applied by the operators using the tables:
+//! - Inserts and updates check the referenced row exists.
+//! - Deletes call a generated `delete_references_<table>` function after
+//!   deleting the row, which fails (restrict), deletes the referencing rows
+//!   (cascade, recursively) or sets the referencing column to `None`
+//!   (set_none). Rows are found by scanning the referencing table (skipping
+//!   rows already deleted), and restrict is checked after the other actions.
+//!
+//! All violations are reported through the generated `references::Error`,
+//! with the key of the referenced row (and of the referencing row for
+//! deletes), and as the query's tables are then aborted, no changes are kept.
+
+use std::collections::HashSet;
+
+use super::namer::SerializedNamer;
+use crate::{plan, utils::mut_scope::Mutability};
+use proc_macro2::TokenStream;
+use pulpit::gen::namer::CodeNamer;
+use quote::quote;
+use quote_debug::Tokens;
+use syn::{Expr, Ident};
+
+/// Tables rows can be deleted from, either directly by a delete operator (or
+/// `delete_where`), or through a cascade.
+pub fn deleted_tables(lp: &plan::Plan) -> HashSet<plan::Key<plan::Table>> {
+    lp.operators
+        .iter()
+        .filter_map(|(_, op)| match op {
+            plan::Operator::Delete(plan::Delete { table, .. })
+            | plan::Operator::DeleteWhere(plan::DeleteWhere { table, .. }) => Some(*table),
+            _ => None,
+        })
+        .flat_map(|table| {
+            lp.delete_references(table)
+                .into_iter()
+                .filter(|r| r.cons.cons.on_delete == plan::OnDelete::Cascade)
+                .map(|r| r.table)
+                .chain([table])
+                .collect::<Vec<_>>()
+        })
+        .collect()
+}
+
+/// The tables used when deleting from a table (sorted by table), and if they
+/// need to be mutable.
+pub fn delete_tables(
+    lp: &plan::Plan,
+    table: plan::Key<plan::Table>,
+) -> Vec<(plan::Key<plan::Table>, Mutability)> {
+    let mut tables: Vec<(plan::Key<plan::Table>, Mutability)> = Vec::new();
+    for r in lp.delete_references(table) {
+        let mutability = if r.cons.cons.on_delete == plan::OnDelete::Restrict {
+            Mutability::Imm
+        } else {
+            Mutability::Mut
+        };
+        match tables.last_mut() {
+            Some((last, m)) if *last == r.table => {
+                if mutability == Mutability::Mut {
+                    *m = Mutability::Mut;
+                }
+            }
+            _ => tables.push((r.table, mutability)),
+        }
+    }
+    tables
+}
+
+/// The path to the error for a reference from a query's error enum
+pub fn reference_error_path(namer: &SerializedNamer) -> Tokens<syn::Path> {
+    let SerializedNamer {
+        mod_references,
+        mod_references_enum_error,
+        ..
+    } = namer;
+    quote!(super::super::#mod_references::#mod_references_enum_error).into()
+}
+
+/// Check a value for the column of a reference, returning the `error` if the
+/// referenced row does not exist.
+pub fn check_reference(
+    lp: &plan::Plan,
+    reference: &plan::Reference,
+    value: Tokens<Expr>,
+    error: &TokenStream,
+    namer: &SerializedNamer,
+) -> TokenStream {
+    let SerializedNamer {
+        pulpit: CodeNamer {
+            struct_window_method_borrow,
+            ..
+        },
+        mod_references,
+        mod_references_enum_error,
+        ..
+    } = namer;
+    let target = namer.table_param_name(lp, reference.cons.cons.table);
+    let variant = namer.reference_error_name(lp, reference);
+    let construct = quote!(return Err(#error(#mod_references::#mod_references_enum_error::#variant { referenced: key, referencing: None })));
+    if is_optional(lp, reference) {
+        quote! {
+            if let Some(key) = #value {
+                if #target.#struct_window_method_borrow(key).is_err() {
+                    #construct;
+                }
+            }
+        }
+    } else {
+        quote! {
+            {
+                let key = #value;
+                if #target.#struct_window_method_borrow(key).is_err() {
+                    #construct;
+                }
+            }
+        }
+    }
+}
+
+/// Call the function handling the references to a deleted row
+pub fn delete_references_call(
+    lp: &plan::Plan,
+    table: plan::Key<plan::Table>,
+    key: TokenStream,
+    namer: &SerializedNamer,
+) -> Option<TokenStream> {
+    if lp.references_to(table).next().is_none() {
+        None
+    } else {
+        let fn_name = namer.reference_delete_fn_name(lp, table);
+        let params = delete_tables(lp, table)
+            .into_iter()
+            .map(|(t, _)| namer.table_param_name(lp, t));
+        Some(quote!(#fn_name(#(#params,)* #key)))
+    }
+}
+
+fn is_optional(lp: &plan::Plan, reference: &plan::Reference) -> bool {
+    let column = lp
+        .get_table(reference.table)
+        .columns
+        .get(reference.field)
+        .unwrap();
+    matches!(
+        lp.get_scalar_type_conc(column.data_type),
+        plan::ScalarTypeConc::Option(_)
+    )
+}
+
+/// Generate the `references` module with the error type, and the functions
+/// to apply the on delete actions.
+pub fn generate_references(lp: &plan::Plan, namer: &SerializedNamer) -> TokenStream {
+    let SerializedNamer {
+        pulpit:
+            CodeNamer {
+                struct_window,
+                struct_window_method_borrow,
+                struct_window_method_scan_brw,
+                struct_window_method_delete,
+                type_key,
+                mod_update,
+                mod_update_struct_update,
+                ..
+            },
+        mod_tables,
+        mod_references,
+        mod_references_enum_error,
+        db_lifetime,
+        ..
+    } = namer;
+
+    let references = lp
+        .tables
+        .iter()
+        .flat_map(|(key, _)| lp.references_from(key))
+        .collect::<Vec<_>>();
+
+    if references.is_empty() {
+        return quote!();
+    }
+
+    let variants = references.iter().map(|r| {
+        let variant = namer.reference_error_name(lp, r);
+        let referenced_mod = namer.table_internal_name(lp, r.cons.cons.table);
+        let referencing_mod = namer.table_internal_name(lp, r.table);
+        quote! {
+            #variant {
+                referenced: super::#mod_tables::#referenced_mod::#type_key,
+                referencing: Option<super::#mod_tables::#referencing_mod::#type_key>
+            }
+        }
+    });
+    let debugs = references.iter().map(|r| {
+        let variant = namer.reference_error_name(lp, r);
+        let variant_str = variant.to_string();
+        quote!(Self::#variant { .. } => f.debug_struct(#variant_str).finish_non_exhaustive())
+    });
+    let displays = references.iter().map(|r| {
+        let variant = namer.reference_error_name(lp, r);
+        let table = &lp.get_table(r.table).name;
+        let referenced = &lp.get_table(r.cons.cons.table).name;
+        let field = r.field;
+        let alias = &r.cons.alias;
+        let missing = format!(
+            "`{table}.{field}` references a row of `{referenced}` that does not exist (`{alias}`)"
+        );
+        let referencing = format!(
+            "The deleted row of `{referenced}` is still referenced by a row of `{table}` (`{alias}`)"
+        );
+        quote! {
+            Self::#variant { referencing: None, .. } => f.write_str(#missing),
+            Self::#variant { referencing: Some(_), .. } => f.write_str(#referencing)
+        }
+    });
+
+    let mut deleted = deleted_tables(lp).into_iter().collect::<Vec<_>>();
+    deleted.sort_by_key(|t| t.arr_idx());
+
+    let delete_fns = deleted
+        .into_iter()
+        .filter(|table| lp.references_to(*table).next().is_some())
+        .map(|table| {
+            let fn_name = namer.reference_delete_fn_name(lp, table);
+            let table_mod = namer.table_internal_name(lp, table);
+            let params = delete_tables(lp, table).into_iter().map(|(t, mutability)| {
+                let param = namer.table_param_name(lp, t);
+                let t_mod = namer.table_internal_name(lp, t);
+                let reference = match mutability {
+                    Mutability::Mut => quote!(&mut),
+                    Mutability::Imm => quote!(&),
+                };
+                quote!(#param: #reference #mod_tables::#t_mod::#struct_window<#db_lifetime>)
+            });
+
+            // restrict is checked last, so rows removed by cascades do not
+            // block the delete
+            let mut refs = lp.references_to(table).collect::<Vec<_>>();
+            refs.sort_by_key(|r| r.cons.cons.on_delete == plan::OnDelete::Restrict);
+
+            let actions = refs.into_iter().map(|r| {
+                let from = namer.table_param_name(lp, r.table);
+                let field = namer.transform_field_name(r.field);
+                let variant = namer.reference_error_name(lp, &r);
+                let error = quote!(#mod_references::#mod_references_enum_error::#variant);
+                let compare = if is_optional(lp, &r) {
+                    quote!(Some(key))
+                } else {
+                    quote!(key)
+                };
+                let referencing = quote! {
+                    #from.#struct_window_method_scan_brw().filter(|k| #from.#struct_window_method_borrow(*k).is_ok_and(|row| *row.#field == #compare))
+                };
+                match r.cons.cons.on_delete {
+                    plan::OnDelete::Restrict => quote! {
+                        if let Some(k) = #from.#struct_window_method_scan_brw().find(|k| #from.#struct_window_method_borrow(*k).is_ok_and(|row| *row.#field == #compare)) {
+                            return Err(#error { referenced: key, referencing: Some(k) });
+                        }
+                    },
+                    plan::OnDelete::Cascade => {
+                        // rows can already be deleted by an earlier cascade
+                        let cascade = delete_references_call(lp, r.table, quote!(k), namer)
+                            .map(|call| quote!(#call?;));
+                        quote! {
+                            for k in #referencing.collect::<Vec<_>>() {
+                                if #from.#struct_window_method_delete(k).is_ok() {
+                                    #cascade
+                                }
+                            }
+                        }
+                    }
+                    plan::OnDelete::SetNone => {
+                        let from_mod = namer.table_internal_name(lp, r.table);
+                        let update = namer.reference_set_none_update(r.field);
+                        quote! {
+                            for k in #referencing.collect::<Vec<_>>() {
+                                #from.#update(
+                                    #mod_tables::#from_mod::#mod_update::#update::#mod_update_struct_update { #field: None },
+                                    k
+                                ).map_err(|_| #error { referenced: key, referencing: Some(k) })?;
+                            }
+                        }
+                    }
+                }
+            });
+
+            quote! {
+                /// Apply the on delete actions of references to a deleted row
+                fn #fn_name<#db_lifetime>(#(#params,)* key: #mod_tables::#table_mod::#type_key) -> Result<(), #mod_references::#mod_references_enum_error> {
+                    #(#actions)*
+                    Ok(())
+                }
+            }
+        });
+
+    quote! {
+        pub mod #mod_references {
+            /// A `valid_ref` constraint would be violated
+            pub enum #mod_references_enum_error {
+                #(#variants,)*
+            }
+
+            impl std::fmt::Debug for #mod_references_enum_error {
+                fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+                    match self {
+                        #(#debugs,)*
+                    }
+                }
+            }
+
+            impl std::fmt::Display for #mod_references_enum_error {
+                fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+                    match self {
+                        #(#displays,)*
+                    }
+                }
+            }
+
+            impl std::error::Error for #mod_references_enum_error {}
+        }
+        #(#delete_fns)*
+    }
+}
+
+/// The updates needed to apply `set_none` to referencing columns
+pub fn set_none_updates(
+    lp: &plan::Plan,
+    namer: &SerializedNamer,
+) -> Vec<(
+    plan::Key<plan::Table>,
+    pulpit::gen::operations::update::Update,
+)> {
+    let mut updates = deleted_tables(lp)
+        .into_iter()
+        .flat_map(|table| lp.references_to(table))
+        .filter(|r| r.cons.cons.on_delete == plan::OnDelete::SetNone)
+        .map(|r| {
+            (
+                r.table,
+                pulpit::gen::operations::update::Update {
+                    fields: vec![namer.transform_field_name(r.field)],
+                    alias: namer.reference_set_none_update(r.field),
+                    current: false,
+                },
+            )
+        })
+        .collect::<Vec<_>>();
+    updates.sort_by_key(|(t, u)| (t.arr_idx(), u.alias.to_string()));
+    updates
+}
+
+/// The name of the column's field and the table it references, for columns
+/// of type `ref <table>` or `Option<ref <table>>`.
+pub fn reference_columns(
+    lp: &plan::Plan,
+    table: plan::Key<plan::Table>,
+    namer: &SerializedNamer,
+) -> Vec<(Ident, plan::Key<plan::Table>, bool)> {
+    lp.get_table(table)
+        .columns
+        .iter()
+        .filter_map(|(field, col)| {
+            let (target, optional) = match lp.get_scalar_type_conc(col.data_type) {
+                plan::ScalarTypeConc::TableRef(t) => (*t, false),
+                plan::ScalarTypeConc::Option(inner) => match lp.get_scalar_type_conc(*inner) {
+                    plan::ScalarTypeConc::TableRef(t) => (*t, true),
+                    _ => return None,
+                },
+                _ => return None,
+            };
+            Some((namer.transform_field_name(field), target, optional))
+        })
+        .collect()
+}
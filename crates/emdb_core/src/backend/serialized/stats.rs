@@ -20,11 +20,25 @@ pub enum StatKind {
     Combine,
     Sort,
     Take,
+    Skip,
+    SkipWhile,
+    TopK,
+    Distinct,
+    DistinctSorted,
+    Window,
     GroupBy,
     CrossJoin,
     EquiJoin,
     PredJoin,
+    LeftCrossJoin,
+    FullCrossJoin,
+    LeftEquiJoin,
+    FullEquiJoin,
+    LeftPredJoin,
+    FullPredJoin,
     Union,
+    Intersect,
+    Except,
     Fork,
     ForkSingle,
 
@@ -48,11 +62,25 @@ impl StatKind {
             StatKind::Combine => quote!(CombineStats),
             StatKind::Sort => quote!(SortStats),
             StatKind::Take => quote!(TakeStats),
+            StatKind::Skip => quote!(SkipStats),
+            StatKind::SkipWhile => quote!(SkipWhileStats),
+            StatKind::TopK => quote!(TopKStats),
+            StatKind::Distinct => quote!(DistinctStats),
+            StatKind::DistinctSorted => quote!(DistinctSortedStats),
+            StatKind::Window => quote!(WindowStats),
             StatKind::GroupBy => quote!(GroupByStats),
             StatKind::CrossJoin => quote!(CrossJoinStats),
             StatKind::EquiJoin => quote!(EquiJoinStats),
             StatKind::PredJoin => quote!(PredJoinStats),
+            StatKind::LeftCrossJoin => quote!(LeftCrossJoinStats),
+            StatKind::FullCrossJoin => quote!(FullCrossJoinStats),
+            StatKind::LeftEquiJoin => quote!(LeftEquiJoinStats),
+            StatKind::FullEquiJoin => quote!(FullEquiJoinStats),
+            StatKind::LeftPredJoin => quote!(LeftPredJoinStats),
+            StatKind::FullPredJoin => quote!(FullPredJoinStats),
             StatKind::Union => quote!(UnionStats),
+            StatKind::Intersect => quote!(IntersectStats),
+            StatKind::Except => quote!(ExceptStats),
             StatKind::Fork => quote!(ForkStats),
             StatKind::ForkSingle => quote!(ForkSingleStats),
             StatKind::Split => quote!(SplitStats),
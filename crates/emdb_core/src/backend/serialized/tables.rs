@@ -1,9 +1,10 @@
-use super::namer::SerializedNamer;
+use super::{namer::SerializedNamer, references};
 use crate::{
+    analysis::access::AccessPatterns,
     backend::interface::{namer::InterfaceNamer, public::exposed_keys, InterfaceTrait},
     plan,
 };
-use pulpit::gen::selector::{SelectorImpl, TableSelectors};
+use pulpit::gen::selector::{SelectorImpl, TableSelectors, ThunderdomeSelector};
 use quote::{quote, ToTokens};
 use quote_debug::Tokens;
 use std::collections::HashMap;
@@ -12,6 +13,7 @@ use syn::{Ident, ItemImpl, ItemMod, ItemStruct, Type};
 pub struct GeneratedInfo<'imm> {
     pub get_types: HashMap<plan::Idx<'imm, plan::Table>, HashMap<Ident, Tokens<Type>>>,
     pub insert_can_error: HashMap<plan::Idx<'imm, plan::Table>, bool>, // TODO: hashset instead?
+    pub restores: HashMap<plan::Idx<'imm, plan::Table>, bool>,
 }
 
 pub struct TableWindow<'imm> {
@@ -23,14 +25,19 @@ pub struct TableWindow<'imm> {
 }
 
 /// Generate the tokens for the tables, and the struct to hold them (in [`TableWindow`]).
-/// - Generates the tokens for the [`plan::ScalarType`]s of table fields assuming they are just [`plan::ScalarTypeConc::Rust`] tyes
+/// - Generates the tokens for the [`plan::ScalarType`]s of table fields assuming they are just [`plan::ScalarTypeConc::Rust`] tyes,
+///   or references to other tables (see [`references`])
 pub fn generate_tables<'imm>(
     lp: &'imm plan::Plan,
     interface_trait: &Option<InterfaceTrait>,
     namer: &SerializedNamer,
     selector: &TableSelectors,
     inlining: bool,
+    concurrent: bool,
+    persist: bool,
 ) -> TableWindow<'imm> {
+    let access = AccessPatterns::analyse(lp);
+
     // get the constraints and fields of each table
     let mut pulpit_configs = lp
         .tables
@@ -51,7 +58,10 @@ pub fn generate_tables<'imm>(
                                     type_context: plan::TypeContext::DataStore,
                                     ty,
                                 } => ty.to_token_stream().into(),
-                                _ => unreachable!("Only Rust types are allowed in the data store"),
+                                // references are typed once the referenced table is selected
+                                plan::ScalarTypeConc::TableRef(_)
+                                | plan::ScalarTypeConc::Option(_) => quote!(()).into(),
+                                _ => unreachable!("Only Rust types and references are allowed in the data store"),
                             },
                         )
                     })
@@ -69,6 +79,23 @@ pub fn generate_tables<'imm>(
                             })
                     })
                     .collect(),
+                indexes: emdb_table
+                    .columns
+                    .iter()
+                    .flat_map(|(field, v)| {
+                        let ordered = v.cons.ordered.as_ref().map(|a| pulpit::gen::indexes::Index {
+                            alias: a.alias.clone(),
+                            field: namer.transform_field_name(field),
+                            kind: pulpit::gen::indexes::IndexKind::Ordered,
+                        });
+                        let hashed = v.cons.hashed.as_ref().map(|a| pulpit::gen::indexes::Index {
+                            alias: a.alias.clone(),
+                            field: namer.transform_field_name(field),
+                            kind: pulpit::gen::indexes::IndexKind::Hashed,
+                        });
+                        ordered.into_iter().chain(hashed)
+                    })
+                    .collect(),
                 predicates: emdb_table
                     .row_cons
                     .preds
@@ -96,6 +123,19 @@ pub fn generate_tables<'imm>(
                 },
                 updates: Vec::new(),
                 gets: Vec::new(),
+                reads: access
+                    .get(key)
+                    .reads
+                    .iter()
+                    .map(|read| pulpit::gen::selector::Read {
+                        fields: read
+                            .columns
+                            .iter()
+                            .map(|rf| namer.transform_field_name(rf))
+                            .collect(),
+                        scan: read.scan,
+                    })
+                    .collect(),
                 public: true,
             };
 
@@ -106,7 +146,12 @@ pub fn generate_tables<'imm>(
     // get the updates and deletions
     for (key, op) in &lp.operators {
         match op {
-            plan::Operator::Update(plan::Update { table, mapping, .. }) => pulpit_configs
+            plan::Operator::Update(plan::Update {
+                table,
+                mapping,
+                current,
+                ..
+            }) => pulpit_configs
                 .get_mut(&plan::Idx::new(*table, lp))
                 .unwrap()
                 .updates
@@ -116,6 +161,38 @@ pub fn generate_tables<'imm>(
                         .map(|rec| namer.transform_field_name(rec))
                         .collect(),
                     alias: namer.pulpit_table_interaction(key),
+                    current: !current.is_empty(),
+                }),
+            plan::Operator::Upsert(plan::Upsert {
+                table,
+                mapping,
+                current,
+                ..
+            }) => pulpit_configs
+                .get_mut(&plan::Idx::new(*table, lp))
+                .unwrap()
+                .updates
+                .push(pulpit::gen::operations::update::Update {
+                    fields: mapping
+                        .keys()
+                        .map(|rec| namer.transform_field_name(rec))
+                        .collect(),
+                    alias: namer.pulpit_table_interaction(key),
+                    current: *current,
+                }),
+            // the updated values are computed from the row borrowed while
+            // scanning, so are not computed inside the update
+            plan::Operator::UpdateWhere(plan::UpdateWhere { table, mapping, .. }) => pulpit_configs
+                .get_mut(&plan::Idx::new(*table, lp))
+                .unwrap()
+                .updates
+                .push(pulpit::gen::operations::update::Update {
+                    fields: mapping
+                        .keys()
+                        .map(|rec| namer.transform_field_name(rec))
+                        .collect(),
+                    alias: namer.pulpit_table_interaction(key),
+                    current: false,
                 }),
             plan::Operator::Delete(plan::Delete { table, .. }) => {
                 pulpit_configs
@@ -145,6 +222,21 @@ pub fn generate_tables<'imm>(
         }
     }
 
+    // referencing rows can be deleted, or have their references removed
+    for table in references::deleted_tables(lp) {
+        pulpit_configs
+            .get_mut(&plan::Idx::new(table, lp))
+            .unwrap()
+            .deletions = true;
+    }
+    for (table, update) in references::set_none_updates(lp, namer) {
+        pulpit_configs
+            .get_mut(&plan::Idx::new(table, lp))
+            .unwrap()
+            .updates
+            .push(update);
+    }
+
     let SerializedNamer {
         pulpit:
             ref pulpit_namer @ pulpit::gen::namer::CodeNamer {
@@ -160,31 +252,76 @@ pub fn generate_tables<'imm>(
         struct_stats,
         struct_datastore_member_stats,
         struct_database_member_stats,
+        struct_database_member_transaction,
         ..
     } = namer;
 
-    let (get_types, gen_data): (HashMap<_, _>, Vec<_>) = pulpit_configs
+    // snapshots place the rows of tables with deletions at their keys when
+    // restored, which needs a primary column with keys that can be written
+    let mut table_impls = pulpit_configs
         .into_iter()
         .map(|(key, config)| {
-            let table_impl = selector.select_table(config);
+            let table_impl = if persist && config.deletions {
+                ThunderdomeSelector.select_table(config)
+            } else {
+                selector.select_table(config)
+            };
+            (key, table_impl)
+        })
+        .collect::<HashMap<_, _>>();
+
+    // the key types of referenced tables depend on their selected primary column
+    let key_types = table_impls
+        .iter()
+        .map(|(key, table_impl)| (*key, table_impl.groups.untyped_key_type(pulpit_namer)))
+        .collect::<HashMap<_, _>>();
+    for (key, _) in &lp.tables {
+        let table_impl = table_impls.get_mut(&plan::Idx::new(key, lp)).unwrap();
+        for (field, target, optional) in references::reference_columns(lp, key, namer) {
+            let key_type = &key_types[&plan::Idx::new(target, lp)];
+            let ty = if optional {
+                quote!(Option<#key_type>)
+            } else {
+                quote!(#key_type)
+            };
+            table_impl.groups.set_type(&field, ty.into());
+        }
+    }
+
+    // the user's attributes (e.g. docs) are applied to each table's module
+    let table_attrs = lp
+        .tables
+        .iter()
+        .map(|(key, table)| (plan::Idx::new(key, lp), &table.attrs))
+        .collect::<HashMap<_, _>>();
+
+    let (get_types, gen_data): (HashMap<_, _>, Vec<_>) = table_impls
+        .into_iter()
+        .map(|(key, table_impl)| {
+            let table_def = table_impl.generate(
+                pulpit_namer,
+                if inlining {
+                    vec![pulpit::gen::table::AttrKinds::Inline]
+                } else {
+                    vec![]
+                },
+            );
+            let attrs = table_attrs[&key];
             (
                 (key, table_impl.op_get_types(pulpit_namer)),
                 (
-                    (key, table_impl.insert_can_error()),
-                    table_impl.generate(
-                        pulpit_namer,
-                        if inlining {
-                            vec![pulpit::gen::table::AttrKinds::Inline]
-                        } else {
-                            vec![]
-                        },
-                    ),
+                    (key, (table_impl.insert_can_error(), table_impl.restores())),
+                    quote!(#(#attrs)* #table_def).into(),
                 ),
             )
         })
         .unzip();
 
-    let (insert_can_error, table_defs): (HashMap<_, _>, Vec<_>) = gen_data.into_iter().unzip();
+    let (table_info, table_defs): (HashMap<_, _>, Vec<_>) = gen_data.into_iter().unzip();
+    let (insert_can_error, restores) = table_info
+        .into_iter()
+        .map(|(key, (can_error, restores))| ((key, can_error), (key, restores)))
+        .unzip();
 
     let table_mod_names = lp
         .tables
@@ -192,20 +329,32 @@ pub fn generate_tables<'imm>(
         .map(|(k, _)| namer.table_internal_name(lp, k))
         .collect::<Vec<_>>();
 
-    let datastore_members = table_mod_names
-        .iter()
-        .map(|mod_name| quote!(#mod_name: #mod_tables::#mod_name::#struct_table));
+    // tables are public so that migrations (generated in a later version's
+    // `emql!`) can move their rows
+    let datastore_members = table_mod_names.iter().map(
+        |mod_name| quote!(#[doc(hidden)] pub #mod_name: #mod_tables::#mod_name::#struct_table),
+    );
     let datastore_members_new = table_mod_names
         .iter()
         .map(|mod_name| quote!(#mod_name: #mod_tables::#mod_name::#struct_table::new(1024)));
 
+    // For concurrent databases each window is locked separately, so queries
+    // using different tables (or only reading the same tables) can run in
+    // parallel.
     let (database_members_window_stream, database_members_stream): (Vec<_>, Vec<_>) = table_mod_names
         .iter()
         .map(|mod_name| {
-            (
-                quote!(#mod_name: self.#mod_name.window()),
-                quote!(#mod_name: #mod_tables::#mod_name::#struct_window<#db_lifetime>),
-            )
+            if concurrent {
+                (
+                    quote!(#mod_name: std::sync::RwLock::new(self.#mod_name.window())),
+                    quote!(#mod_name: std::sync::RwLock<#mod_tables::#mod_name::#struct_window<#db_lifetime>>),
+                )
+            } else {
+                (
+                    quote!(#mod_name: self.#mod_name.window()),
+                    quote!(#mod_name: #mod_tables::#mod_name::#struct_window<#db_lifetime>),
+                )
+            }
         })
         .unzip();
 
@@ -276,6 +425,7 @@ pub fn generate_tables<'imm>(
                     #struct_database {
                         #database_members_window
                         #struct_database_member_stats: &self.#struct_datastore_member_stats,        
+                        #struct_database_member_transaction: false,
                     }
                 }
             }
@@ -285,12 +435,14 @@ pub fn generate_tables<'imm>(
             pub struct #struct_database<#db_lifetime> {
                 #database_members
                 #struct_database_member_stats: &#db_lifetime #struct_stats,
+                #struct_database_member_transaction: bool,
             }
         }
         .into(),
         table_generated_info: GeneratedInfo {
             get_types,
             insert_can_error,
+            restores,
         },
     }
 }
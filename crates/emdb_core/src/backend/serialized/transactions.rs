@@ -0,0 +1,168 @@
+//! # Transactions
+//! Each query commits the tables it mutates when it succeeds, and aborts them
+//! when it fails. To run several queries atomically, the database's
+//! `transaction` runs a closure with the database:
+//! - Queries in the transaction do not commit, and on error only abort their
+//!   own changes (to a savepoint taken as they start).
+//! - If the closure succeeds all tables are committed, otherwise all tables
+//!   are rolled back to before the transaction.
+//! - Transactions can be nested, with a nested transaction only rolling back
+//!   its own changes, and committed by the outermost transaction.
+//!
+//! A `Savepoint` is the length of each table's transaction log, which can be
+//! rolled back to explicitly within a transaction.
+//!
+//! If the closure panics, a guard restores the transaction flag and rolls the
+//! tables back to before the transaction as the stack unwinds (for a
+//! concurrent database, this also clears the poisoning of the table's lock).
+
+use super::namer::SerializedNamer;
+use crate::plan;
+use proc_macro2::TokenStream;
+use pulpit::gen::namer::CodeNamer;
+use quote::quote;
+
+pub fn generate_transactions(
+    lp: &plan::Plan,
+    namer: &SerializedNamer,
+    concurrent: bool,
+) -> TokenStream {
+    let SerializedNamer {
+        pulpit:
+            CodeNamer {
+                struct_window_method_commit,
+                struct_window_method_savepoint,
+                struct_window_method_abort_to,
+                ..
+            },
+        struct_database,
+        db_lifetime,
+        struct_database_member_transaction,
+        struct_database_method_transaction,
+        struct_database_method_savepoint,
+        struct_database_method_rollback_to,
+        struct_savepoint,
+        struct_transaction_guard,
+        ..
+    } = namer;
+
+    let table_names = lp
+        .tables
+        .iter()
+        .map(|(key, _)| namer.table_internal_name(lp, key))
+        .collect::<Vec<_>>();
+
+    // with `&mut self` the locks of a concurrent database are not needed
+    let windows = table_names
+        .iter()
+        .map(|name| {
+            if concurrent {
+                quote!(self.#name.get_mut().unwrap())
+            } else {
+                quote!(self.#name)
+            }
+        })
+        .collect::<Vec<_>>();
+
+    let savepoint_fields = table_names.iter().map(|name| quote!(#name: usize));
+    let savepoints = table_names
+        .iter()
+        .zip(windows.iter())
+        .map(|(name, window)| quote!(#name: #window.#struct_window_method_savepoint()));
+    let rollbacks = table_names
+        .iter()
+        .zip(windows.iter())
+        .map(|(name, window)| quote!(#window.#struct_window_method_abort_to(savepoint.#name)));
+    // the guard rolls back while unwinding, so must not panic on a lock
+    // poisoned by a panicking query
+    let unwind_rollbacks = table_names.iter().map(|name| {
+        if concurrent {
+            quote! {
+                self.db.#name
+                    .get_mut()
+                    .unwrap_or_else(std::sync::PoisonError::into_inner)
+                    .#struct_window_method_abort_to(self.savepoint.#name);
+                self.db.#name.clear_poison()
+            }
+        } else {
+            quote!(self.db.#name.#struct_window_method_abort_to(self.savepoint.#name))
+        }
+    });
+    let commits = windows
+        .iter()
+        .map(|window| quote!(#window.#struct_window_method_commit()));
+
+    quote! {
+        /// The changes applied to each table, which a transaction can be
+        /// rolled back to
+        #[derive(Clone, Copy)]
+        pub struct #struct_savepoint {
+            #(#savepoint_fields,)*
+        }
+
+        /// Ends a transaction, restoring the flag of any enclosing transaction
+        /// and rolling back if the transaction's closure panicked.
+        struct #struct_transaction_guard<'guard, #db_lifetime> {
+            db: &'guard mut #struct_database<#db_lifetime>,
+            savepoint: #struct_savepoint,
+            outer: bool,
+            unwinding: bool,
+        }
+
+        impl <'guard, #db_lifetime> Drop for #struct_transaction_guard<'guard, #db_lifetime> {
+            fn drop(&mut self) {
+                self.db.#struct_database_member_transaction = self.outer;
+                if self.unwinding {
+                    #(#unwind_rollbacks;)*
+                }
+            }
+        }
+
+        impl <#db_lifetime> #struct_database<#db_lifetime> {
+            /// Run queries as a single transaction, committed if the closure
+            /// succeeds, and rolled back otherwise.
+            /// - Transactions can be nested, only the outermost commits.
+            pub fn #struct_database_method_transaction<T, E>(&mut self, f: impl FnOnce(&mut Self) -> Result<T, E>) -> Result<T, E> {
+                let savepoint = self.#struct_database_method_savepoint();
+                let outer = std::mem::replace(&mut self.#struct_database_member_transaction, true);
+                let result = {
+                    let mut guard = #struct_transaction_guard {
+                        db: &mut *self,
+                        savepoint,
+                        outer,
+                        unwinding: true,
+                    };
+                    let result = f(&mut *guard.db);
+                    guard.unwinding = false;
+                    result
+                };
+                match result {
+                    Ok(value) => {
+                        if !outer {
+                            #(#commits;)*
+                        }
+                        Ok(value)
+                    }
+                    Err(err) => {
+                        self.#struct_database_method_rollback_to(savepoint);
+                        Err(err)
+                    }
+                }
+            }
+
+            /// Get a savepoint that the current transaction can be rolled back to
+            pub fn #struct_database_method_savepoint(&mut self) -> #struct_savepoint {
+                #struct_savepoint {
+                    #(#savepoints,)*
+                }
+            }
+
+            /// Undo the changes made since the `savepoint` in the current
+            /// transaction (outside of a transaction, queries' changes are
+            /// already committed)
+            pub fn #struct_database_method_rollback_to(&mut self, savepoint: #struct_savepoint) {
+                #(#rollbacks;)*
+            }
+        }
+    }
+}
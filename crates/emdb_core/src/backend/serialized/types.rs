@@ -26,7 +26,8 @@ use syn::{Ident, ItemStruct, Type};
 use super::namer::SerializedNamer;
 use crate::plan;
 
-/// Gets all the record types that need to be declared public.
+/// Gets all the record types that need to be declared public (used in query
+/// parameters or return types).
 /// - Scalar types do not need this, they are either type aliases (no need to
 ///   be public, they are exposed to the user as the type they alias)
 /// - References to types are aliases, so are also not included.
@@ -64,6 +65,14 @@ fn public_record_types(lp: &plan::Plan) -> HashSet<plan::ImmKey<'_, plan::Record
                         plan::ScalarTypeConc::Bag(r) | plan::ScalarTypeConc::Record(r) => {
                             recursive_collect_record(lp, attrs, *r)
                         }
+                        plan::ScalarTypeConc::Option(s) => {
+                            recursive_collect_scalar(lp, attrs, *s)
+                        }
+                        plan::ScalarTypeConc::Composite { inner, .. } => {
+                            for (_, s) in inner {
+                                recursive_collect_scalar(lp, attrs, *s)
+                            }
+                        }
                         plan::ScalarTypeConc::TableRef(_)
                         | plan::ScalarTypeConc::TableGet { .. } => (
                             // These are already specified to be public, so no need to additionally make public here
@@ -80,9 +89,14 @@ fn public_record_types(lp: &plan::Plan) -> HashSet<plan::ImmKey<'_, plan::Record
 
     let mut public_records = HashSet::new();
     for (_, query) in &lp.queries {
-        if let Some(ret_type) = lp.get_context(query.ctx).get_return_type(lp) {
+        let context = lp.get_context(query.ctx);
+        if let Some(ret_type) = context.get_return_type(lp) {
             recursive_collect_record(lp, &mut public_records, ret_type);
         }
+        // bags can be passed as parameters
+        for (_, ty) in &context.params {
+            recursive_collect_scalar(lp, &mut public_records, *ty);
+        }
     }
 
     public_records
@@ -129,6 +143,16 @@ pub fn generate_scalar_type<'imm>(
         plan::ScalarTypeConc::Record(r) => {
             namer.record_name_lifetimes(*lp.get_record_conc_index(*r))
         }
+        plan::ScalarTypeConc::Option(s) => {
+            let inner = generate_scalar_type(lp, get_types, *s, namer);
+            quote!(Option<#inner>).into()
+        }
+        plan::ScalarTypeConc::Composite { ty, inner } => {
+            plan::substitute_composite(ty, inner, |s| {
+                generate_scalar_type(lp, get_types, s, namer).into_token_stream()
+            })
+            .into()
+        }
         plan::ScalarTypeConc::Rust {
             type_context: _, // can be used on either datastore or query types, wraps in the lifetimes required for query
             ty,
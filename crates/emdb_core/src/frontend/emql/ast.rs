@@ -2,14 +2,29 @@
 //! A basic AST, without error nodes. Includes spans required for [`super::sem`] to create [`super::errors`].
 
 use super::operators::Operator;
+use crate::plan;
 use proc_macro2::{Ident, Span, TokenStream};
-use syn::{Expr, Type};
+use syn::Expr;
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub(super) enum AstType {
     RsType(syn::Type),
     TableRef(Ident),
     Custom(Ident),
+    /// A rust type containing emQL types, which are replaced in `ty` by the
+    /// placeholder identifiers in `inner`.
+    Composite {
+        ty: syn::Type,
+        inner: Vec<(Ident, AstType)>,
+    },
+}
+
+/// The type of a table column
+#[derive(Debug)]
+pub(super) enum ColumnType {
+    RsType(syn::Type),
+    /// `ref <table>`, or `Option<ref <table>>` when optional
+    TableRef { table: Ident, optional: bool },
 }
 
 #[derive(Debug)]
@@ -28,6 +43,9 @@ pub(super) struct StreamExpr {
 #[derive(Debug)]
 pub(super) enum ConstraintExpr {
     Unique { field: Ident },
+    Ordered { field: Ident },
+    Index { field: Ident },
+    ValidRef { field: Ident, on_delete: plan::OnDelete },
     Pred(Expr),
     Limit { size: Expr },
 }
@@ -41,8 +59,9 @@ pub(super) struct Constraint {
 
 #[derive(Debug)]
 pub(super) struct Table {
+    pub attrs: Vec<syn::Attribute>,
     pub name: Ident,
-    pub cols: Vec<(Ident, Type)>,
+    pub cols: Vec<(Ident, ColumnType)>,
     pub cons: Vec<Constraint>,
 }
 
@@ -54,10 +73,53 @@ pub struct Context {
 
 #[derive(Debug)]
 pub(super) struct Query {
+    pub attrs: Vec<syn::Attribute>,
     pub name: Ident,
     pub context: Context,
 }
 
+/// A reusable stream, inlined where it is invoked (see [`super::fragments`]).
+/// - The body is kept as tokens, to be parsed again for each invocation.
+#[derive(Debug)]
+pub(super) struct Fragment {
+    pub name: Ident,
+    pub params: Vec<(Ident, AstType)>,
+    pub body: TokenStream,
+}
+
+/// An invocation of a fragment in a stream (e.g. `active_customers()`)
+#[derive(Debug)]
+pub(crate) struct Invoke {
+    pub call: Ident,
+    pub args: TokenStream,
+}
+
+/// `migration from <previous datastore> to <backend> { ... }`
+#[derive(Debug)]
+pub(super) struct Migration {
+    pub from: syn::Type,
+    pub to: Ident,
+    pub tables: Vec<TableMigration>,
+}
+
+#[derive(Debug)]
+pub(super) struct TableMigration {
+    pub name: Ident,
+    pub source: MigrationSource,
+}
+
+#[derive(Debug)]
+pub(super) enum MigrationSource {
+    /// `table <name> empty;`
+    Empty,
+    /// `table <name> [from <previous>] [as <row>] { <column> = <expr>, ... }`
+    Rows {
+        from: Option<Ident>,
+        row: Option<Ident>,
+        columns: Vec<(Ident, Expr)>,
+    },
+}
+
 #[derive(Debug)]
 pub(super) struct BackendImpl {
     pub impl_name: Ident,
@@ -70,4 +132,6 @@ pub(super) struct Ast {
     pub backends: Vec<BackendImpl>,
     pub tables: Vec<Table>,
     pub queries: Vec<Query>,
+    pub fragments: Vec<Fragment>,
+    pub migrations: Vec<Migration>,
 }
@@ -7,8 +7,9 @@ use crate::plan::{self, Key, Plan, RecordType, ScalarType, Table, With};
 use itertools::Itertools;
 use proc_macro2::{Ident, Span};
 use proc_macro_error2::{Diagnostic, Level};
+use quote::ToTokens;
 use std::collections::HashMap;
-use syn::Type;
+use syn::{spanned::Spanned, Expr, Type};
 
 type ErrCode = usize;
 
@@ -610,7 +611,7 @@ pub fn sort_field_used_twice(field: &Ident, dup_field: &Ident) -> Diagnostic {
     .span_note(dup_field.span(), format!("`{dup_field}` first used here"))
 }
 
-pub fn union_requires_at_least_one_input(call: &Ident) -> Diagnostic {
+pub fn operator_requires_at_least_one_input(call: &Ident) -> Diagnostic {
     emql_error(
         48,
         call.span(),
@@ -638,7 +639,7 @@ pub fn no_return_in_context(call: &Ident) -> Diagnostic {
     )
 }
 
-pub fn union_not_same_type(
+pub fn operator_inputs_not_same_type(
     lp: &plan::Plan,
     call: &Ident,
     var: &Ident,
@@ -646,7 +647,7 @@ pub fn union_not_same_type(
     other_var: &Ident,
     other_data_type: &plan::Key<plan::RecordType>,
 ) -> Diagnostic {
-    emql_error(52, other_var.span(), format!("`{other_var}` has type `{}` but union requires all inputs to be of the same type `{}` (from `{var}`)", plan::With { plan: lp, extended: other_data_type }, plan::With { plan: lp, extended: data_type }))
+    emql_error(52, other_var.span(), format!("`{other_var}` has type `{}` but `{call}` requires all inputs to be of the same type `{}` (from `{var}`)", plan::With { plan: lp, extended: other_data_type }, plan::With { plan: lp, extended: data_type }))
 }
 
 pub fn query_deref_cannot_deref_table_get(
@@ -721,3 +722,383 @@ pub fn query_use_on_variable_with_select(var_name: &Ident) -> Diagnostic {
         ),
     )
 }
+
+pub fn query_deref_cannot_deref_option(
+    lp: &plan::Plan,
+    reference: &Ident,
+    t: &plan::Key<plan::ScalarType>,
+) -> Diagnostic {
+    emql_error(
+        57,
+        reference.span(),
+        format!(
+            "Cannot dereference an optional value `{}`",
+            With {
+                plan: lp,
+                extended: t
+            }
+        ),
+    )
+    .help("Optional values are produced by outer joins, use an inner join or map the value before dereferencing".to_string())
+}
+
+pub fn table_constraint_duplicate_ordered(
+    col_name: &Ident,
+    method_span: Span,
+    prev_alias: &Ident,
+) -> Diagnostic {
+    emql_error(
+        58,
+        method_span,
+        format!("Duplicate ordered constraint on column `{col_name}`"),
+    )
+    .span_note(
+        prev_alias.span(),
+        format!("previously defined as {prev_alias} here."),
+    )
+}
+
+pub fn table_constraint_nonexistent_ordered_column(
+    alias: &Ident,
+    col_name: &Ident,
+    table_name: &Ident,
+    method_span: Span,
+) -> Diagnostic {
+    emql_error(59, method_span, format!(
+        "Column `{col_name}` does not exist in table `{table_name}`, so cannot apply an ordered constraint `{alias}` to it"
+    )).span_help(table_name.span(), format!("Apply the ordered constraint to an available column in {table_name}"))
+}
+
+pub fn table_constraint_duplicate_index(
+    col_name: &Ident,
+    method_span: Span,
+    prev_alias: &Ident,
+) -> Diagnostic {
+    emql_error(
+        60,
+        method_span,
+        format!("Duplicate index constraint on column `{col_name}`"),
+    )
+    .span_note(
+        prev_alias.span(),
+        format!("previously defined as {prev_alias} here."),
+    )
+}
+
+pub fn table_constraint_nonexistent_index_column(
+    alias: &Ident,
+    col_name: &Ident,
+    table_name: &Ident,
+    method_span: Span,
+) -> Diagnostic {
+    emql_error(61, method_span, format!(
+        "Column `{col_name}` does not exist in table `{table_name}`, so cannot apply an index constraint `{alias}` to it"
+    )).span_help(table_name.span(), format!("Apply the index constraint to an available column in {table_name}"))
+}
+
+pub fn query_lookup_table_not_found(table: &Ident) -> Diagnostic {
+    emql_error(
+        62,
+        table.span(),
+        format!("Table `{table}` not found in the query"),
+    )
+    .help(format!(
+        "Either define a `table {table} {{...}} @ [...]` or use a different table in `lookup(..)`",
+    ))
+}
+
+pub fn query_lookup_no_field_in_table(field: &Ident, table_name: &Ident) -> Diagnostic {
+    emql_error(
+        63,
+        field.span(),
+        format!("Field `{field}` not found in table `{table_name}`"),
+    )
+    .span_help(
+        table_name.span(),
+        format!("Add `{field}: ... ,` to {table_name}"),
+    )
+}
+
+pub fn query_lookup_field_is_not_indexed(field: &Ident, table_name: &Ident) -> Diagnostic {
+    emql_error(64, field.span(), format!("Field `{field}` is not indexed in table `{table_name}`"))
+    .span_help(
+        table_name.span(),
+        format!(
+            "Add an index constraint to `{field}` in {table_name} `@ [ ... index({field}) as ... ]`"
+        ),
+    )
+}
+
+pub fn table_column_nonexistent_ref_table(col_name: &Ident, table: &Ident) -> Diagnostic {
+    emql_error(
+        65,
+        table.span(),
+        format!("Table `{table}` does not exist, so column `{col_name}` cannot reference it"),
+    )
+    .help(format!(
+        "Either define a `table {table} {{...}} @ [...]` or reference a different table"
+    ))
+}
+
+pub fn table_constraint_duplicate_valid_ref(
+    col_name: &Ident,
+    method_span: Span,
+    prev_alias: &Ident,
+) -> Diagnostic {
+    emql_error(
+        66,
+        method_span,
+        format!("Duplicate valid_ref constraint on column `{col_name}`"),
+    )
+    .span_note(
+        prev_alias.span(),
+        format!("previously defined as {prev_alias} here."),
+    )
+}
+
+pub fn table_constraint_nonexistent_valid_ref_column(
+    alias: &Ident,
+    col_name: &Ident,
+    table_name: &Ident,
+    method_span: Span,
+) -> Diagnostic {
+    emql_error(67, method_span, format!(
+        "Column `{col_name}` does not exist in table `{table_name}`, so cannot apply a valid_ref constraint `{alias}` to it"
+    )).span_help(table_name.span(), format!("Apply the valid_ref constraint to a reference column in {table_name}"))
+}
+
+pub fn table_constraint_valid_ref_not_ref(
+    alias: &Ident,
+    col_name: &Ident,
+    method_span: Span,
+) -> Diagnostic {
+    emql_error(
+        68,
+        method_span,
+        format!("Column `{col_name}` is not a reference, so cannot apply a valid_ref constraint `{alias}` to it"),
+    )
+    .span_help(
+        col_name.span(),
+        format!("Declare `{col_name}` as `ref <table>` or `Option<ref <table>>`"),
+    )
+}
+
+pub fn table_constraint_valid_ref_set_none(
+    alias: &Ident,
+    col_name: &Ident,
+    method_span: Span,
+) -> Diagnostic {
+    emql_error(
+        69,
+        method_span,
+        format!("Column `{col_name}` is not optional, so the valid_ref constraint `{alias}` cannot use `set_none`"),
+    )
+    .span_help(
+        col_name.span(),
+        format!("Declare `{col_name}` as `Option<ref <table>>`, or use `restrict` or `cascade`"),
+    )
+}
+
+pub fn query_param_ambiguous_type(param: &Ident, t: &Ident) -> Diagnostic {
+    emql_error(
+        70,
+        t.span(),
+        format!("Type `{t}` of parameter `{param}` is defined differently by multiple queries"),
+    )
+    .help(format!(
+        "Rename the `type {t}` collected in other queries so only one defines it"
+    ))
+}
+
+pub fn query_upsert_ref_is_column(out_ref: &Ident, table_name: &Ident) -> Diagnostic {
+    emql_error(
+        71,
+        out_ref.span(),
+        format!("Reference `{out_ref}` has the same name as a column of `{table_name}`"),
+    )
+    .help(format!(
+        "The existing row is borrowed as `{out_ref}` in `set` expressions, so rename it to differ from the fields of `{table_name}`"
+    ))
+}
+
+pub fn query_window_unknown_function(field: &Ident, func: &Expr) -> Diagnostic {
+    emql_error(
+        72,
+        func.span(),
+        format!("`{}` is not a window function", func.to_token_stream()),
+    )
+    .help(format!(
+        "Compute `{field}` with one of `row_number()`, `rank()`, `dense_rank()`, `running_sum(..)`, `running_min(..)` or `running_max(..)`"
+    ))
+}
+
+pub fn query_window_function_arguments(func: &Ident, expected: usize, found: usize) -> Diagnostic {
+    emql_error(
+        73,
+        func.span(),
+        format!("Window function `{func}` takes {expected} argument(s), but {found} were provided"),
+    )
+}
+
+pub fn query_skip_after_requires_sort(call: &Ident, prev_span: Span) -> Diagnostic {
+    emql_error(
+        74,
+        call.span(),
+        format!("`{call}(after ..)` needs to directly follow a `sort` to know the order of the key"),
+    )
+    .span_note(prev_span, "The previous operator is not a sort".to_string())
+}
+
+pub fn fragment_redefined(def: &Ident, original_def: &Ident) -> Diagnostic {
+    redefinition_error(75, "fragment", def, original_def)
+}
+
+pub fn fragment_is_operator(name: &Ident) -> Diagnostic {
+    emql_error(
+        76,
+        name.span(),
+        format!("Fragment `{name}` has the same name as an operator, so cannot be invoked"),
+    )
+}
+
+pub fn fragment_not_single_stream(name: &Ident, streams: usize) -> Diagnostic {
+    emql_error(
+        77,
+        name.span(),
+        format!("Fragment `{name}` contains {streams} streams, but must contain exactly one"),
+    )
+    .help(format!(
+        "The stream of `{name}` is continued where it is invoked, so it cannot be split across several streams"
+    ))
+}
+
+pub fn fragment_not_found(call: &Ident, fragments: &[&Ident]) -> Diagnostic {
+    emql_error(
+        78,
+        call.span(),
+        format!("`{call}` is not an operator or fragment"),
+    )
+    .help(format!(
+        "Available operators are {}{}",
+        super::operators::OPERATOR_NAMES.join(", "),
+        if fragments.is_empty() {
+            String::new()
+        } else {
+            format!(
+                ", and fragments are {}",
+                fragments.iter().map(|f| format!("`{f}`")).join(", ")
+            )
+        }
+    ))
+}
+
+pub fn fragment_recursive(call: &Ident, fragment: &Ident) -> Diagnostic {
+    emql_error(
+        79,
+        call.span(),
+        format!("Fragment `{fragment}` invokes itself, so cannot be inlined"),
+    )
+    .span_note(fragment.span(), format!("`{fragment}` is defined here"))
+}
+
+pub fn fragment_with_arguments(call: &Ident) -> Diagnostic {
+    emql_error(
+        80,
+        call.span(),
+        format!("Fragment `{call}` cannot be passed arguments"),
+    )
+    .help(format!(
+        "`{call}` uses the parameters of the query it is invoked in, so invoke it as `{call}()`"
+    ))
+}
+
+pub fn fragment_param_missing(query: &Ident, fragment: &Ident, param: &Ident) -> Diagnostic {
+    emql_error(
+        81,
+        query.span(),
+        format!("Query `{query}` invokes fragment `{fragment}`, but does not have its parameter `{param}` of the same type"),
+    )
+    .span_note(param.span(), format!("`{fragment}` uses parameter `{param}`"))
+}
+
+pub fn migration_backend_not_found(to: &Ident, backends: Vec<&Ident>) -> Diagnostic {
+    emql_error(
+        82,
+        to.span(),
+        format!("No backend `{to}` to migrate to"),
+    )
+    .help(format!(
+        "Migrate to a backend defined by an `impl <name> as Serialized;`, available backends are {}",
+        backends.iter().map(|b| format!("`{b}`")).sorted().join(", ")
+    ))
+}
+
+pub fn migration_backend_unsupported(to: &Ident) -> Diagnostic {
+    emql_error(
+        83,
+        to.span(),
+        format!("Backend `{to}` does not support migrations"),
+    )
+    .help(String::from(
+        "Only the `Serialized` backend generates a datastore that can be migrated to",
+    ))
+}
+
+pub fn migration_redefined(to: &Ident, original_to: &Ident) -> Diagnostic {
+    emql_error(
+        84,
+        to.span(),
+        format!("Redefinition of a migration to `{to}` from the same datastore"),
+    )
+    .span_note(original_to.span(), "Originally defined here".to_string())
+    .help(String::from(
+        "Each previous datastore can only be migrated to a backend once",
+    ))
+}
+
+pub fn migration_table_not_found(table: &Ident) -> Diagnostic {
+    emql_error(
+        85,
+        table.span(),
+        format!("Table `{table}` does not exist, so cannot be migrated to"),
+    )
+    .help(String::from(
+        "Migrations only describe how to fill the tables of the current schema",
+    ))
+}
+
+pub fn migration_table_redefined(def: &Ident, original_def: &Ident) -> Diagnostic {
+    redefinition_error(86, "table migration", def, original_def)
+}
+
+pub fn migration_column_not_found(table_name: &Ident, column: &Ident) -> Diagnostic {
+    emql_error(
+        87,
+        column.span(),
+        format!(
+            "Column `{column}` does not exist in table `{table_name}`, so cannot be migrated to"
+        ),
+    )
+    .span_note(table_name.span(), format!("{table_name} defined here"))
+}
+
+pub fn migration_column_redefined(def: &Ident, original_def: &Ident) -> Diagnostic {
+    redefinition_error(88, "migrated column", def, original_def)
+}
+
+pub fn migration_source_moved_twice(
+    source: &Ident,
+    original_source: &Ident,
+    table: &Ident,
+    original_table: &Ident,
+) -> Diagnostic {
+    emql_error(
+        89,
+        source.span(),
+        format!("The rows of previous table `{source}` are moved into both `{original_table}` and `{table}`"),
+    )
+    .span_note(original_source.span(), format!("Moved into `{original_table}` here"))
+    .help(String::from(
+        "Rows are moved out of the previous table, so each previous table can fill at most one table (use `table <name> empty;` for the others)",
+    ))
+}
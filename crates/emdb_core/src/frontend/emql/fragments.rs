@@ -0,0 +1,216 @@
+//! # Fragments
+//! Reusable streams declared with `fragment <name>(<params>) { <stream> }`, and
+//! invoked with `<name>()` in the streams of queries (and other fragments).
+//!
+//! Fragments are inlined in the [`super::ast`] before the logical plan is built:
+//! - Each invocation parses the fragment's body again, so gets its own copy of
+//!   the operators (the plan contains a copy for each invocation).
+//! - The invocation is replaced by the fragment's stream, which is continued by
+//!   the operators following the invocation. Hence a fragment can start a
+//!   stream (e.g. `use customers |> filter(..)`), or be used in the middle of
+//!   one (e.g. `filter(..) |> deref(..)`).
+//! - Expressions in the fragment are passed through to the invoking query, so
+//!   can use its parameters. The parameters a fragment declares are checked to
+//!   be parameters of every query invoking it.
+
+use super::{
+    ast::{Fragment, Invoke, StreamExpr},
+    errors,
+    operators::{nested_contexts, Operator, OPERATOR_NAMES},
+    parse::parse_fragment,
+    sem::query_ast_typeto_scalar,
+};
+use crate::plan;
+use proc_macro2::Ident;
+use proc_macro_error2::Diagnostic;
+use std::collections::{HashMap, HashSet, LinkedList};
+
+pub(super) struct Fragments {
+    defs: HashMap<Ident, Fragment>,
+
+    /// Fragments with errors (already reported), whose invocations are ignored
+    invalid: HashSet<Ident>,
+}
+
+impl Fragments {
+    /// Collect the fragments, checking each parses as a single stream
+    pub fn new(fragments: Vec<Fragment>, errors: &mut LinkedList<Diagnostic>) -> Self {
+        let mut defs: HashMap<Ident, Fragment> = HashMap::new();
+        let mut invalid = HashSet::new();
+        for fragment in fragments {
+            if let Some((original, _)) = defs.get_key_value(&fragment.name) {
+                errors.push_back(errors::fragment_redefined(&fragment.name, original));
+                continue;
+            }
+
+            let mut valid = true;
+            if OPERATOR_NAMES.contains(&fragment.name.to_string().as_str()) {
+                errors.push_back(errors::fragment_is_operator(&fragment.name));
+                valid = false;
+            }
+            match parse_fragment(&fragment) {
+                Ok(streams) if streams.len() == 1 => (),
+                Ok(streams) => {
+                    errors.push_back(errors::fragment_not_single_stream(
+                        &fragment.name,
+                        streams.len(),
+                    ));
+                    valid = false;
+                }
+                Err(mut es) => {
+                    errors.append(&mut es);
+                    valid = false;
+                }
+            }
+            if !valid {
+                invalid.insert(fragment.name.clone());
+            }
+            defs.insert(fragment.name.clone(), fragment);
+        }
+        Fragments { defs, invalid }
+    }
+
+    /// Inline the fragments invoked in some streams, adding the fragments used
+    /// to `used`. Streams with invalid invocations are removed.
+    pub fn inline(
+        &self,
+        streams: Vec<StreamExpr>,
+        used: &mut HashSet<Ident>,
+        errors: &mut LinkedList<Diagnostic>,
+    ) -> Vec<StreamExpr> {
+        self.inline_streams(streams, &mut Vec::new(), used, errors)
+    }
+
+    /// Check a query has the parameters of the fragments it uses, with the
+    /// same types.
+    #[allow(clippy::too_many_arguments)]
+    pub fn check_params(
+        &self,
+        lp: &mut plan::Plan,
+        tn: &HashMap<Ident, plan::Key<plan::Table>>,
+        ts: &mut HashMap<Ident, plan::Key<plan::ScalarType>>,
+        query: &Ident,
+        params: &[(Ident, plan::Key<plan::ScalarType>)],
+        used: HashSet<Ident>,
+        errors: &mut LinkedList<Diagnostic>,
+    ) {
+        let mut used = used.into_iter().collect::<Vec<_>>();
+        used.sort_by_key(|name| name.to_string());
+        for name in used {
+            let fragment = &self.defs[&name];
+            for (param, ast_type) in &fragment.params {
+                match query_ast_typeto_scalar(
+                    tn,
+                    ts,
+                    &mut lp.scalar_types,
+                    ast_type.clone(),
+                    |e| errors::query_param_ref_table_not_found(param, e),
+                    errors::query_no_cust_type_found,
+                ) {
+                    Ok(t) => {
+                        let frag_type = lp.scalar_types.insert(t);
+                        if !params.iter().any(|(query_param, query_type)| {
+                            query_param == param && plan::scalar_type_eq(lp, query_type, &frag_type)
+                        }) {
+                            errors.push_back(errors::fragment_param_missing(
+                                query,
+                                &fragment.name,
+                                param,
+                            ));
+                        }
+                    }
+                    Err(e) => errors.push_back(e),
+                }
+            }
+        }
+    }
+
+    fn inline_streams(
+        &self,
+        streams: Vec<StreamExpr>,
+        stack: &mut Vec<Ident>,
+        used: &mut HashSet<Ident>,
+        errors: &mut LinkedList<Diagnostic>,
+    ) -> Vec<StreamExpr> {
+        streams
+            .into_iter()
+            .filter_map(|stream| self.inline_stream(stream, stack, used, errors))
+            .collect()
+    }
+
+    fn inline_stream(
+        &self,
+        StreamExpr { mut op, con }: StreamExpr,
+        stack: &mut Vec<Ident>,
+        used: &mut HashSet<Ident>,
+        errors: &mut LinkedList<Diagnostic>,
+    ) -> Option<StreamExpr> {
+        let inlined = if let Operator::Invoke(invoke) = op {
+            self.invoke(invoke, stack, used, errors)
+        } else {
+            for context in nested_contexts(&mut op) {
+                *context = self.inline_streams(std::mem::take(context), stack, used, errors);
+            }
+            Some(StreamExpr { op, con: None })
+        };
+        let rest = match con {
+            Some((conn, next)) => Some((
+                conn,
+                Box::new(self.inline_stream(*next, stack, used, errors)?),
+            )),
+            None => None,
+        };
+
+        let mut inlined = inlined?;
+        if let Some(rest) = rest {
+            append(&mut inlined, rest);
+        }
+        Some(inlined)
+    }
+
+    fn invoke(
+        &self,
+        Invoke { call, args }: Invoke,
+        stack: &mut Vec<Ident>,
+        used: &mut HashSet<Ident>,
+        errors: &mut LinkedList<Diagnostic>,
+    ) -> Option<StreamExpr> {
+        let Some((name, fragment)) = self.defs.get_key_value(&call) else {
+            let mut names = self
+                .defs
+                .keys()
+                .filter(|name| !self.invalid.contains(*name))
+                .collect::<Vec<_>>();
+            names.sort_by_key(|name| name.to_string());
+            errors.push_back(errors::fragment_not_found(&call, &names));
+            return None;
+        };
+        if !args.is_empty() {
+            errors.push_back(errors::fragment_with_arguments(&call));
+        }
+        if self.invalid.contains(&call) {
+            return None;
+        }
+        if stack.contains(name) {
+            errors.push_back(errors::fragment_recursive(&call, name));
+            return None;
+        }
+        used.insert(name.clone());
+
+        let Ok(mut streams) = parse_fragment(fragment) else {
+            unreachable!("Fragments are checked to parse when collected")
+        };
+        stack.push(name.clone());
+        let inlined = self.inline_stream(streams.pop().unwrap(), stack, used, errors);
+        stack.pop();
+        inlined
+    }
+}
+
+/// Continue the end of a stream with more operators
+fn append(stream: &mut StreamExpr, rest: (super::ast::Connector, Box<StreamExpr>)) {
+    match &mut stream.con {
+        Some((_, next)) => append(next, rest),
+        None => stream.con = Some(rest),
+    }
+}
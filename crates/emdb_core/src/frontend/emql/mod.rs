@@ -5,6 +5,9 @@
 //!
 //! It is partly inspired by [influxdb's flux language](https://awesome.influxdata.com/docs/part-2/introduction-to-flux/)
 //! but is heavily restructed (no custom functions, only streams of data).
+//! The closest to functions are [fragments](#fragments), which take no
+//! arguments: their parameters are bound by name to the parameters of each
+//! query invoking them.
 //!
 //! ## emQL over SQL?
 //! SQL is a natural choice for a new database because it is standard, and allows for easy
@@ -75,57 +78,232 @@
 //! One way to remedy this is to include a semantics generator as a backend, that forwards the
 //! expressions to generated code purely to let rustc check.
 //!
-//! ## Potential Improvements
-//! ### Types
-//! Currently there are 3 main type syntaxes:
+//! ## Types
+//! There are 3 main type syntaxes:
 //! 1. `ref <table>` references a row
 //! 2. `type <emdb type>` for referencing a type decided by emdb (e.g. a bag)
-//! 3. A rust type (that cannot contain the two emdb types)
+//! 3. A rust type, which can contain the two emdb types anywhere
 //!
-//! To improve we could use our own type parser to parse rust types that can
-//! include emdb types.
 //! ```ignore
 //! |> map(x: (ref my_table, Vec<(type bag, ref other_table)>) = ...)
 //! ```
 //!
-//! ### Table References
-//! Change semantic analysis to remove restriction on table reference types
-//! being used in tables.
-//!  - Should include a constraint to ensure that a reference is always valid
-//!    (reference counting, check at end of transaction)
+//! ## Table References
+//! Tables can contain references to rows (`ref <table>`), which can be optional
+//! (`Option<ref <table>>`, also usable in queries). A `valid_ref` constraint
+//! ensures the referenced row exists on insert and update, and chooses what
+//! happens to referencing rows when the referenced row is deleted:
+//! - `restrict` (default) fails the delete
+//! - `cascade` deletes the referencing rows
+//! - `set_none` sets the (optional) reference to `None`
 //!
 //! ```ignore
 //! table people {
 //!     name: String,
-//!     best_friend: ref people,
-//! } @ [ valid_ref(best_friend) ]
+//!     best_friend: Option<ref people>,
+//! } @ [ valid_ref(best_friend, set_none) as friend_exists ]
 //! ```
 //!
-//! ### Better ergonomics
-//! Reducing the boilerplate required for the examples
-//! - `update` being able to reference current values
-//! - `deref` being easier to use in expressions
+//! ## Bag Parameters
+//! Bags collected by one query (`collect(x as type <name>)`) can be passed to
+//! other queries as parameters, and streamed with `use`. Parameter types are
+//! resolved after the queries defining them are analysed, and must be defined
+//! by only one record type.
+//!
+//! ```ignore
+//! query staged() {
+//!     use staged |> map(score: i64 = *score) |> collect(all as type batch) ~> return;
+//! }
+//! query add_all(items: type batch) {
+//!     use items |> insert(scores as ref added) |> count(num) ~> return;
+//! }
+//! ```
+//!
+//! ## Attributes
+//! Doc comments and other attributes (e.g. `#[must_use]`, `#[cfg(...)]`,
+//! `#[deprecated]`) can be placed before queries and tables.
+//! - Query attributes are applied to the generated methods (for backends
+//!   implementing an interface, `#[must_use]` and `#[deprecated]` are only
+//!   applied to the interface's trait)
+//! - Table attributes are applied to the table's generated module
+//!
+//! ```ignore
+//! /// Get the number of users
+//! #[must_use]
+//! query user_count() { use users |> count(num) ~> return; }
+//! ```
+//!
+//! ## Updating from Current Values
+//! Expressions in `update` can use the columns of the row being updated, which
+//! are borrowed (as with `use <table>`) from the row without copying it. Fields
+//! of the input take precedence over columns with the same name.
+//!
+//! ```ignore
+//! query increment(counter: ref counters) {
+//!     row(counter: ref counters = counter)
+//!         ~> update(counter use value = value + 1);
+//! }
+//! ```
+//!
+//! ## Upserts
+//! `upsert` inserts each row (of the table's insert type), or if a row with the
+//! same value for the given `unique` column exists, updates it instead. The
+//! `set` expressions can use the input's fields, and the existing row borrowed
+//! as the name of the returned reference.
+//!
+//! ```ignore
+//! query see(word: &str) {
+//!     row(word: String = word.to_owned(), count: i64 = 1)
+//!         ~> upsert(words as ref entry on word set count = entry.count + 1)
+//!         ~> return;
+//! }
+//! ```
+//!
+//! ## Distinct
+//! `distinct(a, b)` keeps the first record of a stream for each combination of
+//! the fields (`distinct()` compares all fields), which need to implement `Eq`,
+//! `Hash` and `Clone`. When the stream is sorted by those fields first, the
+//! duplicates are adjacent and removed without hashing.
+//!
+//! ```ignore
+//! use readings
+//!     |> sort(sensor asc, level desc)
+//!     |> distinct(sensor) // the highest level of each sensor
+//! ```
+//!
+//! ## Window Functions
+//! `window` sorts each partition of a stream (`partition by` is optional, the
+//! whole stream is one partition without it) and appends computed fields to
+//! each record:
+//! - `row_number()` numbers records from 1
+//! - `rank()` and `dense_rank()` give equal records (by the `order by` fields)
+//!   the same rank, with and without gaps after them
+//! - `running_sum(expr)`, `running_min(expr)` and `running_max(expr)` aggregate
+//!   the expression over the partition so far
 //!
-//! ### Better Bag Support
-//! Allow passing `type <emdb type>` into queries (using the types) from the
-//! query.
+//! ```ignore
+//! use readings
+//!     |> window(
+//!         partition by sensor
+//!         order by level desc
+//!         compute pos: usize = rank(), total: i64 = running_sum(level)
+//!     )
+//! ```
+//!
+//! ## Set Operations
+//! Like `union`, `intersect` and `except` combine streams of the same type:
+//! - `intersect(use a, b)` keeps the records of `a` that are also in `b`
+//! - `except(use a, b)` keeps the records of `a` that are not in `b`
+//!
+//! Each distinct record is output at most once, unless `all` is used (e.g.
+//! `except(all use a, b)`), where each record of `b` matches only one record
+//! of `a`. Every field is compared, so needs to implement `Eq`, `Hash` and
+//! `Clone`.
 //!
-//! Implemented by evaluating the context, then analysing the types used for parameters.
+//! ```ignore
+//! use purchases |> filter(**product == x) |> map(customer: String = customer.clone()) |> let bought_x;
+//! use purchases |> filter(**product == y) |> map(customer: String = customer.clone()) |> let bought_y;
+//! except(use bought_x, bought_y) |> collect(customers) ~> return;
+//! ```
+//!
+//! ## Pagination
+//! `skip` removes rows from the start of a stream, and is used with `take` to
+//! get a page:
+//! - `skip(n)` skips the first `n` rows (a `usize` expression)
+//! - `skip(after key)` directly follows a `sort`, skipping rows ordered up to
+//!   and including the key (a tuple with a value for each sort field, of the
+//!   same types). Pages are stable across inserts before the key.
+//! - `skip(after ref field = row)` skips rows up to and including the first
+//!   where `field` equals `row` (e.g. a reference to the last row of the
+//!   previous page)
 //!
 //! ```ignore
-//! query foo(my_bag: type foo_bag) {
-//!     use my_bag
-//!         |> collect(foo_ret as type foo_bag) // defining the type to input
+//! query next_page(score: i32, name: &str) {
+//!     use scores
+//!         |> map(name: String = name.clone(), score: i32 = *score)
+//!         |> sort(score desc, name asc)
+//!         |> skip(after (score, name.to_owned()))
+//!         |> take(10)
+//!         |> collect(page)
 //!         ~> return;
 //! }
 //! ```
 //!
-//! ### Pass through documentation
-//! Allow doc comments before each query.
-//! - Doc comment stntax is expanded to #[doc = "..."]
-//! - Allow other attributes to be applied to queries
+//! ## Bulk Deletes and Updates
+//! `delete_where` and `update_where` start a query, deleting or updating every
+//! row of a table that matches a predicate in a single scan of the table, and
+//! output the number of rows affected (as a `usize` field). The predicate and
+//! `set` expressions can use the columns of each row, which are borrowed (as
+//! in `update`), and take precedence over query parameters with the same name.
 //!
-//! Requires adding the doc comment to the plan, to pass on to generated code.
+//! ```ignore
+//! query remove_younger(min_age: u8) {
+//!     delete_where(users as removed where *age < min_age) ~> return;
+//! }
+//! query reward(min_age: u8, bonus: i64) {
+//!     update_where(users as rewarded where *age >= min_age set score = score + bonus)
+//!         ~> return;
+//! }
+//! ```
+//!
+//! ## Fragments
+//! A `fragment` names a stream that queries (and other fragments) can reuse,
+//! invoked as `<name>()`. Each invocation is replaced by a copy of the
+//! fragment's operators, and continued by the operators following it, so the
+//! logical plan is the same as if the stream was written out in the query.
+//! - A fragment contains exactly one stream, which can start from a source
+//!   (e.g. `use`) or continue the stream it is invoked in.
+//! - Invocations take no arguments. Expressions in the fragment are bound by
+//!   name to the invoking query's parameters, so `min_age` below is whatever
+//!   `min_age` is in the query invoking `adults()`.
+//! - The fragment's parameters declare these names, and every invoking query
+//!   must have a parameter of the same name and type (to use a different
+//!   value, the query needs a parameter named as the fragment expects).
+//!
+//! ```ignore
+//! fragment adults(min_age: u8) {
+//!     use customers |> filter(**age >= min_age)
+//! }
+//! query count_adults(min_age: u8) {
+//!     adults() |> count(num) ~> return;
+//! }
+//! ```
+//!
+//! ## Migrations
+//! When the schema changes, a `migration from <previous datastore> to <backend>`
+//! moves the rows of a datastore generated from the previous schema (by a
+//! `Serialized` backend with `pub = on`) into a new datastore, with
+//! `Datastore::try_from(previous)`. As for types in queries, the path to the
+//! previous datastore is used from inside the generated module.
+//! - `table <name> [from <previous table>] [as <row>] { <column> = <expr>, .. }`
+//!   moves each row of the previous table (by default the table of the same
+//!   name), computing the given columns from the previous row (borrowed as
+//!   `row`), and moving the others from its columns of the same name.
+//! - `table <name> empty;` starts a new table empty.
+//! - Tables not in the migration are moved from the previous table of the
+//!   same name.
+//!
+//! As rows are moved out of the previous tables, each previous table can fill
+//! at most one table.
+//! Rows violating the new tables' constraints fail the migration. Tables with
+//! reference columns cannot be migrated (keys are not kept). A snapshot of the
+//! previous version can be migrated by restoring it first.
+//!
+//! ```ignore
+//! migration from crate::v1::my_db::Datastore to my_db {
+//!     table people as person {
+//!         birth_year = 2024 - *person.age as u16,
+//!         nickname = None,
+//!     }
+//!     table logs from events {}
+//!     table audit empty;
+//! }
+//! ```
+//!
+//! ## Potential Improvements
+//! ### Better ergonomics
+//! Reducing the boilerplate required for the examples
+//! - `deref` being easier to use in expressions
 //!
 //! ### Pass through references
 //! Allow references to be stored in tables, where the lifetime of the reference
@@ -144,6 +322,7 @@
 
 mod ast;
 mod errors;
+mod fragments;
 mod operators;
 mod parse;
 mod sem;
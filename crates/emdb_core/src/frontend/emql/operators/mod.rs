@@ -11,11 +11,15 @@
 //!
 //! To create a new operator, simply add a new module and [`EMQLOperator`], then
 //! add it to the [`create_operator`] macro invocation.
+//!
+//! Calls that do not match an operator are parsed as the invocation of a
+//! fragment (see [`super::fragments`]).
 
-use super::ast::{AstType, StreamExpr};
+use super::ast::{AstType, Invoke, StreamExpr};
 use crate::frontend::emql::errors;
 use crate::frontend::emql::parse::{
-    fields_assign, fields_expr, functional_style, type_parser_to_punct, ContextRecurHandle,
+    fields_assign, fields_expr, functional_style, invoke_parser, type_parser_to_punct,
+    ContextRecurHandle,
 };
 use crate::frontend::emql::sem::{
     add_streams_to_context, assign_new_var, check_fields_type, create_scanref, discard_ends,
@@ -33,15 +37,16 @@ use combi::{
             collectuntil, getident, gettoken, isempty, matchident, matchpunct, peekident,
             peekpunct, recovgroup, syn,
         },
-        derived::{listseptrailing, syntopunct},
+        derived::{listsep, listseptrailing, syntopunct},
         error::{error, expectederr},
         TokenParser,
     },
 };
-use proc_macro2::{Delimiter, Ident, Span};
+use proc_macro2::{Delimiter, Ident, Span, TokenStream, TokenTree};
 use proc_macro_error2::{Diagnostic, Level};
+use quote::ToTokens;
 use std::{
-    collections::{HashMap, LinkedList},
+    collections::{HashMap, HashSet, LinkedList},
     fmt::Debug,
 };
 use syn::Expr;
@@ -65,6 +70,12 @@ trait EMQLOperator: Sized + Debug {
         op_ctx: plan::Key<plan::Context>,
         cont: Option<Continue>,
     ) -> Result<StreamContext, LinkedList<Diagnostic>>;
+
+    /// The contexts nested in the operator (e.g. the body of a `lift`), in
+    /// which fragments are inlined
+    fn nested_contexts(&mut self) -> Vec<&mut Vec<StreamExpr>> {
+        Vec::new()
+    }
 }
 
 // Boilerplate to connect operators (defined as structs) to the enums used to contain them in the ast and combi operators
@@ -85,17 +96,27 @@ macro_rules! create_operator {
             $(
                 $t($t),
             )*
+            /// Replaced by the fragment's operators before the logical plan is built
+            Invoke(Invoke),
         }
 
+        pub const OPERATOR_NAMES: &[&str] = &[$($t::NAME,)*];
+
         pub fn parse_operator(ctx_recur: ContextRecurHandle) -> impl TokenParser<$op> {
             choices! {
                 $(
                     peekident($t::NAME) => expectederr(mapsuc($t::build_parser(ctx_recur.clone()), $op::$t)),
                 )*
-                otherwise => error(gettoken, |t|
-                    Diagnostic::spanned(t.span(), Level::Error, format!("expected an operator but got {t}"))
-                        .help(format!("Availabe operators are {}", [$($t::NAME,)*].join(", ")))
-                )
+                otherwise => mapsuc(invoke_parser(), $op::Invoke)
+            }
+        }
+
+        pub fn nested_contexts(op: &mut $op) -> Vec<&mut Vec<StreamExpr>> {
+            match op {
+                $(
+                    $op::$t(i) => i.nested_contexts(),
+                )*
+                $op::Invoke(_) => Vec::new(),
             }
         }
 
@@ -113,6 +134,7 @@ macro_rules! create_operator {
                 $(
                     $op::$t(i) => i.build_logical(lp, tn, vs, ts, op_ctx, cont),
                 )*
+                $op::Invoke(Invoke { call, .. }) => unreachable!("Fragment `{call}` should have been inlined"),
             }
         }
     };
@@ -128,23 +150,238 @@ create_operator!(
     op_let::Let,
     op_use::Use,
     op_update::Update,
+    op_upsert::Upsert,
     op_insert::Insert,
     op_delete::Delete,
+    op_delete_where::DeleteWhere,
+    op_update_where::UpdateWhere,
     op_map::Map,
     op_unique::Unique,
+    op_lookup::Lookup,
     op_filter::Filter,
     op_row::Row,
     op_deref::DeRef,
     op_sort::Sort,
+    op_distinct::Distinct,
+    op_window::Window,
     op_fold::Fold,
     op_assert::Assert,
     op_collect::Collect,
     op_take::Take,
+    op_skip::Skip,
     op_fork::Fork,
     op_union::Union,
+    op_intersect::Intersect,
+    op_except::Except,
     op_lift::Lift,
     op_groupby::GroupBy,
     op_join::Join,
     op_combine::Combine,
     op_count::Count
 );
+
+/// Identifiers in an expression that could be variables, excluding field
+/// accesses, method calls and path segments (e.g. `data.count`, `x::count`).
+fn free_idents(expr: &Expr) -> HashSet<Ident> {
+    fn collect(tks: TokenStream, ids: &mut HashSet<Ident>) {
+        let mut accessed = false;
+        for tt in tks {
+            accessed = match tt {
+                TokenTree::Group(g) => {
+                    collect(g.stream(), ids);
+                    false
+                }
+                TokenTree::Ident(id) => {
+                    if !accessed {
+                        ids.insert(id);
+                    }
+                    false
+                }
+                TokenTree::Punct(p) => p.as_char() == '.' || p.as_char() == ':',
+                TokenTree::Literal(_) => false,
+            }
+        }
+    }
+    let mut ids = HashSet::new();
+    collect(expr.to_token_stream(), &mut ids);
+    ids
+}
+
+/// Connect the streams of several variables (which must have the same type)
+/// to a new operator (e.g. `union`).
+#[allow(clippy::too_many_arguments)]
+fn combine_streams(
+    lp: &mut plan::Plan,
+    tn: &HashMap<Ident, plan::Key<plan::Table>>,
+    vs: &mut HashMap<Ident, VarState>,
+    op_ctx: plan::Key<plan::Context>,
+    call: Ident,
+    vars: Vec<Ident>,
+    build_op: impl FnOnce(Vec<plan::Key<plan::DataFlow>>, plan::Key<plan::DataFlow>) -> plan::Operator,
+) -> Result<StreamContext, LinkedList<Diagnostic>> {
+    let mut errors = LinkedList::new();
+
+    let mut var_info = vars.into_iter().filter_map(|var| {
+        if let Some(varstate) = vs.get_mut(&var) {
+            match varstate {
+                VarState::Available { created, state } => {
+                    let saved_state = state.clone();
+                    *varstate = VarState::Used {
+                        created: *created,
+                        used: var.span(),
+                    };
+                    Some((var, saved_state))
+                }
+                VarState::Used { created, used } => {
+                    errors.push_back(errors::query_use_variable_already_used(
+                        &var, *created, *used,
+                    ));
+                    None
+                }
+                VarState::Poisoned { .. } => None,
+            }
+        } else {
+            errors.push_back(errors::query_invalid_use(&var, tn, vs));
+            None
+        }
+    });
+
+    if let Some((var_name, var_type)) = var_info.next() {
+        if var_type.data_type.stream {
+            let out_data_type = var_type.data_type.fields;
+            let mut in_edges = vec![var_type.prev_edge];
+            let mut in_types = Vec::new();
+            let mut other_errors = LinkedList::new();
+
+            for (other_var, other_type) in var_info {
+                if !other_type.data_type.stream {
+                    other_errors.push_back(errors::operator_requires_streams(&call, &other_var));
+                }
+
+                if plan::record_type_eq(lp, &out_data_type, &other_type.data_type.fields) {
+                    in_edges.push(other_type.prev_edge);
+                    in_types.push(other_type.data_type.fields);
+                } else {
+                    other_errors.push_back(errors::operator_inputs_not_same_type(
+                        lp,
+                        &call,
+                        &var_name,
+                        &out_data_type,
+                        &other_var,
+                        &other_type.data_type.fields,
+                    ));
+                }
+            }
+
+            errors.append(&mut other_errors);
+
+            if errors.is_empty() {
+                // NOTE: we can coerce the types to be the same (index/object, not just
+                //       equality), by making one reference the other.
+                //       - equality => can coerce as (type, table ref) must be the same
+                //       - if equal, resetting is fine
+                //       - by changing the type, all references to that type everywhere are
+                //         updated
+                //       - coersion means we can map each type to a rust type, and have the
+                //         same implementation shared (e.g. when choosing `type foo`'s
+                //         implementation )
+                for in_type in in_types {
+                    plan::coerce_record_type(lp, out_data_type, in_type);
+                }
+                let out_edge = lp.dataflow.insert(plan::DataFlow::Null);
+                let combine_op = lp.operators.insert(build_op(in_edges.clone(), out_edge));
+                for in_edge in in_edges {
+                    update_incomplete(lp.get_mut_dataflow(in_edge), combine_op);
+                }
+                let data_t = plan::Data {
+                    fields: out_data_type,
+                    stream: true,
+                };
+                *lp.get_mut_dataflow(out_edge) = plan::DataFlow::Incomplete {
+                    from: combine_op,
+                    with: data_t.clone(),
+                };
+                lp.get_mut_context(op_ctx).add_operator(combine_op);
+                return Ok(StreamContext::Continue(Continue {
+                    data_type: data_t,
+                    prev_edge: out_edge,
+                    last_span: call.span(),
+                }));
+            }
+        } else {
+            errors.push_back(errors::operator_requires_streams(&call, &var_name));
+        }
+    } else {
+        errors.push_back(errors::operator_requires_at_least_one_input(&call));
+    }
+
+    Err(errors)
+}
+
+/// The columns of a table named in expressions (and not shadowed by a
+/// parameter), which are borrowed from each row scanned (sorted).
+fn used_columns<'a>(
+    lp: &plan::Plan,
+    op_ctx: plan::Key<plan::Context>,
+    table: plan::Key<plan::Table>,
+    exprs: impl IntoIterator<Item = &'a Expr>,
+) -> Vec<plan::RecordField> {
+    let mut used = exprs
+        .into_iter()
+        .flat_map(free_idents)
+        .collect::<HashSet<_>>();
+    for param in lp.params_in_scope(op_ctx) {
+        used.remove(param);
+    }
+    let mut columns = lp
+        .get_table(table)
+        .columns
+        .keys()
+        .filter(|col| used.contains(col.get_field()))
+        .cloned()
+        .collect::<Vec<_>>();
+    columns.sort_by_key(|col| col.to_string());
+    columns
+}
+
+/// Start a query with an operator that mutates the rows of a table matching
+/// a predicate (e.g. `delete_where`), which outputs the number of rows
+/// affected as a single `usize` field.
+fn affected_rows(
+    lp: &mut plan::Plan,
+    op_ctx: plan::Key<plan::Context>,
+    call: &Ident,
+    count: Ident,
+    build_op: impl FnOnce(plan::RecordField, plan::Key<plan::DataFlow>) -> plan::Operator,
+) -> StreamContext {
+    // as with `count`, the span of the field is used so type errors are
+    // reported in user code
+    let size_type = lp
+        .scalar_types
+        .insert(plan::ConcRef::Conc(plan::ScalarTypeConc::Rust {
+            type_context: plan::TypeContext::Query,
+            ty: syn::parse2(quote::quote_spanned!(count.span() => usize)).unwrap(),
+        }));
+    let count = plan::RecordField::User(count);
+    let data = plan::Data {
+        fields: lp
+            .record_types
+            .insert(plan::ConcRef::Conc(plan::RecordConc {
+                fields: HashMap::from([(count.clone(), size_type)]),
+            })),
+        stream: false,
+    };
+
+    let out_edge = lp.dataflow.insert(plan::DataFlow::Null);
+    let op = lp.operators.insert(build_op(count, out_edge));
+    *lp.get_mut_dataflow(out_edge) = plan::DataFlow::Incomplete {
+        from: op,
+        with: data.clone(),
+    };
+    lp.get_mut_context(op_ctx).add_operator(op);
+    StreamContext::Continue(Continue {
+        data_type: data,
+        prev_edge: out_edge,
+        last_span: call.span(),
+    })
+}
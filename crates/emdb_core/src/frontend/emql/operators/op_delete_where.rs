@@ -0,0 +1,57 @@
+//! Delete every row of a table that matches a predicate
+use super::*;
+
+#[derive(Debug)]
+pub struct DeleteWhere {
+    call: Ident,
+    table_name: Ident,
+    count: Ident,
+    predicate: Expr,
+}
+
+impl EMQLOperator for DeleteWhere {
+    const NAME: &'static str = "delete_where";
+
+    fn build_parser(ctx_recur: ContextRecurHandle) -> impl TokenParser<Self> {
+        mapsuc(
+            functional_style(Self::NAME, seqs!(
+                setrepr(getident(), "<table to delete from>"),
+                matchident("as"),
+                setrepr(getident(), "<name of the number deleted>"),
+                matchident("where"),
+                setrepr(syn(collectuntil(isempty())), "<predicate on the row>")
+            )),
+            |(call, (table_name, (_, (count, (_, predicate)))))| DeleteWhere { call, table_name, count, predicate },
+        )
+    }
+
+    fn build_logical(
+        self,
+        lp: &mut plan::Plan,
+        tn: &HashMap<Ident, plan::Key<plan::Table>>,
+        vs: &mut HashMap<Ident, VarState>,
+        ts: &mut HashMap<Ident, plan::Key<plan::ScalarType>>,
+        op_ctx: plan::Key<plan::Context>,
+        cont: Option<Continue>,
+    ) -> Result<StreamContext, LinkedList<Diagnostic>> {
+        let Self { call, table_name, count, predicate } = self;
+        if cont.is_none() {
+            let Some(table_id) = tn.get(&table_name) else {
+                return Err(singlelist(errors::query_nonexistent_table(&call, &table_name)));
+            };
+            let columns = used_columns(lp, op_ctx, *table_id, [&predicate]);
+            Ok(affected_rows(lp, op_ctx, &call, count, |count, output| {
+                plan::DeleteWhere {
+                    table: *table_id,
+                    columns,
+                    predicate,
+                    count,
+                    output,
+                }
+                .into()
+            }))
+        } else {
+            Err(singlelist(errors::query_operator_cannot_come_first(&call)))
+        }
+    }
+}
@@ -69,7 +69,7 @@ impl EMQLOperator for DeRef {
                             plan::ScalarTypeConc::Record(r) => Err(singlelist(
                                 errors::query_deref_cannot_deref_record(lp, &reference, r),
                             )),
-                            plan::ScalarTypeConc::Rust { ty, .. } => Err(singlelist(
+                            plan::ScalarTypeConc::Rust { ty, .. } | plan::ScalarTypeConc::Composite { ty, .. } => Err(singlelist(
                                 errors::query_deref_cannot_deref_rust_type(&reference, ty),
                             )),
                             plan::ScalarTypeConc::Bag(b) => Err(singlelist(
@@ -79,6 +79,9 @@ impl EMQLOperator for DeRef {
                             plan::ScalarTypeConc::TableGet { table, field } => Err(singlelist(
                                 errors::query_deref_cannot_deref_table_get(lp, &reference, *table, field)
                             )),
+                            plan::ScalarTypeConc::Option(s) => Err(singlelist(
+                                errors::query_deref_cannot_deref_option(lp, &reference, s)
+                            )),
                             plan::ScalarTypeConc::TableRef(table_id) => {
                                 let table_id_copy = *table_id;
                                 let table_name = lp.get_table(*table_id).name.clone();
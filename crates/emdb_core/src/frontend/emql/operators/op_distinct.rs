@@ -0,0 +1,82 @@
+//! Remove duplicate records from a stream
+use super::*;
+
+#[derive(Debug)]
+pub struct Distinct {
+    call: Ident,
+    fields: Vec<Ident>,
+}
+
+impl EMQLOperator for Distinct {
+    const NAME: &'static str = "distinct";
+
+    fn build_parser(ctx_recur: ContextRecurHandle) -> impl TokenParser<Self> {
+        mapsuc(
+            functional_style(
+                Self::NAME,
+                listseptrailing(',', setrepr(getident(), "<field>")),
+            ),
+            |(call, fields)| Distinct { call, fields },
+        )
+    }
+
+    fn build_logical(
+        self,
+        lp: &mut plan::Plan,
+        tn: &HashMap<Ident, plan::Key<plan::Table>>,
+        vs: &mut HashMap<Ident, VarState>,
+        ts: &mut HashMap<Ident, plan::Key<plan::ScalarType>>,
+        op_ctx: plan::Key<plan::Context>,
+        cont: Option<Continue>,
+    ) -> Result<StreamContext, LinkedList<Diagnostic>> {
+        let Self { call, fields } = self;
+        if let Some(cont) = cont {
+            linear_builder(
+                lp,
+                op_ctx,
+                cont,
+                |lp, op_ctx, prev, next_edge| {
+                    let rec_type = lp.get_record_type_conc(prev.data_type.fields);
+                    let (raw_fields, mut errors) = extract_fields_ordered(
+                        fields.into_iter().map(|f| (f, ())).collect(),
+                        errors::query_operator_field_redefined,
+                    );
+
+                    // with no fields given, the whole record is compared
+                    let fields = if raw_fields.is_empty() {
+                        let mut all = rec_type.fields.keys().cloned().collect::<Vec<_>>();
+                        all.sort_by_key(|field| field.to_string());
+                        all
+                    } else {
+                        let mut fields = Vec::new();
+                        for (field, ()) in raw_fields {
+                            let rec_field = field.clone().into();
+                            if rec_type.fields.contains_key(&rec_field) {
+                                fields.push(rec_field);
+                            } else {
+                                errors.push_back(errors::query_reference_field_missing(&field));
+                            }
+                        }
+                        fields
+                    };
+
+                    if !prev.data_type.stream {
+                        errors.push_back(errors::query_stream_single_connection(call.span(), prev.last_span, true))
+                    }
+
+                    if errors.is_empty() {
+                        Ok(LinearBuilderState {
+                            data_out: prev.data_type,
+                            op: plan::Distinct { input: prev.prev_edge, fields, output: next_edge }.into(),
+                            call_span: call.span(),
+                        })
+                    } else {
+                        Err(errors)
+                    }
+                },
+            )
+        } else {
+            Err(singlelist(errors::query_cannot_start_with_operator(&call)))
+        }
+    }
+}
@@ -0,0 +1,48 @@
+//! Remove the records of the first stream present in any others
+use super::*;
+
+#[derive(Debug)]
+pub struct Except {
+    call: Ident,
+    all: bool,
+    vars: Vec<Ident>,
+}
+
+impl EMQLOperator for Except {
+    const NAME: &'static str = "except";
+
+    fn build_parser(ctx_recur: ContextRecurHandle) -> impl TokenParser<Self> {
+        mapsuc(
+            functional_style(Self::NAME, seqs!(
+                choice(
+                    peekident("all"),
+                    mapsuc(matchident("all"), |_| true),
+                    mapsuc(nothing(), |()| false)
+                ),
+                matchident("use"),
+                listsep(',', setrepr(getident(), "<variable>"))
+            )),
+            |(call, (all, (_, vars)))| Except { call, all, vars },
+        )
+    }
+
+    fn build_logical(
+        self,
+        lp: &mut plan::Plan,
+        tn: &HashMap<Ident, plan::Key<plan::Table>>,
+        vs: &mut HashMap<Ident, VarState>,
+        ts: &mut HashMap<Ident, plan::Key<plan::ScalarType>>,
+        op_ctx: plan::Key<plan::Context>,
+        cont: Option<Continue>,
+    ) -> Result<StreamContext, LinkedList<Diagnostic>> {
+        let Self { call, all, vars } = self;
+        if cont.is_none() {
+            let semantics = if all { plan::SetSemantics::Bag } else { plan::SetSemantics::Set };
+            combine_streams(lp, tn, vs, op_ctx, call, vars, |inputs, output| {
+                plan::Except { inputs, semantics, output }.into()
+            })
+        } else {
+            Err(singlelist(errors::query_operator_cannot_come_first(&call)))
+        }
+    }
+}
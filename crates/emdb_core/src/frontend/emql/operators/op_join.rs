@@ -9,10 +9,12 @@ enum MatchKind {
     Cross
 }
 
-// TODO: Add more join kinds (see `plan::JoinKind`)
 #[derive(Debug)]
 enum JoinKind {
     Inner,
+    Left,
+    Right,
+    Full,
 }
 
 #[derive(Debug)]
@@ -31,7 +33,10 @@ impl EMQLOperator for Join {
         fn kind_parser() -> impl TokenParser<JoinKind> {
             choices!(
                 peekident("inner") => mapsuc(matchident("inner"), |_| JoinKind::Inner),
-                otherwise => error(gettoken, |t| Diagnostic::spanned(t.span(), Level::Error, format!("invalid join kind `{}`", t)))
+                peekident("left") => mapsuc(matchident("left"), |_| JoinKind::Left),
+                peekident("right") => mapsuc(matchident("right"), |_| JoinKind::Right),
+                peekident("full") => mapsuc(matchident("full"), |_| JoinKind::Full),
+                otherwise => error(gettoken, |t| Diagnostic::spanned(t.span(), Level::Error, format!("expected join kind `inner`, `left`, `right` or `full` but got `{}`", t)))
             )
         }
 
@@ -94,6 +99,7 @@ impl EMQLOperator for Join {
                 ,
                 Some(VarState::Used { created, used }) => 
                     {errors.push_back(errors::query_use_variable_already_used(id, *created, *used)); None},
+                Some(VarState::Poisoned { .. }) => None,
                 
     
                 None => {errors.push_back(errors::query_invalid_variable_use(id, vs)); None},
@@ -141,7 +147,12 @@ impl EMQLOperator for Join {
                 // TODO: expose plan types to the user?
                 let join_kind = match join_kind {
                     JoinKind::Inner => plan::JoinKind::Inner,
+                    JoinKind::Left => plan::JoinKind::Left,
+                    JoinKind::Right => plan::JoinKind::Right,
+                    JoinKind::Full => plan::JoinKind::Full,
                 };
+                let left_optional = join_kind.left_optional();
+                let right_optional = join_kind.right_optional();
 
                 if errors.is_empty() {
                     let next_edge = lp.dataflow.insert(plan::DataFlow::Null);
@@ -157,8 +168,16 @@ impl EMQLOperator for Join {
                     update_incomplete(lp.get_mut_dataflow(left_cont.prev_edge), join_op);
                     update_incomplete(lp.get_mut_dataflow(right_cont.prev_edge), join_op);
 
-                    let left_scalar= lp.scalar_types.insert(plan::ConcRef::Conc(plan::ScalarTypeConc::Record(left_cont.data_type.fields)));
-                    let right_scalar= lp.scalar_types.insert(plan::ConcRef::Conc(plan::ScalarTypeConc::Record(right_cont.data_type.fields)));
+                    let mut left_scalar= lp.scalar_types.insert(plan::ConcRef::Conc(plan::ScalarTypeConc::Record(left_cont.data_type.fields)));
+                    let mut right_scalar= lp.scalar_types.insert(plan::ConcRef::Conc(plan::ScalarTypeConc::Record(right_cont.data_type.fields)));
+
+                    // For outer joins the side that may not be matched is optional
+                    if left_optional {
+                        left_scalar = lp.scalar_types.insert(plan::ConcRef::Conc(plan::ScalarTypeConc::Option(left_scalar)));
+                    }
+                    if right_optional {
+                        right_scalar = lp.scalar_types.insert(plan::ConcRef::Conc(plan::ScalarTypeConc::Option(right_scalar)));
+                    }
                     
                     let join_record = lp.record_types.insert(plan::ConcRef::Conc(plan::RecordConc { fields: HashMap::from([
                         (left.into(), left_scalar),
@@ -18,6 +18,10 @@ impl EMQLOperator for Lift {
         })
     }
 
+    fn nested_contexts(&mut self) -> Vec<&mut Vec<StreamExpr>> {
+        vec![&mut self.contents]
+    }
+
     fn build_logical(
         self,
         lp: &mut plan::Plan,
@@ -31,7 +35,7 @@ impl EMQLOperator for Lift {
         if let Some(Continue { data_type, prev_edge, last_span }) = cont {
             let mut errors = LinkedList::new();
             let next_edge = lp.dataflow.insert(plan::DataFlow::Null);
-            let inner_ctx = lp.contexts.insert(plan::Context::from_params(lp.get_record_type_conc(data_type.fields).fields.iter().filter_map(|(field, ty)| {
+            let inner_ctx = lp.contexts.insert(plan::Context::nested(op_ctx, lp.get_record_type_conc(data_type.fields).fields.iter().filter_map(|(field, ty)| {
                 // NOTE: Here we disallow the use of internal fields in a lift.
                 //       - We lift to provide values to the user's closures, as 
                 //         internals cannot be used in user's closures, there is 
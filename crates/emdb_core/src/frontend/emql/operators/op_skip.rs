@@ -0,0 +1,111 @@
+//! Skip rows from the start of the input stream, by an offset or after the
+//! last row of the previous page.
+use super::*;
+
+#[derive(Debug)]
+enum SkipFrom {
+    Offset(Expr),
+    Key(Expr),
+    Row { field: Ident, row: Expr },
+}
+
+#[derive(Debug)]
+pub struct Skip {
+    call: Ident,
+    from: SkipFrom,
+}
+
+impl EMQLOperator for Skip {
+    const NAME: &'static str = "skip";
+
+    fn build_parser(ctx_recur: ContextRecurHandle) -> impl TokenParser<Self> {
+        mapsuc(
+            functional_style(
+                Self::NAME,
+                choices!(
+                    peekident("after") => mapsuc(
+                        seq(
+                            matchident("after"),
+                            choices!(
+                                peekident("ref") => mapsuc(
+                                    seqs!(
+                                        matchident("ref"),
+                                        setrepr(getident(), "<field>"),
+                                        matchpunct('='),
+                                        setrepr(syn(collectuntil(isempty())), "<expression for the row>")
+                                    ),
+                                    |(_, (field, (_, row)))| SkipFrom::Row { field, row }
+                                ),
+                                otherwise => mapsuc(
+                                    setrepr(syn(collectuntil(isempty())), "<expression for the sort key>"),
+                                    SkipFrom::Key
+                                )
+                            )
+                        ),
+                        |(_, from)| from
+                    ),
+                    otherwise => mapsuc(
+                        setrepr(syn(collectuntil(isempty())), "<expression for the number to skip>"),
+                        SkipFrom::Offset
+                    )
+                ),
+            ),
+            |(call, from)| Skip { call, from },
+        )
+    }
+
+    fn build_logical(
+        self,
+        lp: &mut plan::Plan,
+        tn: &HashMap<Ident, plan::Key<plan::Table>>,
+        vs: &mut HashMap<Ident, VarState>,
+        ts: &mut HashMap<Ident, plan::Key<plan::ScalarType>>,
+        op_ctx: plan::Key<plan::Context>,
+        cont: Option<Continue>,
+    ) -> Result<StreamContext, LinkedList<Diagnostic>> {
+        let Self { call, from } = self;
+        if let Some(cont) = cont {
+            linear_builder(
+                lp,
+                op_ctx,
+                cont,
+                |lp, mo, Continue { data_type, prev_edge, last_span }, next_edge| {
+                    if !data_type.stream {
+                        return Err(singlelist(errors::query_stream_single_connection(call.span(), last_span, true)));
+                    }
+                    let from = match from {
+                        SkipFrom::Offset(offset) => plan::SkipFrom::Offset(offset),
+                        SkipFrom::Key(key) => {
+                            let sort_order = match lp.get_dataflow(prev_edge) {
+                                plan::DataFlow::Incomplete { from, .. } => match lp.get_operator(*from) {
+                                    plan::Operator::Sort(plan::Sort { sort_order, .. })
+                                    | plan::Operator::TopK(plan::TopK { sort_order, .. }) => Some(sort_order.clone()),
+                                    _ => None,
+                                },
+                                _ => None,
+                            };
+                            match sort_order {
+                                Some(sort_order) => plan::SkipFrom::Key { sort_order, key },
+                                None => return Err(singlelist(errors::query_skip_after_requires_sort(&call, last_span))),
+                            }
+                        }
+                        SkipFrom::Row { field, row } => {
+                            let rec_field = field.clone().into();
+                            if !lp.get_record_type_conc(data_type.fields).fields.contains_key(&rec_field) {
+                                return Err(singlelist(errors::query_reference_field_missing(&field)));
+                            }
+                            plan::SkipFrom::Row { field: rec_field, row }
+                        }
+                    };
+                    Ok(LinearBuilderState {
+                        data_out: data_type,
+                        op: plan::Skip { input: prev_edge, from, output: next_edge }.into(),
+                        call_span: call.span(),
+                    })
+                }
+            )
+        } else {
+            Err(singlelist(errors::query_cannot_start_with_operator(&call)))
+        }
+    }
+}
@@ -77,6 +77,21 @@ impl EMQLOperator for Update {
                             }
                         }
 
+                        // columns named in expressions (and not shadowed by a
+                        // field of the input, or a parameter) are read from the row
+                        let input_fields = &lp.get_record_type_conc(prev.data_type.fields).fields;
+                        let mut used = nondup_fields.iter().flat_map(|(_, e)| free_idents(e)).collect::<HashSet<_>>();
+                        for param in lp.params_in_scope(op_ctx) {
+                            used.remove(param);
+                        }
+                        let mut current = table
+                            .columns
+                            .keys()
+                            .filter(|col| !input_fields.contains_key(*col) && used.contains(col.get_field()))
+                            .cloned()
+                            .collect::<Vec<_>>();
+                        current.sort_by_key(|col| col.to_string());
+
                         let mapping = nondup_fields.into_iter().map(|(i, e)| (i.into(), e)).collect();
                         let update_type = lp.record_types.insert(plan::ConcRef::Conc(update_record));
                         
@@ -89,6 +104,7 @@ impl EMQLOperator for Update {
                                             reference: rec_reference.clone(),
                                             table: table_id,
                                             mapping,
+                                            current,
                                             update_type,
                                             output: next_edge, 
                                         }.into()
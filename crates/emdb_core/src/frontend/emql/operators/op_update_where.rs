@@ -0,0 +1,81 @@
+//! Update every row of a table that matches a predicate
+use super::*;
+
+#[derive(Debug)]
+pub struct UpdateWhere {
+    call: Ident,
+    table_name: Ident,
+    count: Ident,
+    predicate: Expr,
+    fields: Vec<(Ident, Expr)>,
+}
+
+impl EMQLOperator for UpdateWhere {
+    const NAME: &'static str = "update_where";
+
+    fn build_parser(ctx_recur: ContextRecurHandle) -> impl TokenParser<Self> {
+        mapsuc(
+            functional_style(Self::NAME, seqs!(
+                setrepr(getident(), "<table to update>"),
+                matchident("as"),
+                setrepr(getident(), "<name of the number updated>"),
+                matchident("where"),
+                setrepr(syntopunct(peekident("set")), "<predicate on the row>"),
+                matchident("set"),
+                fields_expr()
+            )),
+            |(call, (table_name, (_, (count, (_, (predicate, (_, fields)))))))| UpdateWhere { call, table_name, count, predicate, fields },
+        )
+    }
+
+    fn build_logical(
+        self,
+        lp: &mut plan::Plan,
+        tn: &HashMap<Ident, plan::Key<plan::Table>>,
+        vs: &mut HashMap<Ident, VarState>,
+        ts: &mut HashMap<Ident, plan::Key<plan::ScalarType>>,
+        op_ctx: plan::Key<plan::Context>,
+        cont: Option<Continue>,
+    ) -> Result<StreamContext, LinkedList<Diagnostic>> {
+        let Self { call, table_name, count, predicate, fields } = self;
+        if cont.is_none() {
+            let Some(table_id) = tn.get(&table_name) else {
+                return Err(singlelist(errors::query_nonexistent_table(&call, &table_name)));
+            };
+            let table = lp.get_table(*table_id);
+            let (nondup_fields, mut errors) = extract_fields_ordered(fields, errors::query_operator_field_redefined);
+
+            let mut update_record = plan::RecordConc { fields: HashMap::new() };
+            for (id, _) in &nondup_fields {
+                match table.columns.get(&id.clone().into()) {
+                    Some(col) => {
+                        update_record.fields.insert(id.clone().into(), col.data_type);
+                    }
+                    None => errors.push_back(errors::query_update_field_not_in_table(&table.name, id)),
+                }
+            }
+
+            if !errors.is_empty() {
+                return Err(errors);
+            }
+
+            let columns = used_columns(lp, op_ctx, *table_id, std::iter::once(&predicate).chain(nondup_fields.iter().map(|(_, e)| e)));
+            let mapping = nondup_fields.into_iter().map(|(i, e)| (i.into(), e)).collect();
+            let update_type = lp.record_types.insert(plan::ConcRef::Conc(update_record));
+            Ok(affected_rows(lp, op_ctx, &call, count, |count, output| {
+                plan::UpdateWhere {
+                    table: *table_id,
+                    columns,
+                    predicate,
+                    mapping,
+                    update_type,
+                    count,
+                    output,
+                }
+                .into()
+            }))
+        } else {
+            Err(singlelist(errors::query_operator_cannot_come_first(&call)))
+        }
+    }
+}
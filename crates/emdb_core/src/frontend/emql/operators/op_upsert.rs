@@ -0,0 +1,116 @@
+//! Insert records into a table, or update the row with the same unique value
+use super::*;
+
+#[derive(Debug)]
+pub struct Upsert {
+    call: Ident,
+    table_name: Ident,
+    out_ref: Ident,
+    field: Ident,
+    fields: Vec<(Ident, Expr)>,
+}
+
+impl EMQLOperator for Upsert {
+    const NAME: &'static str = "upsert";
+
+    fn build_parser(ctx_recur: ContextRecurHandle) -> impl TokenParser<Self> {
+        mapsuc(
+            functional_style(Self::NAME, seqs!(
+                setrepr(getident(), "<table to upsert into>"),
+                matchident("as"),
+                matchident("ref"),
+                setrepr(getident(), "<name of refs to return>"),
+                matchident("on"),
+                setrepr(getident(), "<unique column>"),
+                matchident("set"),
+                fields_expr()
+            )),
+            |(call, (table_name, (_, (_, (out_ref, (_, (field, (_, fields))))))))| Upsert { call, table_name, out_ref, field, fields },
+        )
+    }
+
+    fn build_logical(
+        self,
+        lp: &mut plan::Plan,
+        tn: &HashMap<Ident, plan::Key<plan::Table>>,
+        vs: &mut HashMap<Ident, VarState>,
+        ts: &mut HashMap<Ident, plan::Key<plan::ScalarType>>,
+        op_ctx: plan::Key<plan::Context>,
+        cont: Option<Continue>,
+    ) -> Result<StreamContext, LinkedList<Diagnostic>> {
+        let Self { call, table_name, out_ref, field, fields } = self;
+        if let Some(cont) = cont {
+            linear_builder(
+                lp,
+                op_ctx,
+                cont,
+                |lp, _, Continue {
+                    data_type: plan::Data { fields: in_fields, stream },
+                    prev_edge,
+                    last_span,
+                }, next_edge| {
+                    let Some(table_id) = tn.get(&table_name) else {
+                        return Err(singlelist(errors::query_nonexistent_table(&call, &table_name)));
+                    };
+                    let table = lp.get_table(*table_id);
+                    let (nondup_fields, mut errors) = extract_fields_ordered(fields, errors::query_operator_field_redefined);
+
+                    let rec_field = field.clone().into();
+                    match table.columns.get(&rec_field) {
+                        Some(col) if col.cons.unique.is_none() => errors.push_back(errors::query_unique_field_is_not_unique(&field, &table.name)),
+                        Some(_) => (),
+                        None => errors.push_back(errors::query_unique_no_field_in_table(&field, &table.name)),
+                    }
+
+                    let rec_out_ref = out_ref.clone().into();
+                    if table.columns.contains_key(&rec_out_ref) {
+                        errors.push_back(errors::query_upsert_ref_is_column(&out_ref, &table.name));
+                    }
+
+                    let mut update_record = plan::RecordConc { fields: HashMap::new() };
+                    for (id, _) in &nondup_fields {
+                        match table.columns.get(&id.clone().into()) {
+                            Some(col) => {
+                                update_record.fields.insert(id.clone().into(), col.data_type);
+                            }
+                            None => errors.push_back(errors::query_update_field_not_in_table(&table.name, id)),
+                        }
+                    }
+
+                    let insert_access = generate_access::insert(*table_id, lp);
+                    if !plan::record_type_eq(lp, &insert_access, &in_fields) {
+                        errors.push_back(errors::query_invalid_record_type(lp, &call, last_span, &insert_access, &in_fields));
+                    }
+
+                    if !errors.is_empty() {
+                        return Err(errors);
+                    }
+
+                    // the existing row is borrowed only if the expressions use it
+                    let current = nondup_fields.iter().any(|(_, e)| free_idents(e).contains(&out_ref));
+                    let mapping = nondup_fields.into_iter().map(|(i, e)| (i.into(), e)).collect();
+                    let update_type = lp.record_types.insert(plan::ConcRef::Conc(update_record));
+
+                    let table_ref_t = lp.scalar_types.insert(plan::ConcRef::Conc(plan::ScalarTypeConc::TableRef(*table_id)));
+                    let out_data_type = lp.record_types.insert(plan::ConcRef::Conc(plan::RecordConc { fields: HashMap::from([(rec_out_ref.clone(), table_ref_t)]) }));
+                    Ok(LinearBuilderState {
+                        data_out: plan::Data { fields: out_data_type, stream },
+                        op: plan::Upsert {
+                            input: prev_edge,
+                            table: *table_id,
+                            field: rec_field,
+                            mapping,
+                            current,
+                            update_type,
+                            out_ref: rec_out_ref,
+                            output: next_edge,
+                        }.into(),
+                        call_span: call.span(),
+                    })
+                }
+            )
+        } else {
+            Err(singlelist(errors::query_cannot_start_with_operator(&call)))
+        }
+    }
+}
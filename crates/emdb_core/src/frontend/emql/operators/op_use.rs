@@ -164,6 +164,7 @@ impl EMQLOperator for Use {
                         };
                         ret
                     }
+                    VarState::Poisoned { .. } => Err(LinkedList::new()),
                 }
             } else {
                 Err(singlelist(errors::query_invalid_use(&var_name, tn, vs)))
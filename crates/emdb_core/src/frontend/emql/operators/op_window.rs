@@ -0,0 +1,158 @@
+//! Compute window functions over sorted partitions of a stream
+use super::*;
+
+#[derive(Debug)]
+pub struct Window {
+    call: Ident,
+    partition: Vec<Ident>,
+    order: Vec<(Ident, bool)>,
+    compute: Vec<(Ident, (AstType, Expr))>,
+}
+
+impl EMQLOperator for Window {
+    const NAME: &'static str = "window";
+
+    fn build_parser(ctx_recur: ContextRecurHandle) -> impl TokenParser<Self> {
+        mapsuc(
+            functional_style(Self::NAME, seqs!(
+                choice(
+                    peekident("partition"),
+                    mapsuc(
+                        seqs!(matchident("partition"), matchident("by"), listsep(',', setrepr(getident(), "<field>"))),
+                        |(_, (_, fields))| fields
+                    ),
+                    mapsuc(nothing(), |()| Vec::new())
+                ),
+                matchident("order"),
+                matchident("by"),
+                listsep(',', seq(
+                    setrepr(getident(), "<field>"),
+                    choices!(
+                        peekident("asc") => mapsuc(matchident("asc"), |_| false),
+                        peekident("desc") => mapsuc(matchident("desc"), |_| true),
+                        otherwise => mapsuc(nothing(), |()| false)
+                    )
+                )),
+                matchident("compute"),
+                fields_assign()
+            )),
+            |(call, (partition, (_, (_, (order, (_, compute))))))| Window { call, partition, order, compute },
+        )
+    }
+
+    fn build_logical(
+        self,
+        lp: &mut plan::Plan,
+        tn: &HashMap<Ident, plan::Key<plan::Table>>,
+        vs: &mut HashMap<Ident, VarState>,
+        ts: &mut HashMap<Ident, plan::Key<plan::ScalarType>>,
+        op_ctx: plan::Key<plan::Context>,
+        cont: Option<Continue>,
+    ) -> Result<StreamContext, LinkedList<Diagnostic>> {
+        let Self { call, partition, order, compute } = self;
+        if let Some(cont) = cont {
+            linear_builder(
+                lp,
+                op_ctx,
+                cont,
+                |lp, op_ctx, prev, next_edge| {
+                    let mut fields = lp.get_record_type_conc(prev.data_type.fields).fields.clone();
+                    let mut errors = LinkedList::new();
+
+                    if !prev.data_type.stream {
+                        errors.push_back(errors::query_stream_single_connection(call.span(), prev.last_span, true))
+                    }
+
+                    let (raw_partition, mut partition_errors) = extract_fields_ordered(
+                        partition.into_iter().map(|f| (f, ())).collect(),
+                        errors::query_operator_field_redefined,
+                    );
+                    errors.append(&mut partition_errors);
+                    let mut partition = Vec::new();
+                    for (field, ()) in raw_partition {
+                        let rec_field = field.clone().into();
+                        if fields.contains_key(&rec_field) {
+                            partition.push(rec_field);
+                        } else {
+                            errors.push_back(errors::query_reference_field_missing(&field));
+                        }
+                    }
+
+                    let (raw_order, mut order_errors) = extract_fields_ordered(order, errors::sort_field_used_twice);
+                    errors.append(&mut order_errors);
+                    let mut order = Vec::new();
+                    for (field, desc) in raw_order {
+                        let rec_field = field.clone().into();
+                        if fields.contains_key(&rec_field) {
+                            order.push((rec_field, if desc { plan::SortOrder::Desc } else { plan::SortOrder::Asc }));
+                        } else {
+                            errors.push_back(errors::query_reference_field_missing(&field));
+                        }
+                    }
+
+                    let (raw_compute, mut compute_errors) = extract_fields_ordered(compute, errors::query_operator_field_redefined);
+                    errors.append(&mut compute_errors);
+                    let mut compute = Vec::new();
+                    let mut computed_types = Vec::new();
+                    for (field, (ast_type, expr)) in raw_compute {
+                        let rec_field: plan::RecordField = field.clone().into();
+                        if let Some((existing, _)) = fields.get_key_value(&rec_field) {
+                            errors.push_back(errors::query_cannot_append_to_record(&field, existing.get_field()));
+                            continue;
+                        }
+                        match query_ast_typeto_scalar(tn, ts, &mut lp.scalar_types, ast_type, |e| errors::query_nonexistent_table(&call, e), errors::query_no_cust_type_found) {
+                            Ok(t) => computed_types.push((rec_field.clone(), lp.scalar_types.insert(t))),
+                            Err(e) => errors.push_back(e),
+                        }
+                        match window_function(&field, expr) {
+                            Ok(function) => compute.push((rec_field, function)),
+                            Err(e) => errors.push_back(e),
+                        }
+                    }
+
+                    if errors.is_empty() {
+                        fields.extend(computed_types);
+                        Ok(LinearBuilderState {
+                            data_out: plan::Data {
+                                fields: lp.record_types.insert(plan::ConcRef::Conc(plan::RecordConc { fields })),
+                                stream: true,
+                            },
+                            op: plan::Window { input: prev.prev_edge, partition, order, compute, output: next_edge }.into(),
+                            call_span: call.span(),
+                        })
+                    } else {
+                        Err(errors)
+                    }
+                },
+            )
+        } else {
+            Err(singlelist(errors::query_cannot_start_with_operator(&call)))
+        }
+    }
+}
+
+/// Get the window function called by an expression (e.g. `running_sum(x)`)
+fn window_function(field: &Ident, expr: Expr) -> Result<plan::WindowFunction, Diagnostic> {
+    let unknown = errors::query_window_unknown_function(field, &expr);
+    let Expr::Call(syn::ExprCall { func, args, .. }) = expr else {
+        return Err(unknown);
+    };
+    let Expr::Path(syn::ExprPath { path, qself: None, .. }) = *func else {
+        return Err(unknown);
+    };
+    let Some(name) = path.get_ident() else {
+        return Err(unknown);
+    };
+    let mut args = args.into_iter().collect::<Vec<_>>();
+    match (name.to_string().as_str(), args.len()) {
+        ("row_number", 0) => Ok(plan::WindowFunction::RowNumber),
+        ("rank", 0) => Ok(plan::WindowFunction::Rank),
+        ("dense_rank", 0) => Ok(plan::WindowFunction::DenseRank),
+        ("running_sum", 1) => Ok(plan::WindowFunction::RunningSum(args.pop().unwrap())),
+        ("running_min", 1) => Ok(plan::WindowFunction::RunningMin(args.pop().unwrap())),
+        ("running_max", 1) => Ok(plan::WindowFunction::RunningMax(args.pop().unwrap())),
+        ("row_number" | "rank" | "dense_rank", found) => Err(errors::query_window_function_arguments(name, 0, found)),
+        ("running_sum" | "running_min" | "running_max", found) => Err(errors::query_window_function_arguments(name, 1, found)),
+        _ => Err(unknown),
+    }
+}
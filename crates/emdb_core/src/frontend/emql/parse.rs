@@ -16,6 +16,7 @@
 //! Relying on changes to [combi] and the `!Send + !Sync` properties of [`TokenStream`].
 
 use super::{ast, operators::parse_operator};
+use crate::plan;
 use combi::{
     core::{choice, mapsuc, nothing, recover, recursive, seq, seqdiff, setrepr, RecursiveHandle},
     derived::many0,
@@ -33,10 +34,10 @@ use combi::{
     },
     Combi,
 };
-use proc_macro2::{Delimiter, Ident, Span, TokenStream};
+use proc_macro2::{Delimiter, Group, Ident, Span, TokenStream, TokenTree};
 use proc_macro_error2::{Diagnostic, Level};
 use std::collections::LinkedList;
-use syn::{Expr, Type};
+use syn::Expr;
 
 pub(super) fn parse(ts: TokenStream) -> Result<ast::Ast, LinkedList<Diagnostic>> {
     let parser = emql_parser();
@@ -48,36 +49,67 @@ pub(super) fn parse(ts: TokenStream) -> Result<ast::Ast, LinkedList<Diagnostic>>
 enum EmqlItem {
     Query(ast::Query),
     Table(ast::Table),
+    Fragment(ast::Fragment),
+    Migration(ast::Migration),
     Backend(ast::BackendImpl),
 }
 
+/// Queries and tables can be preceded by attributes (including doc comments)
+enum AttrItem {
+    Query(ast::Query),
+    Table(ast::Table),
+}
+
 fn emql_parser() -> impl TokenParser<ast::Ast> {
     mapsuc(
         many0(
             not(isempty()),
             recover(
                 choices!(
-                    peekident("query") => mapsuc(query_parser(), EmqlItem::Query),
-                    peekident("table") => mapsuc(table_parser(), EmqlItem::Table),
                     peekident("impl") => mapsuc(backend_parser(), EmqlItem::Backend),
+                    peekident("fragment") => mapsuc(fragment_parser(), EmqlItem::Fragment),
+                    peekident("migration") => mapsuc(migration_parser(), EmqlItem::Migration),
+                    or(peekpunct('#'), or(peekident("query"), peekident("table"))) => mapsuc(
+                        seq(
+                            attributes_parser(),
+                            choices!(
+                                peekident("query") => mapsuc(query_parser(), AttrItem::Query),
+                                peekident("table") => mapsuc(table_parser(), AttrItem::Table),
+                                otherwise => error(gettoken, |t| {
+                                    Diagnostic::spanned(t.span(), Level::Error, String::from("expected query or table after attributes"))
+                                })
+                            )
+                        ),
+                        |(attrs, item)| match item {
+                            AttrItem::Query(query) => EmqlItem::Query(ast::Query { attrs, ..query }),
+                            AttrItem::Table(table) => EmqlItem::Table(ast::Table { attrs, ..table }),
+                        }
+                    ),
                     otherwise => error(gettoken, |t| {
-                        Diagnostic::spanned(t.span(), Level::Error, String::from("expected impl, query or table"))
+                        Diagnostic::spanned(t.span(), Level::Error, String::from("expected impl, query, fragment, migration or table"))
                     })
                 ),
                 until(or(
-                    peekident("table"),
-                    or(peekident("query"), peekident("impl")),
+                    or(peekident("table"), peekpunct('#')),
+                    or(
+                        peekident("query"),
+                        or(peekident("fragment"), peekident("impl")),
+                    ),
                 )),
             ),
         ),
         |emql_items| {
             let mut tables = vec![];
             let mut queries = vec![];
+            let mut fragments = vec![];
+            let mut migrations = vec![];
             let mut backends = vec![];
             for obj in emql_items {
                 match obj {
                     EmqlItem::Query(q) => queries.push(q),
                     EmqlItem::Table(t) => tables.push(t),
+                    EmqlItem::Fragment(f) => fragments.push(f),
+                    EmqlItem::Migration(m) => migrations.push(m),
                     EmqlItem::Backend(b) => backends.push(b),
                 }
             }
@@ -85,11 +117,27 @@ fn emql_parser() -> impl TokenParser<ast::Ast> {
                 backends,
                 tables,
                 queries,
+                fragments,
+                migrations,
             }
         },
     )
 }
 
+/// Outer attributes, doc comments are passed to the macro as `#[doc = "..."]`
+fn attributes_parser() -> impl TokenParser<Vec<syn::Attribute>> {
+    many0(
+        peekpunct('#'),
+        mapsuc(
+            seq(
+                matchpunct('#'),
+                recovgroup(Delimiter::Bracket, syn(collectuntil(isempty()))),
+            ),
+            |(_, meta): (_, syn::Meta)| syn::parse_quote!(#[#meta]),
+        ),
+    )
+}
+
 fn backend_parser() -> impl TokenParser<ast::BackendImpl> {
     mapsuc(
         seq(
@@ -158,12 +206,101 @@ fn query_parser() -> impl TokenParser<ast::Query> {
             recovgroup(Delimiter::Brace, context_parser())
         ),
         |(_, (name, (params, streams)))| ast::Query {
+            attrs: Vec::new(),
             name,
             context: ast::Context { params, streams },
         },
     )
 }
 
+/// The body of a fragment is parsed when it is used, as each invocation
+/// needs its own copy of the stream.
+fn fragment_parser() -> impl TokenParser<ast::Fragment> {
+    mapsuc(
+        seqs!(
+            matchident("fragment"),
+            getident(),
+            recovgroup(Delimiter::Parenthesis, query_param_list_parser()),
+            recovgroup(Delimiter::Brace, collectuntil(isempty()))
+        ),
+        |(_, (name, (params, body)))| ast::Fragment { name, params, body },
+    )
+}
+
+/// Parse the body of a fragment, which (unlike the streams of a query) does
+/// not need to end with a `;`, as it is continued where it is invoked.
+pub(super) fn parse_fragment(
+    fragment: &ast::Fragment,
+) -> Result<Vec<ast::StreamExpr>, LinkedList<Diagnostic>> {
+    let mut body = fragment.body.clone();
+    if !matches!(body.clone().into_iter().last(), Some(TokenTree::Punct(p)) if p.as_char() == ';') {
+        body.extend(quote::quote!(;));
+    }
+    let (_, res) = mapsuc(seqdiff(context_parser(), terminal), |(o, ())| o)
+        .comp(TokenIter::from(body, fragment.name.span()));
+    res.to_result().map_err(TokenDiagnostic::into_list)
+}
+
+/// Any call that is not an operator is parsed as the invocation of a fragment
+pub(super) fn invoke_parser() -> impl TokenParser<ast::Invoke> {
+    mapsuc(
+        seq(
+            setrepr(getident(), "<operator or fragment>"),
+            recovgroup(Delimiter::Parenthesis, collectuntil(isempty())),
+        ),
+        |(call, args)| ast::Invoke { call, args },
+    )
+}
+
+fn migration_parser() -> impl TokenParser<ast::Migration> {
+    mapsuc(
+        seqs!(
+            matchident("migration"),
+            matchident("from"),
+            setrepr(syntopunct(peekident("to")), "<previous datastore>"),
+            matchident("to"),
+            getident(),
+            recovgroup(
+                Delimiter::Brace,
+                many0(not(isempty()), table_migration_parser())
+            )
+        ),
+        |(_, (_, (from, (_, (to, tables)))))| ast::Migration { from, to, tables },
+    )
+}
+
+fn table_migration_parser() -> impl TokenParser<ast::TableMigration> {
+    fn optional(keyword: &'static str) -> impl TokenParser<Option<Ident>> {
+        choice(
+            peekident(keyword),
+            mapsuc(seq(matchident(keyword), getident()), |(_, i)| Some(i)),
+            mapsuc(nothing(), |()| None),
+        )
+    }
+
+    mapsuc(
+        seqs!(
+            matchident("table"),
+            getident(),
+            choices!(
+                peekident("empty") => mapsuc(
+                    seq(matchident("empty"), matchpunct(';')),
+                    |_| ast::MigrationSource::Empty
+                ),
+                otherwise => mapsuc(
+                    seqs!(
+                        optional("from"),
+                        optional("as"),
+                        recovgroup(Delimiter::Brace, fields_expr())
+                    ),
+                    |(from, (row, columns))| ast::MigrationSource::Rows { from, row, columns }
+                )
+            )
+        ),
+        |(_, (name, source))| ast::TableMigration { name, source },
+    )
+}
+
 fn table_parser() -> impl TokenParser<ast::Table> {
     mapsuc(
         seqs!(
@@ -185,11 +322,16 @@ fn table_parser() -> impl TokenParser<ast::Table> {
                 mapsuc(nothing(), |()| vec![])
             )
         ),
-        |(_, (name, (cols, cons)))| ast::Table { name, cols, cons },
+        |(_, (name, (cols, cons)))| ast::Table {
+            attrs: Vec::new(),
+            name,
+            cols,
+            cons,
+        },
     )
 }
 
-fn member_list_parser() -> impl TokenParser<Vec<(Ident, Type)>> {
+fn member_list_parser() -> impl TokenParser<Vec<(Ident, ast::ColumnType)>> {
     listseptrailing(
         ',',
         mapsuc(
@@ -199,6 +341,35 @@ fn member_list_parser() -> impl TokenParser<Vec<(Ident, Type)>> {
     )
 }
 
+/// Column types are rust types, or references to other tables (`ref <table>`
+/// and `Option<ref <table>>`).
+impl syn::parse::Parse for ast::ColumnType {
+    fn parse(input: syn::parse::ParseStream) -> syn::Result<Self> {
+        if input.peek(syn::Token![ref]) {
+            input.parse::<syn::Token![ref]>()?;
+            Ok(ast::ColumnType::TableRef {
+                table: input.parse()?,
+                optional: false,
+            })
+        } else if input.peek2(syn::Token![<])
+            && input.peek3(syn::Token![ref])
+            && input.fork().parse::<Ident>().is_ok_and(|i| i == "Option")
+        {
+            input.parse::<Ident>()?;
+            input.parse::<syn::Token![<]>()?;
+            input.parse::<syn::Token![ref]>()?;
+            let table = input.parse()?;
+            input.parse::<syn::Token![>]>()?;
+            Ok(ast::ColumnType::TableRef {
+                table,
+                optional: true,
+            })
+        } else {
+            Ok(ast::ColumnType::RsType(input.parse()?))
+        }
+    }
+}
+
 fn query_param_list_parser() -> impl TokenParser<Vec<(Ident, ast::AstType)>> {
     setrepr(
         listseptrailing(
@@ -234,9 +405,33 @@ fn constraint_parser() -> impl TokenParser<ast::Constraint> {
 
     choices!(
         peekident("unique") => inner("unique", mapsuc(getident(), |i| ast::ConstraintExpr::Unique{field:i})),
+        peekident("ordered") => inner("ordered", mapsuc(getident(), |i| ast::ConstraintExpr::Ordered{field:i})),
+        peekident("index") => inner("index", mapsuc(getident(), |i| ast::ConstraintExpr::Index{field:i})),
+        peekident("valid_ref") => inner("valid_ref", mapsuc(seq(getident(), on_delete_parser()), |(i, on_delete)| ast::ConstraintExpr::ValidRef{field:i, on_delete})),
         peekident("pred") => inner("pred", mapsuc(syn(collectuntil(isempty())), ast::ConstraintExpr::Pred)),
         peekident("limit") => inner("limit", mapsuc(syn(collectuntil(isempty())), |e| ast::ConstraintExpr::Limit{size:e})),
-        otherwise => error(getident(), |i| Diagnostic::spanned(i.span(), Level::Error, format!("expected a constraint (e.g. pred, unique) but got {i}")))
+        otherwise => error(getident(), |i| Diagnostic::spanned(i.span(), Level::Error, format!("expected a constraint (e.g. pred, unique, ordered, index, valid_ref) but got {i}")))
+    )
+}
+
+/// The optional `, restrict`, `, cascade` or `, set_none` of a `valid_ref`,
+/// defaulting to restrict.
+fn on_delete_parser() -> impl TokenParser<plan::OnDelete> {
+    choice(
+        peekpunct(','),
+        mapsuc(
+            seq(
+                matchpunct(','),
+                choices!(
+                    peekident("restrict") => mapsuc(matchident("restrict"), |_| plan::OnDelete::Restrict),
+                    peekident("cascade") => mapsuc(matchident("cascade"), |_| plan::OnDelete::Cascade),
+                    peekident("set_none") => mapsuc(matchident("set_none"), |_| plan::OnDelete::SetNone),
+                    otherwise => error(getident(), |i| Diagnostic::spanned(i.span(), Level::Error, format!("expected an on delete action (restrict, cascade or set_none) but got {i}")))
+                ),
+            ),
+            |(_, on_delete)| on_delete,
+        ),
+        mapsuc(nothing(), |()| plan::OnDelete::Restrict),
     )
 }
 
@@ -274,12 +469,66 @@ pub fn type_parser(end: impl TokenParser<bool>) -> impl TokenParser<ast::AstType
         choices! {
             peekident("ref") => mapsuc(seq(matchident("ref"), getident()), |(_, i)| ast::AstType::TableRef(i)),
             peekident("type") => mapsuc(seq(matchident("type"), getident()), |(_, i)| ast::AstType::Custom(i)),
-            otherwise => mapsuc(syntopunct(end), ast::AstType::RsType)
+            otherwise => syntopunct(end)
         },
         "<type>",
     )
 }
 
+/// Rust types can contain `ref <table>` and `type <alias>` anywhere (e.g.
+/// `Vec<(ref foos, type my_bag)>`), these are replaced with placeholder
+/// identifiers so syn can parse the rest of the type.
+impl syn::parse::Parse for ast::AstType {
+    fn parse(input: syn::parse::ParseStream) -> syn::Result<Self> {
+        fn replace_emql_types(
+            tks: TokenStream,
+            inner: &mut Vec<(Ident, ast::AstType)>,
+        ) -> syn::Result<TokenStream> {
+            let mut tks = tks.into_iter();
+            let mut new_tks = Vec::new();
+            while let Some(tt) = tks.next() {
+                match tt {
+                    TokenTree::Ident(kw) if kw == "ref" || kw == "type" => {
+                        let Some(TokenTree::Ident(name)) = tks.next() else {
+                            return Err(syn::Error::new(
+                                kw.span(),
+                                format!("Expected `{kw} <name>`"),
+                            ));
+                        };
+                        let placeholder =
+                            Ident::new(&format!("__emql_type_{}", inner.len()), name.span());
+                        new_tks.push(TokenTree::Ident(placeholder.clone()));
+                        inner.push((
+                            placeholder,
+                            if kw == "ref" {
+                                ast::AstType::TableRef(name)
+                            } else {
+                                ast::AstType::Custom(name)
+                            },
+                        ));
+                    }
+                    TokenTree::Group(g) => {
+                        let mut new_group =
+                            Group::new(g.delimiter(), replace_emql_types(g.stream(), inner)?);
+                        new_group.set_span(g.span());
+                        new_tks.push(TokenTree::Group(new_group));
+                    }
+                    tt => new_tks.push(tt),
+                }
+            }
+            Ok(TokenStream::from_iter(new_tks))
+        }
+
+        let mut inner = Vec::new();
+        let ty = syn::parse2(replace_emql_types(input.parse()?, &mut inner)?)?;
+        if inner.is_empty() {
+            Ok(ast::AstType::RsType(ty))
+        } else {
+            Ok(ast::AstType::Composite { ty, inner })
+        }
+    }
+}
+
 pub fn type_parser_to_punct(end: char) -> impl TokenParser<ast::AstType> {
     type_parser(peekpunct(end))
 }
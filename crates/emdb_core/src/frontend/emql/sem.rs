@@ -6,13 +6,13 @@
 //! - Tracking of emql concepts such as types & type aliases are managed here.
 //! - Complex or reused analysis for the operators is included in theis module.
 
-use super::{ast::Context, operators::build_logical};
+use super::{ast::Context, fragments::Fragments, operators::build_logical};
 use crate::{
     backend,
     frontend::emql::{
         ast::{
-            Ast, AstType, BackendImpl, Connector, Constraint, ConstraintExpr, Query, StreamExpr,
-            Table,
+            Ast, AstType, BackendImpl, ColumnType, Connector, Constraint, ConstraintExpr,
+            Migration, MigrationSource, Query, StreamExpr, Table, TableMigration,
         },
         errors,
     },
@@ -20,7 +20,9 @@ use crate::{
 };
 use proc_macro2::{Ident, Span};
 use proc_macro_error2::Diagnostic;
+use quote::ToTokens;
 use std::collections::{HashMap, HashSet, LinkedList};
+use typed_generational_arena::StandardArena;
 
 /// The return value of a [`Context`], including the [`ReturnVal::span`] for multiple
 /// return errors.
@@ -52,6 +54,9 @@ pub(super) enum StreamContext {
 pub(super) enum VarState {
     Used { created: Span, used: Span },
     Available { created: Span, state: Continue },
+    /// Could not be created due to errors already reported, so uses of the
+    /// variable report no further errors.
+    Poisoned { created: Span },
 }
 
 /// Convert an [`Ast`] to a [`plan::Plan`] and [`backend::Targets`]
@@ -59,6 +64,8 @@ pub fn ast_to_logical(
     Ast {
         backends,
         tables,
+        fragments,
+        migrations,
         queries,
     }: Ast,
 ) -> Result<(plan::Plan, backend::Targets), LinkedList<Diagnostic>> {
@@ -68,18 +75,57 @@ pub fn ast_to_logical(
     let mut tn = HashMap::new();
     let mut bks = HashMap::new();
 
-    for table in tables {
-        errors.append(&mut add_table(&mut lp, &mut tn, table));
+    // tables are declared before their columns are added, so columns can
+    // reference any table
+    let table_keys = tables
+        .iter()
+        .map(|table| {
+            let (tk, mut errs) = declare_table(&mut lp, &mut tn, &table.name);
+            errors.append(&mut errs);
+            tk
+        })
+        .collect::<Vec<_>>();
+
+    for (tk, table) in table_keys.into_iter().zip(tables) {
+        errors.append(&mut add_table(&mut lp, &tn, tk, table));
     }
 
-    for query in queries {
-        errors.append(&mut add_query(&mut lp, &mut qs, &tn, query));
+    // fragments are inlined into the queries invoking them
+    let fragments = Fragments::new(fragments, &mut errors);
+
+    // queries can take bags collected by other queries as parameters, so are
+    // added once the queries defining their parameters' types have been
+    let mut qts = HashMap::new();
+    let mut pending = queries;
+    loop {
+        let (ready, waiting): (Vec<_>, Vec<_>) = pending
+            .into_iter()
+            .partition(|query| param_types_defined(query, &qts));
+        pending = waiting;
+        if ready.is_empty() {
+            break;
+        }
+        for query in ready {
+            errors.append(&mut add_query(
+                &mut lp, &mut qs, &tn, &mut qts, &fragments, query,
+            ));
+        }
+    }
+    // any remaining have parameter types that are never defined
+    for query in pending {
+        errors.append(&mut add_query(
+            &mut lp, &mut qs, &tn, &mut qts, &fragments, query,
+        ));
     }
 
     for backend in backends {
         errors.append(&mut add_backend(&mut bks, backend));
     }
 
+    for migration in migrations {
+        errors.append(&mut add_migration(&mut lp, &tn, &bks, migration));
+    }
+
     if errors.is_empty() {
         Ok((lp, backend::Targets { impls: bks }))
     } else {
@@ -111,15 +157,48 @@ fn add_backend(
     errors
 }
 
-/// Add a table to a [`plan::Plan`]
+/// Add an empty table to a [`plan::Plan`], to be completed by [`add_table`]
 /// - Table must be unique
+fn declare_table(
+    lp: &mut plan::Plan,
+    tn: &mut HashMap<Ident, plan::Key<plan::Table>>,
+    name: &Ident,
+) -> (plan::Key<plan::Table>, LinkedList<Diagnostic>) {
+    let mut errs = LinkedList::new();
+    let tk = lp.tables.insert(plan::Table {
+        attrs: Vec::new(),
+        name: name.clone(),
+        row_cons: plan::RowConstraints {
+            limit: None,
+            preds: Vec::new(),
+        },
+        columns: HashMap::new(),
+    });
+
+    if let Some((other_t, _)) = tn.get_key_value(name) {
+        errs.push_back(errors::table_redefined(name, other_t));
+    } else {
+        tn.insert(name.clone(), tk);
+    }
+    (tk, errs)
+}
+
+/// Add the columns and constraints of a table declared by [`declare_table`]
 /// - Constraint names must be unique
 /// - Some constraints are not redefinable (e.g. unique cannot be used multiple
 ///   times of one column)
+/// - Referenced tables must exist, and only be constrained by `valid_ref`
+///   when the column is a reference
 fn add_table(
     lp: &mut plan::Plan,
-    tn: &mut HashMap<Ident, plan::Key<plan::Table>>,
-    Table { name, cols, cons }: Table,
+    tn: &HashMap<Ident, plan::Key<plan::Table>>,
+    tk: plan::Key<plan::Table>,
+    Table {
+        attrs,
+        name,
+        cols,
+        cons,
+    }: Table,
 ) -> LinkedList<Diagnostic> {
     let mut errs = LinkedList::new();
 
@@ -134,16 +213,38 @@ fn add_table(
                 duplicate.get_field(),
             ));
         } else {
-            let type_index =
-                lp.scalar_types
-                    .insert(plan::ConcRef::Conc(plan::ScalarTypeConc::Rust {
-                        type_context: plan::TypeContext::DataStore,
-                        ty: col_type,
-                    }));
+            let type_conc = match col_type {
+                ColumnType::RsType(ty) => plan::ScalarTypeConc::Rust {
+                    type_context: plan::TypeContext::DataStore,
+                    ty,
+                },
+                ColumnType::TableRef { table, optional } => match tn.get(&table) {
+                    Some(ref_tk) => {
+                        let table_ref = plan::ScalarTypeConc::TableRef(*ref_tk);
+                        if optional {
+                            plan::ScalarTypeConc::Option(
+                                lp.scalar_types.insert(plan::ConcRef::Conc(table_ref)),
+                            )
+                        } else {
+                            table_ref
+                        }
+                    }
+                    None => {
+                        errs.push_back(errors::table_column_nonexistent_ref_table(&col_name, &table));
+                        continue;
+                    }
+                },
+            };
+            let type_index = lp.scalar_types.insert(plan::ConcRef::Conc(type_conc));
             columns.insert(
                 col_rf,
                 plan::Column {
-                    cons: plan::ColumnConstraints { unique: None },
+                    cons: plan::ColumnConstraints {
+                        unique: None,
+                        ordered: None,
+                        hashed: None,
+                        valid_ref: None,
+                    },
                     data_type: type_index,
                 },
             );
@@ -196,6 +297,109 @@ fn add_table(
                     )),
                 }
             }
+            ConstraintExpr::Ordered { field } => {
+                let rf_field = field.clone().into();
+                match columns.get_mut(&rf_field) {
+                    Some(plan::Column { cons, data_type }) => match &cons.ordered {
+                        Some(cons) => {
+                            errs.push_back(errors::table_constraint_duplicate_ordered(
+                                &field,
+                                method_span,
+                                &cons.alias,
+                            ));
+                        }
+                        None => {
+                            cons.ordered = Some(plan::Constraint {
+                                alias,
+                                cons: plan::Ordered,
+                            });
+                        }
+                    },
+                    None => errs.push_back(errors::table_constraint_nonexistent_ordered_column(
+                        &alias,
+                        &field,
+                        &name,
+                        method_span,
+                    )),
+                }
+            }
+            ConstraintExpr::Index { field } => {
+                let rf_field = field.clone().into();
+                match columns.get_mut(&rf_field) {
+                    Some(plan::Column { cons, data_type }) => match &cons.hashed {
+                        Some(cons) => {
+                            errs.push_back(errors::table_constraint_duplicate_index(
+                                &field,
+                                method_span,
+                                &cons.alias,
+                            ));
+                        }
+                        None => {
+                            cons.hashed = Some(plan::Constraint {
+                                alias,
+                                cons: plan::Hashed,
+                            });
+                        }
+                    },
+                    None => errs.push_back(errors::table_constraint_nonexistent_index_column(
+                        &alias,
+                        &field,
+                        &name,
+                        method_span,
+                    )),
+                }
+            }
+            ConstraintExpr::ValidRef { field, on_delete } => {
+                let rf_field = field.clone().into();
+                match columns.get_mut(&rf_field) {
+                    Some(plan::Column { cons, data_type }) => {
+                        if let Some(cons) = &cons.valid_ref {
+                            errs.push_back(errors::table_constraint_duplicate_valid_ref(
+                                &field,
+                                method_span,
+                                &cons.alias,
+                            ));
+                        } else {
+                            let (table, optional) = match lp.get_scalar_type_conc(*data_type) {
+                                ScalarTypeConc::TableRef(table) => (Some(*table), false),
+                                ScalarTypeConc::Option(inner) => {
+                                    match lp.get_scalar_type_conc(*inner) {
+                                        ScalarTypeConc::TableRef(table) => (Some(*table), true),
+                                        _ => (None, true),
+                                    }
+                                }
+                                _ => (None, false),
+                            };
+                            match table {
+                                None => errs.push_back(errors::table_constraint_valid_ref_not_ref(
+                                    &alias,
+                                    &field,
+                                    method_span,
+                                )),
+                                Some(_) if on_delete == plan::OnDelete::SetNone && !optional => {
+                                    errs.push_back(errors::table_constraint_valid_ref_set_none(
+                                        &alias,
+                                        &field,
+                                        method_span,
+                                    ))
+                                }
+                                Some(table) => {
+                                    cons.valid_ref = Some(plan::Constraint {
+                                        alias,
+                                        cons: plan::ValidRef { table, on_delete },
+                                    });
+                                }
+                            }
+                        }
+                    }
+                    None => errs.push_back(errors::table_constraint_nonexistent_valid_ref_column(
+                        &alias,
+                        &field,
+                        &name,
+                        method_span,
+                    )),
+                }
+            }
             ConstraintExpr::Pred(expr) => {
                 row_cons.preds.push(plan::Constraint {
                     alias,
@@ -219,27 +423,157 @@ fn add_table(
         }
     }
 
-    let tk = lp.tables.insert(plan::Table {
-        name: name.clone(),
-        row_cons,
-        columns,
-    });
+    let table = lp.tables.get_mut(tk).unwrap();
+    table.attrs = attrs;
+    table.row_cons = row_cons;
+    table.columns = columns;
 
-    if let Some((other_t, _)) = tn.get_key_value(&name) {
-        errs.push_back(errors::table_redefined(&name, other_t));
-    } else {
-        tn.insert(name, tk);
+    errs
+}
+
+/// Get the `type <name>` types used by an [`AstType`]
+fn custom_types(t: &AstType) -> Vec<&Ident> {
+    match t {
+        AstType::Custom(id) => vec![id],
+        AstType::Composite { inner, .. } => {
+            inner.iter().flat_map(|(_, t)| custom_types(t)).collect()
+        }
+        AstType::RsType(_) | AstType::TableRef(_) => Vec::new(),
     }
+}
 
-    errs
+/// Add a migration to a backend
+/// - Only one migration from each previous datastore to a backend
+/// - Each table and column is migrated to at most once
+fn add_migration(
+    lp: &mut plan::Plan,
+    tn: &HashMap<Ident, plan::Key<plan::Table>>,
+    bks: &HashMap<Ident, backend::Backend>,
+    Migration { from, to, tables }: Migration,
+) -> LinkedList<Diagnostic> {
+    let mut errors = LinkedList::new();
+
+    match bks.get_key_value(&to) {
+        None => errors.push_back(errors::migration_backend_not_found(
+            &to,
+            bks.keys().collect(),
+        )),
+        Some((_, backend::Backend::Serialized(_))) => (),
+        Some(_) => errors.push_back(errors::migration_backend_unsupported(&to)),
+    }
+
+    let from_str = from.to_token_stream().to_string();
+    if let Some((_, original)) = lp
+        .migrations
+        .iter()
+        .find(|(_, m)| m.to == to && m.from.to_token_stream().to_string() == from_str)
+    {
+        errors.push_back(errors::migration_redefined(&to, &original.to));
+    }
+
+    let mut migrated = HashMap::new();
+    let mut seen = HashSet::new();
+    for TableMigration { name, source } in tables {
+        if let Some(original) = seen.get(&name) {
+            errors.push_back(errors::migration_table_redefined(&name, original));
+            continue;
+        }
+        seen.insert(name.clone());
+
+        let Some(table_key) = tn.get(&name) else {
+            errors.push_back(errors::migration_table_not_found(&name));
+            continue;
+        };
+
+        let table_migration = match source {
+            MigrationSource::Empty => plan::TableMigration::Empty,
+            MigrationSource::Rows { from, row, columns } => {
+                let (columns, mut errs) =
+                    extract_fields(columns, errors::migration_column_redefined);
+                errors.append(&mut errs);
+                let table = lp.get_table(*table_key);
+                let columns = columns
+                    .into_iter()
+                    .filter_map(|(column, expr)| {
+                        if table
+                            .columns
+                            .contains_key(&plan::RecordField::User(column.clone()))
+                        {
+                            Some((plan::RecordField::User(column), expr))
+                        } else {
+                            errors.push_back(errors::migration_column_not_found(
+                                &table.name,
+                                &column,
+                            ));
+                            None
+                        }
+                    })
+                    .collect();
+                plan::TableMigration::Rows {
+                    from: from.unwrap_or(name),
+                    row,
+                    columns,
+                }
+            }
+        };
+        migrated.insert(*table_key, table_migration);
+    }
+
+    // rows are moved out of the previous tables, tables not in the migration
+    // are moved from the previous table of the same name
+    let mut sources: HashMap<&Ident, &Ident> = HashMap::new();
+    for (key, table) in lp.tables.iter() {
+        let source = match migrated.get(&key) {
+            Some(plan::TableMigration::Empty) => continue,
+            Some(plan::TableMigration::Rows { from, .. }) => from,
+            None => &table.name,
+        };
+        if let Some((original_source, original)) = sources.get_key_value(source) {
+            errors.push_back(errors::migration_source_moved_twice(
+                source,
+                original_source,
+                &table.name,
+                original,
+            ));
+        } else {
+            sources.insert(source, &table.name);
+        }
+    }
+
+    lp.migrations.insert(plan::Migration {
+        from,
+        to,
+        tables: migrated,
+    });
+    errors
+}
+
+/// Check all `type <name>` query parameters are defined by already added queries
+fn param_types_defined(
+    query: &Query,
+    qts: &HashMap<Ident, Vec<plan::Key<plan::ScalarType>>>,
+) -> bool {
+    query
+        .context
+        .params
+        .iter()
+        .flat_map(|(_, t)| custom_types(t))
+        .all(|id| qts.contains_key(id))
 }
 
 /// Add a query to a [`plan::Plan`], using [`add_streams_to_context`].
+/// - Parameters can use the types defined in other queries (`qts`), provided
+///   they are only defined as one type.
+/// - Bag parameters are available as variables to `use` as a stream.
+/// - Fragments are inlined, and must only use parameters of the query.
 fn add_query(
     lp: &mut plan::Plan,
     qs: &mut HashSet<Ident>,
     tn: &HashMap<Ident, plan::Key<plan::Table>>,
+    qts: &mut HashMap<Ident, Vec<plan::Key<plan::ScalarType>>>,
+    fragments: &Fragments,
     Query {
+        attrs,
         name,
         context: Context { params, streams },
     }: Query,
@@ -248,35 +582,76 @@ fn add_query(
     let mut ts = HashMap::new();
 
     // Analyse the query parameters
+    let mut param_ts = qts
+        .iter()
+        .filter_map(|(id, types)| match types.as_slice() {
+            [first, rest @ ..] if rest.iter().all(|t| plan::scalar_type_eq(lp, first, t)) => {
+                Some((id.clone(), *first))
+            }
+            _ => None,
+        })
+        .collect();
     let (raw_params, mut errors) =
         extract_fields_ordered(params, errors::query_parameter_redefined);
-    let params =
-        raw_params.into_iter().filter_map(|(name, data_type)| {
+    let mut poisoned = Vec::new();
+    let params = raw_params
+        .into_iter()
+        .filter_map(|(name, data_type)| {
+            let custom = !custom_types(&data_type).is_empty();
             match query_ast_typeto_scalar(
                 tn,
-                &mut ts,
+                &mut param_ts,
+                &mut lp.scalar_types,
                 data_type,
                 |e| errors::query_param_ref_table_not_found(&name, e),
-                errors::query_no_cust_type_found,
+                |t| {
+                    if qts.contains_key(t) {
+                        errors::query_param_ambiguous_type(&name, t)
+                    } else {
+                        errors::query_no_cust_type_found(t)
+                    }
+                },
             ) {
                 Ok(t) => Some((name, lp.scalar_types.insert(t))),
                 Err(e) => {
                     errors.push_back(e);
+                    // the parameter may be a bag used as a stream
+                    if custom {
+                        poisoned.push(name);
+                    }
                     None
                 }
             }
-        });
+        })
+        .collect::<Vec<_>>();
+
+    // Inline the fragments used
+    let mut used = HashSet::new();
+    let streams = fragments.inline(streams, &mut used, &mut errors);
+    fragments.check_params(lp, tn, &mut param_ts, &name, &params, used, &mut errors);
 
     // Create and populate the query context
     let op_ctx = lp
         .contexts
-        .insert(plan::Context::from_params(params.collect(), Vec::new()));
+        .insert(plan::Context::from_params(params.clone(), Vec::new()));
 
     lp.queries.insert(plan::Query {
+        attrs,
         name: name.clone(),
         ctx: op_ctx,
     });
 
+    for (param, ty) in params {
+        if let plan::ScalarTypeConc::Bag(record) = lp.get_scalar_type_conc(ty) {
+            let cont = create_scanbag(lp, op_ctx, param.clone(), *record);
+            assign_new_var(param, cont, &mut vs, tn, &mut errors);
+        }
+    }
+    for param in poisoned {
+        let created = param.span();
+        vs.insert(param, VarState::Poisoned { created });
+    }
+
     add_streams_to_context(
         lp,
         tn,
@@ -291,6 +666,10 @@ fn add_query(
     // discard the unused variables of the context
     discard_ends(lp, op_ctx, vs);
 
+    for (id, ty) in ts {
+        qts.entry(id).or_default().push(ty);
+    }
+
     if let Some(original) = qs.get(&name) {
         errors.push_back(errors::query_redefined(&name, original));
     } else {
@@ -516,6 +895,7 @@ pub fn extract_fields_ordered<T>(
 pub fn query_ast_typeto_scalar(
     tn: &HashMap<Ident, plan::Key<plan::Table>>,
     ts: &mut HashMap<Ident, plan::Key<plan::ScalarType>>,
+    scalar_types: &mut StandardArena<plan::ScalarType>,
     t: AstType,
     table_err_fn: impl Fn(&Ident) -> Diagnostic,
     cust_err_fn: impl Fn(&Ident) -> Diagnostic,
@@ -541,6 +921,60 @@ pub fn query_ast_typeto_scalar(
                 Err(cust_err_fn(&id))
             }
         }
+        AstType::Composite { ty, inner } => {
+            let optional = option_placeholder(&ty).cloned();
+            // dyn to avoid infinitely instantiating for nested references
+            let table_err_fn: &dyn Fn(&Ident) -> Diagnostic = &table_err_fn;
+            let cust_err_fn: &dyn Fn(&Ident) -> Diagnostic = &cust_err_fn;
+            let mut inner_types = Vec::new();
+            for (placeholder, inner_ast) in inner {
+                let inner_type = query_ast_typeto_scalar(
+                    tn,
+                    ts,
+                    scalar_types,
+                    inner_ast,
+                    table_err_fn,
+                    cust_err_fn,
+                )?;
+                inner_types.push((placeholder, scalar_types.insert(inner_type)));
+            }
+            match (optional, inner_types.as_slice()) {
+                // `Option<ref <table>>` is the same type as used for optional
+                // table columns.
+                (Some(placeholder), [(inner_placeholder, inner_type)])
+                    if placeholder == *inner_placeholder =>
+                {
+                    Ok(plan::ConcRef::Conc(plan::ScalarTypeConc::Option(
+                        *inner_type,
+                    )))
+                }
+                _ => Ok(plan::ConcRef::Conc(plan::ScalarTypeConc::Composite {
+                    ty,
+                    inner: inner_types,
+                })),
+            }
+        }
+    }
+}
+
+/// Gets the identifier `x` for a type `Option<x>`
+fn option_placeholder(ty: &syn::Type) -> Option<&Ident> {
+    let syn::Type::Path(syn::TypePath { qself: None, path }) = ty else {
+        return None;
+    };
+    let [segment] = path.segments.iter().collect::<Vec<_>>()[..] else {
+        return None;
+    };
+    let syn::PathArguments::AngleBracketed(args) = &segment.arguments else {
+        return None;
+    };
+    match args.args.iter().collect::<Vec<_>>()[..] {
+        [syn::GenericArgument::Type(syn::Type::Path(syn::TypePath { qself: None, path: inner }))]
+            if segment.ident == "Option" =>
+        {
+            inner.get_ident()
+        }
+        _ => None,
     }
 }
 
@@ -622,6 +1056,41 @@ pub fn create_scanref(
     }
 }
 
+/// Stream a bag parameter of a context
+pub fn create_scanbag(
+    lp: &mut plan::Plan,
+    op_ctx: plan::Key<plan::Context>,
+    bag: Ident,
+    record: plan::Key<plan::RecordType>,
+) -> Continue {
+    let last_span = bag.span();
+    let record_out = plan::Data {
+        fields: record,
+        stream: true,
+    };
+    let out_edge = lp.dataflow.insert(plan::DataFlow::Null);
+    let bag_op = lp.operators.insert(
+        plan::ScanBag {
+            bag,
+            output: out_edge,
+        }
+        .into(),
+    );
+
+    *lp.get_mut_dataflow(out_edge) = plan::DataFlow::Incomplete {
+        from: bag_op,
+        with: record_out.clone(),
+    };
+
+    lp.get_mut_context(op_ctx).add_operator(bag_op);
+
+    Continue {
+        data_type: record_out,
+        prev_edge: out_edge,
+        last_span,
+    }
+}
+
 pub struct FieldComparison<'res> {
     pub extra_fields: Vec<&'res Ident>,
     pub missing_fields: Vec<&'res Ident>,
@@ -978,7 +1447,7 @@ pub fn assign_new_var(
             VarState::Used { created, used } => {
                 errors::query_let_variable_already_assigned(&var_name, *created, Some(*used))
             }
-            VarState::Available { created, state } => {
+            VarState::Available { created, .. } | VarState::Poisoned { created } => {
                 errors::query_let_variable_already_assigned(&var_name, *created, None)
             }
         });
@@ -18,6 +18,7 @@ use crate::backend;
 use crate::plan;
 
 pub use emql::Emql;
+pub use sql::Sql;
 
 use proc_macro2::TokenStream;
 use proc_macro_error2::Diagnostic;
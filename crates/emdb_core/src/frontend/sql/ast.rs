@@ -0,0 +1,197 @@
+//! # SQL Abstract Syntax Tree
+//! A basic AST for the supported subset of SQL, spans are kept for the errors
+//! produced in [`super::lower`].
+
+use proc_macro2::{Ident, Span, TokenStream};
+use syn::Type;
+
+/// A column or parameter type, either one of the SQL types supported (mapped to
+/// the equivalent rust type) or a rust type.
+#[derive(Debug)]
+pub(super) enum SqlType {
+    Rust(Type),
+    /// A reference to a row in a table, as used with `ROWID`
+    Ref(Ident),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(super) enum BinOp {
+    Or,
+    And,
+    Eq,
+    Neq,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+    Add,
+    Sub,
+    Mul,
+    Div,
+    Rem,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub(super) enum UnOp {
+    Not,
+    Neg,
+}
+
+/// A SQL scalar expression
+#[derive(Debug)]
+pub(super) enum Expr {
+    Column(Ident),
+    /// A query parameter (`:name`)
+    Param(Ident),
+    /// The `ROWID` keyword
+    Rowid(Ident),
+    /// A literal value, `TRUE` and `FALSE` are converted to rust booleans
+    Lit(TokenStream),
+    Unary {
+        op: UnOp,
+        span: Span,
+        expr: Box<Expr>,
+    },
+    Binary {
+        op: BinOp,
+        span: Span,
+        left: Box<Expr>,
+        right: Box<Expr>,
+    },
+}
+
+#[derive(Debug)]
+pub(super) struct ColumnDef {
+    pub name: Ident,
+    pub data_type: Type,
+    /// The `UNIQUE` keyword if the column is unique
+    pub unique: Option<Ident>,
+}
+
+#[derive(Debug)]
+pub(super) enum TableConstraint {
+    Unique {
+        alias: Option<Ident>,
+        call: Ident,
+        column: Ident,
+    },
+    Check {
+        alias: Option<Ident>,
+        call: Ident,
+        expr: Expr,
+    },
+}
+
+#[derive(Debug)]
+pub(super) enum TableElement {
+    Column(ColumnDef),
+    Constraint(TableConstraint),
+}
+
+#[derive(Debug)]
+pub(super) struct CreateTable {
+    pub name: Ident,
+    pub elements: Vec<TableElement>,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub(super) enum Aggregate {
+    Count,
+    Sum,
+    Min,
+    Max,
+}
+
+#[derive(Debug)]
+pub(super) enum SelectItem {
+    /// `*` for all columns
+    Star(Span),
+    Rowid {
+        rowid: Ident,
+        alias: Option<Ident>,
+    },
+    Column {
+        column: Ident,
+        alias: Option<Ident>,
+    },
+    /// An aggregate, with no argument for `COUNT(*)`
+    Aggregate {
+        call: Ident,
+        func: Aggregate,
+        arg: Option<Ident>,
+        alias: Option<Ident>,
+    },
+}
+
+#[derive(Debug)]
+pub(super) struct Projection {
+    pub items: Vec<SelectItem>,
+}
+
+#[derive(Debug)]
+pub(super) struct Ordering {
+    pub fields: Vec<(Ident, bool)>,
+}
+
+#[derive(Debug)]
+pub(super) struct Assignments {
+    pub fields: Vec<(Ident, Expr)>,
+}
+
+#[derive(Debug)]
+pub(super) struct Select {
+    pub call: Ident,
+    pub projection: Projection,
+    pub table: Ident,
+    pub filter: Option<Expr>,
+    pub order: Option<Ordering>,
+    pub limit: Option<Expr>,
+}
+
+#[derive(Debug)]
+pub(super) struct Insert {
+    pub call: Ident,
+    pub table: Ident,
+    pub columns: Vec<Ident>,
+    pub values: Vec<Expr>,
+    /// `RETURNING ROWID`, with an optional alias
+    pub returning: Option<(Ident, Option<Ident>)>,
+}
+
+#[derive(Debug)]
+pub(super) struct Update {
+    pub call: Ident,
+    pub table: Ident,
+    pub assignments: Assignments,
+    pub filter: Option<Expr>,
+}
+
+#[derive(Debug)]
+pub(super) struct Delete {
+    pub call: Ident,
+    pub table: Ident,
+    pub filter: Option<Expr>,
+}
+
+#[derive(Debug)]
+pub(super) enum Statement {
+    Select(Select),
+    Insert(Insert),
+    Update(Update),
+    Delete(Delete),
+}
+
+#[derive(Debug)]
+pub(super) struct Prepare {
+    pub name: Ident,
+    pub params: Vec<(Ident, SqlType)>,
+    pub statement: Statement,
+}
+
+#[derive(Debug)]
+pub(super) struct Sql {
+    /// The `impl <name> as <backend> ...;` items, passed unchanged to emQL
+    pub backends: Vec<TokenStream>,
+    pub tables: Vec<CreateTable>,
+    pub queries: Vec<Prepare>,
+}
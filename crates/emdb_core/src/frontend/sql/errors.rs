@@ -0,0 +1,155 @@
+//! # SQL Error Messages
+//! The errors produced when lowering SQL to emQL (from [`super::lower`]).
+//! - Each error has a code identified (for easy communication/bug reports)
+//! - Errors for redefinitions, and for types are produced by emQL's semantic
+//!   analysis.
+
+use itertools::Itertools;
+use proc_macro2::{Ident, Span};
+use proc_macro_error2::{Diagnostic, Level};
+
+type ErrCode = usize;
+
+fn sql_error(code: ErrCode, span: Span, message: String) -> Diagnostic {
+    Diagnostic::spanned(span, Level::Error, format!("[SQL-{code}] {message}"))
+}
+
+pub fn table_not_found<'a>(
+    table: &Ident,
+    mut tables: impl Iterator<Item = &'a Ident>,
+) -> Diagnostic {
+    sql_error(0, table.span(), format!("No table named `{table}`"))
+        .help(format!("Available tables are {}", tables.join(", ")))
+}
+
+pub fn column_not_found(column: &Ident, table: &Ident) -> Diagnostic {
+    sql_error(
+        1,
+        column.span(),
+        format!("No column named `{column}` in table `{table}`"),
+    )
+    .span_note(table.span(), format!("`{table}` defined here"))
+}
+
+pub fn param_not_found(param: &Ident, query: &Ident) -> Diagnostic {
+    sql_error(
+        2,
+        param.span(),
+        format!("No parameter `:{param}` in query `{query}`"),
+    )
+    .span_help(
+        query.span(),
+        format!("Add `{param} <type>` to the parameters of `{query}`"),
+    )
+}
+
+pub fn param_in_constraint(param: &Ident) -> Diagnostic {
+    sql_error(
+        3,
+        param.span(),
+        format!("Parameter `:{param}` cannot be used in a table constraint"),
+    )
+}
+
+pub fn column_in_values(column: &Ident) -> Diagnostic {
+    sql_error(
+        4,
+        column.span(),
+        format!("Column `{column}` cannot be used in a value to insert"),
+    )
+    .help(String::from("Use parameters (`:name`) and literals only"))
+}
+
+pub fn rowid_misused(rowid: &Ident) -> Diagnostic {
+    sql_error(
+        5,
+        rowid.span(),
+        String::from("`ROWID` can only be used as `WHERE ROWID = :param`"),
+    )
+}
+
+pub fn rowid_param_type(param: &Ident, table: &Ident) -> Diagnostic {
+    sql_error(
+        6,
+        param.span(),
+        format!("Parameter `:{param}` must have type `REF {table}` to be compared with `ROWID`"),
+    )
+}
+
+pub fn insert_missing_columns<'a>(
+    table: &Ident,
+    missing: impl Iterator<Item = &'a Ident>,
+) -> Diagnostic {
+    sql_error(
+        7,
+        table.span(),
+        format!(
+            "Columns {} of `{table}` are not inserted",
+            missing.map(|c| format!("`{c}`")).join(", ")
+        ),
+    )
+    .help(String::from("All columns must be given a value"))
+}
+
+pub fn insert_values_mismatch(call: &Ident, columns: usize, values: usize) -> Diagnostic {
+    sql_error(
+        8,
+        call.span(),
+        format!("{columns} columns are inserted, but {values} values are provided"),
+    )
+}
+
+pub fn duplicate_column(column: &Ident, original: &Ident) -> Diagnostic {
+    sql_error(9, column.span(), format!("Column `{column}` is used twice"))
+        .span_note(original.span(), String::from("First used here"))
+}
+
+pub fn aggregate_mixed(span: Span) -> Diagnostic {
+    sql_error(
+        10,
+        span,
+        String::from("Cannot select both aggregates and columns"),
+    )
+    .help(String::from(
+        "`GROUP BY` is not supported, so all selected columns must be aggregates",
+    ))
+}
+
+pub fn single_row_clause(span: Span) -> Diagnostic {
+    sql_error(
+        11,
+        span,
+        String::from("Cannot sort, limit or aggregate a single row selected by `ROWID`"),
+    )
+}
+
+pub fn param_shadows_column(param: &Ident, column: &Ident) -> Diagnostic {
+    sql_error(
+        12,
+        param.span(),
+        format!("Parameter `:{param}` has the same name as column `{column}` used in this query"),
+    )
+    .span_note(column.span(), String::from("Column used here"))
+    .help(String::from("Rename the parameter"))
+}
+
+pub fn reserved_param(param: &Ident) -> Diagnostic {
+    sql_error(
+        13,
+        param.span(),
+        format!("Parameter name `{param}` is reserved"),
+    )
+    .help(String::from(
+        "`row` and `rowid` are used when translating to emQL, rename the parameter",
+    ))
+}
+
+pub fn order_not_selected(field: &Ident) -> Diagnostic {
+    sql_error(
+        14,
+        field.span(),
+        format!(
+            "Cannot order by `{field}`, as it is not a selected column or a column of the table"
+        ),
+    )
+}
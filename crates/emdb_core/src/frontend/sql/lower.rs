@@ -0,0 +1,911 @@
+//! # Lowering SQL to emQL
+//! Each table and prepared statement is translated to the equivalent emQL,
+//! which is then analysed by [`Emql`](crate::frontend::Emql) to produce the
+//! [`plan::Plan`](crate::plan::Plan).
+//!
+//! | SQL                                        | emQL                                                   |
+//! |--------------------------------------------|--------------------------------------------------------|
+//! | `SELECT .. FROM t WHERE ROWID = :id`       | `row(rowid: ref t = id) ~> deref(rowid as row) ~> ..`  |
+//! | `SELECT a, ROWID FROM t`                   | `ref t as rowid \|> deref(rowid as row use a) \|> ..`  |
+//! | `SELECT a FROM t WHERE ..`                 | `use t as (a) \|> filter(..) \|> ..`                   |
+//! | `INSERT INTO t (..) VALUES (..)`           | `row(..) ~> insert(t as ref rowid)`                    |
+//! | `UPDATE t SET a = .. WHERE ..`             | `ref t as rowid \|> .. \|> update(rowid use a = ..)`   |
+//! | `DELETE FROM t WHERE ..`                   | `ref t as rowid \|> .. \|> delete(rowid)`              |
+//!
+//! ## Expressions
+//! SQL expressions are translated to rust expressions, each column and parameter
+//! is cloned (`<T as Clone>::clone(&x)`) so that expressions are over owned
+//! values of the declared type, regardless of whether emQL provides the column
+//! by value or by reference.
+
+use super::{ast, errors};
+use proc_macro2::{Ident, Span, TokenStream};
+use quote::{format_ident, quote, quote_spanned};
+use std::collections::{HashMap, LinkedList};
+use syn::Type;
+
+use proc_macro_error2::Diagnostic;
+
+pub(super) fn lower(
+    ast::Sql {
+        backends,
+        tables,
+        queries,
+    }: ast::Sql,
+) -> Result<TokenStream, LinkedList<Diagnostic>> {
+    let mut errors = LinkedList::new();
+
+    let table_infos = tables
+        .iter()
+        .map(|table| (&table.name, TableInfo::new(table)))
+        .rev() // the first definition is used, emQL reports the redefinitions
+        .collect::<HashMap<_, _>>();
+
+    let table_tks = tables
+        .iter()
+        .map(|table| lower_table(table, &table_infos[&table.name], &mut errors))
+        .collect::<Vec<_>>();
+
+    let query_tks = queries
+        .iter()
+        .map(|query| {
+            let mut lowering = Lowering {
+                tables: &table_infos,
+                query,
+                errors: LinkedList::new(),
+            };
+            let tks = lowering.query();
+            errors.append(&mut lowering.errors);
+            tks
+        })
+        .collect::<Vec<_>>();
+
+    if errors.is_empty() {
+        Ok(quote! {
+            #(#backends)*
+            #(#table_tks)*
+            #(#query_tks)*
+        })
+    } else {
+        Err(errors)
+    }
+}
+
+struct TableInfo<'a> {
+    name: &'a Ident,
+    columns: Vec<(&'a Ident, &'a Type)>,
+}
+
+impl<'a> TableInfo<'a> {
+    fn new(table: &'a ast::CreateTable) -> Self {
+        Self {
+            name: &table.name,
+            columns: table
+                .elements
+                .iter()
+                .filter_map(|element| match element {
+                    ast::TableElement::Column(ast::ColumnDef {
+                        name, data_type, ..
+                    }) => Some((name, data_type)),
+                    ast::TableElement::Constraint(_) => None,
+                })
+                .collect(),
+        }
+    }
+
+    fn column(&self, column: &Ident) -> Option<&'a Type> {
+        self.columns
+            .iter()
+            .find_map(|(name, data_type)| (*name == column).then_some(*data_type))
+    }
+}
+
+fn rowid_ident() -> Ident {
+    Ident::new("rowid", Span::call_site())
+}
+
+fn row_ident() -> Ident {
+    Ident::new("row", Span::call_site())
+}
+
+/// How columns are accessed in an expression
+#[derive(Clone, Copy)]
+enum ColumnAccess {
+    /// Columns cannot be used (e.g. in the values to insert)
+    Disallowed,
+    /// As fields of the same name (after `use <table>`)
+    Bare,
+    /// As members of the dereferenced `row`
+    Row,
+}
+
+/// The context to lower an expression in
+struct Scope<'a, 'b> {
+    table: &'b TableInfo<'a>,
+    access: ColumnAccess,
+    /// The query and its parameters, or none for a table constraint
+    query: Option<&'a ast::Prepare>,
+}
+
+/// Clone a value to the declared type, works for both `T` and `&T` (through
+/// deref coercion).
+fn clone_value(data_type: &Type, value: TokenStream, span: Span) -> TokenStream {
+    quote_spanned! {span=> <#data_type as Clone>::clone(&#value) }
+}
+
+fn lower_expr(
+    expr: &ast::Expr,
+    scope: &Scope<'_, '_>,
+    errors: &mut LinkedList<Diagnostic>,
+) -> TokenStream {
+    match expr {
+        ast::Expr::Column(column) => match (scope.table.column(column), scope.access) {
+            (None, _) => {
+                errors.push_back(errors::column_not_found(column, scope.table.name));
+                quote!(#column)
+            }
+            (Some(_), ColumnAccess::Disallowed) => {
+                errors.push_back(errors::column_in_values(column));
+                quote!(#column)
+            }
+            (Some(data_type), ColumnAccess::Bare) => {
+                clone_value(data_type, quote!(#column), column.span())
+            }
+            (Some(data_type), ColumnAccess::Row) => {
+                let row = row_ident();
+                clone_value(data_type, quote!(#row.#column), column.span())
+            }
+        },
+        ast::Expr::Param(param) => match scope.query {
+            None => {
+                errors.push_back(errors::param_in_constraint(param));
+                quote!(#param)
+            }
+            Some(query) => match query.params.iter().find(|(name, _)| name == param) {
+                None => {
+                    errors.push_back(errors::param_not_found(param, &query.name));
+                    quote!(#param)
+                }
+                Some((_, ast::SqlType::Rust(data_type))) => {
+                    clone_value(data_type, quote!(#param), param.span())
+                }
+                Some((_, ast::SqlType::Ref(_))) => quote!(#param),
+            },
+        },
+        ast::Expr::Rowid(rowid) => {
+            errors.push_back(errors::rowid_misused(rowid));
+            quote!(#rowid)
+        }
+        ast::Expr::Lit(lit) => lit.clone(),
+        ast::Expr::Unary { op, span, expr } => {
+            let inner = lower_subexpr(expr, scope, errors);
+            match op {
+                ast::UnOp::Not => quote_spanned!(*span=> !#inner),
+                ast::UnOp::Neg => quote_spanned!(*span=> -#inner),
+            }
+        }
+        ast::Expr::Binary {
+            op,
+            span,
+            left,
+            right,
+        } => {
+            // comparisons with `TRUE` and `FALSE` are simplified (avoids
+            // `x == true`)
+            if let Some((value, other)) = bool_comparison(*op, left, right) {
+                return if value {
+                    lower_expr(other, scope, errors)
+                } else {
+                    let other = lower_subexpr(other, scope, errors);
+                    quote_spanned!(*span=> !#other)
+                };
+            }
+
+            let left = lower_subexpr(left, scope, errors);
+            let right = lower_subexpr(right, scope, errors);
+            let op = match op {
+                ast::BinOp::Or => quote_spanned!(*span=> ||),
+                ast::BinOp::And => quote_spanned!(*span=> &&),
+                ast::BinOp::Eq => quote_spanned!(*span=> ==),
+                ast::BinOp::Neq => quote_spanned!(*span=> !=),
+                ast::BinOp::Lt => quote_spanned!(*span=> <),
+                ast::BinOp::Le => quote_spanned!(*span=> <=),
+                ast::BinOp::Gt => quote_spanned!(*span=> >),
+                ast::BinOp::Ge => quote_spanned!(*span=> >=),
+                ast::BinOp::Add => quote_spanned!(*span=> +),
+                ast::BinOp::Sub => quote_spanned!(*span=> -),
+                ast::BinOp::Mul => quote_spanned!(*span=> *),
+                ast::BinOp::Div => quote_spanned!(*span=> /),
+                ast::BinOp::Rem => quote_spanned!(*span=> %),
+            };
+            quote!(#left #op #right)
+        }
+    }
+}
+
+/// For `expr = TRUE`, `expr <> FALSE` etc. get the value `expr` must be
+fn bool_comparison<'a>(
+    op: ast::BinOp,
+    left: &'a ast::Expr,
+    right: &'a ast::Expr,
+) -> Option<(bool, &'a ast::Expr)> {
+    fn bool_lit(expr: &ast::Expr) -> Option<bool> {
+        match expr {
+            ast::Expr::Lit(lit) => match lit.to_string().as_str() {
+                "true" => Some(true),
+                "false" => Some(false),
+                _ => None,
+            },
+            _ => None,
+        }
+    }
+
+    let negate = match op {
+        ast::BinOp::Eq => false,
+        ast::BinOp::Neq => true,
+        _ => return None,
+    };
+    match (bool_lit(left), bool_lit(right)) {
+        (_, Some(value)) => Some((value != negate, left)),
+        (Some(value), None) => Some((value != negate, right)),
+        (None, None) => None,
+    }
+}
+
+/// Lower an operand, bracketed to preserve the SQL precedence
+fn lower_subexpr(
+    expr: &ast::Expr,
+    scope: &Scope<'_, '_>,
+    errors: &mut LinkedList<Diagnostic>,
+) -> TokenStream {
+    let tks = lower_expr(expr, scope, errors);
+    match expr {
+        ast::Expr::Unary { .. } | ast::Expr::Binary { .. } => quote!((#tks)),
+        _ => tks,
+    }
+}
+
+/// The columns used in an expression, in order of use
+fn used_columns<'a>(expr: &'a ast::Expr, columns: &mut Vec<&'a Ident>) {
+    match expr {
+        ast::Expr::Column(column) => columns.push(column),
+        ast::Expr::Unary { expr, .. } => used_columns(expr, columns),
+        ast::Expr::Binary { left, right, .. } => {
+            used_columns(left, columns);
+            used_columns(right, columns);
+        }
+        ast::Expr::Param(_) | ast::Expr::Rowid(_) | ast::Expr::Lit(_) => (),
+    }
+}
+
+/// If the filter is `ROWID = :param`
+fn rowid_lookup(filter: &Option<ast::Expr>) -> Option<&Ident> {
+    if let Some(ast::Expr::Binary {
+        op: ast::BinOp::Eq,
+        left,
+        right,
+        ..
+    }) = filter
+    {
+        match (left.as_ref(), right.as_ref()) {
+            (ast::Expr::Rowid(_), ast::Expr::Param(param))
+            | (ast::Expr::Param(param), ast::Expr::Rowid(_)) => Some(param),
+            _ => None,
+        }
+    } else {
+        None
+    }
+}
+
+fn lower_table(
+    table: &ast::CreateTable,
+    info: &TableInfo<'_>,
+    errors: &mut LinkedList<Diagnostic>,
+) -> TokenStream {
+    let name = &table.name;
+    let columns = info
+        .columns
+        .iter()
+        .map(|(column, data_type)| quote!(#column: #data_type));
+
+    let scope = Scope {
+        table: info,
+        access: ColumnAccess::Bare,
+        query: None,
+    };
+    let mut unnamed_checks: usize = 0;
+    let constraints = table
+        .elements
+        .iter()
+        .filter_map(|element| match element {
+            ast::TableElement::Column(ast::ColumnDef {
+                name,
+                unique: Some(unique),
+                ..
+            }) => {
+                let alias = format_ident!("unique_{}", name);
+                Some(quote_spanned!(unique.span()=> unique(#name) as #alias))
+            }
+            ast::TableElement::Column(_) => None,
+            ast::TableElement::Constraint(ast::TableConstraint::Unique {
+                alias,
+                call,
+                column,
+            }) => {
+                let alias = alias
+                    .clone()
+                    .unwrap_or_else(|| format_ident!("unique_{}", column));
+                Some(quote_spanned!(call.span()=> unique(#column) as #alias))
+            }
+            ast::TableElement::Constraint(ast::TableConstraint::Check { alias, call, expr }) => {
+                let alias = alias.clone().unwrap_or_else(|| {
+                    unnamed_checks += 1;
+                    format_ident!("check_{}", unnamed_checks - 1)
+                });
+                let expr = lower_expr(expr, &scope, errors);
+                Some(quote_spanned!(call.span()=> pred(#expr) as #alias))
+            }
+        })
+        .collect::<Vec<_>>();
+
+    let constraints = if constraints.is_empty() {
+        quote!()
+    } else {
+        quote!(@ [ #(#constraints),* ])
+    };
+
+    quote! {
+        table #name {
+            #(#columns,)*
+        } #constraints
+    }
+}
+
+/// An output field of a `SELECT`
+struct Output<'a> {
+    name: Ident,
+    data_type: TokenStream,
+    value: OutputValue<'a>,
+}
+
+enum OutputValue<'a> {
+    Rowid,
+    Column(&'a Ident, &'a Type),
+    Aggregate(ast::Aggregate, Option<(&'a Ident, &'a Type)>),
+}
+
+struct Lowering<'a, 'b> {
+    tables: &'b HashMap<&'a Ident, TableInfo<'a>>,
+    query: &'a ast::Prepare,
+    errors: LinkedList<Diagnostic>,
+}
+
+impl<'a, 'b> Lowering<'a, 'b> {
+    fn query(&mut self) -> TokenStream {
+        let ast::Prepare {
+            name,
+            params,
+            statement,
+        } = self.query;
+
+        let params = params
+            .iter()
+            .map(|(param, data_type)| {
+                if param == "row" || param == "rowid" {
+                    self.errors.push_back(errors::reserved_param(param));
+                }
+                match data_type {
+                    ast::SqlType::Rust(data_type) => quote!(#param: #data_type),
+                    ast::SqlType::Ref(table) => quote!(#param: ref #table),
+                }
+            })
+            .collect::<Vec<_>>();
+
+        let streams = match statement {
+            ast::Statement::Select(select) => self.select(select),
+            ast::Statement::Insert(insert) => self.insert(insert),
+            ast::Statement::Update(update) => self.update(update),
+            ast::Statement::Delete(delete) => self.delete(delete),
+        }
+        .unwrap_or_default();
+
+        quote! {
+            query #name(#(#params),*) {
+                #streams
+            }
+        }
+    }
+
+    fn table(&mut self, table: &Ident) -> Option<&'b TableInfo<'a>> {
+        let info = self.tables.get(table);
+        if info.is_none() {
+            self.errors
+                .push_back(errors::table_not_found(table, self.tables.keys().copied()));
+        }
+        info
+    }
+
+    fn column(&mut self, table: &TableInfo<'a>, column: &Ident) -> Option<&'a Type> {
+        let data_type = table.column(column);
+        if data_type.is_none() {
+            self.errors
+                .push_back(errors::column_not_found(column, table.name));
+        }
+        data_type
+    }
+
+    fn expr(
+        &mut self,
+        expr: &ast::Expr,
+        table: &TableInfo<'a>,
+        access: ColumnAccess,
+    ) -> TokenStream {
+        lower_expr(
+            expr,
+            &Scope {
+                table,
+                access,
+                query: Some(self.query),
+            },
+            &mut self.errors,
+        )
+    }
+
+    /// Check the parameter compared with `ROWID` is a reference to the table
+    fn rowid_param(&mut self, param: &Ident, table: &TableInfo<'a>) {
+        match self.query.params.iter().find(|(name, _)| name == param) {
+            None => self
+                .errors
+                .push_back(errors::param_not_found(param, &self.query.name)),
+            Some((_, ast::SqlType::Ref(ref_table))) if ref_table == table.name => (),
+            Some(_) => self
+                .errors
+                .push_back(errors::rowid_param_type(param, table.name)),
+        }
+    }
+
+    /// Parameters have the same name as the columns they would be shadowed by
+    fn check_shadowing(&mut self, columns: &[&Ident]) {
+        for (param, _) in &self.query.params {
+            if let Some(column) = columns.iter().find(|c| **c == param) {
+                self.errors
+                    .push_back(errors::param_shadows_column(param, column));
+            }
+        }
+    }
+
+    /// Get the references to the rows (either all rows, or a single row by
+    /// `ROWID`), dereferencing the columns needed as `row`
+    fn source(
+        &mut self,
+        table: &TableInfo<'a>,
+        lookup: Option<&Ident>,
+        needed: &[&Ident],
+    ) -> (TokenStream, TokenStream) {
+        let table_name = table.name;
+        let (rowid, row) = (rowid_ident(), row_ident());
+        let (source, conn) = if let Some(param) = lookup {
+            self.rowid_param(param, table);
+            (quote!(row(#rowid: ref #table_name = #param)), quote!(~>))
+        } else {
+            (quote!(ref #table_name as #rowid), quote!(|>))
+        };
+
+        if needed.is_empty() {
+            (source, conn)
+        } else {
+            (
+                quote!(#source #conn deref(#rowid as #row use #(#needed),*)),
+                conn,
+            )
+        }
+    }
+
+    fn select(&mut self, select: &'a ast::Select) -> Option<TokenStream> {
+        let ast::Select {
+            call,
+            projection,
+            table,
+            filter,
+            order,
+            limit,
+        } = select;
+        let table = self.table(table)?;
+        let table_name = table.name;
+        let rowid = rowid_ident();
+
+        let mut outputs = Vec::new();
+        for item in &projection.items {
+            match item {
+                ast::SelectItem::Star(_) => {
+                    outputs.extend(table.columns.iter().map(|(column, data_type)| Output {
+                        name: (*column).clone(),
+                        data_type: quote!(#data_type),
+                        value: OutputValue::Column(column, data_type),
+                    }))
+                }
+                ast::SelectItem::Rowid { rowid, alias } => outputs.push(Output {
+                    name: alias
+                        .clone()
+                        .unwrap_or_else(|| Ident::new("rowid", rowid.span())),
+                    data_type: quote!(ref #table_name),
+                    value: OutputValue::Rowid,
+                }),
+                ast::SelectItem::Column { column, alias } => {
+                    if let Some(data_type) = self.column(table, column) {
+                        outputs.push(Output {
+                            name: alias.clone().unwrap_or_else(|| column.clone()),
+                            data_type: quote!(#data_type),
+                            value: OutputValue::Column(column, data_type),
+                        })
+                    }
+                }
+                ast::SelectItem::Aggregate {
+                    call,
+                    func,
+                    arg,
+                    alias,
+                } => {
+                    let arg = match arg {
+                        Some(column) => {
+                            let data_type = self.column(table, column)?;
+                            Some((column, data_type))
+                        }
+                        None => None,
+                    };
+                    let (default_name, data_type) = match (func, arg) {
+                        (ast::Aggregate::Count, _) => (format_ident!("count"), quote!(usize)),
+                        (ast::Aggregate::Sum, Some((column, data_type))) => {
+                            (format_ident!("sum_{}", column), quote!(#data_type))
+                        }
+                        (ast::Aggregate::Min, Some((column, data_type))) => {
+                            (format_ident!("min_{}", column), quote!(Option<#data_type>))
+                        }
+                        (ast::Aggregate::Max, Some((column, data_type))) => {
+                            (format_ident!("max_{}", column), quote!(Option<#data_type>))
+                        }
+                        (_, None) => unreachable!("Only COUNT can be used with `*`"),
+                    };
+                    outputs.push(Output {
+                        name: alias
+                            .clone()
+                            .unwrap_or_else(|| Ident::new(&default_name.to_string(), call.span())),
+                        data_type,
+                        value: OutputValue::Aggregate(*func, arg),
+                    })
+                }
+            }
+        }
+
+        let aggregates = outputs
+            .iter()
+            .filter(|o| matches!(o.value, OutputValue::Aggregate(..)))
+            .count();
+        if aggregates > 0 && aggregates < outputs.len() {
+            self.errors.push_back(errors::aggregate_mixed(call.span()));
+            return None;
+        }
+
+        let lookup = rowid_lookup(filter);
+        if lookup.is_some() && (order.is_some() || limit.is_some() || aggregates > 0) {
+            self.errors
+                .push_back(errors::single_row_clause(call.span()));
+            return None;
+        }
+
+        // columns only needed for ordering, are added to the output, then removed
+        let mut sort_keys = Vec::new();
+        let mut hidden = Vec::new();
+        for (field, desc) in order.iter().flat_map(|o| &o.fields) {
+            let dir = if *desc {
+                quote_spanned!(field.span()=> desc)
+            } else {
+                quote_spanned!(field.span()=> asc)
+            };
+            if outputs.iter().any(|o| &o.name == field) {
+                sort_keys.push(quote!(#field #dir));
+            } else if let Some(data_type) = table.column(field) {
+                hidden.push(Output {
+                    name: field.clone(),
+                    data_type: quote!(#data_type),
+                    value: OutputValue::Column(field, data_type),
+                });
+                sort_keys.push(quote!(#field #dir));
+            } else {
+                self.errors.push_back(errors::order_not_selected(field));
+            }
+        }
+
+        let mut needed = Vec::new();
+        for output in outputs.iter().chain(hidden.iter()) {
+            match output.value {
+                OutputValue::Column(column, _) | OutputValue::Aggregate(_, Some((column, _))) => {
+                    needed.push(column)
+                }
+                OutputValue::Rowid | OutputValue::Aggregate(_, None) => (),
+            }
+        }
+        if lookup.is_none() {
+            if let Some(filter) = filter {
+                used_columns(filter, &mut needed);
+            }
+        }
+        dedup(&mut needed);
+
+        let uses_rowid = lookup.is_some()
+            || outputs
+                .iter()
+                .any(|o| matches!(o.value, OutputValue::Rowid));
+        let (source, conn, access) = if uses_rowid {
+            let (source, conn) = self.source(table, lookup, &needed);
+            (source, conn, ColumnAccess::Row)
+        } else {
+            self.check_shadowing(&needed);
+            (
+                quote!(use #table_name as (#(#needed),*)),
+                quote!(|>),
+                ColumnAccess::Bare,
+            )
+        };
+
+        let filter = match (filter, lookup) {
+            (Some(filter), None) => {
+                let filter = self.expr(filter, table, access);
+                quote!(#conn filter(#filter))
+            }
+            _ => quote!(),
+        };
+
+        if aggregates > 0 {
+            let tail = if let [Output {
+                name,
+                value: OutputValue::Aggregate(ast::Aggregate::Count, _),
+                ..
+            }] = outputs.as_slice()
+            {
+                quote!(|> count(#name))
+            } else {
+                let folds = outputs.iter().map(|Output { name, data_type, value }| {
+                    let (init, update) = match value {
+                        OutputValue::Aggregate(ast::Aggregate::Count, _) => (quote!(0), quote!(#name + 1)),
+                        OutputValue::Aggregate(func, Some((column, _))) => {
+                            let value = lower_expr(
+                                &ast::Expr::Column((*column).clone()),
+                                &Scope { table, access, query: Some(self.query) },
+                                &mut self.errors,
+                            );
+                            match func {
+                                ast::Aggregate::Sum => (quote!(Default::default()), quote!(#name + #value)),
+                                ast::Aggregate::Min => (quote!(None), quote!(match (#name, #value) { (Some(current), value) if current <= value => Some(current), (_, value) => Some(value) })),
+                                ast::Aggregate::Max => (quote!(None), quote!(match (#name, #value) { (Some(current), value) if current >= value => Some(current), (_, value) => Some(value) })),
+                                ast::Aggregate::Count => unreachable!("Count is matched above"),
+                            }
+                        }
+                        _ => unreachable!("All outputs are aggregates"),
+                    };
+                    quote!(#name: #data_type = #init -> #update)
+                }).collect::<Vec<_>>();
+                quote!(|> fold(#(#folds),*))
+            };
+            return Some(quote! {
+                #source #filter #tail ~> return;
+            });
+        }
+
+        let row = row_ident();
+        let map_fields = outputs
+            .iter()
+            .chain(hidden.iter())
+            .map(
+                |Output {
+                     name,
+                     data_type,
+                     value,
+                 }| {
+                    let value = match value {
+                        OutputValue::Rowid => quote!(#rowid),
+                        OutputValue::Column(column, column_type) => match access {
+                            ColumnAccess::Row => {
+                                clone_value(column_type, quote!(#row.#column), column.span())
+                            }
+                            _ => clone_value(column_type, quote!(#column), column.span()),
+                        },
+                        OutputValue::Aggregate(..) => unreachable!("No aggregates in a projection"),
+                    };
+                    quote!(#name: #data_type = #value)
+                },
+            )
+            .collect::<Vec<_>>();
+
+        let sort = if sort_keys.is_empty() {
+            quote!()
+        } else {
+            quote!(|> sort(#(#sort_keys),*))
+        };
+
+        let take = match limit {
+            Some(limit) => {
+                let limit = self.expr(limit, table, ColumnAccess::Disallowed);
+                quote!(|> take(#limit))
+            }
+            None => quote!(),
+        };
+
+        let remove_hidden = if hidden.is_empty() {
+            quote!()
+        } else {
+            let fields = outputs.iter().map(
+                |Output {
+                     name, data_type, ..
+                 }| quote!(#name: #data_type = #name),
+            );
+            quote!(|> map(#(#fields),*))
+        };
+
+        let end = if lookup.is_some() {
+            quote!(~> return;)
+        } else {
+            quote!(|> collect(rows) ~> return;)
+        };
+
+        Some(quote! {
+            #source #filter #conn map(#(#map_fields),*) #sort #take #remove_hidden #end
+        })
+    }
+
+    fn insert(&mut self, insert: &'a ast::Insert) -> Option<TokenStream> {
+        let ast::Insert {
+            call,
+            table,
+            columns,
+            values,
+            returning,
+        } = insert;
+        let table = self.table(table)?;
+        let table_name = table.name;
+
+        if columns.len() != values.len() {
+            self.errors.push_back(errors::insert_values_mismatch(
+                call,
+                columns.len(),
+                values.len(),
+            ));
+            return None;
+        }
+
+        let mut inserted: Vec<&Ident> = Vec::new();
+        let mut fields = Vec::new();
+        for (column, value) in columns.iter().zip(values) {
+            if let Some(original) = inserted.iter().find(|c| *c == &column) {
+                self.errors
+                    .push_back(errors::duplicate_column(column, original));
+            } else if let Some(data_type) = self.column(table, column) {
+                let value = self.expr(value, table, ColumnAccess::Disallowed);
+                fields.push(quote!(#column: #data_type = #value));
+            }
+            inserted.push(column);
+        }
+
+        let missing = table
+            .columns
+            .iter()
+            .filter(|(column, _)| !columns.contains(column))
+            .map(|(column, _)| *column)
+            .collect::<Vec<_>>();
+        if !missing.is_empty() {
+            self.errors.push_back(errors::insert_missing_columns(
+                table_name,
+                missing.into_iter(),
+            ));
+        }
+
+        let (out_ref, end) = match returning {
+            Some((rowid, alias)) => (
+                alias
+                    .clone()
+                    .unwrap_or_else(|| Ident::new("rowid", rowid.span())),
+                quote!(~> return;),
+            ),
+            None => (rowid_ident(), quote!(;)),
+        };
+
+        Some(quote! {
+            row(#(#fields),*) ~> insert(#table_name as ref #out_ref) #end
+        })
+    }
+
+    fn update(&mut self, update: &'a ast::Update) -> Option<TokenStream> {
+        let ast::Update {
+            call,
+            table,
+            assignments,
+            filter,
+        } = update;
+        let table = self.table(table)?;
+        let lookup = rowid_lookup(filter);
+
+        let mut needed = Vec::new();
+        let mut assigned: Vec<&Ident> = Vec::new();
+        for (column, value) in &assignments.fields {
+            if let Some(original) = assigned.iter().find(|c| *c == &column) {
+                self.errors
+                    .push_back(errors::duplicate_column(column, original));
+            }
+            self.column(table, column);
+            assigned.push(column);
+            used_columns(value, &mut needed);
+        }
+        if let (Some(filter), None) = (filter, lookup) {
+            used_columns(filter, &mut needed);
+        }
+        dedup(&mut needed);
+
+        let (source, conn) = self.source(table, lookup, &needed);
+        let filter = match (filter, lookup) {
+            (Some(filter), None) => {
+                let filter = self.expr(filter, table, ColumnAccess::Row);
+                quote!(#conn filter(#filter))
+            }
+            _ => quote!(),
+        };
+
+        let rowid = rowid_ident();
+        let fields = assignments
+            .fields
+            .iter()
+            .map(|(column, value)| {
+                let value = self.expr(value, table, ColumnAccess::Row);
+                quote!(#column = #value)
+            })
+            .collect::<Vec<_>>();
+        let update = quote_spanned!(call.span()=> update);
+
+        Some(quote! {
+            #source #filter #conn #update(#rowid use #(#fields),*);
+        })
+    }
+
+    fn delete(&mut self, delete: &'a ast::Delete) -> Option<TokenStream> {
+        let ast::Delete {
+            call,
+            table,
+            filter,
+        } = delete;
+        let table = self.table(table)?;
+        let lookup = rowid_lookup(filter);
+
+        let mut needed = Vec::new();
+        if let (Some(filter), None) = (filter, lookup) {
+            used_columns(filter, &mut needed);
+        }
+        dedup(&mut needed);
+
+        let (source, conn) = self.source(table, lookup, &needed);
+        let filter = match (filter, lookup) {
+            (Some(filter), None) => {
+                let filter = self.expr(filter, table, ColumnAccess::Row);
+                quote!(#conn filter(#filter))
+            }
+            _ => quote!(),
+        };
+
+        let rowid = rowid_ident();
+        let delete = quote_spanned!(call.span()=> delete);
+        Some(quote! {
+            #source #filter #conn #delete(#rowid);
+        })
+    }
+}
+
+/// Remove repeated columns, keeping the first use
+fn dedup(columns: &mut Vec<&Ident>) {
+    let mut seen = Vec::new();
+    columns.retain(|c| {
+        if seen.contains(c) {
+            false
+        } else {
+            seen.push(*c);
+            true
+        }
+    });
+}
@@ -1,3 +1,59 @@
-//! SQL Implementation as a frontend for emDB
+//! # The SQL frontend
+//! A subset of SQL that is translated to [`emQL`](crate::emql!), and hence
+//! produces the same [`plan::Plan`] for all backends.
 //!
-//! TODO: Have not yet decided if I want to explore this.
+//! ```text
+//! impl my_db as Serialized;
+//!
+//! CREATE TABLE users (
+//!     name TEXT UNIQUE,
+//!     credits INTEGER,
+//!     CONSTRAINT sensible_credits CHECK (credits >= 0)
+//! );
+//!
+//! PREPARE new_user(username TEXT) AS
+//!     INSERT INTO users (name, credits) VALUES (:username, 0) RETURNING ROWID AS user_id;
+//!
+//! PREPARE get_user(id REF users) AS
+//!     SELECT name, credits FROM users WHERE ROWID = :id;
+//!
+//! PREPARE top_users(top INTEGER) AS
+//!     SELECT name FROM users ORDER BY credits DESC LIMIT :top;
+//! ```
+//!
+//! ## Supported SQL
+//! - `CREATE TABLE` with typed columns, `UNIQUE` and `CHECK` constraints. There
+//!   are no primary keys, nor nullable columns; rows are identified by `ROWID`
+//!   (a row reference, typed as `REF <table>`).
+//! - Named, parameterised `SELECT`, `INSERT`, `UPDATE` and `DELETE` statements
+//!   (`PREPARE <name>(<params>) AS <statement>;`), parameters are used as `:name`.
+//! - `SELECT` with `WHERE`, `ORDER BY`, `LIMIT` and the `COUNT`, `SUM`, `MIN`
+//!   and `MAX` aggregates (without `GROUP BY`).
+//! - SQL types (e.g. `INTEGER`, `TEXT`) are mapped to rust types, any other rust
+//!   type can also be used.
+//!
+//! ## Translation to emQL
+//! See [`lower`] for how each statement is desugared. As the output is emQL, type
+//! errors and redefinitions are reported by emQL's semantic analysis.
+
+mod ast;
+mod errors;
+mod lower;
+mod parse;
+
+use std::collections::LinkedList;
+
+use super::{Emql, Frontend};
+use crate::{backend, plan};
+use proc_macro2::TokenStream;
+use proc_macro_error2::Diagnostic;
+
+pub struct Sql;
+
+impl Frontend for Sql {
+    fn from_tokens(
+        input: TokenStream,
+    ) -> Result<(plan::Plan, backend::Targets), LinkedList<Diagnostic>> {
+        Emql::from_tokens(lower::lower(parse::parse(input)?)?)
+    }
+}
@@ -0,0 +1,637 @@
+//! # Parsing
+//! Parses the supported SQL subset into an [`ast::Sql`].
+//! - Statements and their clauses are parsed with [combi], in the same way as
+//!   [emQL](crate::frontend::emql).
+//! - Expressions, types, column definitions and clause contents are parsed with
+//!   [`syn`] (collected until the next clause's keyword).
+//!
+//! ## Keywords
+//! SQL keywords must be uppercase (e.g. `SELECT`, not `select`), this avoids
+//! ambiguity with table & column names, which are rust identifiers.
+
+use super::ast;
+use combi::{
+    core::{choice, mapsuc, nothing, recover, seq, seqdiff, setrepr},
+    derived::many0,
+    logical::{not, or},
+    macros::{choices, seqs},
+    tokens::{
+        basic::{
+            collectuntil, getident, gettoken, isempty, matchident, matchpunct, peekident,
+            peekpunct, recovgroup, syn, terminal,
+        },
+        derived::{listseptrailing, syntopunct},
+        error::error,
+        matcher::matcher,
+        recovery::until,
+        TokenDiagnostic, TokenIter, TokenParser,
+    },
+    Combi,
+};
+use proc_macro2::{Delimiter, Ident, Span, TokenStream, TokenTree};
+use proc_macro_error2::{Diagnostic, Level};
+use quote::{quote, quote_spanned, ToTokens};
+use std::collections::LinkedList;
+use syn::{
+    parenthesized,
+    parse::{Parse, ParseStream},
+    punctuated::Punctuated,
+    Token, Type,
+};
+
+pub(super) fn parse(ts: TokenStream) -> Result<ast::Sql, LinkedList<Diagnostic>> {
+    let parser = sql_parser();
+    let (_, res) =
+        mapsuc(seqdiff(parser, terminal), |(o, ())| o).comp(TokenIter::from(ts, Span::call_site()));
+    res.to_result().map_err(TokenDiagnostic::into_list)
+}
+
+enum SqlItem {
+    Backend(TokenStream),
+    Table(ast::CreateTable),
+    Query(ast::Prepare),
+}
+
+fn sql_parser() -> impl TokenParser<ast::Sql> {
+    mapsuc(
+        many0(
+            not(isempty()),
+            recover(
+                choices!(
+                    peekident("impl") => mapsuc(backend_parser(), SqlItem::Backend),
+                    peekident("CREATE") => mapsuc(create_table_parser(), SqlItem::Table),
+                    peekident("PREPARE") => mapsuc(prepare_parser(), SqlItem::Query),
+                    otherwise => error(gettoken, |t| {
+                        Diagnostic::spanned(t.span(), Level::Error, String::from("expected impl, CREATE TABLE or PREPARE"))
+                    })
+                ),
+                until(or(
+                    peekident("CREATE"),
+                    or(peekident("PREPARE"), peekident("impl")),
+                )),
+            ),
+        ),
+        |sql_items| {
+            let mut backends = vec![];
+            let mut tables = vec![];
+            let mut queries = vec![];
+            for item in sql_items {
+                match item {
+                    SqlItem::Backend(b) => backends.push(b),
+                    SqlItem::Table(t) => tables.push(t),
+                    SqlItem::Query(q) => queries.push(q),
+                }
+            }
+            ast::Sql {
+                backends,
+                tables,
+                queries,
+            }
+        },
+    )
+}
+
+/// Backends are declared as in emQL, and passed through unchanged
+fn backend_parser() -> impl TokenParser<TokenStream> {
+    mapsuc(
+        seq(collectuntil(peekpunct(';')), matchpunct(';')),
+        |(backend, semi)| quote!(#backend #semi),
+    )
+}
+
+fn create_table_parser() -> impl TokenParser<ast::CreateTable> {
+    mapsuc(
+        seqs!(
+            matchident("CREATE"),
+            matchident("TABLE"),
+            setrepr(getident(), "<table name>"),
+            recovgroup(
+                Delimiter::Parenthesis,
+                listseptrailing(
+                    ',',
+                    setrepr(syntopunct(peekpunct(',')), "<column or constraint>")
+                )
+            ),
+            matchpunct(';')
+        ),
+        |(_, (_, (name, (elements, _))))| ast::CreateTable { name, elements },
+    )
+}
+
+fn prepare_parser() -> impl TokenParser<ast::Prepare> {
+    mapsuc(
+        seqs!(
+            matchident("PREPARE"),
+            setrepr(getident(), "<query name>"),
+            recovgroup(
+                Delimiter::Parenthesis,
+                setrepr(
+                    listseptrailing(',', seq(getident(), syntopunct(peekpunct(',')))),
+                    "<name> <type>, ..."
+                )
+            ),
+            matchident("AS"),
+            statement_parser(),
+            matchpunct(';')
+        ),
+        |(_, (name, (params, (_, (statement, _)))))| ast::Prepare {
+            name,
+            params,
+            statement,
+        },
+    )
+}
+
+fn statement_parser() -> impl TokenParser<ast::Statement> {
+    choices!(
+        peekident("SELECT") => mapsuc(select_parser(), ast::Statement::Select),
+        peekident("INSERT") => mapsuc(insert_parser(), ast::Statement::Insert),
+        peekident("UPDATE") => mapsuc(update_parser(), ast::Statement::Update),
+        peekident("DELETE") => mapsuc(delete_parser(), ast::Statement::Delete),
+        otherwise => error(gettoken, |t| {
+            Diagnostic::spanned(t.span(), Level::Error, format!("expected a statement but got {t}"))
+                .help(String::from("Available statements are SELECT, INSERT, UPDATE, DELETE"))
+        })
+    )
+}
+
+/// The end of a clause, at the statement's `;` or at one of the keywords of
+/// the following clauses.
+fn clause_end(keywords: &'static [&'static str]) -> impl TokenParser<bool> {
+    matcher::<true, _>(
+        |tt| match tt {
+            TokenTree::Ident(i) => keywords.iter().any(|k| i == k),
+            TokenTree::Punct(p) => p.as_char() == ';',
+            _ => false,
+        },
+        "<end of clause>",
+    )
+}
+
+fn optional_clause<T>(
+    keyword: &'static str,
+    clause: impl TokenParser<T>,
+) -> impl TokenParser<Option<T>> {
+    choice(
+        peekident(keyword),
+        mapsuc(seq(matchident(keyword), clause), |(_, t)| Some(t)),
+        mapsuc(nothing(), |()| None),
+    )
+}
+
+fn select_parser() -> impl TokenParser<ast::Select> {
+    mapsuc(
+        seqs!(
+            matchident("SELECT"),
+            setrepr(syn(collectuntil(peekident("FROM"))), "<columns>"),
+            matchident("FROM"),
+            setrepr(getident(), "<table>"),
+            optional_clause("WHERE", syn(collectuntil(clause_end(&["ORDER", "LIMIT"])))),
+            optional_clause(
+                "ORDER",
+                mapsuc(
+                    seq(matchident("BY"), syn(collectuntil(clause_end(&["LIMIT"])))),
+                    |(_, order)| order
+                )
+            ),
+            optional_clause("LIMIT", syn(collectuntil(clause_end(&[]))))
+        ),
+        |(call, (projection, (_, (table, (filter, (order, limit))))))| ast::Select {
+            call,
+            projection,
+            table,
+            filter,
+            order,
+            limit,
+        },
+    )
+}
+
+fn insert_parser() -> impl TokenParser<ast::Insert> {
+    mapsuc(
+        seqs!(
+            matchident("INSERT"),
+            matchident("INTO"),
+            setrepr(getident(), "<table>"),
+            recovgroup(Delimiter::Parenthesis, listseptrailing(',', getident())),
+            matchident("VALUES"),
+            recovgroup(
+                Delimiter::Parenthesis,
+                listseptrailing(',', setrepr(syntopunct(peekpunct(',')), "<value>"))
+            ),
+            optional_clause(
+                "RETURNING",
+                seq(matchident("ROWID"), optional_clause("AS", getident()))
+            )
+        ),
+        |(call, (_, (table, (columns, (_, (values, returning))))))| ast::Insert {
+            call,
+            table,
+            columns,
+            values,
+            returning,
+        },
+    )
+}
+
+fn update_parser() -> impl TokenParser<ast::Update> {
+    mapsuc(
+        seqs!(
+            matchident("UPDATE"),
+            setrepr(getident(), "<table>"),
+            matchident("SET"),
+            setrepr(
+                syn(collectuntil(clause_end(&["WHERE"]))),
+                "<column> = <value>, ..."
+            ),
+            optional_clause("WHERE", syn(collectuntil(clause_end(&[]))))
+        ),
+        |(call, (table, (_, (assignments, filter))))| ast::Update {
+            call,
+            table,
+            assignments,
+            filter,
+        },
+    )
+}
+
+fn delete_parser() -> impl TokenParser<ast::Delete> {
+    mapsuc(
+        seqs!(
+            matchident("DELETE"),
+            matchident("FROM"),
+            setrepr(getident(), "<table>"),
+            optional_clause("WHERE", syn(collectuntil(clause_end(&[]))))
+        ),
+        |(call, (_, (table, filter)))| ast::Delete {
+            call,
+            table,
+            filter,
+        },
+    )
+}
+
+fn peek_keyword(input: ParseStream, keyword: &str) -> bool {
+    input.cursor().ident().is_some_and(|(i, _)| i == keyword)
+}
+
+fn parse_keyword(input: ParseStream, keyword: &str) -> syn::Result<Ident> {
+    input.step(|cursor| match cursor.ident() {
+        Some((i, rest)) if i == keyword => Ok((i, rest)),
+        _ => Err(cursor.error(format!("expected `{keyword}`"))),
+    })
+}
+
+fn parse_alias(input: ParseStream) -> syn::Result<Option<Ident>> {
+    if peek_keyword(input, "AS") {
+        parse_keyword(input, "AS")?;
+        Ok(Some(input.parse()?))
+    } else {
+        Ok(None)
+    }
+}
+
+/// The SQL types supported, and the rust types they are mapped to
+const SQL_TYPES: &[(&str, &str)] = &[
+    ("INTEGER", "i64"),
+    ("INT", "i64"),
+    ("BIGINT", "i64"),
+    ("MEDIUMINT", "i32"),
+    ("SMALLINT", "i16"),
+    ("TINYINT", "i8"),
+    ("REAL", "f64"),
+    ("DOUBLE", "f64"),
+    ("FLOAT", "f64"),
+    ("TEXT", "String"),
+    ("VARCHAR", "String"),
+    ("BOOLEAN", "bool"),
+    ("BOOL", "bool"),
+];
+
+/// Parse a SQL type (e.g. `INTEGER` or `VARCHAR(255)`), or otherwise a rust type.
+fn parse_type(input: ParseStream) -> syn::Result<Type> {
+    if let Some((id, _)) = input.cursor().ident() {
+        if let Some((_, rust_type)) = SQL_TYPES.iter().find(|(sql_type, _)| id == sql_type) {
+            let id: Ident = input.parse()?;
+            if id == "VARCHAR" && input.peek(syn::token::Paren) {
+                // the maximum length is ignored
+                let _length;
+                parenthesized!(_length in input);
+            }
+            return Ok(Type::Path(syn::TypePath {
+                qself: None,
+                path: Ident::new(rust_type, id.span()).into(),
+            }));
+        }
+    }
+    input.parse()
+}
+
+impl Parse for ast::SqlType {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        if peek_keyword(input, "REF") {
+            parse_keyword(input, "REF")?;
+            Ok(ast::SqlType::Ref(input.parse()?))
+        } else {
+            Ok(ast::SqlType::Rust(parse_type(input)?))
+        }
+    }
+}
+
+impl Parse for ast::TableElement {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let alias = if peek_keyword(input, "CONSTRAINT") {
+            parse_keyword(input, "CONSTRAINT")?;
+            Some(input.parse()?)
+        } else {
+            None
+        };
+
+        if peek_keyword(input, "CHECK") {
+            let call = parse_keyword(input, "CHECK")?;
+            let content;
+            parenthesized!(content in input);
+            Ok(ast::TableElement::Constraint(ast::TableConstraint::Check {
+                alias,
+                call,
+                expr: content.parse()?,
+            }))
+        } else if peek_keyword(input, "UNIQUE") {
+            let call = parse_keyword(input, "UNIQUE")?;
+            let content;
+            parenthesized!(content in input);
+            Ok(ast::TableElement::Constraint(
+                ast::TableConstraint::Unique {
+                    alias,
+                    call,
+                    column: content.parse()?,
+                },
+            ))
+        } else if alias.is_some() {
+            Err(input.error("expected a `CHECK` or `UNIQUE` constraint"))
+        } else {
+            let name = input.parse()?;
+            let data_type = parse_type(input)?;
+            let mut unique = None;
+            while !input.is_empty() {
+                if peek_keyword(input, "UNIQUE") {
+                    unique = Some(parse_keyword(input, "UNIQUE")?);
+                } else if peek_keyword(input, "NOT") {
+                    // all columns are non-null
+                    parse_keyword(input, "NOT")?;
+                    parse_keyword(input, "NULL")?;
+                } else if peek_keyword(input, "PRIMARY") {
+                    return Err(input.error(
+                        "primary keys are not supported, every row has a `ROWID` to reference it by",
+                    ));
+                } else {
+                    return Err(input.error("expected `UNIQUE` or `NOT NULL`"));
+                }
+            }
+            Ok(ast::TableElement::Column(ast::ColumnDef {
+                name,
+                data_type,
+                unique,
+            }))
+        }
+    }
+}
+
+impl Parse for ast::Projection {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let items = Punctuated::<ast::SelectItem, Token![,]>::parse_terminated(input)?;
+        if items.is_empty() {
+            Err(input.error("expected columns to select"))
+        } else {
+            Ok(ast::Projection {
+                items: items.into_iter().collect(),
+            })
+        }
+    }
+}
+
+impl Parse for ast::SelectItem {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        if input.peek(Token![*]) {
+            let star: Token![*] = input.parse()?;
+            return Ok(ast::SelectItem::Star(star.span));
+        }
+        if peek_keyword(input, "ROWID") {
+            return Ok(ast::SelectItem::Rowid {
+                rowid: parse_keyword(input, "ROWID")?,
+                alias: parse_alias(input)?,
+            });
+        }
+
+        let column: Ident = input.parse()?;
+        if input.peek(syn::token::Paren) {
+            let func = match column.to_string().as_str() {
+                "COUNT" => ast::Aggregate::Count,
+                "SUM" => ast::Aggregate::Sum,
+                "MIN" => ast::Aggregate::Min,
+                "MAX" => ast::Aggregate::Max,
+                _ => {
+                    return Err(syn::Error::new(
+                        column.span(),
+                        format!("`{column}` is not a supported aggregate, expected one of COUNT, SUM, MIN, MAX"),
+                    ))
+                }
+            };
+            let content;
+            parenthesized!(content in input);
+            let arg = if matches!(func, ast::Aggregate::Count) && content.peek(Token![*]) {
+                content.parse::<Token![*]>()?;
+                None
+            } else {
+                Some(content.parse()?)
+            };
+            Ok(ast::SelectItem::Aggregate {
+                call: column,
+                func,
+                arg,
+                alias: parse_alias(input)?,
+            })
+        } else {
+            Ok(ast::SelectItem::Column {
+                column,
+                alias: parse_alias(input)?,
+            })
+        }
+    }
+}
+
+impl Parse for ast::Ordering {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let fields =
+            Punctuated::<(Ident, bool), Token![,]>::parse_terminated_with(input, |input| {
+                let field = input.parse()?;
+                let desc = if peek_keyword(input, "DESC") {
+                    parse_keyword(input, "DESC")?;
+                    true
+                } else {
+                    if peek_keyword(input, "ASC") {
+                        parse_keyword(input, "ASC")?;
+                    }
+                    false
+                };
+                Ok((field, desc))
+            })?;
+        Ok(ast::Ordering {
+            fields: fields.into_iter().collect(),
+        })
+    }
+}
+
+impl Parse for ast::Assignments {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let fields =
+            Punctuated::<(Ident, ast::Expr), Token![,]>::parse_terminated_with(input, |input| {
+                let field = input.parse()?;
+                input.parse::<Token![=]>()?;
+                Ok((field, input.parse()?))
+            })?;
+        Ok(ast::Assignments {
+            fields: fields.into_iter().collect(),
+        })
+    }
+}
+
+impl ast::BinOp {
+    /// The binding power of the operator, with `NOT` between `AND` and the
+    /// comparisons (as in SQL)
+    fn precedence(self) -> usize {
+        match self {
+            ast::BinOp::Or => 1,
+            ast::BinOp::And => 2,
+            ast::BinOp::Eq
+            | ast::BinOp::Neq
+            | ast::BinOp::Lt
+            | ast::BinOp::Le
+            | ast::BinOp::Gt
+            | ast::BinOp::Ge => 4,
+            ast::BinOp::Add | ast::BinOp::Sub => 5,
+            ast::BinOp::Mul | ast::BinOp::Div | ast::BinOp::Rem => 6,
+        }
+    }
+}
+
+const NOT_PRECEDENCE: usize = 3;
+
+/// Parse the next binary operator, if there is one
+fn parse_binop(input: ParseStream) -> syn::Result<Option<(ast::BinOp, Span)>> {
+    let span = input.span();
+    macro_rules! ops {
+        ($($tk:tt => $op:ident),*) => {
+            $(
+                if input.peek(Token![$tk]) {
+                    input.parse::<Token![$tk]>()?;
+                    return Ok(Some((ast::BinOp::$op, span)));
+                }
+            )*
+        };
+    }
+
+    if peek_keyword(input, "OR") {
+        parse_keyword(input, "OR")?;
+        return Ok(Some((ast::BinOp::Or, span)));
+    }
+    if peek_keyword(input, "AND") {
+        parse_keyword(input, "AND")?;
+        return Ok(Some((ast::BinOp::And, span)));
+    }
+    if input.peek(Token![<]) && input.peek2(Token![>]) {
+        input.parse::<Token![<]>()?;
+        input.parse::<Token![>]>()?;
+        return Ok(Some((ast::BinOp::Neq, span)));
+    }
+    ops!(== => Eq, != => Neq, <= => Le, >= => Ge, = => Eq, < => Lt, > => Gt, + => Add, - => Sub, * => Mul, / => Div, % => Rem);
+    Ok(None)
+}
+
+/// Precedence climbing, parsing all operators binding at least as tightly as
+/// `min_precedence`.
+fn parse_binary(input: ParseStream, min_precedence: usize) -> syn::Result<ast::Expr> {
+    let mut left = parse_unary(input)?;
+    loop {
+        let ahead = input.fork();
+        match parse_binop(&ahead)? {
+            Some((op, span)) if op.precedence() >= min_precedence => {
+                parse_binop(input)?;
+                let right = parse_binary(input, op.precedence() + 1)?;
+                left = ast::Expr::Binary {
+                    op,
+                    span,
+                    left: Box::new(left),
+                    right: Box::new(right),
+                };
+            }
+            _ => break Ok(left),
+        }
+    }
+}
+
+fn parse_unary(input: ParseStream) -> syn::Result<ast::Expr> {
+    if peek_keyword(input, "NOT") {
+        let keyword = parse_keyword(input, "NOT")?;
+        Ok(ast::Expr::Unary {
+            op: ast::UnOp::Not,
+            span: keyword.span(),
+            expr: Box::new(parse_binary(input, NOT_PRECEDENCE)?),
+        })
+    } else if input.peek(Token![-]) {
+        let neg: Token![-] = input.parse()?;
+        Ok(ast::Expr::Unary {
+            op: ast::UnOp::Neg,
+            span: neg.span,
+            expr: Box::new(parse_unary(input)?),
+        })
+    } else {
+        parse_primary(input)
+    }
+}
+
+fn parse_primary(input: ParseStream) -> syn::Result<ast::Expr> {
+    if input.peek(syn::token::Paren) {
+        let content;
+        parenthesized!(content in input);
+        let expr = content.parse()?;
+        if content.is_empty() {
+            Ok(expr)
+        } else {
+            Err(content.error("expected `)`"))
+        }
+    } else if input.peek(Token![:]) {
+        input.parse::<Token![:]>()?;
+        Ok(ast::Expr::Param(input.parse()?))
+    } else if input.peek(syn::Lit) {
+        Ok(ast::Expr::Lit(
+            input.parse::<syn::Lit>()?.into_token_stream(),
+        ))
+    } else if let Some((id, _)) = input.cursor().ident() {
+        if id == "TRUE" || id == "FALSE" {
+            let id: Ident = input.parse()?;
+            Ok(ast::Expr::Lit(if id == "TRUE" {
+                quote_spanned!(id.span() => true)
+            } else {
+                quote_spanned!(id.span() => false)
+            }))
+        } else if id == "ROWID" {
+            Ok(ast::Expr::Rowid(input.parse()?))
+        } else if id == "NULL" {
+            Err(syn::Error::new(
+                id.span(),
+                "`NULL` is not supported, all columns are non-null",
+            ))
+        } else {
+            Ok(ast::Expr::Column(input.parse()?))
+        }
+    } else {
+        Err(input.error("expected a column, `:parameter` or literal"))
+    }
+}
+
+impl Parse for ast::Expr {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        parse_binary(input, 0)
+    }
+}
@@ -23,7 +23,19 @@ mod macros {
                 }
                 TokenStream::new()
             }
-            Ok((lp, bks)) => {
+            Ok((mut lp, bks)) => {
+                let checked = if cfg!(debug_assertions) {
+                    crate::analysis::validity::check_valid(&lp)
+                } else {
+                    Ok(())
+                };
+                if let Err(errors) = checked.and_then(|()| crate::optimise::optimise(&mut lp)) {
+                    for e in errors {
+                        e.emit();
+                    }
+                    return TokenStream::new();
+                }
+
                 let mut errors = LinkedList::new();
                 let impls = bks
                     .impls
@@ -56,3 +68,9 @@ mod macros {
 pub fn emql(tk: proc_macro::TokenStream) -> proc_macro::TokenStream {
     crate::macros::make_impl::<crate::frontend::Emql>(tk.into()).into()
 }
+
+#[proc_macro_error2::proc_macro_error]
+#[proc_macro]
+pub fn sql(tk: proc_macro::TokenStream) -> proc_macro::TokenStream {
+    crate::macros::make_impl::<crate::frontend::Sql>(tk.into()).into()
+}
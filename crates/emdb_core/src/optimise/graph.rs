@@ -0,0 +1,110 @@
+//! Helpers for rewriting the operator graph in place.
+//! - Operators are only ever removed from their context, keys for remaining
+//!   operators and dataflows are unchanged.
+
+use std::collections::HashSet;
+
+use proc_macro2::{TokenStream, TokenTree};
+use quote::ToTokens;
+use syn::{Expr, Ident};
+
+use crate::plan;
+
+/// The operator consuming the output of a dataflow
+pub fn next(lp: &plan::Plan, df: plan::Key<plan::DataFlow>) -> plan::Key<plan::Operator> {
+    lp.get_dataflow(df).get_conn().to
+}
+
+pub fn next_operator(lp: &plan::Plan, df: plan::Key<plan::DataFlow>) -> &plan::Operator {
+    lp.get_operator(next(lp, df))
+}
+
+pub fn is_stream(lp: &plan::Plan, df: plan::Key<plan::DataFlow>) -> bool {
+    lp.get_dataflow(df).get_conn().with.stream
+}
+
+pub fn set_from(
+    lp: &mut plan::Plan,
+    df: plan::Key<plan::DataFlow>,
+    from: plan::Key<plan::Operator>,
+) {
+    if let plan::DataFlow::Conn(conn) = lp.get_mut_dataflow(df) {
+        conn.from = from;
+    }
+}
+
+pub fn set_to(lp: &mut plan::Plan, df: plan::Key<plan::DataFlow>, to: plan::Key<plan::Operator>) {
+    if let plan::DataFlow::Conn(conn) = lp.get_mut_dataflow(df) {
+        conn.to = to;
+    }
+}
+
+fn context_of(lp: &plan::Plan, op: plan::Key<plan::Operator>) -> plan::Key<plan::Context> {
+    lp.contexts
+        .iter()
+        .find_map(|(key, ctx)| ctx.ordering.contains(&op).then_some(key))
+        .expect("All operators are in a context")
+}
+
+/// Remove an operator from the plan, and from the ordering of its context
+pub fn remove_operator(lp: &mut plan::Plan, op: plan::Key<plan::Operator>) -> plan::Operator {
+    let ctx = context_of(lp, op);
+    lp.get_mut_context(ctx).ordering.retain(|k| *k != op);
+    lp.operators.remove(op).unwrap()
+}
+
+/// Swap the positions of two operators in the ordering of their context
+pub fn swap_ordering(
+    lp: &mut plan::Plan,
+    a: plan::Key<plan::Operator>,
+    b: plan::Key<plan::Operator>,
+) {
+    let ctx = context_of(lp, a);
+    let ordering = &mut lp.get_mut_context(ctx).ordering;
+    let pos_a = ordering.iter().position(|k| *k == a).unwrap();
+    let pos_b = ordering.iter().position(|k| *k == b).unwrap();
+    ordering.swap(pos_a, pos_b);
+}
+
+/// The user named fields of a record
+pub fn user_fields(lp: &plan::Plan, record: plan::Key<plan::RecordType>) -> HashSet<Ident> {
+    lp.get_record_type_conc(record)
+        .fields
+        .keys()
+        .filter_map(|field| match field {
+            plan::RecordField::User(id) => Some(id.clone()),
+            plan::RecordField::Internal(_) => None,
+        })
+        .collect()
+}
+
+/// The fields of the record carried by a dataflow
+pub fn dataflow_fields(lp: &plan::Plan, df: plan::Key<plan::DataFlow>) -> HashSet<Ident> {
+    user_fields(lp, lp.get_dataflow(df).get_conn().with.fields)
+}
+
+/// Conservatively get all identifiers that could be used to refer to fields
+/// in an expression (any identifier, including paths & method names).
+pub fn idents(expr: &Expr) -> HashSet<Ident> {
+    fn collect(tks: TokenStream, ids: &mut HashSet<Ident>) {
+        for tt in tks {
+            match tt {
+                TokenTree::Group(g) => collect(g.stream(), ids),
+                TokenTree::Ident(id) => {
+                    ids.insert(id);
+                }
+                _ => (),
+            }
+        }
+    }
+    let mut ids = HashSet::new();
+    collect(expr.to_token_stream(), &mut ids);
+    ids
+}
+
+pub fn mentions(expr: &Expr, field: &plan::RecordField) -> bool {
+    match field {
+        plan::RecordField::User(id) => idents(expr).contains(id),
+        plan::RecordField::Internal(_) => false,
+    }
+}
@@ -0,0 +1,283 @@
+//! ## Ordered Index Range Scans
+//! Converts scans of a table that are immediately filtered on bounds of a
+//! column with an ordered index into a range scan of that index.
+//! ```text
+//! scan_refs(TABLE) -> deref -> expand -> filter(COLUMN >= a && COLUMN < b && ..)
+//! range_refs(TABLE at COLUMN in a..b) -> deref -> expand -> filter(COLUMN >= a && COLUMN < b && ..)
+//! ```
+//! The filter is left in place, so any conditions not used for the range are
+//! still applied (and the range conditions are checked redundantly).
+//!
+//! Bounds are only taken from comparisons where the other side does not
+//! mention any of the fields in the filtered record (so it can be evaluated
+//! before the scan).
+
+use std::{collections::HashSet, ops::Bound};
+
+use syn::{BinOp, Expr, Ident};
+
+use super::graph::{idents, next_operator, user_fields};
+use crate::plan;
+
+struct RangeScan {
+    scan: plan::Key<plan::Operator>,
+    field: plan::RecordField,
+    lower: Bound<Expr>,
+    upper: Bound<Expr>,
+}
+
+pub fn apply(lp: &mut plan::Plan) -> bool {
+    let range_scans = lp
+        .operators
+        .iter()
+        .filter_map(|(key, op)| match op {
+            plan::Operator::ScanRefs(scan) => find_range(lp, key, scan),
+            _ => None,
+        })
+        .collect::<Vec<_>>();
+
+    let changed = !range_scans.is_empty();
+    for RangeScan {
+        scan,
+        field,
+        lower,
+        upper,
+    } in range_scans
+    {
+        let op = lp.operators.get_mut(scan).unwrap();
+        if let plan::Operator::ScanRefs(plan::ScanRefs {
+            table,
+            out_ref,
+            output,
+        }) = op
+        {
+            *op = plan::RangeRefs {
+                table: *table,
+                field,
+                lower,
+                upper,
+                out_ref: out_ref.clone(),
+                output: *output,
+            }
+            .into();
+        }
+    }
+    changed
+}
+
+/// Match `scan_refs -> deref -> expand -> filter` and get the bounds from the filter
+fn find_range(
+    lp: &plan::Plan,
+    key: plan::Key<plan::Operator>,
+    scan: &plan::ScanRefs,
+) -> Option<RangeScan> {
+    let plan::Operator::DeRef(deref) = next_operator(lp, scan.output) else {
+        return None;
+    };
+    if deref.reference != scan.out_ref || deref.table != scan.table {
+        return None;
+    }
+    let plan::Operator::Expand(expand) = next_operator(lp, deref.output) else {
+        return None;
+    };
+    if expand.field != deref.named {
+        return None;
+    }
+    let plan::Operator::Filter(filter) = next_operator(lp, expand.output) else {
+        return None;
+    };
+
+    let record_fields = user_fields(lp, deref.named_type);
+
+    let table = lp.get_table(scan.table);
+    let mut comparisons = Vec::new();
+    conjuncts(&filter.predicate, &mut comparisons);
+
+    // use the first ordered column with any bounds
+    table
+        .columns
+        .iter()
+        .filter_map(|(field, col)| match field {
+            plan::RecordField::User(column) if col.cons.ordered.is_some() => Some((field, column)),
+            _ => None,
+        })
+        .filter(|(_, column)| record_fields.contains(*column))
+        .find_map(|(field, column)| {
+            let mut lower = Bound::Unbounded;
+            let mut upper = Bound::Unbounded;
+            for (left, op, right) in &comparisons {
+                if let Some((is_lower, bound)) =
+                    column_bound(column, &record_fields, left, op, right)
+                {
+                    let target = if is_lower { &mut lower } else { &mut upper };
+                    if matches!(target, Bound::Unbounded) {
+                        *target = bound;
+                    }
+                }
+                if let (BinOp::Eq(_), Some(value)) =
+                    (op, equals_value(column, &record_fields, left, right))
+                {
+                    if matches!(lower, Bound::Unbounded) {
+                        lower = Bound::Included(value.clone());
+                    }
+                    if matches!(upper, Bound::Unbounded) {
+                        upper = Bound::Included(value.clone());
+                    }
+                }
+            }
+            if matches!((&lower, &upper), (Bound::Unbounded, Bound::Unbounded)) {
+                None
+            } else {
+                Some(RangeScan {
+                    scan: key,
+                    field: field.clone(),
+                    lower,
+                    upper,
+                })
+            }
+        })
+}
+
+/// Split a predicate on `&&` into binary comparisons
+fn conjuncts<'a>(expr: &'a Expr, comparisons: &mut Vec<(&'a Expr, &'a BinOp, &'a Expr)>) {
+    match expr {
+        Expr::Paren(p) => conjuncts(&p.expr, comparisons),
+        Expr::Binary(b) => match b.op {
+            BinOp::And(_) => {
+                conjuncts(&b.left, comparisons);
+                conjuncts(&b.right, comparisons);
+            }
+            BinOp::Lt(_) | BinOp::Le(_) | BinOp::Gt(_) | BinOp::Ge(_) | BinOp::Eq(_) => {
+                comparisons.push((&b.left, &b.op, &b.right))
+            }
+            _ => (),
+        },
+        _ => (),
+    }
+}
+
+/// Is the expression the column, with any number of dereferences
+fn is_column(column: &Ident, expr: &Expr) -> bool {
+    match expr {
+        Expr::Paren(p) => is_column(column, &p.expr),
+        Expr::Unary(u) if matches!(u.op, syn::UnOp::Deref(_)) => is_column(column, &u.expr),
+        Expr::Path(p) => p.qself.is_none() && p.path.is_ident(column),
+        _ => false,
+    }
+}
+
+/// Conservatively check the expression does not use any fields of the record
+fn independent(fields: &HashSet<Ident>, expr: &Expr) -> bool {
+    idents(expr).is_disjoint(fields)
+}
+
+/// Get the bound (and if it is a lower bound) for a comparison of the column
+fn column_bound(
+    column: &Ident,
+    fields: &HashSet<Ident>,
+    left: &Expr,
+    op: &BinOp,
+    right: &Expr,
+) -> Option<(bool, Bound<Expr>)> {
+    let (value, column_left) = if is_column(column, left) && independent(fields, right) {
+        (right.clone(), true)
+    } else if is_column(column, right) && independent(fields, left) {
+        (left.clone(), false)
+    } else {
+        return None;
+    };
+
+    // `column > value` is a lower bound, as is `value < column`
+    let (is_lower, bound) = match op {
+        BinOp::Gt(_) => (true, Bound::Excluded(value)),
+        BinOp::Ge(_) => (true, Bound::Included(value)),
+        BinOp::Lt(_) => (false, Bound::Excluded(value)),
+        BinOp::Le(_) => (false, Bound::Included(value)),
+        _ => return None,
+    };
+    Some((is_lower == column_left, bound))
+}
+
+fn equals_value<'a>(
+    column: &Ident,
+    fields: &HashSet<Ident>,
+    left: &'a Expr,
+    right: &'a Expr,
+) -> Option<&'a Expr> {
+    if is_column(column, left) && independent(fields, right) {
+        Some(right)
+    } else if is_column(column, right) && independent(fields, left) {
+        Some(left)
+    } else {
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::frontend::{Emql, Frontend};
+    use proc_macro2::TokenStream;
+    use quote::{quote, ToTokens};
+
+    /// Apply the rule to a schema with a single query, getting the bounds of
+    /// the range scan (if any) as strings
+    fn range(tks: TokenStream) -> Option<(Bound<String>, Bound<String>)> {
+        let Ok((mut lp, _)) = Emql::from_tokens(tks) else {
+            panic!("Invalid emql in test")
+        };
+        if !apply(&mut lp) {
+            return None;
+        }
+        let to_string =
+            |bound: &Bound<Expr>| bound.as_ref().map(|e| e.to_token_stream().to_string());
+        let bounds = lp.operators.iter().find_map(|(_, op)| match op {
+            plan::Operator::RangeRefs(range) => {
+                Some((to_string(&range.lower), to_string(&range.upper)))
+            }
+            _ => None,
+        });
+        bounds
+    }
+
+    fn readings(filter: TokenStream) -> TokenStream {
+        quote! {
+            table readings { time: u64, other: u64 } @ [ordered(time) as by_time]
+            query q(start: u64, end: u64) {
+                use readings |> filter(#filter) |> count(num) ~> return;
+            }
+        }
+    }
+
+    #[test]
+    fn bounds() {
+        assert_eq!(
+            range(readings(quote!(**time >= start && **time < end))),
+            Some((
+                Bound::Included(String::from("start")),
+                Bound::Excluded(String::from("end"))
+            ))
+        );
+        assert_eq!(
+            range(readings(quote!(start < **time))),
+            Some((Bound::Excluded(String::from("start")), Bound::Unbounded))
+        );
+    }
+
+    #[test]
+    fn equality() {
+        assert_eq!(
+            range(readings(quote!(**time == start && **other > 3))),
+            Some((
+                Bound::Included(String::from("start")),
+                Bound::Included(String::from("start"))
+            ))
+        );
+    }
+
+    #[test]
+    fn field_bounds_not_used() {
+        assert_eq!(range(readings(quote!(**time >= **other))), None);
+        assert_eq!(range(readings(quote!(**other >= start))), None);
+    }
+}
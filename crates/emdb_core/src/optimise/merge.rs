@@ -0,0 +1,149 @@
+//! ## Merge Adjacent Operators
+//! Combines adjacent filters into a single filter, and adjacent maps into a
+//! single map.
+//! ```text
+//! filter(p1) -> filter(p2)      => filter((p1) && (p2))
+//! map(a = e1, b = e2) -> map(c = f(a)) => map(c = { let (a,) = (e1,); f(a) })
+//! ```
+//! Maps are only merged when each field of the first map is used by at most one
+//! expression of the second (so no expression is duplicated), and the second
+//! map does not refer to any of the first map's input fields (which would be
+//! captured once merged). Fields of the first map that are not used are not
+//! computed.
+
+use std::collections::HashSet;
+
+use quote::quote;
+use syn::{parse_quote, Expr, Ident};
+
+use super::graph::{dataflow_fields, idents, next, remove_operator, set_from};
+use crate::plan;
+
+pub fn filters(lp: &mut plan::Plan) -> bool {
+    let Some((first, second)) = lp.operators.iter().find_map(|(key, op)| match op {
+        plan::Operator::Filter(filter) => {
+            let next_key = next(lp, filter.output);
+            matches!(lp.get_operator(next_key), plan::Operator::Filter(_))
+                .then_some((key, next_key))
+        }
+        _ => None,
+    }) else {
+        return false;
+    };
+
+    let plan::Operator::Filter(plan::Filter {
+        predicate: second_predicate,
+        output,
+        ..
+    }) = remove_operator(lp, second)
+    else {
+        unreachable!("Matched as a filter")
+    };
+
+    let plan::Operator::Filter(filter) = lp.operators.get_mut(first).unwrap() else {
+        unreachable!("Matched as a filter")
+    };
+    let first_predicate = &filter.predicate;
+    filter.predicate = parse_quote!((#first_predicate) && (#second_predicate));
+    let between = std::mem::replace(&mut filter.output, output);
+
+    lp.dataflow.remove(between);
+    set_from(lp, output, first);
+    true
+}
+
+pub fn maps(lp: &mut plan::Plan) -> bool {
+    let Some((first, second, mapping)) = lp.operators.iter().find_map(|(key, op)| match op {
+        plan::Operator::Map(first) => {
+            let next_key = next(lp, first.output);
+            match lp.get_operator(next_key) {
+                plan::Operator::Map(second) => {
+                    merged_mapping(lp, first, second).map(|mapping| (key, next_key, mapping))
+                }
+                _ => None,
+            }
+        }
+        _ => None,
+    }) else {
+        return false;
+    };
+
+    let plan::Operator::Map(plan::Map { output, .. }) = remove_operator(lp, second) else {
+        unreachable!("Matched as a map")
+    };
+
+    let plan::Operator::Map(map) = lp.operators.get_mut(first).unwrap() else {
+        unreachable!("Matched as a map")
+    };
+    map.mapping = mapping;
+    let between = std::mem::replace(&mut map.output, output);
+
+    lp.dataflow.remove(between);
+    set_from(lp, output, first);
+    true
+}
+
+/// Substitute the first map's expressions into the second's, binding them
+/// with the types of the intermediate record (so literals & conversions are
+/// inferred as before).
+fn merged_mapping(
+    lp: &plan::Plan,
+    first: &plan::Map,
+    second: &plan::Map,
+) -> Option<Vec<(plan::RecordField, Expr)>> {
+    let input_fields = dataflow_fields(lp, first.input);
+    let between = lp.get_record_type_conc(lp.get_dataflow(first.output).get_conn().with.fields);
+
+    let mut intermediate = Vec::new();
+    for (field, expr) in &first.mapping {
+        let plan::RecordField::User(id) = field else {
+            return None;
+        };
+        let ty = match lp.get_scalar_type_conc(*between.fields.get(field)?) {
+            plan::ScalarTypeConc::Rust { ty, .. } => quote!(#ty),
+            _ => quote!(_),
+        };
+        intermediate.push((id, expr, ty));
+    }
+    let intermediate_fields = intermediate
+        .iter()
+        .map(|(id, _, _)| *id)
+        .collect::<HashSet<_>>();
+
+    let mut used_fields: HashSet<Ident> = HashSet::new();
+    second
+        .mapping
+        .iter()
+        .map(|(field, expr)| {
+            let used = idents(expr);
+            if used
+                .iter()
+                .any(|id| input_fields.contains(id) && !intermediate_fields.contains(id))
+            {
+                return None;
+            }
+
+            let mut ids = Vec::new();
+            let mut exprs = Vec::new();
+            let mut tys = Vec::new();
+            for (id, value, ty) in intermediate.iter().filter(|(id, _, _)| used.contains(*id)) {
+                if !used_fields.insert((*id).clone()) {
+                    return None;
+                }
+                ids.push(id);
+                exprs.push(value);
+                tys.push(ty);
+            }
+
+            let merged = if ids.is_empty() {
+                expr.clone()
+            } else {
+                parse_quote!({
+                    let (#(#ids,)*): (#(#tys,)*) = (#(#exprs,)*);
+                    #expr
+                })
+            };
+            Some((field.clone(), merged))
+        })
+        .collect()
+}
@@ -1 +1,210 @@
 //! Optimisations to mutate and improve the plan.
+//!
+//! Each rule rewrites the plan in place, returning if it changed anything. The
+//! rules are applied repeatedly until none apply. In debug builds the plan is
+//! checked for validity after every change.
+//!
+//! Rules inspect expressions syntactically and conservatively, any identifier
+//! in an expression with the same name as a field is considered a use of that
+//! field. Expressions in maps and filters are assumed to be pure, so they can be
+//! reordered, merged or removed if their results are unused.
+
+pub mod graph;
+mod index_range;
+mod merge;
+mod pushdown;
+mod top_k;
+mod unused;
+
+use std::collections::LinkedList;
+
+use proc_macro_error2::Diagnostic;
+
+use crate::{analysis::validity::check_valid, plan};
+
+type Rule = fn(&mut plan::Plan) -> bool;
+
+const RULES: &[(&str, Rule)] = &[
+    ("filter pushdown", pushdown::apply),
+    ("merge filters", merge::filters),
+    ("merge maps", merge::maps),
+    ("remove unused derefs", unused::derefs),
+    ("remove unused maps", unused::maps),
+    ("remove unused fields", unused::fields),
+    ("top-k", top_k::apply),
+    ("index range scans", index_range::apply),
+];
+
+pub fn optimise(lp: &mut plan::Plan) -> Result<(), LinkedList<Diagnostic>> {
+    'rewrite: loop {
+        for (name, rule) in RULES {
+            if rule(lp) {
+                if cfg!(debug_assertions) {
+                    check_valid(lp).map_err(|errors| {
+                        errors
+                            .into_iter()
+                            .map(|e| e.note(format!("After the `{name}` optimisation")))
+                            .collect::<LinkedList<_>>()
+                    })?;
+                }
+                continue 'rewrite;
+            }
+        }
+        return Ok(());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::frontend::{Emql, Frontend};
+    use proc_macro2::TokenStream;
+    use quote::quote;
+
+    /// Optimise a schema with a single query, and get the kinds of operators
+    /// in the query's context (in order)
+    fn optimised(tks: TokenStream) -> (plan::Plan, Vec<&'static str>) {
+        let Ok((mut lp, _)) = Emql::from_tokens(tks) else {
+            panic!("Invalid emql in test")
+        };
+        optimise(&mut lp).unwrap_or_else(|_| panic!("Optimised plan is invalid"));
+        let (_, query) = lp.queries.iter().next().unwrap();
+        let kinds = lp
+            .get_context(query.ctx)
+            .ordering
+            .iter()
+            .map(|op| match lp.get_operator(*op) {
+                plan::Operator::ScanRefs(_) => "scan_refs",
+                plan::Operator::RangeRefs(_) => "range_refs",
+                plan::Operator::DeRef(_) => "deref",
+                plan::Operator::Expand(_) => "expand",
+                plan::Operator::Map(_) => "map",
+                plan::Operator::Filter(_) => "filter",
+                plan::Operator::Sort(_) => "sort",
+                plan::Operator::Take(_) => "take",
+                plan::Operator::TopK(_) => "top_k",
+                plan::Operator::Count(_) => "count",
+                plan::Operator::Collect(_) => "collect",
+                plan::Operator::Return(_) => "return",
+                _ => "other",
+            })
+            .collect();
+        (lp, kinds)
+    }
+
+    #[test]
+    fn filter_pushed_before_map() {
+        let (_, kinds) = optimised(quote! {
+            table values { value: i32 }
+            query q(min: i32) {
+                use values
+                    |> map(value: i32 = *value)
+                    |> map(value: i32 = value, doubled: i32 = value * 2)
+                    |> filter(*value > min)
+                    |> filter(*value < 100)
+                    |> collect(it)
+                    ~> return;
+            }
+        });
+        assert_eq!(
+            kinds,
+            [
+                "scan_refs",
+                "deref",
+                "expand",
+                "map",
+                "filter",
+                "map",
+                "collect",
+                "return"
+            ]
+        );
+    }
+
+    #[test]
+    fn filter_kept_after_checked_deref() {
+        let (_, kinds) = optimised(quote! {
+            table values { value: i32 }
+            query q(enabled: bool) {
+                ref values as value_ref
+                    |> deref(value_ref as row)
+                    |> filter(enabled)
+                    |> collect(it)
+                    ~> return;
+            }
+        });
+        assert_eq!(kinds, ["scan_refs", "deref", "filter", "collect", "return"]);
+    }
+
+    #[test]
+    fn maps_merged() {
+        let (lp, kinds) = optimised(quote! {
+            table values { value: i32 }
+            query q() {
+                use values
+                    |> map(base: i64 = 1000, value: i32 = *value)
+                    |> map(offset: i64 = base + value as i64)
+                    |> collect(it)
+                    ~> return;
+            }
+        });
+        assert_eq!(
+            kinds,
+            ["scan_refs", "deref", "expand", "map", "collect", "return"]
+        );
+        let merged = lp
+            .operators
+            .iter()
+            .find_map(|(_, op)| match op {
+                plan::Operator::Map(map) => Some(map),
+                _ => None,
+            })
+            .unwrap();
+        assert_eq!(merged.mapping.len(), 1);
+        assert_eq!(merged.mapping[0].0.to_string(), "offset");
+    }
+
+    #[test]
+    fn sort_and_take_to_top_k() {
+        let (_, kinds) = optimised(quote! {
+            table values { value: i32 }
+            query q(n: usize) {
+                use values
+                    |> map(value: i32 = *value)
+                    |> sort(value desc)
+                    |> take(n)
+                    |> collect(it)
+                    ~> return;
+            }
+        });
+        assert_eq!(
+            kinds,
+            [
+                "scan_refs",
+                "deref",
+                "expand",
+                "map",
+                "top_k",
+                "collect",
+                "return"
+            ]
+        );
+    }
+
+    #[test]
+    fn range_scan_of_ordered_index() {
+        let (_, kinds) = optimised(quote! {
+            table readings { time: u64, value: i32 } @ [ordered(time) as by_time]
+            query q(start: u64, end: u64) {
+                use readings
+                    |> filter(**time >= start && **time < end)
+                    |> count(num)
+                    ~> return;
+            }
+        });
+        assert_eq!(
+            kinds,
+            ["range_refs", "deref", "expand", "filter", "count", "return"]
+        );
+    }
+}
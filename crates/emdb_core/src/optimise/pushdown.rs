@@ -0,0 +1,116 @@
+//! ## Filter Pushdown
+//! Moves filters before maps and derefs, so rows are discarded before the work
+//! of mapping or dereferencing them is done.
+//! ```text
+//! map(a = a, b = f(a)) -> filter(p(a))  => filter(p(a)) -> map(a = a, b = f(a))
+//! deref(r as row) -> filter(p(x))       => filter(p(x)) -> deref(r as row)
+//! ```
+//! - A filter is pushed before a map when the only fields of the map it uses
+//!   are copied unchanged from the map's input.
+//! - A filter is pushed before a deref when it does not use the dereferenced
+//!   row, and the deref is unchecked (a checked deref returns an error for an
+//!   invalid reference, even in rows the filter would remove).
+
+use std::collections::HashSet;
+
+use super::graph::{
+    dataflow_fields, idents, is_stream, mentions, next, set_from, set_to, swap_ordering,
+};
+use crate::plan;
+
+pub fn apply(lp: &mut plan::Plan) -> bool {
+    let Some((upper, filter)) = lp.operators.iter().find_map(|(key, op)| {
+        let output = match op {
+            plan::Operator::Map(map) if identity_only(lp, map) => map.output,
+            plan::Operator::DeRef(deref) if deref_unused(lp, deref) => deref.output,
+            _ => return None,
+        };
+        let next_key = next(lp, output);
+        (is_stream(lp, output) && matches!(lp.get_operator(next_key), plan::Operator::Filter(_)))
+            .then_some((key, next_key))
+    }) else {
+        return false;
+    };
+
+    let (upper_input, upper_output) = match lp.operators.get_mut(upper).unwrap() {
+        plan::Operator::Map(plan::Map { input, output, .. })
+        | plan::Operator::DeRef(plan::DeRef { input, output, .. }) => (input, output),
+        _ => unreachable!("Matched as a map or deref"),
+    };
+    let d1 = *upper_input;
+    let d2 = *upper_output;
+    let plan::Operator::Filter(filter_op) = lp.operators.get_mut(filter).unwrap() else {
+        unreachable!("Matched as a filter")
+    };
+    let d3 = std::mem::replace(&mut filter_op.output, d2);
+    filter_op.input = d1;
+
+    match lp.operators.get_mut(upper).unwrap() {
+        plan::Operator::Map(plan::Map { input, output, .. })
+        | plan::Operator::DeRef(plan::DeRef { input, output, .. }) => {
+            *input = d2;
+            *output = d3;
+        }
+        _ => unreachable!("Matched as a map or deref"),
+    }
+
+    // the filter's output now carries the filter's input type
+    let with = lp.get_dataflow(d1).get_conn().with.clone();
+    *lp.get_mut_dataflow(d2) = plan::DataFlow::Conn(plan::DataFlowConn {
+        from: filter,
+        to: upper,
+        with,
+    });
+    set_to(lp, d1, filter);
+    set_from(lp, d3, upper);
+    swap_ordering(lp, upper, filter);
+    true
+}
+
+/// The filter after the map only uses fields that are copied from the input.
+fn identity_only(lp: &plan::Plan, map: &plan::Map) -> bool {
+    let plan::Operator::Filter(filter) = lp.get_operator(next(lp, map.output)) else {
+        return false;
+    };
+    let input_fields = dataflow_fields(lp, map.input);
+    let input_record = lp.get_record_type_conc(lp.get_dataflow(map.input).get_conn().with.fields);
+    let output_record = lp.get_record_type_conc(lp.get_dataflow(map.output).get_conn().with.fields);
+    let used = idents(&filter.predicate);
+
+    let copied = map
+        .mapping
+        .iter()
+        .filter_map(|(field, expr)| match (field, expr) {
+            (plan::RecordField::User(id), syn::Expr::Path(path))
+                if path.qself.is_none() && path.path.is_ident(id) =>
+            {
+                match (
+                    input_record.fields.get(field),
+                    output_record.fields.get(field),
+                ) {
+                    (Some(t_in), Some(t_out)) if plan::scalar_type_eq(lp, t_in, t_out) => Some(id),
+                    _ => None,
+                }
+            }
+            _ => None,
+        })
+        .collect::<HashSet<_>>();
+
+    map.mapping.iter().all(|(field, _)| match field {
+        plan::RecordField::User(id) => !used.contains(id) || copied.contains(id),
+        plan::RecordField::Internal(_) => true,
+    }) && input_fields
+        .iter()
+        .all(|id| !used.contains(id) || copied.contains(id))
+}
+
+/// The deref is unchecked, and the filter after it does not use the
+/// dereferenced row.
+fn deref_unused(lp: &plan::Plan, deref: &plan::DeRef) -> bool {
+    match lp.get_operator(next(lp, deref.output)) {
+        plan::Operator::Filter(filter) => {
+            deref.unchecked && !mentions(&filter.predicate, &deref.named)
+        }
+        _ => false,
+    }
+}
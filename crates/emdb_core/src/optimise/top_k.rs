@@ -0,0 +1,47 @@
+//! ## Top-K
+//! Replaces a sort followed by a take with a single [`plan::TopK`], which does
+//! not need to sort the entire input.
+//! ```text
+//! sort(a asc) -> take(n)  => top_k(a asc, n)
+//! ```
+
+use super::graph::{next, remove_operator, set_from};
+use crate::plan;
+
+pub fn apply(lp: &mut plan::Plan) -> bool {
+    let Some((sort, take)) = lp.operators.iter().find_map(|(key, op)| match op {
+        plan::Operator::Sort(sort) => {
+            let next_key = next(lp, sort.output);
+            matches!(lp.get_operator(next_key), plan::Operator::Take(_)).then_some((key, next_key))
+        }
+        _ => None,
+    }) else {
+        return false;
+    };
+
+    let plan::Operator::Take(plan::Take { limit, output, .. }) = remove_operator(lp, take) else {
+        unreachable!("Matched as a take")
+    };
+
+    let op = lp.operators.get_mut(sort).unwrap();
+    let plan::Operator::Sort(plan::Sort {
+        input,
+        sort_order,
+        output: between,
+    }) = op
+    else {
+        unreachable!("Matched as a sort")
+    };
+    let between = *between;
+    *op = plan::TopK {
+        input: *input,
+        sort_order: std::mem::take(sort_order),
+        limit,
+        output,
+    }
+    .into();
+
+    lp.dataflow.remove(between);
+    set_from(lp, output, sort);
+    true
+}
@@ -0,0 +1,144 @@
+//! ## Remove Unused Derefs & Fields
+//! Removes work whose results are never used.
+//! ```text
+//! deref(r as row) -> count                 => count
+//! deref(r as row) -> expand(row) -> count  => count
+//! deref(r as row) -> map(no use of row)    => map(no use of row)
+//! map(..) -> count                         => count
+//! map(a = e1, b = e2) -> map(c = f(a))     => map(a = e1) -> map(c = f(a))
+//! ```
+//! Only unchecked derefs are removed, as a checked deref can return an error
+//! for an invalid reference.
+
+use std::collections::HashSet;
+
+use super::graph::{idents, mentions, next, next_operator, remove_operator, set_to};
+use crate::plan;
+
+pub fn derefs(lp: &mut plan::Plan) -> bool {
+    let Some((deref, removed, consumer)) = lp.operators.iter().find_map(|(key, op)| match op {
+        plan::Operator::DeRef(deref) if deref.unchecked => {
+            let next_key = next(lp, deref.output);
+            match lp.get_operator(next_key) {
+                plan::Operator::Count(_) => Some((key, vec![key], next_key)),
+                plan::Operator::Map(map)
+                    if !map.mapping.iter().any(|(_, e)| mentions(e, &deref.named)) =>
+                {
+                    Some((key, vec![key], next_key))
+                }
+                plan::Operator::Expand(expand) if expand.field == deref.named => {
+                    let after_key = next(lp, expand.output);
+                    matches!(lp.get_operator(after_key), plan::Operator::Count(_))
+                        .then(|| (key, vec![key, next_key], after_key))
+                }
+                _ => None,
+            }
+        }
+        _ => None,
+    }) else {
+        return false;
+    };
+
+    let plan::Operator::DeRef(plan::DeRef { input, .. }) = lp.get_operator(deref) else {
+        unreachable!("Matched as a deref")
+    };
+    let input = *input;
+    remove_before(lp, input, removed, consumer);
+    true
+}
+
+pub fn maps(lp: &mut plan::Plan) -> bool {
+    let Some((map, consumer)) = lp.operators.iter().find_map(|(key, op)| match op {
+        plan::Operator::Map(map) => {
+            let next_key = next(lp, map.output);
+            matches!(lp.get_operator(next_key), plan::Operator::Count(_)).then_some((key, next_key))
+        }
+        _ => None,
+    }) else {
+        return false;
+    };
+
+    let plan::Operator::Map(plan::Map { input, .. }) = lp.get_operator(map) else {
+        unreachable!("Matched as a map")
+    };
+    let input = *input;
+    remove_before(lp, input, vec![map], consumer);
+    true
+}
+
+pub fn fields(lp: &mut plan::Plan) -> bool {
+    let Some((map, unused)) = lp.operators.iter().find_map(|(key, op)| match op {
+        plan::Operator::Map(map) => {
+            let plan::Operator::Map(next_map) = next_operator(lp, map.output) else {
+                return None;
+            };
+            let used = next_map
+                .mapping
+                .iter()
+                .flat_map(|(_, e)| idents(e))
+                .collect::<HashSet<_>>();
+            let unused = map
+                .mapping
+                .iter()
+                .filter_map(|(field, _)| match field {
+                    plan::RecordField::User(id) if !used.contains(id) => Some(field.clone()),
+                    _ => None,
+                })
+                .collect::<HashSet<_>>();
+            (!unused.is_empty() && unused.len() < map.mapping.len()).then_some((key, unused))
+        }
+        _ => None,
+    }) else {
+        return false;
+    };
+
+    let plan::Operator::Map(map) = lp.operators.get_mut(map).unwrap() else {
+        unreachable!("Matched as a map")
+    };
+    map.mapping.retain(|(field, _)| !unused.contains(field));
+    let output = map.output;
+
+    // the record type may be shared with other dataflows, so a new one is used
+    let mut fields = lp
+        .get_record_type_conc(lp.get_dataflow(output).get_conn().with.fields)
+        .fields
+        .clone();
+    fields.retain(|field, _| !unused.contains(field));
+    let record = lp.record_types.insert(plan::RecordConc { fields }.into());
+    if let plan::DataFlow::Conn(conn) = lp.get_mut_dataflow(output) {
+        conn.with.fields = record;
+    }
+    true
+}
+
+/// Remove a chain of operators, connecting their input directly to the consumer
+/// of their output.
+fn remove_before(
+    lp: &mut plan::Plan,
+    input: plan::Key<plan::DataFlow>,
+    removed: Vec<plan::Key<plan::Operator>>,
+    consumer: plan::Key<plan::Operator>,
+) {
+    for op in removed {
+        let output = match remove_operator(lp, op) {
+            plan::Operator::DeRef(plan::DeRef { output, .. })
+            | plan::Operator::Expand(plan::Expand { output, .. })
+            | plan::Operator::Map(plan::Map { output, .. }) => output,
+            _ => unreachable!("Only derefs, expands and maps are removed"),
+        };
+        lp.dataflow.remove(output);
+    }
+
+    match lp.operators.get_mut(consumer).unwrap() {
+        plan::Operator::Count(plan::Count {
+            input: consumer_input,
+            ..
+        })
+        | plan::Operator::Map(plan::Map {
+            input: consumer_input,
+            ..
+        }) => *consumer_input = input,
+        _ => unreachable!("Only counts and maps consume removed operators"),
+    }
+    set_to(lp, input, consumer);
+}
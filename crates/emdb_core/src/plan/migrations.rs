@@ -0,0 +1,29 @@
+use super::{Key, RecordField, Table};
+use proc_macro2::Ident;
+use std::collections::HashMap;
+use syn::{Expr, Type};
+
+/// A migration of the rows in the datastore of a previous version of the
+/// schema (`from`, generated by another `emql!`), to the datastore generated by
+/// the backend `to`.
+pub struct Migration {
+    pub from: Type,
+    pub to: Ident,
+    /// Tables without a [`TableMigration`] are copied from the previous table
+    /// of the same name.
+    pub tables: HashMap<Key<Table>, TableMigration>,
+}
+
+pub enum TableMigration {
+    /// A new table, which starts empty
+    Empty,
+
+    /// Rows are moved from the previous version's `from` table. The `columns`
+    /// are computed from each previous row (borrowed as `row`), and the other
+    /// columns are copied.
+    Rows {
+        from: Ident,
+        row: Option<Ident>,
+        columns: HashMap<RecordField, Expr>,
+    },
+}
@@ -9,12 +9,14 @@
 use typed_generational_arena::{Index, NonzeroGeneration, StandardArena as GenArena};
 
 mod access;
+mod migrations;
 mod operators;
 mod queries;
 mod tables;
 mod types;
 
 pub use access::*;
+pub use migrations::*;
 pub use operators::*;
 pub use queries::*;
 pub use tables::*;
@@ -32,6 +34,7 @@ pub struct Plan {
     pub dataflow: GenArena<DataFlow>,
     pub scalar_types: GenArena<ScalarType>,
     pub record_types: GenArena<RecordType>,
+    pub migrations: GenArena<Migration>,
     _holder: (),
 }
 
@@ -45,6 +48,7 @@ impl Plan {
             dataflow: GenArena::new(),
             scalar_types: GenArena::new(),
             record_types: GenArena::new(),
+            migrations: GenArena::new(),
             _holder: (),
         }
     }
@@ -28,7 +28,7 @@
 //! - Strongly type single and stream dataflows. (`Key<DataStream>`, `Key<DataSingle>`)
 
 use super::{Context, Data, Key, Plan, RecordField, RecordType, Table};
-use std::collections::HashMap;
+use std::{collections::HashMap, ops::Bound};
 use syn::{Expr, Ident};
 
 /// A complete data flow connection (only type allowed for valid, constructed plans)
@@ -64,7 +64,8 @@ impl DataFlow {
 }
 
 /// Write to columns of a row in the table from a stream/single, specifying:
-/// - The values to provide for columns (using references to the input)
+/// - The values to provide for columns (using references to the input, and to
+///   the row's current values)
 /// - The field that contains the table reference to use.
 ///
 /// Returns the input type, table is mutated.
@@ -82,6 +83,11 @@ pub struct Update {
     // `INV`: each field in mapping is in the table.
     pub mapping: HashMap<RecordField, Expr>,
 
+    /// The columns whose current values are used by the mapping (sorted)
+    /// - `INV`: each field is a column of the table, and not a field of the
+    ///   input or a parameter in scope
+    pub current: Vec<RecordField>,
+
     /// `INV`: All fields in the record are fields in the table
     pub update_type: Key<RecordType>,
 
@@ -107,6 +113,38 @@ pub struct Insert {
     pub output: Key<DataFlow>,
 }
 
+/// Insert the record, or if a row with the same value for a unique column
+/// exists, update that row, producing a stream/single of row references.
+/// - The update can use the input's fields, and the existing row's current
+///   values (borrowed as `out_ref`).
+///
+/// ```text
+/// TABLE::INSERT -> upsert(TABLE on COLUMN set Fields = |&TABLE::INSERT, CURRENT| { .. }) -> TABLE::REF
+/// ```
+pub struct Upsert {
+    pub input: Key<DataFlow>,
+    pub table: Key<Table>,
+
+    /// `INV`: `field` is a column of the table with a unique constraint
+    pub field: RecordField,
+
+    /// `INV`: each field in mapping is in the table.
+    pub mapping: HashMap<RecordField, Expr>,
+
+    /// If the mapping uses the current values of the existing row
+    pub current: bool,
+
+    /// `INV`: All fields in the record are fields in the table
+    pub update_type: Key<RecordType>,
+
+    /// The single field to place the out_ref in.
+    /// `INV`: `out_ref` is the only field in `output.with`, and is not a
+    ///        column of the table
+    pub out_ref: RecordField,
+
+    pub output: Key<DataFlow>,
+}
+
 /// Delete a rows from a table using a row reference.
 ///
 /// ```text
@@ -123,6 +161,50 @@ pub struct Delete {
     pub output: Key<DataFlow>,
 }
 
+/// Delete every row of a table that matches a predicate, in a single scan of
+/// the table, producing the number of rows deleted.
+///
+/// ```text
+/// delete_where(TABLE as FIELD where |&ROW| -> bool) -> { FIELD: usize }
+/// ```
+pub struct DeleteWhere {
+    pub table: Key<Table>,
+
+    /// The columns borrowed from each row by the predicate (sorted)
+    /// - `INV`: each field is a column of the table
+    pub columns: Vec<RecordField>,
+    pub predicate: Expr,
+
+    /// `INV`: `count` is the only field in `output.with`, which is a single
+    pub count: RecordField,
+    pub output: Key<DataFlow>,
+}
+
+/// Update every row of a table that matches a predicate, in a single scan of
+/// the table, producing the number of rows updated.
+///
+/// ```text
+/// update_where(TABLE as FIELD where |&ROW| -> bool set Fields = |&ROW| { .. }) -> { FIELD: usize }
+/// ```
+pub struct UpdateWhere {
+    pub table: Key<Table>,
+
+    /// The columns borrowed from each row by the predicate and mapping (sorted)
+    /// - `INV`: each field is a column of the table
+    pub columns: Vec<RecordField>,
+    pub predicate: Expr,
+
+    /// `INV`: each field in mapping is in the table.
+    pub mapping: HashMap<RecordField, Expr>,
+
+    /// `INV`: All fields in the record are fields in the table
+    pub update_type: Key<RecordType>,
+
+    /// `INV`: `count` is the only field in `output.with`, which is a single
+    pub count: RecordField,
+    pub output: Key<DataFlow>,
+}
+
 /// Borrow a field and use it for unique lookup into a table, to get a row
 /// reference.
 /// - The column used for the row lookup must have the unique constraint
@@ -146,6 +228,31 @@ pub struct UniqueRef {
     pub output: Key<DataFlow>,
 }
 
+/// Borrow a field and use it for lookup in a (non-unique) index on a table, to
+/// get a stream of all row references with that value.
+/// - The column used for the row lookup must have the index constraint
+/// - Each input record is copied into an output record for each matching row
+///
+/// ```text
+/// RECORD [-> or |>] lookup_refs(use RECORD.{ .. } TABLE at COLUMN ) |> RECORD + TABLE::REF
+/// ```
+pub struct LookupRefs {
+    pub input: Key<DataFlow>,
+
+    /// the dataflow field to get the value to lookup from
+    pub from: RecordField,
+
+    /// the table that is being referenced, and the indexed column in that table
+    pub table: Key<Table>,
+    pub field: RecordField,
+
+    /// the new field to add to the record
+    pub out: RecordField,
+
+    /// `INV`: is a stream
+    pub output: Key<DataFlow>,
+}
+
 /// Scan all refs from a table into a stream.
 ///
 /// ```text
@@ -159,6 +266,25 @@ pub struct ScanRefs {
     pub output: Key<DataFlow>,
 }
 
+/// Scan refs from a table in the order of an ordered index on a column, only
+/// including rows with values in the range given.
+/// - `INV`: `field` has an ordered constraint in `table`
+/// - `INV`: bound expressions only contain globals and the context's parameters
+///
+/// ```text
+/// range_refs(TABLE at COLUMN in LOWER..UPPER) -> TABLE::REF
+/// ```
+pub struct RangeRefs {
+    pub table: Key<Table>,
+    pub field: RecordField,
+    pub lower: Bound<Expr>,
+    pub upper: Bound<Expr>,
+    pub out_ref: RecordField,
+
+    /// `INV`: is a stream with a single field (`out_ref`) of table reference to `table`
+    pub output: Key<DataFlow>,
+}
+
 /// Dereference a table reference and place in a variable
 /// - `INV`: the 'named' not present in the input record
 pub struct DeRef {
@@ -219,6 +345,7 @@ pub struct Filter {
     pub output: Key<DataFlow>,
 }
 
+#[derive(Clone, Copy)]
 pub enum SortOrder {
     Asc,
     Desc,
@@ -234,6 +361,52 @@ pub struct Sort {
     pub output: Key<DataFlow>,
 }
 
+/// A function computed over the rows of a [`Window`] partition, in order.
+pub enum WindowFunction {
+    /// The position of the row in the partition (from 1)
+    RowNumber,
+
+    /// The position of the first row with the same ordering (from 1, with
+    /// gaps after rows with the same ordering)
+    Rank,
+
+    /// The number of distinct orderings up to the row (from 1, without gaps)
+    DenseRank,
+
+    /// The sum of the expression over the rows up to the row
+    RunningSum(Expr),
+
+    /// The minimum of the expression over the rows up to the row
+    RunningMin(Expr),
+
+    /// The maximum of the expression over the rows up to the row
+    RunningMax(Expr),
+}
+
+/// Partition the input by some fields, sort each partition and compute new
+/// fields from the rows in order.
+/// - `INV`: input and output must both be streams
+/// - `INV`: the partition and order fields must exist in the input
+/// - `INV`: the output has the fields of the input, and the computed fields
+/// - `INV`: window function expressions only contains fields from input and globals
+pub struct Window {
+    pub input: Key<DataFlow>,
+    pub partition: Vec<RecordField>,
+    pub order: Vec<(RecordField, SortOrder)>,
+    pub compute: Vec<(RecordField, WindowFunction)>,
+    pub output: Key<DataFlow>,
+}
+
+/// Remove records with the same values for some fields, keeping the first
+/// - `INV`: input and output must have the same fields
+/// - `INV`: input and output must both be streams
+/// - `INV`: The identified fields must exist in the input
+pub struct Distinct {
+    pub input: Key<DataFlow>,
+    pub fields: Vec<RecordField>,
+    pub output: Key<DataFlow>,
+}
+
 /// Assert a boolean expression over a stream, or single value
 /// - `INV`: input type is same as output type
 /// - `INV`: predicate expression only contains fields from input and globals
@@ -262,6 +435,45 @@ pub struct Take {
     pub output: Key<DataFlow>,
 }
 
+/// Where a [`Skip`] resumes the stream from
+pub enum SkipFrom {
+    /// After the first n rows
+    Offset(Expr),
+
+    /// After the rows ordered up to and including the key (containing a value
+    /// for each of the sort fields)
+    /// - `INV`: the input is from a sort with the same order
+    Key {
+        sort_order: Vec<(RecordField, SortOrder)>,
+        key: Expr,
+    },
+
+    /// After the first row where the field is equal to the expression (e.g. a
+    /// row reference from the last page)
+    Row { field: RecordField, row: Expr },
+}
+
+/// Skip rows from the start of a stream
+/// - `INV`: input and output must have the same fields
+/// - `INV`: input and output must be streams
+pub struct Skip {
+    pub input: Key<DataFlow>,
+    pub from: SkipFrom,
+    pub output: Key<DataFlow>,
+}
+
+/// Sort the input and take the first n, introduced by the optimiser to replace
+/// a [`Sort`] followed by a [`Take`].
+/// - `INV`: input and output must have the same fields
+/// - `INV`: input and output must both be streams
+/// - `INV`: The identified fields must exist in the input
+pub struct TopK {
+    pub input: Key<DataFlow>,
+    pub sort_order: Vec<(RecordField, SortOrder)>,
+    pub limit: Expr,
+    pub output: Key<DataFlow>,
+}
+
 pub struct Count {
     pub input: Key<DataFlow>,
     pub output: Key<DataFlow>,
@@ -277,11 +489,32 @@ pub enum MatchKind {
     },
 }
 
-// TODO: Add more join kinds (left, outer), this is partially waiting on being
-//       able to wrap emdb type in rust types, e.g. left join producing (left, Option<right>)
 /// The join type of the operator
+/// - For outer joins the side that may be missing is wrapped in a
+///   [`super::ScalarTypeConc::Option`] in the output record.
 pub enum JoinKind {
     Inner,
+
+    /// Every left row is kept, paired with `None` if there is no matching right
+    Left,
+
+    /// Every right row is kept, paired with `None` if there is no matching left
+    Right,
+
+    /// Both sides are kept, unmatched rows from either side paired with `None`
+    Full,
+}
+
+impl JoinKind {
+    /// Is the left input optional in the output record
+    pub fn left_optional(&self) -> bool {
+        matches!(self, JoinKind::Right | JoinKind::Full)
+    }
+
+    /// Is the right input optional in the output record
+    pub fn right_optional(&self) -> bool {
+        matches!(self, JoinKind::Left | JoinKind::Full)
+    }
 }
 
 pub struct JoinInput {
@@ -342,6 +575,32 @@ pub struct Union {
     pub output: Key<DataFlow>,
 }
 
+/// How duplicate records are treated by [`Intersect`] and [`Except`]
+pub enum SetSemantics {
+    /// Each distinct record is output at most once
+    Set,
+
+    /// Each record of an input can only match one record of the others, so
+    /// duplicates are kept (SQL's `ALL`)
+    Bag,
+}
+
+/// Keep the records of the first stream present in all the others
+/// - `INV`: All incoming dataflows are streams with the same type index
+pub struct Intersect {
+    pub inputs: Vec<Key<DataFlow>>,
+    pub semantics: SetSemantics,
+    pub output: Key<DataFlow>,
+}
+
+/// Remove the records of the first stream present in any of the others
+/// - `INV`: All incoming dataflows are streams with the same type index
+pub struct Except {
+    pub inputs: Vec<Key<DataFlow>>,
+    pub semantics: SetSemantics,
+    pub output: Key<DataFlow>,
+}
+
 /// Generate a single row
 /// - `INV`: output matches fields
 /// - `INV`: output is a single
@@ -350,6 +609,14 @@ pub struct Row {
     pub output: Key<DataFlow>,
 }
 
+/// Stream the records of a bag passed as a parameter
+/// - `INV`: `bag` is a parameter of the context, with a [`super::ScalarTypeConc::Bag`] type
+/// - `INV`: output is a stream of the bag's record type
+pub struct ScanBag {
+    pub bag: Ident,
+    pub output: Key<DataFlow>,
+}
+
 /// Return values from a query
 pub struct Return {
     pub input: Key<DataFlow>,
@@ -366,7 +633,9 @@ pub struct Discard {
 pub enum Operator {
     // get references
     UniqueRef,
+    LookupRefs,
     ScanRefs,
+    RangeRefs,
 
     // read operator
     DeRef,
@@ -374,7 +643,10 @@ pub enum Operator {
     // write operators
     Update,
     Insert,
+    Upsert,
     Delete,
+    DeleteWhere,
+    UpdateWhere,
 
     // pure operators
     Map,
@@ -382,12 +654,16 @@ pub enum Operator {
     Fold,
     Filter,
     Sort,
+    Distinct,
+    Window,
     Assert,
     Combine,
     Count,
 
     // cardinality set
     Take,
+    Skip,
+    TopK,
     Collect,
 
     // nested contexts
@@ -398,9 +674,12 @@ pub enum Operator {
     Join,
     Fork,
     Union,
+    Intersect,
+    Except,
 
     // control flow
     Row,
+    ScanBag,
     Return,
     Discard,
 }
@@ -2,6 +2,8 @@ use super::{DataFlow, Key, Operator, Plan, RecordType, ScalarType};
 use proc_macro2::Ident;
 
 pub struct Query {
+    /// Attributes (including doc comments) for the query's generated method
+    pub attrs: Vec<syn::Attribute>,
     pub name: Ident,
     pub ctx: Key<Context>,
 }
@@ -20,6 +22,9 @@ pub struct Context {
     /// INV is a [super::Return]
     pub returnflow: Option<Key<Operator>>,
     pub discards: Vec<Key<Operator>>,
+    /// The context containing the operator that contains this context (none
+    /// for a query's context)
+    pub parent: Option<Key<Context>>,
 }
 
 impl Context {
@@ -30,6 +35,19 @@ impl Context {
             inflows,
             returnflow: None,
             discards: Vec::new(),
+            parent: None,
+        }
+    }
+
+    /// A context nested inside an operator of the `parent` context
+    pub fn nested(
+        parent: Key<Context>,
+        params: Vec<(Ident, Key<ScalarType>)>,
+        inflows: Vec<Key<DataFlow>>,
+    ) -> Self {
+        Context {
+            parent: Some(parent),
+            ..Self::from_params(params, inflows)
         }
     }
 
@@ -71,4 +89,13 @@ impl Plan {
     pub fn get_mut_context(&mut self, key: Key<Context>) -> &mut Context {
         self.contexts.get_mut(key).unwrap()
     }
+
+    /// The parameters available in a context, including those of the contexts
+    /// it is nested in
+    pub fn params_in_scope(&self, key: Key<Context>) -> impl Iterator<Item = &Ident> {
+        std::iter::successors(Some(self.get_context(key)), |ctx| {
+            ctx.parent.map(|parent| self.get_context(parent))
+        })
+        .flat_map(|ctx| ctx.params.iter().map(|(id, _)| id))
+    }
 }
@@ -16,11 +16,36 @@ pub struct Constraint<C> {
 }
 
 pub struct Unique;
+/// An ordered index over the column, for range scans
+pub struct Ordered;
+/// A (non-unique) hash index over the column, for equality lookups
+pub struct Hashed;
 pub struct Limit(pub Expr);
 pub struct Pred(pub Expr);
 
+/// What to do with the rows referencing a row when it is deleted
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum OnDelete {
+    /// Fail the delete
+    Restrict,
+    /// Delete the referencing rows
+    Cascade,
+    /// Set the referencing column to `None` (only for optional references)
+    SetNone,
+}
+
+/// The column (of type `ref table` or `Option<ref table>`) must reference an
+/// existing row of `table`.
+pub struct ValidRef {
+    pub table: Key<Table>,
+    pub on_delete: OnDelete,
+}
+
 pub struct ColumnConstraints {
     pub unique: Option<Constraint<Unique>>,
+    pub ordered: Option<Constraint<Ordered>>,
+    pub hashed: Option<Constraint<Hashed>>,
+    pub valid_ref: Option<Constraint<ValidRef>>,
 }
 pub struct RowConstraints {
     pub limit: Option<Constraint<Limit>>,
@@ -33,6 +58,8 @@ pub struct Column {
 }
 
 pub struct Table {
+    /// Attributes (including doc comments) for the table's generated code
+    pub attrs: Vec<syn::Attribute>,
     pub name: Ident,
     pub row_cons: RowConstraints,
     pub columns: HashMap<RecordField, Column>,
@@ -44,8 +71,62 @@ pub struct ColSelect {
     pub select_as: Ident,
 }
 
+/// A [`ValidRef`] constraint on a column of `table`
+#[derive(Clone, Copy)]
+pub struct Reference<'imm> {
+    pub table: Key<Table>,
+    pub field: &'imm RecordField,
+    pub cons: &'imm Constraint<ValidRef>,
+}
+
 impl Plan {
     pub fn get_table(&self, k: Key<Table>) -> &Table {
         self.tables.get(k).unwrap()
     }
+
+    /// The references from columns of a table to other tables (sorted by
+    /// column name, so generated code is deterministic).
+    pub fn references_from(&self, table: Key<Table>) -> impl Iterator<Item = Reference<'_>> {
+        let mut refs = self
+            .get_table(table)
+            .columns
+            .iter()
+            .filter_map(move |(field, col)| {
+                col.cons
+                    .valid_ref
+                    .as_ref()
+                    .map(|cons| Reference { table, field, cons })
+            })
+            .collect::<Vec<_>>();
+        refs.sort_by_key(|r| r.field.to_string());
+        refs.into_iter()
+    }
+
+    /// The references from any table's columns to a table
+    pub fn references_to(&self, table: Key<Table>) -> impl Iterator<Item = Reference<'_>> {
+        self.tables
+            .iter()
+            .flat_map(move |(from, _)| self.references_from(from))
+            .filter(move |r| r.cons.cons.table == table)
+    }
+
+    /// All references that need to be checked or updated when deleting from
+    /// a table, including those of rows deleted by [`OnDelete::Cascade`]
+    /// (sorted by referencing table).
+    pub fn delete_references(&self, table: Key<Table>) -> Vec<Reference<'_>> {
+        let mut visited = vec![table];
+        let mut refs = Vec::new();
+        let mut i = 0;
+        while let Some(current) = visited.get(i).copied() {
+            for r in self.references_to(current) {
+                if r.cons.cons.on_delete == OnDelete::Cascade && !visited.contains(&r.table) {
+                    visited.push(r.table);
+                }
+                refs.push(r);
+            }
+            i += 1;
+        }
+        refs.sort_by_key(|r| r.table.arr_idx());
+        refs
+    }
 }
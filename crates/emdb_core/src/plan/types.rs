@@ -6,7 +6,8 @@
 //! context of the record type they are in)
 //!
 //! Types supported include rust types, ref types (backend decides implementation)
-//! and wrappers such as [`ScalarTypeConc::Record`], and [`ScalarTypeConc::Bag`]
+//! and wrappers such as [`ScalarTypeConc::Record`], and [`ScalarTypeConc::Bag`].
+//! Rust types can also contain these types ([`ScalarTypeConc::Composite`]).
 //!
 //! It is important to allow all rust types to be supported, to allow frontends
 //! maximum flexibility in the types they use.
@@ -57,7 +58,7 @@
 //!   compilation, [`ConcRef`] are assumed to be non-cyclic.
 
 use super::{GenArena, ImmKey, Key, Plan, Table, With};
-use proc_macro2::Ident;
+use proc_macro2::{Group, Ident, TokenStream, TokenTree};
 use quote::ToTokens;
 use std::{
     collections::{HashMap, HashSet},
@@ -212,6 +213,11 @@ pub enum ScalarTypeConc {
     /// A record/struct of named fields
     Record(Key<RecordType>),
 
+    /// A value that may not be present
+    /// - Produced by outer joins for the side that has no match
+    ///   (see [`super::JoinKind`]).
+    Option(Key<ScalarType>),
+
     /// A rust type propagated from the user
     /// - Can be from the user's code (e.g. a library or user defined type)
     /// - Can be incorrect (need to propagate spans to backend for rustc to
@@ -228,6 +234,47 @@ pub enum ScalarTypeConc {
     /// Note: The lifetimes are not applied outside of a query context, namely
     ///       in the types used for table members
     Rust { type_context: TypeContext, ty: Type },
+
+    /// A rust type containing other types, for example `Vec<(ref foos, type my_bag)>`
+    /// - The `ty` contains placeholder identifiers, each substituted with the
+    ///   type generated for its `inner` type (see [`substitute_composite`])
+    /// - Only the `inner` types are checked, the rest of the type is left for
+    ///   rustc (as with [`ScalarTypeConc::Rust`])
+    Composite {
+        ty: Type,
+        inner: Vec<(Ident, Key<ScalarType>)>,
+    },
+}
+
+/// Substitute the placeholders of a [`ScalarTypeConc::Composite`] type with
+/// the tokens generated for each inner type.
+pub fn substitute_composite(
+    ty: &Type,
+    inner: &[(Ident, Key<ScalarType>)],
+    mut generate: impl FnMut(Key<ScalarType>) -> TokenStream,
+) -> TokenStream {
+    fn substitute(tks: TokenStream, subs: &HashMap<&Ident, TokenStream>) -> TokenStream {
+        tks.into_iter()
+            .map(|tt| match tt {
+                TokenTree::Ident(id) => subs
+                    .get(&id)
+                    .cloned()
+                    .unwrap_or_else(|| TokenTree::Ident(id).into()),
+                TokenTree::Group(g) => {
+                    let mut new_group = Group::new(g.delimiter(), substitute(g.stream(), subs));
+                    new_group.set_span(g.span());
+                    TokenTree::Group(new_group).into()
+                }
+                tt => tt.into(),
+            })
+            .collect()
+    }
+
+    let subs = inner
+        .iter()
+        .map(|(id, key)| (id, generate(*key)))
+        .collect::<HashMap<_, _>>();
+    substitute(ty.to_token_stream(), &subs)
 }
 
 /// Check two record types are equal.
@@ -256,21 +303,44 @@ pub fn record_type_eq(lp: &Plan, r1: &Key<RecordType>, r2: &Key<RecordType>) ->
 
 /// Check two scalar types are equal.
 /// - The indicies in the types arenas (in [`Plan`]) may be different, if you want to coerse, use [`coerce_scalar_type`]
+/// - Rust types are always considered equal, as different paths may name the
+///   same type (e.g. `super::Foo` and `crate::bar::Foo`), rustc checks these.
 pub fn scalar_type_eq(lp: &Plan, t1: &Key<ScalarType>, t2: &Key<ScalarType>) -> bool {
     if t1 == t2 {
         return true;
     }
 
     let conc_t1 = lp.scalar_types.get(*t1).unwrap().get_conc(&lp.scalar_types);
-    let conc_t2 = lp.scalar_types.get(*t1).unwrap().get_conc(&lp.scalar_types);
+    let conc_t2 = lp.scalar_types.get(*t2).unwrap().get_conc(&lp.scalar_types);
 
     match (conc_t1, conc_t2) {
         (ScalarTypeConc::TableRef(t1), ScalarTypeConc::TableRef(t2)) => t1 == t2,
+        (
+            ScalarTypeConc::TableGet {
+                table: t1,
+                field: f1,
+            },
+            ScalarTypeConc::TableGet {
+                table: t2,
+                field: f2,
+            },
+        ) => t1 == t2 && f1 == f2,
         (
             ScalarTypeConc::Bag(r1) | ScalarTypeConc::Record(r1),
             ScalarTypeConc::Bag(r2) | ScalarTypeConc::Record(r2),
         ) => record_type_eq(lp, r1, r2),
-        (ScalarTypeConc::Rust { ty: rt1, .. }, ScalarTypeConc::Rust { ty: rt2, .. }) => rt1 == rt2,
+        (ScalarTypeConc::Rust { .. }, ScalarTypeConc::Rust { .. }) => true,
+        (ScalarTypeConc::Option(s1), ScalarTypeConc::Option(s2)) => scalar_type_eq(lp, s1, s2),
+        (
+            ScalarTypeConc::Composite { inner: i1, .. },
+            ScalarTypeConc::Composite { inner: i2, .. },
+        ) => {
+            i1.len() == i2.len()
+                && i1
+                    .iter()
+                    .zip(i2)
+                    .all(|((_, s1), (_, s2))| scalar_type_eq(lp, s1, s2))
+        }
         _ => false,
     }
 }
@@ -284,11 +354,28 @@ pub fn coerce_scalar_type(lp: &mut Plan, conform_to: Key<ScalarType>, change: Ke
         let conform_scalar = lp.get_scalar_type_conc(conform_index);
         let conforming_scalar = lp.get_scalar_type_conc(conforming_index);
 
-        if let (ScalarTypeConc::Bag(r1), ScalarTypeConc::Bag(r2))
-        | (ScalarTypeConc::Record(r1), ScalarTypeConc::Record(r2)) =
-            (conform_scalar, conforming_scalar)
-        {
-            coerce_record_type(lp, *r1, *r2);
+        match (conform_scalar, conforming_scalar) {
+            (ScalarTypeConc::Bag(r1), ScalarTypeConc::Bag(r2))
+            | (ScalarTypeConc::Record(r1), ScalarTypeConc::Record(r2)) => {
+                coerce_record_type(lp, *r1, *r2);
+            }
+            (ScalarTypeConc::Option(s1), ScalarTypeConc::Option(s2)) => {
+                coerce_scalar_type(lp, *s1, *s2);
+            }
+            (
+                ScalarTypeConc::Composite { inner: i1, .. },
+                ScalarTypeConc::Composite { inner: i2, .. },
+            ) => {
+                let pairs = i1
+                    .iter()
+                    .zip(i2)
+                    .map(|((_, s1), (_, s2))| (*s1, *s2))
+                    .collect::<Vec<_>>();
+                for (to, from) in pairs {
+                    coerce_scalar_type(lp, to, from);
+                }
+            }
+            _ => (),
         }
         *lp.scalar_types.get_mut(conforming_index).unwrap() = ConcRef::Ref(conform_index);
     }
@@ -408,6 +495,30 @@ impl Display for With<'_, &Key<ScalarType>> {
             }
             .fmt(f),
             ScalarTypeConc::Rust { ty, .. } => ty.to_token_stream().fmt(f),
+            ScalarTypeConc::Composite { ty, inner } => {
+                write!(f, "{}", ty.to_token_stream())?;
+                for (id, s) in inner {
+                    write!(
+                        f,
+                        " {id} = {}",
+                        With {
+                            plan: self.plan,
+                            extended: s
+                        }
+                    )?;
+                }
+                Ok(())
+            }
+            ScalarTypeConc::Option(s) => {
+                write!(
+                    f,
+                    "optional {}",
+                    With {
+                        plan: self.plan,
+                        extended: s
+                    }
+                )
+            }
             ScalarTypeConc::TableGet { table, field } => {
                 write!(
                     f,
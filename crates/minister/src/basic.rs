@@ -1,5 +1,5 @@
 #![allow(clippy::ptr_arg)]
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 
 macro_rules! single {
     ($data:ty) => {
@@ -222,6 +222,78 @@ impl BasicOps for Basic {
         stream
     }
 
+    type SkipStats = ();
+    fn skip<Data>(mut stream: stream!(Data), n: usize, _stats: &Self::SkipStats) -> stream!(Data)
+    where
+        Data: Send + Sync,
+    {
+        stream.drain(..n.min(stream.len()));
+        stream
+    }
+
+    type SkipWhileStats = ();
+    fn skip_while<Data>(
+        mut stream: stream!(Data),
+        pred: impl Fn(&Data) -> bool + Send + Sync,
+        _stats: &Self::SkipWhileStats,
+    ) -> stream!(Data)
+    where
+        Data: Send + Sync,
+    {
+        let skipped = stream
+            .iter()
+            .position(|data| !pred(data))
+            .unwrap_or(stream.len());
+        stream.drain(..skipped);
+        stream
+    }
+
+    type TopKStats = ();
+    fn top_k<Data>(
+        mut stream: stream!(Data),
+        ordering: impl Fn(&Data, &Data) -> std::cmp::Ordering + Send + Sync,
+        n: usize,
+        _stats: &Self::TopKStats,
+    ) -> stream!(Data)
+    where
+        Data: Send + Sync,
+    {
+        if n < stream.len() {
+            stream.select_nth_unstable_by(n, &ordering);
+            stream.truncate(n);
+        }
+        stream.sort_unstable_by(ordering);
+        stream
+    }
+
+    type DistinctStats = ();
+    fn distinct<Key, Data>(
+        mut stream: stream!(Data),
+        key: impl Fn(&Data) -> Key + Send + Sync,
+        _stats: &Self::DistinctStats,
+    ) -> stream!(Data)
+    where
+        Data: Send + Sync,
+        Key: Eq + std::hash::Hash + Send + Sync,
+    {
+        let mut seen = HashSet::new();
+        stream.retain(|data| seen.insert(key(data)));
+        stream
+    }
+
+    type DistinctSortedStats = ();
+    fn distinct_sorted<Data>(
+        mut stream: stream!(Data),
+        same: impl Fn(&Data, &Data) -> bool + Send + Sync,
+        _stats: &Self::DistinctSortedStats,
+    ) -> stream!(Data)
+    where
+        Data: Send + Sync,
+    {
+        stream.dedup_by(|next, prev| same(prev, next));
+        stream
+    }
+
     type GroupByStats = ();
     fn group_by<Key, Rest, Data>(
         stream: stream!(Data),
@@ -241,6 +313,37 @@ impl BasicOps for Basic {
         groups.into_iter().collect()
     }
 
+    type WindowStats = ();
+    fn window<Key, State, InData, OutData>(
+        stream: stream!(InData),
+        partition: impl Fn(&InData) -> Key + Send + Sync,
+        ordering: impl Fn(&InData, &InData) -> std::cmp::Ordering + Send + Sync,
+        initial: State,
+        compute: impl Fn(&mut State, bool, InData) -> OutData + Send + Sync,
+        _stats: &Self::WindowStats,
+    ) -> stream!(OutData)
+    where
+        Key: Eq + std::hash::Hash + Send + Sync,
+        State: Clone + Send + Sync,
+        InData: Send + Sync,
+        OutData: Send + Sync,
+    {
+        let groups = Self::group_by(stream, move |data| (partition(&data), data), &());
+        let mut results = Vec::new();
+        for (_, group) in groups {
+            let group = Self::sort(group, &ordering, &());
+            let peers = peers(&group, &ordering);
+            let mut state = initial.clone();
+            results.extend(
+                group
+                    .into_iter()
+                    .zip(peers)
+                    .map(|(data, peer)| compute(&mut state, peer, data)),
+            );
+        }
+        results
+    }
+
     type CrossJoinStats = ();
     fn cross_join<LeftData, RightData>(
         left: stream!(LeftData),
@@ -328,6 +431,189 @@ impl BasicOps for Basic {
         results
     }
 
+    type LeftCrossJoinStats = ();
+    fn left_cross_join<LeftData, RightData>(
+        left: stream!(LeftData),
+        right: stream!(RightData),
+        _stats: &Self::LeftCrossJoinStats,
+    ) -> stream!((LeftData, Option<RightData>))
+    where
+        LeftData: Clone + Send + Sync,
+        RightData: Clone + Send + Sync,
+    {
+        if right.is_empty() {
+            return left.into_iter().map(|l| (l, None)).collect();
+        }
+        let mut result = Vec::with_capacity(left.len() * right.len());
+        for l in left {
+            for r in &right {
+                result.push((l.clone(), Some(r.clone())));
+            }
+        }
+        result
+    }
+
+    type FullCrossJoinStats = ();
+    fn full_cross_join<LeftData, RightData>(
+        left: stream!(LeftData),
+        right: stream!(RightData),
+        _stats: &Self::FullCrossJoinStats,
+    ) -> stream!((Option<LeftData>, Option<RightData>))
+    where
+        LeftData: Clone + Send + Sync,
+        RightData: Clone + Send + Sync,
+    {
+        if right.is_empty() {
+            return left.into_iter().map(|l| (Some(l), None)).collect();
+        }
+        if left.is_empty() {
+            return right.into_iter().map(|r| (None, Some(r))).collect();
+        }
+        let mut result = Vec::with_capacity(left.len() * right.len());
+        for l in left {
+            for r in &right {
+                result.push((Some(l.clone()), Some(r.clone())));
+            }
+        }
+        result
+    }
+
+    type LeftEquiJoinStats = ();
+    fn left_equi_join<LeftData, RightData, Key>(
+        left: stream!(LeftData),
+        right: stream!(RightData),
+        left_split: impl Fn(&LeftData) -> &Key + Send + Sync,
+        right_split: impl Fn(&RightData) -> &Key + Send + Sync,
+        _stats: &Self::LeftEquiJoinStats,
+    ) -> stream!((LeftData, Option<RightData>))
+    where
+        Key: Eq + std::hash::Hash + Send + Sync,
+        LeftData: Clone + Send + Sync,
+        RightData: Clone + Send + Sync,
+    {
+        let mut rights = HashMap::with_capacity(right.len());
+        for r in &right {
+            rights
+                .entry(right_split(r))
+                .or_insert_with(Vec::new)
+                .push(r);
+        }
+        let mut results = Vec::with_capacity(left.len());
+        for l in left {
+            match rights.get(left_split(&l)) {
+                Some(rs) => {
+                    for r in rs {
+                        results.push((l.clone(), Some((*r).clone())))
+                    }
+                }
+                None => results.push((l, None)),
+            }
+        }
+        results
+    }
+
+    type FullEquiJoinStats = ();
+    fn full_equi_join<LeftData, RightData, Key>(
+        left: stream!(LeftData),
+        right: stream!(RightData),
+        left_split: impl Fn(&LeftData) -> &Key + Send + Sync,
+        right_split: impl Fn(&RightData) -> &Key + Send + Sync,
+        _stats: &Self::FullEquiJoinStats,
+    ) -> stream!((Option<LeftData>, Option<RightData>))
+    where
+        Key: Eq + std::hash::Hash + Send + Sync,
+        LeftData: Clone + Send + Sync,
+        RightData: Clone + Send + Sync,
+    {
+        let mut rights = HashMap::with_capacity(right.len());
+        for (i, r) in right.iter().enumerate() {
+            rights
+                .entry(right_split(r))
+                .or_insert_with(Vec::new)
+                .push(i);
+        }
+        let mut matched = vec![false; right.len()];
+        let mut results = Vec::with_capacity(left.len() + right.len());
+        for l in left {
+            match rights.get(left_split(&l)) {
+                Some(is) => {
+                    for i in is {
+                        matched[*i] = true;
+                        results.push((Some(l.clone()), Some(right[*i].clone())))
+                    }
+                }
+                None => results.push((Some(l), None)),
+            }
+        }
+        for (r, m) in right.into_iter().zip(matched) {
+            if !m {
+                results.push((None, Some(r)))
+            }
+        }
+        results
+    }
+
+    type LeftPredJoinStats = ();
+    fn left_predicate_join<LeftData, RightData>(
+        left: stream!(LeftData),
+        right: stream!(RightData),
+        pred: impl Fn(&LeftData, &RightData) -> bool + Send + Sync,
+        _stats: &Self::LeftPredJoinStats,
+    ) -> stream!((LeftData, Option<RightData>))
+    where
+        LeftData: Clone + Send + Sync,
+        RightData: Clone + Send + Sync,
+    {
+        let mut results = Vec::with_capacity(left.len());
+        for l in left {
+            let mut found = false;
+            for r in &right {
+                if pred(&l, r) {
+                    found = true;
+                    results.push((l.clone(), Some(r.clone())));
+                }
+            }
+            if !found {
+                results.push((l, None));
+            }
+        }
+        results
+    }
+
+    type FullPredJoinStats = ();
+    fn full_predicate_join<LeftData, RightData>(
+        left: stream!(LeftData),
+        right: stream!(RightData),
+        pred: impl Fn(&LeftData, &RightData) -> bool + Send + Sync,
+        _stats: &Self::FullPredJoinStats,
+    ) -> stream!((Option<LeftData>, Option<RightData>))
+    where
+        LeftData: Clone + Send + Sync,
+        RightData: Clone + Send + Sync,
+    {
+        let mut matched = vec![false; right.len()];
+        let mut results = Vec::with_capacity(left.len() + right.len());
+        for l in left {
+            let mut found = false;
+            for (r, m) in right.iter().zip(matched.iter_mut()) {
+                if pred(&l, r) {
+                    found = true;
+                    *m = true;
+                    results.push((Some(l.clone()), Some(r.clone())));
+                }
+            }
+            if !found {
+                results.push((Some(l), None));
+            }
+        }
+        for (r, m) in right.into_iter().zip(matched) {
+            if !m {
+                results.push((None, Some(r)))
+            }
+        }
+        results
+    }
+
     type UnionStats = ();
     fn union<Data>(
         mut left: stream!(Data),
@@ -341,6 +627,38 @@ impl BasicOps for Basic {
         left
     }
 
+    type IntersectStats = ();
+    fn intersect<Key, Data>(
+        mut left: stream!(Data),
+        right: stream!(Data),
+        key: impl Fn(&Data) -> Key + Send + Sync,
+        _stats: &Self::IntersectStats,
+    ) -> stream!(Data)
+    where
+        Data: Send + Sync,
+        Key: Eq + std::hash::Hash + Send + Sync,
+    {
+        let mut counts = key_counts(right.iter().map(&key));
+        left.retain(|data| take_count(&mut counts, &key(data)));
+        left
+    }
+
+    type ExceptStats = ();
+    fn except<Key, Data>(
+        mut left: stream!(Data),
+        right: stream!(Data),
+        key: impl Fn(&Data) -> Key + Send + Sync,
+        _stats: &Self::ExceptStats,
+    ) -> stream!(Data)
+    where
+        Data: Send + Sync,
+        Key: Eq + std::hash::Hash + Send + Sync,
+    {
+        let mut counts = key_counts(right.iter().map(&key));
+        left.retain(|data| !take_count(&mut counts, &key(data)));
+        left
+    }
+
     type ForkStats = ();
     fn fork<Data>(stream: stream!(Data), _stats: &Self::ForkStats) -> (stream!(Data), stream!(Data))
     where
@@ -372,3 +690,43 @@ impl BasicOps for Basic {
         stream.into_iter().unzip()
     }
 }
+
+/// For each data in a sorted stream, if it has the same ordering as the
+/// previous data.
+pub(crate) fn peers<Data>(
+    sorted: &[Data],
+    ordering: impl Fn(&Data, &Data) -> std::cmp::Ordering,
+) -> Vec<bool> {
+    std::iter::once(false)
+        .chain(
+            sorted
+                .windows(2)
+                .map(|pair| ordering(&pair[0], &pair[1]).is_eq()),
+        )
+        .collect()
+}
+
+/// The number of times each key occurs.
+pub(crate) fn key_counts<Key: Eq + std::hash::Hash>(
+    keys: impl Iterator<Item = Key>,
+) -> HashMap<Key, usize> {
+    let mut counts = HashMap::new();
+    for key in keys {
+        *counts.entry(key).or_insert(0) += 1;
+    }
+    counts
+}
+
+/// Remove one occurence of a key from the counts, returning if it was present.
+pub(crate) fn take_count<Key: Eq + std::hash::Hash>(
+    counts: &mut HashMap<Key, usize>,
+    key: &Key,
+) -> bool {
+    match counts.get_mut(key) {
+        Some(count) if *count > 0 => {
+            *count -= 1;
+            true
+        }
+        _ => false,
+    }
+}
@@ -1,6 +1,10 @@
 #![allow(clippy::ptr_arg)]
 use rayon::{current_num_threads, prelude::*};
-use std::{collections::HashMap, iter::FlatMap, vec::IntoIter};
+use std::{
+    collections::{HashMap, HashSet},
+    iter::FlatMap,
+    vec::IntoIter,
+};
 
 macro_rules! single {
     ($data:ty) => {
@@ -321,6 +325,129 @@ impl ChunkOps for Chunk {
         ChunkVecs::split_chunks(data.len(), data.into_iter())
     }
 
+    type SkipStats = ();
+    fn skip<Data>(
+        mut stream: stream!(Data),
+        mut n: usize,
+        _stats: &Self::SkipStats,
+    ) -> stream!(Data)
+    where
+        Data: Send + Sync,
+    {
+        // whole chunks are skipped without moving their data
+        let mut skipped_chunks = 0;
+        for chunk in &mut stream.chunks {
+            if n < chunk.len() {
+                chunk.drain(..n);
+                break;
+            }
+            n -= chunk.len();
+            skipped_chunks += 1;
+        }
+        stream.chunks.drain(..skipped_chunks);
+        stream
+    }
+
+    type SkipWhileStats = ();
+    fn skip_while<Data>(
+        mut stream: stream!(Data),
+        pred: impl Fn(&Data) -> bool + Send + Sync,
+        _stats: &Self::SkipWhileStats,
+    ) -> stream!(Data)
+    where
+        Data: Send + Sync,
+    {
+        let mut skipped_chunks = 0;
+        for chunk in &mut stream.chunks {
+            if let Some(skipped) = chunk.par_iter().position_first(|data| !pred(data)) {
+                chunk.drain(..skipped);
+                break;
+            }
+            skipped_chunks += 1;
+        }
+        stream.chunks.drain(..skipped_chunks);
+        stream
+    }
+
+    type TopKStats = ();
+    fn top_k<Data>(
+        stream: stream!(Data),
+        ordering: impl Fn(&Data, &Data) -> std::cmp::Ordering + Send + Sync,
+        n: usize,
+        _stats: &Self::TopKStats,
+    ) -> stream!(Data)
+    where
+        Data: Send + Sync,
+    {
+        let mut data = stream
+            .chunks
+            .into_iter()
+            .flat_map(|v| v.into_iter())
+            .collect::<Vec<_>>();
+        if n < data.len() {
+            data.select_nth_unstable_by(n, &ordering);
+            data.truncate(n);
+        }
+        data.par_sort_unstable_by(ordering);
+        ChunkVecs::split_chunks(data.len(), data.into_iter())
+    }
+
+    type DistinctStats = ();
+    fn distinct<Key, Data>(
+        stream: stream!(Data),
+        key: impl Fn(&Data) -> Key + Send + Sync,
+        _stats: &Self::DistinctStats,
+    ) -> stream!(Data)
+    where
+        Data: Send + Sync,
+        Key: Eq + std::hash::Hash + Send + Sync,
+    {
+        let mut seen = HashSet::new();
+        stream
+            .chunks
+            .into_iter()
+            .map(|mut v| {
+                v.retain(|data| seen.insert(key(data)));
+                v
+            })
+            .collect::<Vec<_>>()
+            .into()
+    }
+
+    type DistinctSortedStats = ();
+    fn distinct_sorted<Data>(
+        stream: stream!(Data),
+        same: impl Fn(&Data, &Data) -> bool + Send + Sync,
+        _stats: &Self::DistinctSortedStats,
+    ) -> stream!(Data)
+    where
+        Data: Send + Sync,
+    {
+        let mut chunks = stream
+            .chunks
+            .into_par_iter()
+            .map(|mut v: Vec<Data>| {
+                v.dedup_by(|next, prev| same(prev, next));
+                v
+            })
+            .collect::<Vec<_>>();
+
+        // the same data can span the boundaries between chunks
+        let mut last_chunk: Option<usize> = None;
+        for i in 0..chunks.len() {
+            if let Some(last) = last_chunk {
+                let (before, after) = chunks.split_at_mut(i);
+                let prev = before[last].last().unwrap();
+                let repeated = after[0].iter().take_while(|next| same(prev, next)).count();
+                after[0].drain(..repeated);
+            }
+            if !chunks[i].is_empty() {
+                last_chunk = Some(i);
+            }
+        }
+        chunks.into()
+    }
+
     type GroupByStats = ();
     fn group_by<Key, Rest, Data>(
         stream: stream!(Data),
@@ -347,6 +474,40 @@ impl ChunkOps for Chunk {
         )
     }
 
+    type WindowStats = ();
+    fn window<Key, State, InData, OutData>(
+        stream: stream!(InData),
+        partition: impl Fn(&InData) -> Key + Send + Sync,
+        ordering: impl Fn(&InData, &InData) -> std::cmp::Ordering + Send + Sync,
+        initial: State,
+        compute: impl Fn(&mut State, bool, InData) -> OutData + Send + Sync,
+        _stats: &Self::WindowStats,
+    ) -> stream!(OutData)
+    where
+        Key: Eq + std::hash::Hash + Send + Sync,
+        State: Clone + Send + Sync,
+        InData: Send + Sync,
+        OutData: Send + Sync,
+    {
+        // each partition is computed as a separate chunk
+        let groups = Self::group_by(stream, move |data| (partition(&data), data), &());
+        groups
+            .merge_chunks()
+            .into_par_iter()
+            .map(|(_, group)| {
+                let group = Self::sort(group, &ordering, &()).merge_chunks();
+                let peers = super::basic::peers(&group, &ordering);
+                let mut state = initial.clone();
+                group
+                    .into_iter()
+                    .zip(peers)
+                    .map(|(data, peer)| compute(&mut state, peer, data))
+                    .collect::<Vec<_>>()
+            })
+            .collect::<Vec<_>>()
+            .into()
+    }
+
     type CrossJoinStats = ();
     fn cross_join<LeftData, RightData>(
         left: stream!(LeftData),
@@ -454,6 +615,232 @@ impl ChunkOps for Chunk {
             .into()
     }
 
+    type LeftCrossJoinStats = ();
+    fn left_cross_join<LeftData, RightData>(
+        left: stream!(LeftData),
+        right: stream!(RightData),
+        _stats: &Self::LeftCrossJoinStats,
+    ) -> stream!((LeftData, Option<RightData>))
+    where
+        LeftData: Clone + Send + Sync,
+        RightData: Clone + Send + Sync,
+    {
+        let right_empty = right.chunks.iter().all(Vec::is_empty);
+        left.chunks
+            .into_par_iter()
+            .map(|ls| {
+                let mut v = Vec::new();
+                for l in ls {
+                    if right_empty {
+                        v.push((l, None))
+                    } else {
+                        for rs in &right.chunks {
+                            for r in rs {
+                                v.push((l.clone(), Some(r.clone())))
+                            }
+                        }
+                    }
+                }
+                v
+            })
+            .collect::<Vec<_>>()
+            .into()
+    }
+
+    type FullCrossJoinStats = ();
+    fn full_cross_join<LeftData, RightData>(
+        left: stream!(LeftData),
+        right: stream!(RightData),
+        _stats: &Self::FullCrossJoinStats,
+    ) -> stream!((Option<LeftData>, Option<RightData>))
+    where
+        LeftData: Clone + Send + Sync,
+        RightData: Clone + Send + Sync,
+    {
+        if left.chunks.iter().all(Vec::is_empty) {
+            return right
+                .chunks
+                .into_par_iter()
+                .map(|rs| rs.into_iter().map(|r| (None, Some(r))).collect::<Vec<_>>())
+                .collect::<Vec<_>>()
+                .into();
+        }
+        let right_empty = right.chunks.iter().all(Vec::is_empty);
+        left.chunks
+            .into_par_iter()
+            .map(|ls| {
+                let mut v = Vec::new();
+                for l in ls {
+                    if right_empty {
+                        v.push((Some(l), None))
+                    } else {
+                        for rs in &right.chunks {
+                            for r in rs {
+                                v.push((Some(l.clone()), Some(r.clone())))
+                            }
+                        }
+                    }
+                }
+                v
+            })
+            .collect::<Vec<_>>()
+            .into()
+    }
+
+    type LeftEquiJoinStats = ();
+    fn left_equi_join<LeftData, RightData, Key>(
+        left: stream!(LeftData),
+        right: stream!(RightData),
+        left_split: impl Fn(&LeftData) -> &Key + Send + Sync,
+        right_split: impl Fn(&RightData) -> &Key + Send + Sync,
+        _stats: &Self::LeftEquiJoinStats,
+    ) -> stream!((LeftData, Option<RightData>))
+    where
+        Key: Eq + std::hash::Hash + Send + Sync,
+        LeftData: Clone + Send + Sync,
+        RightData: Clone + Send + Sync,
+    {
+        // NOTE: Not optimised at all, but does mantain balance of chunk sizes
+        let left = left.merge_chunks();
+        let right = right.merge_chunks();
+        let mut results = Vec::with_capacity(left.len());
+        let mut rights = HashMap::with_capacity(right.len());
+        for r in &right {
+            rights
+                .entry(right_split(r))
+                .or_insert_with(Vec::new)
+                .push(r);
+        }
+        for l in left {
+            match rights.get(left_split(&l)) {
+                Some(rs) => {
+                    for r in rs {
+                        results.push((l.clone(), Some((*r).clone())))
+                    }
+                }
+                None => results.push((l, None)),
+            }
+        }
+        ChunkVecs::split_chunks(results.len(), results.into_iter())
+    }
+
+    type FullEquiJoinStats = ();
+    fn full_equi_join<LeftData, RightData, Key>(
+        left: stream!(LeftData),
+        right: stream!(RightData),
+        left_split: impl Fn(&LeftData) -> &Key + Send + Sync,
+        right_split: impl Fn(&RightData) -> &Key + Send + Sync,
+        _stats: &Self::FullEquiJoinStats,
+    ) -> stream!((Option<LeftData>, Option<RightData>))
+    where
+        Key: Eq + std::hash::Hash + Send + Sync,
+        LeftData: Clone + Send + Sync,
+        RightData: Clone + Send + Sync,
+    {
+        // NOTE: Not optimised at all, but does mantain balance of chunk sizes
+        let left = left.merge_chunks();
+        let right = right.merge_chunks();
+        let mut results = Vec::with_capacity(left.len() + right.len());
+        let mut rights = HashMap::with_capacity(right.len());
+        for (i, r) in right.iter().enumerate() {
+            rights
+                .entry(right_split(r))
+                .or_insert_with(Vec::new)
+                .push(i);
+        }
+        let mut matched = vec![false; right.len()];
+        for l in left {
+            match rights.get(left_split(&l)) {
+                Some(is) => {
+                    for i in is {
+                        matched[*i] = true;
+                        results.push((Some(l.clone()), Some(right[*i].clone())))
+                    }
+                }
+                None => results.push((Some(l), None)),
+            }
+        }
+        for (r, m) in right.into_iter().zip(matched) {
+            if !m {
+                results.push((None, Some(r)))
+            }
+        }
+        ChunkVecs::split_chunks(results.len(), results.into_iter())
+    }
+
+    type LeftPredJoinStats = ();
+    fn left_predicate_join<LeftData, RightData>(
+        left: stream!(LeftData),
+        right: stream!(RightData),
+        pred: impl Fn(&LeftData, &RightData) -> bool + Send + Sync,
+        _stats: &Self::LeftPredJoinStats,
+    ) -> stream!((LeftData, Option<RightData>))
+    where
+        LeftData: Clone + Send + Sync,
+        RightData: Clone + Send + Sync,
+    {
+        // NOTE: Can unbalance the chunk sizes
+        left.chunks
+            .into_par_iter()
+            .map(|ls| {
+                let mut v = Vec::new();
+                for l in ls {
+                    let mut found = false;
+                    for rs in &right.chunks {
+                        for r in rs {
+                            if pred(&l, r) {
+                                found = true;
+                                v.push((l.clone(), Some(r.clone())))
+                            }
+                        }
+                    }
+                    if !found {
+                        v.push((l, None))
+                    }
+                }
+                v
+            })
+            .collect::<Vec<_>>()
+            .into()
+    }
+
+    type FullPredJoinStats = ();
+    fn full_predicate_join<LeftData, RightData>(
+        left: stream!(LeftData),
+        right: stream!(RightData),
+        pred: impl Fn(&LeftData, &RightData) -> bool + Send + Sync,
+        _stats: &Self::FullPredJoinStats,
+    ) -> stream!((Option<LeftData>, Option<RightData>))
+    where
+        LeftData: Clone + Send + Sync,
+        RightData: Clone + Send + Sync,
+    {
+        // NOTE: Not optimised at all, but does mantain balance of chunk sizes
+        let left = left.merge_chunks();
+        let right = right.merge_chunks();
+        let mut matched = vec![false; right.len()];
+        let mut results = Vec::with_capacity(left.len() + right.len());
+        for l in left {
+            let mut found = false;
+            for (r, m) in right.iter().zip(matched.iter_mut()) {
+                if pred(&l, r) {
+                    found = true;
+                    *m = true;
+                    results.push((Some(l.clone()), Some(r.clone())));
+                }
+            }
+            if !found {
+                results.push((Some(l), None));
+            }
+        }
+        for (r, m) in right.into_iter().zip(matched) {
+            if !m {
+                results.push((None, Some(r)))
+            }
+        }
+        ChunkVecs::split_chunks(results.len(), results.into_iter())
+    }
+
     type UnionStats = ();
     fn union<Data>(
         mut left: stream!(Data),
@@ -467,6 +854,42 @@ impl ChunkOps for Chunk {
         left
     }
 
+    type IntersectStats = ();
+    fn intersect<Key, Data>(
+        mut left: stream!(Data),
+        right: stream!(Data),
+        key: impl Fn(&Data) -> Key + Send + Sync,
+        _stats: &Self::IntersectStats,
+    ) -> stream!(Data)
+    where
+        Data: Send + Sync,
+        Key: Eq + std::hash::Hash + Send + Sync,
+    {
+        let mut counts = super::basic::key_counts(right.chunks.iter().flatten().map(&key));
+        for chunk in &mut left.chunks {
+            chunk.retain(|data| super::basic::take_count(&mut counts, &key(data)));
+        }
+        left
+    }
+
+    type ExceptStats = ();
+    fn except<Key, Data>(
+        mut left: stream!(Data),
+        right: stream!(Data),
+        key: impl Fn(&Data) -> Key + Send + Sync,
+        _stats: &Self::ExceptStats,
+    ) -> stream!(Data)
+    where
+        Data: Send + Sync,
+        Key: Eq + std::hash::Hash + Send + Sync,
+    {
+        let mut counts = super::basic::key_counts(right.chunks.iter().flatten().map(&key));
+        for chunk in &mut left.chunks {
+            chunk.retain(|data| !super::basic::take_count(&mut counts, &key(data)));
+        }
+        left
+    }
+
     type ForkStats = ();
     fn fork<Data>(stream: stream!(Data), _stats: &Self::ForkStats) -> (stream!(Data), stream!(Data))
     where
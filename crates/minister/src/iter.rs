@@ -1,4 +1,4 @@
-use rustc_hash::{FxBuildHasher, FxHashMap};
+use rustc_hash::{FxBuildHasher, FxHashMap, FxHashSet};
 macro_rules! single {
     ($data:ty) => {
         $data
@@ -228,6 +228,76 @@ impl IterOps for Iter {
         stream.take(n)
     }
 
+    type SkipStats = ();
+    fn skip<Data>(stream: stream!(Data), n: usize, _stats: &Self::SkipStats) -> stream!(Data)
+    where
+        Data: Send + Sync,
+    {
+        stream.skip(n)
+    }
+
+    type SkipWhileStats = ();
+    fn skip_while<Data>(
+        stream: stream!(Data),
+        pred: impl Fn(&Data) -> bool + Send + Sync,
+        _stats: &Self::SkipWhileStats,
+    ) -> stream!(Data)
+    where
+        Data: Send + Sync,
+    {
+        stream.skip_while(move |data| pred(data))
+    }
+
+    type TopKStats = ();
+    fn top_k<Data>(
+        stream: stream!(Data),
+        ordering: impl Fn(&Data, &Data) -> std::cmp::Ordering + Send + Sync,
+        n: usize,
+        _stats: &Self::TopKStats,
+    ) -> stream!(Data)
+    where
+        Data: Send + Sync,
+    {
+        let mut data = stream.collect::<Vec<_>>();
+        if n < data.len() {
+            data.select_nth_unstable_by(n, &ordering);
+            data.truncate(n);
+        }
+        data.sort_unstable_by(ordering);
+        data.into_iter()
+    }
+
+    type DistinctStats = ();
+    fn distinct<Key, Data>(
+        stream: stream!(Data),
+        key: impl Fn(&Data) -> Key + Send + Sync,
+        _stats: &Self::DistinctStats,
+    ) -> stream!(Data)
+    where
+        Data: Send + Sync,
+        Key: Eq + std::hash::Hash + Send + Sync,
+    {
+        let mut seen = FxHashSet::with_hasher(FxBuildHasher);
+        stream.filter(move |data| seen.insert(key(data)))
+    }
+
+    type DistinctSortedStats = ();
+    fn distinct_sorted<Data>(
+        stream: stream!(Data),
+        same: impl Fn(&Data, &Data) -> bool + Send + Sync,
+        _stats: &Self::DistinctSortedStats,
+    ) -> stream!(Data)
+    where
+        Data: Send + Sync,
+    {
+        let mut stream = stream.peekable();
+        std::iter::from_fn(move || {
+            let data = stream.next()?;
+            while stream.next_if(|next| same(&data, next)).is_some() {}
+            Some(data)
+        })
+    }
+
     type GroupByStats = ();
     fn group_by<Key, Rest, Data>(
         stream: stream!(Data),
@@ -247,6 +317,34 @@ impl IterOps for Iter {
         groups.into_iter().map(|(k, v)| (k, v.into_iter()))
     }
 
+    type WindowStats = ();
+    fn window<Key, State, InData, OutData>(
+        stream: stream!(InData),
+        partition: impl Fn(&InData) -> Key + Send + Sync,
+        ordering: impl Fn(&InData, &InData) -> std::cmp::Ordering + Send + Sync,
+        initial: State,
+        compute: impl Fn(&mut State, bool, InData) -> OutData + Send + Sync,
+        _stats: &Self::WindowStats,
+    ) -> stream!(OutData)
+    where
+        Key: Eq + std::hash::Hash + Send + Sync,
+        State: Clone + Send + Sync,
+        InData: Send + Sync,
+        OutData: Send + Sync,
+    {
+        let groups = Self::group_by(stream, move |data| (partition(&data), data), &());
+        groups.flat_map(move |(_, group)| {
+            let group = Self::sort(group, &ordering, &()).collect::<Vec<_>>();
+            let peers = super::basic::peers(&group, &ordering);
+            let mut state = initial.clone();
+            group
+                .into_iter()
+                .zip(peers)
+                .map(|(data, peer)| compute(&mut state, peer, data))
+                .collect::<Vec<_>>()
+        })
+    }
+
     type CrossJoinStats = ();
     fn cross_join<LeftData, RightData>(
         left: stream!(LeftData),
@@ -360,6 +458,202 @@ impl IterOps for Iter {
         }
     }
 
+    type LeftCrossJoinStats = ();
+    fn left_cross_join<LeftData, RightData>(
+        left: stream!(LeftData),
+        right: stream!(RightData),
+        _stats: &Self::LeftCrossJoinStats,
+    ) -> stream!((LeftData, Option<RightData>))
+    where
+        LeftData: Clone + Send + Sync,
+        RightData: Clone + Send + Sync,
+    {
+        let right_vals = right.collect::<Vec<_>>();
+        let mut result =
+            Vec::with_capacity(right_vals.len().max(1) * left.size_hint().1.unwrap_or(ASSUME_SIZE));
+        for l in left {
+            if right_vals.is_empty() {
+                result.push((l, None));
+            } else {
+                for r in &right_vals {
+                    result.push((l.clone(), Some(r.clone())));
+                }
+            }
+        }
+        result.into_iter()
+    }
+
+    type FullCrossJoinStats = ();
+    fn full_cross_join<LeftData, RightData>(
+        left: stream!(LeftData),
+        right: stream!(RightData),
+        _stats: &Self::FullCrossJoinStats,
+    ) -> stream!((Option<LeftData>, Option<RightData>))
+    where
+        LeftData: Clone + Send + Sync,
+        RightData: Clone + Send + Sync,
+    {
+        let left_vals = left.collect::<Vec<_>>();
+        let right_vals = right.collect::<Vec<_>>();
+        let mut result = Vec::with_capacity(left_vals.len().max(1) * right_vals.len().max(1));
+        if right_vals.is_empty() {
+            result.extend(left_vals.into_iter().map(|l| (Some(l), None)));
+        } else if left_vals.is_empty() {
+            result.extend(right_vals.into_iter().map(|r| (None, Some(r))));
+        } else {
+            for l in left_vals {
+                for r in &right_vals {
+                    result.push((Some(l.clone()), Some(r.clone())));
+                }
+            }
+        }
+        result.into_iter()
+    }
+
+    type LeftEquiJoinStats = ();
+    fn left_equi_join<LeftData, RightData, Key>(
+        left: stream!(LeftData),
+        right: stream!(RightData),
+        left_split: impl Fn(&LeftData) -> &Key + Send + Sync,
+        right_split: impl Fn(&RightData) -> &Key + Send + Sync,
+        _stats: &Self::LeftEquiJoinStats,
+    ) -> stream!((LeftData, Option<RightData>))
+    where
+        Key: Eq + std::hash::Hash + Send + Sync,
+        LeftData: Clone + Send + Sync,
+        RightData: Clone + Send + Sync,
+    {
+        let mut results = Vec::with_capacity(get_side_size(left.size_hint().1));
+        let right = right.collect::<Vec<_>>();
+        let mut rights = FxHashMap::with_capacity_and_hasher(right.len(), FxBuildHasher);
+        for r in &right {
+            rights
+                .entry(right_split(r))
+                .or_insert_with(Vec::new)
+                .push(r);
+        }
+        for l in left {
+            match rights.get(left_split(&l)) {
+                Some(rs) => {
+                    for r in rs {
+                        results.push((l.clone(), Some((*r).clone())))
+                    }
+                }
+                None => results.push((l, None)),
+            }
+        }
+        results.into_iter()
+    }
+
+    type FullEquiJoinStats = ();
+    fn full_equi_join<LeftData, RightData, Key>(
+        left: stream!(LeftData),
+        right: stream!(RightData),
+        left_split: impl Fn(&LeftData) -> &Key + Send + Sync,
+        right_split: impl Fn(&RightData) -> &Key + Send + Sync,
+        _stats: &Self::FullEquiJoinStats,
+    ) -> stream!((Option<LeftData>, Option<RightData>))
+    where
+        Key: Eq + std::hash::Hash + Send + Sync,
+        LeftData: Clone + Send + Sync,
+        RightData: Clone + Send + Sync,
+    {
+        let right = right.collect::<Vec<_>>();
+        let mut results = Vec::with_capacity(get_side_size(left.size_hint().1) + right.len());
+        let mut rights = FxHashMap::with_capacity_and_hasher(right.len(), FxBuildHasher);
+        for (i, r) in right.iter().enumerate() {
+            rights
+                .entry(right_split(r))
+                .or_insert_with(Vec::new)
+                .push(i);
+        }
+        let mut matched = vec![false; right.len()];
+        for l in left {
+            match rights.get(left_split(&l)) {
+                Some(is) => {
+                    for i in is {
+                        matched[*i] = true;
+                        results.push((Some(l.clone()), Some(right[*i].clone())))
+                    }
+                }
+                None => results.push((Some(l), None)),
+            }
+        }
+        results.extend(right.into_iter().zip(matched).filter_map(|(r, m)| {
+            if m {
+                None
+            } else {
+                Some((None, Some(r)))
+            }
+        }));
+        results.into_iter()
+    }
+
+    type LeftPredJoinStats = ();
+    fn left_predicate_join<LeftData, RightData>(
+        left: stream!(LeftData),
+        right: stream!(RightData),
+        pred: impl Fn(&LeftData, &RightData) -> bool + Send + Sync,
+        _stats: &Self::LeftPredJoinStats,
+    ) -> stream!((LeftData, Option<RightData>))
+    where
+        LeftData: Clone + Send + Sync,
+        RightData: Clone + Send + Sync,
+    {
+        let right = right.collect::<Vec<_>>();
+        let mut results = Vec::with_capacity(get_side_size(left.size_hint().1));
+        for l in left {
+            let mut found = false;
+            for r in &right {
+                if pred(&l, r) {
+                    found = true;
+                    results.push((l.clone(), Some(r.clone())));
+                }
+            }
+            if !found {
+                results.push((l, None));
+            }
+        }
+        results.into_iter()
+    }
+
+    type FullPredJoinStats = ();
+    fn full_predicate_join<LeftData, RightData>(
+        left: stream!(LeftData),
+        right: stream!(RightData),
+        pred: impl Fn(&LeftData, &RightData) -> bool + Send + Sync,
+        _stats: &Self::FullPredJoinStats,
+    ) -> stream!((Option<LeftData>, Option<RightData>))
+    where
+        LeftData: Clone + Send + Sync,
+        RightData: Clone + Send + Sync,
+    {
+        let right = right.collect::<Vec<_>>();
+        let mut matched = vec![false; right.len()];
+        let mut results = Vec::with_capacity(get_side_size(left.size_hint().1) + right.len());
+        for l in left {
+            let mut found = false;
+            for (r, m) in right.iter().zip(matched.iter_mut()) {
+                if pred(&l, r) {
+                    found = true;
+                    *m = true;
+                    results.push((Some(l.clone()), Some(r.clone())));
+                }
+            }
+            if !found {
+                results.push((Some(l), None));
+            }
+        }
+        results.extend(right.into_iter().zip(matched).filter_map(|(r, m)| {
+            if m {
+                None
+            } else {
+                Some((None, Some(r)))
+            }
+        }));
+        results.into_iter()
+    }
+
     type UnionStats = ();
     fn union<Data>(
         left: stream!(Data),
@@ -372,6 +666,36 @@ impl IterOps for Iter {
         left.chain(right)
     }
 
+    type IntersectStats = ();
+    fn intersect<Key, Data>(
+        left: stream!(Data),
+        right: stream!(Data),
+        key: impl Fn(&Data) -> Key + Send + Sync,
+        _stats: &Self::IntersectStats,
+    ) -> stream!(Data)
+    where
+        Data: Send + Sync,
+        Key: Eq + std::hash::Hash + Send + Sync,
+    {
+        let mut counts = super::basic::key_counts(right.map(|data| key(&data)));
+        left.filter(move |data| super::basic::take_count(&mut counts, &key(data)))
+    }
+
+    type ExceptStats = ();
+    fn except<Key, Data>(
+        left: stream!(Data),
+        right: stream!(Data),
+        key: impl Fn(&Data) -> Key + Send + Sync,
+        _stats: &Self::ExceptStats,
+    ) -> stream!(Data)
+    where
+        Data: Send + Sync,
+        Key: Eq + std::hash::Hash + Send + Sync,
+    {
+        let mut counts = super::basic::key_counts(right.map(|data| key(&data)));
+        left.filter(move |data| !super::basic::take_count(&mut counts, &key(data)))
+    }
+
     type ForkStats = ();
     fn fork<Data>(stream: stream!(Data), _stats: &Self::ForkStats) -> (stream!(Data), stream!(Data))
     where
@@ -218,6 +218,61 @@ macro_rules! generate_minister_trait {
             where
                 Data: Send + Sync;
 
+            /// Removes the first `n` data from the stream.
+            type SkipStats: Sync + Default;
+            fn skip<Data>(
+                stream: stream!(Data),
+                n: usize,
+                stats: &Self::SkipStats,
+            ) -> stream!(Data)
+            where
+                Data: Send + Sync;
+
+            /// Removes data from the start of the stream until the predicate
+            /// is false.
+            type SkipWhileStats: Sync + Default;
+            fn skip_while<Data>(
+                stream: stream!(Data),
+                pred: impl Fn(&Data) -> bool + Send + Sync,
+                stats: &Self::SkipWhileStats,
+            ) -> stream!(Data)
+            where
+                Data: Send + Sync;
+
+            /// Equivalent to a [`sort`](Self::sort) followed by a [`take`](Self::take),
+            /// but without needing to sort the entire stream.
+            type TopKStats: Sync + Default;
+            fn top_k<Data>(
+                stream: stream!(Data),
+                ordering: impl Fn(&Data, &Data) -> std::cmp::Ordering + Send + Sync,
+                n: usize,
+                stats: &Self::TopKStats,
+            ) -> stream!(Data)
+            where
+                Data: Send + Sync;
+
+            /// Removes all but the first of the data with the same key.
+            type DistinctStats: Sync + Default;
+            fn distinct<Key, Data>(
+                stream: stream!(Data),
+                key: impl Fn(&Data) -> Key + Send + Sync,
+                stats: &Self::DistinctStats,
+            ) -> stream!(Data)
+            where
+                Data: Send + Sync,
+                Key: Eq + std::hash::Hash + Send + Sync;
+
+            /// Equivalent to a [`distinct`](Self::distinct) for a stream where
+            /// the same data are adjacent (e.g. sorted), but without hashing.
+            type DistinctSortedStats: Sync + Default;
+            fn distinct_sorted<Data>(
+                stream: stream!(Data),
+                same: impl Fn(&Data, &Data) -> bool + Send + Sync,
+                stats: &Self::DistinctSortedStats,
+            ) -> stream!(Data)
+            where
+                Data: Send + Sync;
+
             type GroupByStats: Sync + Default;
             fn group_by<Key, Rest, Data>(
                 stream: stream!(Data),
@@ -229,6 +284,27 @@ macro_rules! generate_minister_trait {
                 Key: Eq + std::hash::Hash + Send + Sync,
                 Rest: Send + Sync;
 
+            /// Partitions the stream by key (as [`group_by`](Self::group_by)),
+            /// sorts each partition (as [`sort`](Self::sort)) and maps each
+            /// data in order, with a state (starting from `initial` for each
+            /// partition) and if the data has the same ordering as the previous
+            /// data in the partition.
+            /// - The partitions are not in any particular order.
+            type WindowStats: Sync + Default;
+            fn window<Key, State, InData, OutData>(
+                stream: stream!(InData),
+                partition: impl Fn(&InData) -> Key + Send + Sync,
+                ordering: impl Fn(&InData, &InData) -> std::cmp::Ordering + Send + Sync,
+                initial: State,
+                compute: impl Fn(&mut State, bool, InData) -> OutData + Send + Sync,
+                stats: &Self::WindowStats,
+            ) -> stream!(OutData)
+            where
+                Key: Eq + std::hash::Hash + Send + Sync,
+                State: Clone + Send + Sync,
+                InData: Send + Sync,
+                OutData: Send + Sync;
+
             type CrossJoinStats: Sync + Default;
             fn cross_join<LeftData, RightData>(
                 left: stream!(LeftData),
@@ -263,6 +339,78 @@ macro_rules! generate_minister_trait {
                 LeftData: Clone + Send + Sync,
                 RightData: Clone + Send + Sync;
 
+            // Outer joins
+            // - Right outer joins are not included, they are the left outer join with
+            //   the inputs swapped.
+
+            type LeftCrossJoinStats: Sync + Default;
+            fn left_cross_join<LeftData, RightData>(
+                left: stream!(LeftData),
+                right: stream!(RightData),
+                stats: &Self::LeftCrossJoinStats,
+            ) -> stream!((LeftData, Option<RightData>))
+            where
+                LeftData: Clone + Send + Sync,
+                RightData: Clone + Send + Sync;
+
+            type FullCrossJoinStats: Sync + Default;
+            fn full_cross_join<LeftData, RightData>(
+                left: stream!(LeftData),
+                right: stream!(RightData),
+                stats: &Self::FullCrossJoinStats,
+            ) -> stream!((Option<LeftData>, Option<RightData>))
+            where
+                LeftData: Clone + Send + Sync,
+                RightData: Clone + Send + Sync;
+
+            type LeftEquiJoinStats: Sync + Default;
+            fn left_equi_join<LeftData, RightData, Key>(
+                left: stream!(LeftData),
+                right: stream!(RightData),
+                left_split: impl Fn(&LeftData) -> &Key + Send + Sync,
+                right_split: impl Fn(&RightData) -> &Key + Send + Sync,
+                stats: &Self::LeftEquiJoinStats,
+            ) -> stream!((LeftData, Option<RightData>))
+            where
+                Key: Eq + std::hash::Hash + Send + Sync,
+                LeftData: Clone + Send + Sync,
+                RightData: Clone + Send + Sync;
+
+            type FullEquiJoinStats: Sync + Default;
+            fn full_equi_join<LeftData, RightData, Key>(
+                left: stream!(LeftData),
+                right: stream!(RightData),
+                left_split: impl Fn(&LeftData) -> &Key + Send + Sync,
+                right_split: impl Fn(&RightData) -> &Key + Send + Sync,
+                stats: &Self::FullEquiJoinStats,
+            ) -> stream!((Option<LeftData>, Option<RightData>))
+            where
+                Key: Eq + std::hash::Hash + Send + Sync,
+                LeftData: Clone + Send + Sync,
+                RightData: Clone + Send + Sync;
+
+            type LeftPredJoinStats: Sync + Default;
+            fn left_predicate_join<LeftData, RightData>(
+                left: stream!(LeftData),
+                right: stream!(RightData),
+                pred: impl Fn(&LeftData, &RightData) -> bool + Send + Sync,
+                stats: &Self::LeftPredJoinStats,
+            ) -> stream!((LeftData, Option<RightData>))
+            where
+                LeftData: Clone + Send + Sync,
+                RightData: Clone + Send + Sync;
+
+            type FullPredJoinStats: Sync + Default;
+            fn full_predicate_join<LeftData, RightData>(
+                left: stream!(LeftData),
+                right: stream!(RightData),
+                pred: impl Fn(&LeftData, &RightData) -> bool + Send + Sync,
+                stats: &Self::FullPredJoinStats,
+            ) -> stream!((Option<LeftData>, Option<RightData>))
+            where
+                LeftData: Clone + Send + Sync,
+                RightData: Clone + Send + Sync;
+
             type UnionStats: Sync + Default;
             fn union<Data>(
                 left: stream!(Data),
@@ -272,6 +420,32 @@ macro_rules! generate_minister_trait {
             where
                 Data: Send + Sync;
 
+            /// Keep the data from `left` with a matching key in `right`, each
+            /// data in `right` can only match one in `left` (bag semantics).
+            type IntersectStats: Sync + Default;
+            fn intersect<Key, Data>(
+                left: stream!(Data),
+                right: stream!(Data),
+                key: impl Fn(&Data) -> Key + Send + Sync,
+                stats: &Self::IntersectStats,
+            ) -> stream!(Data)
+            where
+                Data: Send + Sync,
+                Key: Eq + std::hash::Hash + Send + Sync;
+
+            /// Remove the data from `left` with a matching key in `right`, each
+            /// data in `right` can only remove one in `left` (bag semantics).
+            type ExceptStats: Sync + Default;
+            fn except<Key, Data>(
+                left: stream!(Data),
+                right: stream!(Data),
+                key: impl Fn(&Data) -> Key + Send + Sync,
+                stats: &Self::ExceptStats,
+            ) -> stream!(Data)
+            where
+                Data: Send + Sync,
+                Key: Eq + std::hash::Hash + Send + Sync;
+
             type ForkStats: Sync + Default;
             fn fork<Data>(
                 stream: stream!(Data),
@@ -299,3 +473,318 @@ macro_rules! generate_minister_trait {
         }
     };
 }
+
+/// Each operator implementation is checked against the same expected outputs.
+#[cfg(test)]
+mod tests {
+    macro_rules! test_ops {
+        ($name:ident, $ops:ty, $ops_trait:path) => {
+            mod $name {
+                use $ops_trait;
+                type Ops = $ops;
+
+                fn stream<Data: Send + Sync>(data: Vec<Data>) -> impl Iterator<Item = Data> {
+                    data.into_iter()
+                }
+
+                /// Joins do not guarantee an output order.
+                fn sorted<Data: Ord>(data: impl Iterator<Item = Data>) -> Vec<Data> {
+                    let mut data = data.collect::<Vec<_>>();
+                    data.sort();
+                    data
+                }
+
+                #[test]
+                fn distinct() {
+                    let data = vec![(1, 'a'), (2, 'b'), (1, 'c'), (3, 'd'), (2, 'e')];
+                    let out = Ops::export_stream(Ops::distinct(
+                        Ops::consume_stream(stream(data)),
+                        |(key, _)| *key,
+                        &Default::default(),
+                    ))
+                    .collect::<Vec<_>>();
+                    assert_eq!(out, vec![(1, 'a'), (2, 'b'), (3, 'd')]);
+                }
+
+                #[test]
+                fn distinct_sorted() {
+                    let data = vec![(1, 'a'), (1, 'b'), (2, 'c'), (3, 'd'), (3, 'e')];
+                    let out = Ops::export_stream(Ops::distinct_sorted(
+                        Ops::consume_stream(stream(data)),
+                        |(a, _), (b, _)| a == b,
+                        &Default::default(),
+                    ))
+                    .collect::<Vec<_>>();
+                    assert_eq!(out, vec![(1, 'a'), (2, 'c'), (3, 'd')]);
+                }
+
+                #[test]
+                fn window() {
+                    // rank within each partition, with equal values ranked the same
+                    let data = vec![('a', 3), ('b', 1), ('a', 1), ('a', 3), ('b', 2), ('a', 2)];
+                    let mut out = Ops::export_stream(Ops::window(
+                        Ops::consume_stream(stream(data)),
+                        |(part, _)| *part,
+                        |(_, a), (_, b)| a.cmp(b),
+                        (0, 0),
+                        |(count, rank), peer, (part, value)| {
+                            *count += 1;
+                            if !peer {
+                                *rank = *count;
+                            }
+                            (part, value, *rank)
+                        },
+                        &Default::default(),
+                    ))
+                    .collect::<Vec<_>>();
+                    out.sort();
+                    assert_eq!(
+                        out,
+                        vec![
+                            ('a', 1, 1),
+                            ('a', 2, 2),
+                            ('a', 3, 3),
+                            ('a', 3, 3),
+                            ('b', 1, 1),
+                            ('b', 2, 2)
+                        ]
+                    );
+                }
+
+                #[test]
+                fn intersect() {
+                    let out = Ops::export_stream(Ops::intersect(
+                        Ops::consume_stream(stream(vec![1, 2, 2, 2, 3, 4])),
+                        Ops::consume_stream(stream(vec![2, 4, 2, 5])),
+                        |x| *x,
+                        &Default::default(),
+                    ))
+                    .collect::<Vec<_>>();
+                    assert_eq!(out, vec![2, 2, 4]);
+                }
+
+                #[test]
+                fn except() {
+                    let out = Ops::export_stream(Ops::except(
+                        Ops::consume_stream(stream(vec![1, 2, 2, 2, 3, 4])),
+                        Ops::consume_stream(stream(vec![2, 4, 2, 5])),
+                        |x| *x,
+                        &Default::default(),
+                    ))
+                    .collect::<Vec<_>>();
+                    assert_eq!(out, vec![1, 2, 3]);
+                }
+
+                #[test]
+                fn left_cross_join() {
+                    let left_cross = |left: Vec<i32>, right: Vec<char>| {
+                        sorted(Ops::export_stream(Ops::left_cross_join(
+                            Ops::consume_stream(stream(left)),
+                            Ops::consume_stream(stream(right)),
+                            &Default::default(),
+                        )))
+                    };
+                    assert_eq!(
+                        left_cross(vec![1, 2], vec!['a', 'b']),
+                        vec![
+                            (1, Some('a')),
+                            (1, Some('b')),
+                            (2, Some('a')),
+                            (2, Some('b'))
+                        ]
+                    );
+                    assert_eq!(left_cross(vec![1, 2], vec![]), vec![(1, None), (2, None)]);
+                    assert_eq!(left_cross(vec![], vec!['a']), vec![]);
+                }
+
+                #[test]
+                fn full_cross_join() {
+                    let full_cross = |left: Vec<i32>, right: Vec<char>| {
+                        sorted(Ops::export_stream(Ops::full_cross_join(
+                            Ops::consume_stream(stream(left)),
+                            Ops::consume_stream(stream(right)),
+                            &Default::default(),
+                        )))
+                    };
+                    assert_eq!(
+                        full_cross(vec![1, 2], vec!['a']),
+                        vec![(Some(1), Some('a')), (Some(2), Some('a'))]
+                    );
+                    assert_eq!(
+                        full_cross(vec![1, 2], vec![]),
+                        vec![(Some(1), None), (Some(2), None)]
+                    );
+                    assert_eq!(
+                        full_cross(vec![], vec!['a', 'b']),
+                        vec![(None, Some('a')), (None, Some('b'))]
+                    );
+                    assert_eq!(full_cross(vec![], vec![]), vec![]);
+                }
+
+                #[test]
+                fn left_equi_join() {
+                    let left_equi = |left: Vec<(i32, char)>, right: Vec<(i32, char)>| {
+                        sorted(Ops::export_stream(Ops::left_equi_join(
+                            Ops::consume_stream(stream(left)),
+                            Ops::consume_stream(stream(right)),
+                            |(key, _)| key,
+                            |(key, _)| key,
+                            &Default::default(),
+                        )))
+                    };
+                    // duplicate keys on both sides produce every pair
+                    assert_eq!(
+                        left_equi(
+                            vec![(1, 'a'), (1, 'b'), (2, 'c'), (3, 'd')],
+                            vec![(1, 'x'), (1, 'y'), (2, 'z'), (4, 'w')]
+                        ),
+                        vec![
+                            ((1, 'a'), Some((1, 'x'))),
+                            ((1, 'a'), Some((1, 'y'))),
+                            ((1, 'b'), Some((1, 'x'))),
+                            ((1, 'b'), Some((1, 'y'))),
+                            ((2, 'c'), Some((2, 'z'))),
+                            ((3, 'd'), None),
+                        ]
+                    );
+                    assert_eq!(
+                        left_equi(vec![(1, 'a'), (2, 'b')], vec![(3, 'x')]),
+                        vec![((1, 'a'), None), ((2, 'b'), None)]
+                    );
+                    assert_eq!(left_equi(vec![(1, 'a')], vec![]), vec![((1, 'a'), None)]);
+                    assert_eq!(left_equi(vec![], vec![(1, 'x')]), vec![]);
+                }
+
+                #[test]
+                fn full_equi_join() {
+                    let full_equi = |left: Vec<(i32, char)>, right: Vec<(i32, char)>| {
+                        sorted(Ops::export_stream(Ops::full_equi_join(
+                            Ops::consume_stream(stream(left)),
+                            Ops::consume_stream(stream(right)),
+                            |(key, _)| key,
+                            |(key, _)| key,
+                            &Default::default(),
+                        )))
+                    };
+                    assert_eq!(
+                        full_equi(
+                            vec![(1, 'a'), (1, 'b'), (2, 'c'), (3, 'd')],
+                            vec![(1, 'x'), (1, 'y'), (2, 'z'), (4, 'w')]
+                        ),
+                        vec![
+                            (None, Some((4, 'w'))),
+                            (Some((1, 'a')), Some((1, 'x'))),
+                            (Some((1, 'a')), Some((1, 'y'))),
+                            (Some((1, 'b')), Some((1, 'x'))),
+                            (Some((1, 'b')), Some((1, 'y'))),
+                            (Some((2, 'c')), Some((2, 'z'))),
+                            (Some((3, 'd')), None),
+                        ]
+                    );
+                    assert_eq!(
+                        full_equi(vec![(1, 'a')], vec![(2, 'x'), (2, 'y')]),
+                        vec![
+                            (None, Some((2, 'x'))),
+                            (None, Some((2, 'y'))),
+                            (Some((1, 'a')), None)
+                        ]
+                    );
+                    assert_eq!(
+                        full_equi(vec![(1, 'a')], vec![]),
+                        vec![(Some((1, 'a')), None)]
+                    );
+                    assert_eq!(
+                        full_equi(vec![], vec![(1, 'x')]),
+                        vec![(None, Some((1, 'x')))]
+                    );
+                }
+
+                #[test]
+                fn left_predicate_join() {
+                    let left_pred = |left: Vec<i32>, right: Vec<i32>| {
+                        sorted(Ops::export_stream(Ops::left_predicate_join(
+                            Ops::consume_stream(stream(left)),
+                            Ops::consume_stream(stream(right)),
+                            |l, r| l < r,
+                            &Default::default(),
+                        )))
+                    };
+                    assert_eq!(
+                        left_pred(vec![1, 3, 5], vec![2, 4]),
+                        vec![(1, Some(2)), (1, Some(4)), (3, Some(4)), (5, None)]
+                    );
+                    assert_eq!(left_pred(vec![5, 6], vec![1]), vec![(5, None), (6, None)]);
+                    assert_eq!(left_pred(vec![1], vec![]), vec![(1, None)]);
+                    assert_eq!(left_pred(vec![], vec![1]), vec![]);
+                }
+
+                #[test]
+                fn full_predicate_join() {
+                    let full_pred = |left: Vec<i32>, right: Vec<i32>| {
+                        sorted(Ops::export_stream(Ops::full_predicate_join(
+                            Ops::consume_stream(stream(left)),
+                            Ops::consume_stream(stream(right)),
+                            |l, r| l < r,
+                            &Default::default(),
+                        )))
+                    };
+                    assert_eq!(
+                        full_pred(vec![1, 3, 5], vec![0, 2, 4]),
+                        vec![
+                            (None, Some(0)),
+                            (Some(1), Some(2)),
+                            (Some(1), Some(4)),
+                            (Some(3), Some(4)),
+                            (Some(5), None)
+                        ]
+                    );
+                    assert_eq!(
+                        full_pred(vec![5], vec![1, 2]),
+                        vec![(None, Some(1)), (None, Some(2)), (Some(5), None)]
+                    );
+                    assert_eq!(full_pred(vec![1], vec![]), vec![(Some(1), None)]);
+                    assert_eq!(full_pred(vec![], vec![1]), vec![(None, Some(1))]);
+                }
+
+                #[test]
+                fn skip() {
+                    let out = Ops::export_stream(Ops::skip(
+                        Ops::consume_stream(stream((0..10).collect())),
+                        7,
+                        &Default::default(),
+                    ))
+                    .collect::<Vec<_>>();
+                    assert_eq!(out, vec![7, 8, 9]);
+
+                    let out = Ops::export_stream(Ops::skip(
+                        Ops::consume_stream(stream((0..10).collect())),
+                        20,
+                        &Default::default(),
+                    ))
+                    .count();
+                    assert_eq!(out, 0);
+                }
+
+                #[test]
+                fn skip_while() {
+                    let out = Ops::export_stream(Ops::skip_while(
+                        Ops::consume_stream(stream(vec![1, 2, 5, 1, 6])),
+                        |x| *x < 3,
+                        &Default::default(),
+                    ))
+                    .collect::<Vec<_>>();
+                    assert_eq!(out, vec![5, 1, 6]);
+                }
+            }
+        };
+    }
+
+    test_ops!(basic, crate::basic::Basic, crate::basic::BasicOps);
+    test_ops!(iter, crate::iter::Iter, crate::iter::IterOps);
+    test_ops!(
+        parallel,
+        crate::parallel::Parallel,
+        crate::parallel::ParallelOps
+    );
+    test_ops!(chunk, crate::chunk::Chunk, crate::chunk::ChunkOps);
+}
@@ -1,5 +1,5 @@
 use rayon::prelude::*;
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 
 macro_rules! single {
     ($data:ty) => {
@@ -225,6 +225,81 @@ impl ParallelOps for Parallel {
         values.into_par_iter()
     }
 
+    type SkipStats = ();
+    fn skip<Data>(stream: stream!(Data), n: usize, _stats: &Self::SkipStats) -> stream!(Data)
+    where
+        Data: Send + Sync,
+    {
+        stream.collect::<Vec<_>>().into_par_iter().skip(n)
+    }
+
+    type SkipWhileStats = ();
+    fn skip_while<Data>(
+        stream: stream!(Data),
+        pred: impl Fn(&Data) -> bool + Send + Sync,
+        _stats: &Self::SkipWhileStats,
+    ) -> stream!(Data)
+    where
+        Data: Send + Sync,
+    {
+        let values = stream.collect::<Vec<_>>();
+        let skipped = values
+            .par_iter()
+            .position_first(|data| !pred(data))
+            .unwrap_or(values.len());
+        values.into_par_iter().skip(skipped)
+    }
+
+    type TopKStats = ();
+    fn top_k<Data>(
+        stream: stream!(Data),
+        ordering: impl Fn(&Data, &Data) -> std::cmp::Ordering + Send + Sync,
+        n: usize,
+        _stats: &Self::TopKStats,
+    ) -> stream!(Data)
+    where
+        Data: Send + Sync,
+    {
+        let mut data = stream.collect::<Vec<_>>();
+        if n < data.len() {
+            data.select_nth_unstable_by(n, &ordering);
+            data.truncate(n);
+        }
+        data.par_sort_unstable_by(ordering);
+        data.into_par_iter()
+    }
+
+    type DistinctStats = ();
+    fn distinct<Key, Data>(
+        stream: stream!(Data),
+        key: impl Fn(&Data) -> Key + Send + Sync,
+        _stats: &Self::DistinctStats,
+    ) -> stream!(Data)
+    where
+        Data: Send + Sync,
+        Key: Eq + std::hash::Hash + Send + Sync,
+    {
+        // can improve parallelism
+        let mut seen = HashSet::new();
+        let mut data = stream.collect::<Vec<_>>();
+        data.retain(|data| seen.insert(key(data)));
+        data.into_par_iter()
+    }
+
+    type DistinctSortedStats = ();
+    fn distinct_sorted<Data>(
+        stream: stream!(Data),
+        same: impl Fn(&Data, &Data) -> bool + Send + Sync,
+        _stats: &Self::DistinctSortedStats,
+    ) -> stream!(Data)
+    where
+        Data: Send + Sync,
+    {
+        let mut data = stream.collect::<Vec<_>>();
+        data.dedup_by(|next, prev| same(prev, next));
+        data.into_par_iter()
+    }
+
     type GroupByStats = ();
     fn group_by<Key, Rest, Data>(
         stream: stream!(Data),
@@ -245,6 +320,34 @@ impl ParallelOps for Parallel {
         groups.into_par_iter().map(|(k, v)| (k, v.into_par_iter()))
     }
 
+    type WindowStats = ();
+    fn window<Key, State, InData, OutData>(
+        stream: stream!(InData),
+        partition: impl Fn(&InData) -> Key + Send + Sync,
+        ordering: impl Fn(&InData, &InData) -> std::cmp::Ordering + Send + Sync,
+        initial: State,
+        compute: impl Fn(&mut State, bool, InData) -> OutData + Send + Sync,
+        _stats: &Self::WindowStats,
+    ) -> stream!(OutData)
+    where
+        Key: Eq + std::hash::Hash + Send + Sync,
+        State: Clone + Send + Sync,
+        InData: Send + Sync,
+        OutData: Send + Sync,
+    {
+        let groups = Self::group_by(stream, move |data| (partition(&data), data), &());
+        groups.flat_map_iter(move |(_, group)| {
+            let group = Self::sort(group, &ordering, &()).collect::<Vec<_>>();
+            let peers = super::basic::peers(&group, &ordering);
+            let mut state = initial.clone();
+            group
+                .into_iter()
+                .zip(peers)
+                .map(|(data, peer)| compute(&mut state, peer, data))
+                .collect::<Vec<_>>()
+        })
+    }
+
     type CrossJoinStats = ();
     fn cross_join<LeftData, RightData>(
         left: stream!(LeftData),
@@ -348,6 +451,204 @@ impl ParallelOps for Parallel {
             .into_par_iter()
     }
 
+    type LeftCrossJoinStats = ();
+    fn left_cross_join<LeftData, RightData>(
+        left: stream!(LeftData),
+        right: stream!(RightData),
+        _stats: &Self::LeftCrossJoinStats,
+    ) -> stream!((LeftData, Option<RightData>))
+    where
+        LeftData: Clone + Send + Sync,
+        RightData: Clone + Send + Sync,
+    {
+        let right = right.collect::<Vec<_>>();
+        if right.is_empty() {
+            left.map(|l| (l, None)).collect::<Vec<_>>().into_par_iter()
+        } else {
+            left.map(|l| right.par_iter().map(move |r| (l.clone(), Some(r.clone()))))
+                .flatten()
+                .collect::<Vec<_>>()
+                .into_par_iter()
+        }
+    }
+
+    type FullCrossJoinStats = ();
+    fn full_cross_join<LeftData, RightData>(
+        left: stream!(LeftData),
+        right: stream!(RightData),
+        _stats: &Self::FullCrossJoinStats,
+    ) -> stream!((Option<LeftData>, Option<RightData>))
+    where
+        LeftData: Clone + Send + Sync,
+        RightData: Clone + Send + Sync,
+    {
+        let left = left.collect::<Vec<_>>();
+        let right = right.collect::<Vec<_>>();
+        if right.is_empty() {
+            left.into_par_iter()
+                .map(|l| (Some(l), None))
+                .collect::<Vec<_>>()
+                .into_par_iter()
+        } else if left.is_empty() {
+            right
+                .into_par_iter()
+                .map(|r| (None, Some(r)))
+                .collect::<Vec<_>>()
+                .into_par_iter()
+        } else {
+            left.par_iter()
+                .map(|l| {
+                    right
+                        .par_iter()
+                        .map(move |r| (Some(l.clone()), Some(r.clone())))
+                })
+                .flatten()
+                .collect::<Vec<_>>()
+                .into_par_iter()
+        }
+    }
+
+    type LeftEquiJoinStats = ();
+    fn left_equi_join<LeftData, RightData, Key>(
+        left: stream!(LeftData),
+        right: stream!(RightData),
+        left_split: impl Fn(&LeftData) -> &Key + Send + Sync,
+        right_split: impl Fn(&RightData) -> &Key + Send + Sync,
+        _stats: &Self::LeftEquiJoinStats,
+    ) -> stream!((LeftData, Option<RightData>))
+    where
+        Key: Eq + std::hash::Hash + Send + Sync,
+        LeftData: Clone + Send + Sync,
+        RightData: Clone + Send + Sync,
+    {
+        let right = right.collect::<Vec<_>>();
+        let mut rights = HashMap::with_capacity(right.len());
+        for r in &right {
+            rights
+                .entry(right_split(r))
+                .or_insert_with(Vec::new)
+                .push(r);
+        }
+        left.flat_map_iter(|l| match rights.get(left_split(&l)) {
+            Some(rs) => rs
+                .iter()
+                .map(|r| (l.clone(), Some((*r).clone())))
+                .collect::<Vec<_>>(),
+            None => vec![(l, None)],
+        })
+        .collect::<Vec<_>>()
+        .into_par_iter()
+    }
+
+    type FullEquiJoinStats = ();
+    fn full_equi_join<LeftData, RightData, Key>(
+        left: stream!(LeftData),
+        right: stream!(RightData),
+        left_split: impl Fn(&LeftData) -> &Key + Send + Sync,
+        right_split: impl Fn(&RightData) -> &Key + Send + Sync,
+        _stats: &Self::FullEquiJoinStats,
+    ) -> stream!((Option<LeftData>, Option<RightData>))
+    where
+        Key: Eq + std::hash::Hash + Send + Sync,
+        LeftData: Clone + Send + Sync,
+        RightData: Clone + Send + Sync,
+    {
+        let left = left.collect::<Vec<_>>();
+        let right = right.collect::<Vec<_>>();
+        let mut rights = HashMap::with_capacity(right.len());
+        for r in &right {
+            rights
+                .entry(right_split(r))
+                .or_insert_with(Vec::new)
+                .push(r);
+        }
+        let left_keys = left.iter().map(&left_split).collect::<HashSet<_>>();
+
+        let left_results = left
+            .par_iter()
+            .flat_map_iter(|l| match rights.get(left_split(l)) {
+                Some(rs) => rs
+                    .iter()
+                    .map(|r| (Some(l.clone()), Some((*r).clone())))
+                    .collect::<Vec<_>>(),
+                None => vec![(Some(l.clone()), None)],
+            });
+        let right_results = right
+            .par_iter()
+            .filter(|r| !left_keys.contains(right_split(r)))
+            .map(|r| (None, Some(r.clone())));
+
+        left_results
+            .chain(right_results)
+            .collect::<Vec<_>>()
+            .into_par_iter()
+    }
+
+    type LeftPredJoinStats = ();
+    fn left_predicate_join<LeftData, RightData>(
+        left: stream!(LeftData),
+        right: stream!(RightData),
+        pred: impl Fn(&LeftData, &RightData) -> bool + Send + Sync,
+        _stats: &Self::LeftPredJoinStats,
+    ) -> stream!((LeftData, Option<RightData>))
+    where
+        LeftData: Clone + Send + Sync,
+        RightData: Clone + Send + Sync,
+    {
+        let right = right.collect::<Vec<_>>();
+        left.flat_map_iter(|l| {
+            let matches = right
+                .iter()
+                .filter(|r| pred(&l, r))
+                .map(|r| (l.clone(), Some(r.clone())))
+                .collect::<Vec<_>>();
+            if matches.is_empty() {
+                vec![(l, None)]
+            } else {
+                matches
+            }
+        })
+        .collect::<Vec<_>>()
+        .into_par_iter()
+    }
+
+    type FullPredJoinStats = ();
+    fn full_predicate_join<LeftData, RightData>(
+        left: stream!(LeftData),
+        right: stream!(RightData),
+        pred: impl Fn(&LeftData, &RightData) -> bool + Send + Sync,
+        _stats: &Self::FullPredJoinStats,
+    ) -> stream!((Option<LeftData>, Option<RightData>))
+    where
+        LeftData: Clone + Send + Sync,
+        RightData: Clone + Send + Sync,
+    {
+        let left = left.collect::<Vec<_>>();
+        let right = right.collect::<Vec<_>>();
+
+        let left_results = left.par_iter().flat_map_iter(|l| {
+            let matches = right
+                .iter()
+                .filter(|r| pred(l, r))
+                .map(|r| (Some(l.clone()), Some(r.clone())))
+                .collect::<Vec<_>>();
+            if matches.is_empty() {
+                vec![(Some(l.clone()), None)]
+            } else {
+                matches
+            }
+        });
+        let right_results = right
+            .par_iter()
+            .filter(|r| !left.iter().any(|l| pred(l, r)))
+            .map(|r| (None, Some(r.clone())));
+
+        left_results
+            .chain(right_results)
+            .collect::<Vec<_>>()
+            .into_par_iter()
+    }
+
     type UnionStats = ();
     fn union<Data>(
         left: stream!(Data),
@@ -360,6 +661,42 @@ impl ParallelOps for Parallel {
         left.chain(right)
     }
 
+    type IntersectStats = ();
+    fn intersect<Key, Data>(
+        left: stream!(Data),
+        right: stream!(Data),
+        key: impl Fn(&Data) -> Key + Send + Sync,
+        _stats: &Self::IntersectStats,
+    ) -> stream!(Data)
+    where
+        Data: Send + Sync,
+        Key: Eq + std::hash::Hash + Send + Sync,
+    {
+        let keys = right.map(|data| key(&data)).collect::<Vec<_>>();
+        let mut counts = super::basic::key_counts(keys.into_iter());
+        let mut data = left.collect::<Vec<_>>();
+        data.retain(|data| super::basic::take_count(&mut counts, &key(data)));
+        data.into_par_iter()
+    }
+
+    type ExceptStats = ();
+    fn except<Key, Data>(
+        left: stream!(Data),
+        right: stream!(Data),
+        key: impl Fn(&Data) -> Key + Send + Sync,
+        _stats: &Self::ExceptStats,
+    ) -> stream!(Data)
+    where
+        Data: Send + Sync,
+        Key: Eq + std::hash::Hash + Send + Sync,
+    {
+        let keys = right.map(|data| key(&data)).collect::<Vec<_>>();
+        let mut counts = super::basic::key_counts(keys.into_iter());
+        let mut data = left.collect::<Vec<_>>();
+        data.retain(|data| !super::basic::take_count(&mut counts, &key(data)));
+        data.into_par_iter()
+    }
+
     type ForkStats = ();
     fn fork<Data>(stream: stream!(Data), _stats: &Self::ForkStats) -> (stream!(Data), stream!(Data))
     where
@@ -0,0 +1,88 @@
+//! ## Storage of a non-unique hash index
+//! Allows for fast equality lookups of all rows with a given value in a column,
+//! without requiring values to be unique.
+//!
+//! ## Design
+//! An O(1) map of values to the keys with that value.
+//! - Values are cloned into the index, as with [`super::Unique`].
+//! - Keys with the same value are kept in insertion order.
+//! - Maintenance never fails, so (as with [`super::Ordered`]) it can be applied
+//!   after all fallible checks are complete.
+
+use rustc_hash::{FxBuildHasher, FxHashMap};
+use std::hash::Hash;
+
+#[derive(Debug)]
+pub struct MissingHashedValue;
+
+/// A simple wrapper for storing copies of values, and the keys of all rows
+/// containing them.
+pub struct Hashed<Field, Key> {
+    mapping: FxHashMap<Field, Vec<Key>>,
+}
+
+impl<Field: Eq + Hash + Clone, Key: Copy + Eq> Hashed<Field, Key> {
+    pub fn new(size_hint: usize) -> Self {
+        Self {
+            mapping: FxHashMap::with_capacity_and_hasher(size_hint, FxBuildHasher),
+        }
+    }
+
+    pub fn insert(&mut self, field: Field, key: Key) {
+        self.mapping.entry(field).or_default().push(key);
+    }
+
+    pub fn pull(&mut self, field: &Field, key: Key) -> Result<(), MissingHashedValue> {
+        let keys = self.mapping.get_mut(field).ok_or(MissingHashedValue)?;
+        let pos = keys
+            .iter()
+            .position(|k| *k == key)
+            .ok_or(MissingHashedValue)?;
+        keys.remove(pos);
+        if keys.is_empty() {
+            self.mapping.remove(field);
+        }
+        Ok(())
+    }
+
+    /// At the given key, move from the old value in `replace` to `to_insert`.
+    pub fn replace(&mut self, to_insert: &Field, replace: &Field, key: Key) {
+        if to_insert != replace {
+            self.pull(replace, key).unwrap();
+            self.insert(to_insert.clone(), key);
+        }
+    }
+
+    /// Get all keys with exactly the value provided, in insertion order.
+    pub fn lookup(&self, value: &Field) -> impl Iterator<Item = Key> + '_ {
+        self.mapping.get(value).into_iter().flatten().copied()
+    }
+
+    /// The number of rows with the value provided.
+    pub fn count(&self, value: &Field) -> usize {
+        self.mapping.get(value).map_or(0, Vec::len)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn lookup_duplicates() {
+        let mut index: Hashed<&str, usize> = Hashed::new(4);
+        for (v, k) in [("a", 0), ("b", 1), ("a", 2), ("a", 3)] {
+            index.insert(v, k);
+        }
+
+        assert_eq!(index.lookup(&"a").collect::<Vec<_>>(), vec![0, 2, 3]);
+        assert_eq!(index.count(&"b"), 1);
+        assert_eq!(index.count(&"c"), 0);
+
+        index.replace(&"b", &"a", 2);
+        index.pull(&"a", 0).unwrap();
+        assert!(index.pull(&"a", 0).is_err());
+        assert_eq!(index.lookup(&"a").collect::<Vec<_>>(), vec![3]);
+        assert_eq!(index.lookup(&"b").collect::<Vec<_>>(), vec![1, 2]);
+    }
+}
@@ -2,3 +2,7 @@
 
 mod unique;
 pub use unique::*;
+mod ordered;
+pub use ordered::*;
+mod hashed;
+pub use hashed::*;
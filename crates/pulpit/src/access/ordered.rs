@@ -0,0 +1,189 @@
+//! ## Storage of an ordered index
+//! Allows for range scans, min/max and ordered iteration over the keys of a
+//! table by the value of a column.
+//!
+//! ## Design
+//! A [`BTreeMap`] of values to the keys with that value, so the index is
+//! non-unique (a unique & ordered column can be expressed by applying both
+//! [`super::Unique`] and [`Ordered`] to it).
+//! - Values are cloned into the index, as with [`super::Unique`].
+//! - Keys with the same value are kept in insertion order.
+//! - Maintenance never fails, so it can be applied after all fallible checks
+//!   (e.g. unique constraints) are complete.
+
+use std::{
+    borrow::Borrow,
+    collections::BTreeMap,
+    ops::{Bound, RangeBounds},
+};
+
+#[derive(Debug)]
+pub struct MissingOrderedValue;
+
+/// A simple wrapper for storing copies of ordered values, and the keys of
+/// rows containing them.
+pub struct Ordered<Field, Key> {
+    mapping: BTreeMap<Field, Vec<Key>>,
+}
+
+impl<Field: Ord + Clone, Key: Copy + Eq> Ordered<Field, Key> {
+    pub fn new(_size_hint: usize) -> Self {
+        Self {
+            mapping: BTreeMap::new(),
+        }
+    }
+
+    pub fn insert(&mut self, field: Field, key: Key) {
+        self.mapping.entry(field).or_default().push(key);
+    }
+
+    pub fn pull(&mut self, field: &Field, key: Key) -> Result<(), MissingOrderedValue> {
+        let keys = self.mapping.get_mut(field).ok_or(MissingOrderedValue)?;
+        let pos = keys
+            .iter()
+            .position(|k| *k == key)
+            .ok_or(MissingOrderedValue)?;
+        keys.remove(pos);
+        if keys.is_empty() {
+            self.mapping.remove(field);
+        }
+        Ok(())
+    }
+
+    /// At the given key, move from the old value in `replace` to `to_insert`.
+    pub fn replace(&mut self, to_insert: &Field, replace: &Field, key: Key) {
+        if to_insert != replace {
+            self.pull(replace, key).unwrap();
+            self.insert(to_insert.clone(), key);
+        }
+    }
+
+    /// Get all keys with exactly the value provided.
+    pub fn lookup<'a, Q>(&'a self, value: &Q) -> impl Iterator<Item = Key> + 'a
+    where
+        Field: Borrow<Q>,
+        Q: Ord + ?Sized,
+    {
+        self.mapping.get(value).into_iter().flatten().copied()
+    }
+
+    /// Get the keys of all values in the range, in ascending order of value.
+    pub fn range<Q, R>(&self, range: R) -> impl DoubleEndedIterator<Item = Key> + '_
+    where
+        Field: Borrow<Q>,
+        Q: Ord + ?Sized,
+        R: RangeBounds<Q>,
+    {
+        self.mapping
+            .range(range)
+            .flat_map(|(_, keys)| keys.iter().copied())
+    }
+
+    /// A non-generic [`Ordered::range`], for when the type of the bounds needs to be
+    /// inferred from the index (e.g. in generated code).
+    /// - Bounds are taken from runtime values, so a lower bound above the upper
+    ///   (or an empty excluded range) is an empty range, rather than a panic
+    ///   (as with [`BTreeMap::range`]).
+    pub fn range_bounds(
+        &self,
+        lower: Bound<&Field>,
+        upper: Bound<&Field>,
+    ) -> impl DoubleEndedIterator<Item = Key> + '_ {
+        let empty = match (lower, upper) {
+            (Bound::Included(l), Bound::Included(u)) => l > u,
+            (Bound::Included(l) | Bound::Excluded(l), Bound::Included(u) | Bound::Excluded(u)) => {
+                l >= u
+            }
+            _ => false,
+        };
+        (!empty)
+            .then(|| self.mapping.range::<Field, _>((lower, upper)))
+            .into_iter()
+            .flatten()
+            .flat_map(|(_, keys)| keys.iter().copied())
+    }
+
+    /// Get the keys of all values, in ascending order of value.
+    pub fn iter(&self) -> impl DoubleEndedIterator<Item = Key> + '_ {
+        self.mapping.values().flat_map(|keys| keys.iter().copied())
+    }
+
+    /// The key of the (first inserted) row with the smallest value.
+    pub fn min(&self) -> Option<Key> {
+        self.mapping
+            .first_key_value()
+            .and_then(|(_, keys)| keys.first().copied())
+    }
+
+    /// The key of the (first inserted) row with the largest value.
+    pub fn max(&self) -> Option<Key> {
+        self.mapping
+            .last_key_value()
+            .and_then(|(_, keys)| keys.first().copied())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn range_and_extremes() {
+        let mut index: Ordered<usize, char> = Ordered::new(0);
+        for (v, k) in [(3, 'a'), (1, 'b'), (3, 'c'), (7, 'd')] {
+            index.insert(v, k);
+        }
+
+        assert_eq!(index.min(), Some('b'));
+        assert_eq!(index.max(), Some('d'));
+        assert_eq!(index.range(2..=3).collect::<Vec<_>>(), vec!['a', 'c']);
+        assert_eq!(
+            index.iter().rev().collect::<Vec<_>>(),
+            vec!['d', 'c', 'a', 'b']
+        );
+
+        index.replace(&0, &3, 'c');
+        index.pull(&7, 'd').unwrap();
+        assert!(index.pull(&7, 'd').is_err());
+        assert_eq!(index.iter().collect::<Vec<_>>(), vec!['c', 'b', 'a']);
+        assert_eq!(
+            index
+                .range_bounds(Bound::Excluded(&0), Bound::Unbounded)
+                .collect::<Vec<_>>(),
+            vec!['b', 'a']
+        );
+    }
+
+    #[test]
+    fn empty_range_bounds() {
+        let mut index: Ordered<usize, char> = Ordered::new(0);
+        for (v, k) in [(3, 'a'), (5, 'b')] {
+            index.insert(v, k);
+        }
+
+        assert_eq!(
+            index
+                .range_bounds(Bound::Included(&5), Bound::Included(&3))
+                .count(),
+            0
+        );
+        assert_eq!(
+            index
+                .range_bounds(Bound::Excluded(&3), Bound::Excluded(&3))
+                .count(),
+            0
+        );
+        assert_eq!(
+            index
+                .range_bounds(Bound::Included(&3), Bound::Excluded(&3))
+                .count(),
+            0
+        );
+        assert_eq!(
+            index
+                .range_bounds(Bound::Included(&3), Bound::Included(&3))
+                .collect::<Vec<_>>(),
+            vec!['a']
+        );
+    }
+}
@@ -14,11 +14,20 @@
 //! To save dev time: just `get` the value (cringe but easy).
 
 use rustc_hash::{FxBuildHasher, FxHashMap};
-use std::hash::Hash;
+use std::{fmt::Debug, hash::Hash};
 #[derive(Debug)]
 pub struct MissingUniqueValue;
-#[derive(Debug)]
-pub struct UniqueConflict;
+/// A value is already in the index, for the row with key `existing`.
+pub struct UniqueConflict<Key> {
+    pub existing: Key,
+}
+
+// keys are not required to implement debug
+impl<Key> Debug for UniqueConflict<Key> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("UniqueConflict").finish_non_exhaustive()
+    }
+}
 
 /// A simple wrapper for storing copies of keys and associated unique values in
 /// an index.
@@ -41,11 +50,11 @@ impl<Field: Eq + Hash + Clone, Key: Copy + Eq> Unique<Field, Key> {
     }
 
     // TODO: avoid copies
-    pub fn insert(&mut self, field: Field, key: Key) -> Result<(), UniqueConflict> {
+    pub fn insert(&mut self, field: Field, key: Key) -> Result<(), UniqueConflict<Key>> {
         match self.mapping.insert(field.clone(), key) {
-            Some(old_key) => {
-                *self.mapping.get_mut(&field).unwrap() = old_key;
-                Err(UniqueConflict)
+            Some(existing) => {
+                *self.mapping.get_mut(&field).unwrap() = existing;
+                Err(UniqueConflict { existing })
             }
             None => Ok(()),
         }
@@ -66,7 +75,7 @@ impl<Field: Eq + Hash + Clone, Key: Copy + Eq> Unique<Field, Key> {
         to_insert: &Field,
         replace: &Field,
         key: Key,
-    ) -> Result<Field, UniqueConflict> {
+    ) -> Result<Field, UniqueConflict<Key>> {
         if to_insert == replace {
             Ok(replace.clone())
         } else {
@@ -74,9 +83,11 @@ impl<Field: Eq + Hash + Clone, Key: Copy + Eq> Unique<Field, Key> {
             debug_assert!(old_key == key, "Keys for replace do not match");
 
             match self.mapping.insert(to_insert.clone(), key) {
-                Some(_) => {
-                    *self.mapping.get_mut(to_insert).unwrap() = old_key;
-                    Err(UniqueConflict)
+                Some(existing) => {
+                    // restore the conflicting row's entry, and the replaced value
+                    *self.mapping.get_mut(to_insert).unwrap() = existing;
+                    self.mapping.insert(old_val, old_key);
+                    Err(UniqueConflict { existing })
                 }
                 None => Ok(old_val),
             }
@@ -90,3 +101,24 @@ impl<Field: Eq + Hash + Clone, Key: Copy + Eq> Unique<Field, Key> {
         debug_assert!(res.is_none(), "Undo replace failed");
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn conflicts_keep_index() {
+        let mut index: Unique<&str, usize> = Unique::new(4);
+        index.insert("a", 0).unwrap();
+        index.insert("b", 1).unwrap();
+
+        assert_eq!(index.insert("a", 2).unwrap_err().existing, 0);
+        assert_eq!(index.replace(&"a", &"b", 1).unwrap_err().existing, 0);
+        assert_eq!(index.lookup(&"a").unwrap(), 0);
+        assert_eq!(index.lookup(&"b").unwrap(), 1);
+
+        assert_eq!(index.replace(&"c", &"b", 1).unwrap(), "b");
+        assert!(index.lookup(&"b").is_err());
+        assert_eq!(index.lookup(&"c").unwrap(), 1);
+    }
+}
@@ -149,3 +149,9 @@ where
         self.inner.data.pop();
     }
 }
+
+impl<ImmData, MutData> ColumnTake<ImmData, MutData> for AssocAppVec<ImmData, MutData> {
+    fn take(self) -> Vec<Option<Data<ImmData, MutData>>> {
+        self.data.into_iter().map(Some).collect()
+    }
+}
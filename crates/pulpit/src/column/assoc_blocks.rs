@@ -180,3 +180,11 @@ where
         self.inner.blocks.unppend();
     }
 }
+
+impl<ImmData, MutData, const BLOCK_SIZE: usize> ColumnTake<ImmData, MutData>
+    for AssocBlocks<ImmData, MutData, BLOCK_SIZE>
+{
+    fn take(self) -> Vec<Option<Data<ImmData, MutData>>> {
+        self.blocks.into_values().into_iter().map(Some).collect()
+    }
+}
@@ -4,7 +4,7 @@ use super::*;
 
 /// Like a PrimaryRetain, but as an associated index.
 pub struct AssocPullBlocks<ImmData, MutData, const BLOCK_SIZE: usize> {
-    data: Vec<Data<PtrGen<ImmData>, MutData>>,
+    data: Vec<Data<PtrGen, MutData>>,
     blocks: Blocks<ImmData, BLOCK_SIZE>,
     _holder: (),
 }
@@ -77,7 +77,7 @@ where
     fn assoc_append(&mut self, Data { imm_data, mut_data }: Data<ImmData, MutData>) {
         let ptr = self.inner.blocks.append(imm_data);
         self.inner.data.push(Data {
-            imm_data: PtrGen(ptr),
+            imm_data: PtrGen(ptr.cast()),
             mut_data,
         });
     }
@@ -92,3 +92,68 @@ where
         get.clone()
     }
 }
+
+impl<'imm, MutData, ImmData, const BLOCK_SIZE: usize> AssocWindowPull<'imm, ImmData, MutData>
+    for Window<'imm, AssocPullBlocks<ImmData, MutData, BLOCK_SIZE>>
+where
+    ImmData: Clone,
+    MutData: Clone,
+{
+    type ImmPull = &'imm ImmData;
+
+    /// The immutable data is retained in its block, so references to it
+    /// remain valid. The mutable data is left in place until the index is
+    /// placed into.
+    #[inline(always)]
+    unsafe fn assoc_pull(&mut self, ind: UnsafeIndex) -> Data<Self::ImmPull, MutData> {
+        let Data {
+            imm_data: imm_ptr,
+            mut_data,
+        } = self.inner.data.get_unchecked(ind);
+        Data {
+            imm_data: &*(imm_ptr.0).cast::<ImmData>(),
+            mut_data: mut_data.clone(),
+        }
+    }
+
+    #[inline(always)]
+    unsafe fn assoc_place(
+        &mut self,
+        ind: UnsafeIndex,
+        Data { imm_data, mut_data }: Data<ImmData, MutData>,
+    ) {
+        let ptr = self.inner.blocks.append(imm_data);
+        *self.inner.data.get_unchecked_mut(ind) = Data {
+            imm_data: PtrGen(ptr.cast()),
+            mut_data,
+        };
+    }
+
+    #[inline(always)]
+    fn conv_pull(pull: Self::ImmPull) -> ImmData {
+        pull.clone()
+    }
+}
+
+impl<ImmData, MutData, const BLOCK_SIZE: usize> ColumnTake<ImmData, MutData>
+    for AssocPullBlocks<ImmData, MutData, BLOCK_SIZE>
+{
+    fn take(self) -> Vec<Option<Data<ImmData, MutData>>> {
+        let AssocPullBlocks { data, blocks, .. } = self;
+        let (imm_ptrs, mut_data): (Vec<_>, Vec<_>) = data
+            .into_iter()
+            .map(|Data { imm_data, mut_data }| (Some(imm_data.0), mut_data))
+            .unzip();
+        blocks
+            .take_ptrs(imm_ptrs)
+            .into_iter()
+            .zip(mut_data)
+            .map(|(imm_data, mut_data)| {
+                Some(Data {
+                    imm_data: imm_data?,
+                    mut_data,
+                })
+            })
+            .collect()
+    }
+}
@@ -101,3 +101,9 @@ where
         pull
     }
 }
+
+impl<ImmData, MutData> ColumnTake<ImmData, MutData> for AssocVec<ImmData, MutData> {
+    fn take(self) -> Vec<Option<Data<ImmData, MutData>>> {
+        self.data
+    }
+}
@@ -332,6 +332,23 @@ pub trait PrimaryWindowPull<'imm, ImmData, MutData>: PrimaryWindow<'imm, ImmData
     fn conv_pull(pull: Self::ImmPull) -> ImmData;
 }
 
+/// Places a row at a given key, rather than at a key chosen by the column.
+/// - Allows rows to be restored with the keys they had when snapshot (see
+///   [`crate::persist`]).
+/// - Does not provide an [`InsertAction`], so is only for tables without
+///   associated columns.
+pub trait PrimaryWindowRestore<'imm, ImmData, MutData>:
+    PrimaryWindowPull<'imm, ImmData, MutData>
+{
+    /// Place a row at the key.
+    /// - Fails if the key's slot is already occupied.
+    fn restore(
+        &mut self,
+        key: <Self::Col as Keyable>::Key,
+        val: Data<ImmData, MutData>,
+    ) -> Result<(), KeyError>;
+}
+
 /// Hides a given key temporarily, until revealed or removed.
 /// - Allows for 'deletions' that are not actually enforced until commit.
 /// - Allows the deletion from other associated columns to be postponed till the
@@ -410,6 +427,20 @@ pub trait AssocWindowPull<'imm, ImmData, MutData>: AssocWindow<'imm, ImmData, Mu
     fn conv_pull(pull: Self::ImmPull) -> ImmData;
 }
 
+/// Consume a column to move out its values (e.g. to move the rows of a table
+/// into another table).
+/// - Takes the column by value, rather than through a window, as values gotten
+///   with [`PrimaryWindow::get`] can outlive the window, but not the column.
+/// - Immutable values retained for such references (e.g. in [`PrimaryRetain`])
+///   can hence be moved out.
+pub trait ColumnTake<ImmData, MutData>: Column {
+    /// The value at each [`UnsafeIndex`], or `None` for indices with no visible
+    /// row (pulled, or hidden by a transaction).
+    /// - Associated columns may return values for indices pulled from the
+    ///   primary column, so only indices with a primary value are rows.
+    fn take(self) -> Vec<Option<Data<ImmData, MutData>>>;
+}
+
 /// A Simple Generational Index Key
 pub struct GenKey<GenCounter: Copy + Eq> {
     index: UnsafeIndex,
@@ -438,7 +469,10 @@ impl<GenCounter: Copy + Eq + Hash> Hash for GenKey<GenCounter> {
     }
 }
 mod utils {
-    use std::mem::MaybeUninit;
+    use std::{
+        collections::HashMap,
+        mem::{size_of, ManuallyDrop, MaybeUninit},
+    };
 
     /// A sequence of allocated blocks providing stable pointers.
     pub struct Blocks<Value, const BLOCK_SIZE: usize> {
@@ -504,6 +538,60 @@ mod utils {
             let (block, seq) = quotrem::<BLOCK_SIZE>(ind);
             self.data.get_unchecked_mut(block)[seq].assume_init_mut()
         }
+
+        /// Move out all values, in the order they were appended.
+        pub fn into_values(self) -> Vec<Value> {
+            let mut blocks = ManuallyDrop::new(self);
+            let count = blocks.count;
+            let mut values = Vec::with_capacity(count);
+            for (block_ind, block) in std::mem::take(&mut blocks.data).into_iter().enumerate() {
+                let alive = count.saturating_sub(block_ind * BLOCK_SIZE).min(BLOCK_SIZE);
+                for value in &block[..alive] {
+                    values.push(unsafe { value.assume_init_read() });
+                }
+            }
+            values
+        }
+
+        /// Move out the values at the pointers returned by [`Blocks::append`],
+        /// dropping the values not taken.
+        /// - Zero sized values share a pointer, but are interchangeable, so are
+        ///   taken in order (for columns that use other values in their place).
+        /// - Panics if a pointer is not to a value, or is taken twice.
+        pub fn take_ptrs(self, ptrs: Vec<Option<*const ()>>) -> Vec<Option<Value>> {
+            if size_of::<Value>() == 0 {
+                let mut values = self.into_values().into_iter();
+                ptrs.into_iter()
+                    .map(|ptr| ptr.and_then(|_| values.next()))
+                    .collect()
+            } else {
+                let positions = (0..self.count)
+                    .map(|ind| ((unsafe { self.get(ind) } as *const Value).cast(), ind))
+                    .collect::<HashMap<*const (), usize>>();
+                let mut values = self.into_values().into_iter().map(Some).collect::<Vec<_>>();
+                ptrs.into_iter()
+                    .map(|ptr| {
+                        ptr.map(|ptr| {
+                            values[positions[&ptr]]
+                                .take()
+                                .expect("Each value is taken once")
+                        })
+                    })
+                    .collect()
+            }
+        }
+    }
+
+    /// Place values at their indices, with `None` for the indices missing.
+    pub fn by_index<Value>(values: impl Iterator<Item = (usize, Value)>) -> Vec<Option<Value>> {
+        let mut placed = Vec::new();
+        for (ind, value) in values {
+            if ind >= placed.len() {
+                placed.resize_with(ind + 1, || None);
+            }
+            placed[ind] = Some(value);
+        }
+        placed
     }
 
     #[inline(always)]
@@ -738,6 +826,139 @@ mod verif {
             }
         }
 
+        /// Check the values taken from a column are the rows in it (and that
+        /// values moved out are not dropped twice).
+        fn check_taken(
+            taken: Vec<Option<Data<String, String>>>,
+            expected: Vec<Data<String, String>>,
+        ) {
+            let mut taken = taken
+                .into_iter()
+                .flatten()
+                .map(|Data { imm_data, mut_data }| (imm_data, mut_data))
+                .collect::<Vec<_>>();
+            let mut expected = expected
+                .into_iter()
+                .map(|Data { imm_data, mut_data }| (imm_data, mut_data))
+                .collect::<Vec<_>>();
+            taken.sort();
+            expected.sort();
+            assert_eq!(taken, expected, "Incorrect values taken");
+        }
+
+        fn check_primary_pull_take<Col>()
+        where
+            Col: Column + ColumnTake<String, String>,
+            for<'a> Col::WindowKind<'a>: PrimaryWindowPull<'a, String, String>,
+            for<'a> <<Col::WindowKind<'a> as PrimaryWindow<'a, String, String>>::Col as Keyable>::Key:
+                Eq + Hash,
+        {
+            const ITERS: usize = 100;
+            let mut col = Col::new(ITERS);
+            // the window (and keys) borrow the column, so must be dropped before it is taken
+            let expected = {
+                let mut check: CheckPrimary<_, _, _, HashMap<_, _>> =
+                    CheckPrimary::new(ITERS, col.window());
+
+                for n in 0..ITERS {
+                    check.check_insert(Data {
+                        imm_data: format!("imm {n}"),
+                        mut_data: format!("mut {n}"),
+                    });
+                    if n % 3 == 0 {
+                        if let Some(next_key) = check.items.get_next_key() {
+                            check.check_pull(next_key);
+                        }
+                    }
+                }
+                check.items.into_values().map(|(_, data)| data).collect()
+            };
+            check_taken(col.take(), expected);
+        }
+
+        fn check_primary_app_take<Col>()
+        where
+            Col: Column + ColumnTake<String, String>,
+            for<'a> Col::WindowKind<'a>: PrimaryWindowApp<'a, String, String>,
+            for<'a> <<Col::WindowKind<'a> as PrimaryWindow<'a, String, String>>::Col as Keyable>::Key:
+                Eq + Hash,
+        {
+            const ITERS: usize = 100;
+            let mut col = Col::new(ITERS);
+            // the window (and keys) borrow the column, so must be dropped before it is taken
+            let expected = {
+                let mut check: CheckPrimary<_, _, _, HashMap<_, _>> =
+                    CheckPrimary::new(ITERS, col.window());
+
+                for n in 0..ITERS {
+                    check.check_append(Data {
+                        imm_data: format!("imm {n}"),
+                        mut_data: format!("mut {n}"),
+                    });
+                }
+                check.items.into_values().map(|(_, data)| data).collect()
+            };
+            check_taken(col.take(), expected);
+        }
+
+        /// Check rows restored at the keys they had in another column can be
+        /// accessed by those keys, and that occupied keys cannot be restored to.
+        fn check_primary_restore<Col>()
+        where
+            Col: Column,
+            for<'a> Col::WindowKind<'a>: PrimaryWindowRestore<'a, usize, usize>,
+            for<'a> <<Col::WindowKind<'a> as PrimaryWindow<'a, usize, usize>>::Col as Keyable>::Key:
+                Copy,
+        {
+            const ITERS: usize = 100;
+            let mut original = Col::new(ITERS);
+            let mut restored = Col::new(ITERS);
+            let mut original_window = original.window();
+            let mut rows = Vec::new();
+            for n in 0..ITERS {
+                let (key, _) = original_window.insert(Data {
+                    imm_data: n,
+                    mut_data: n,
+                });
+                rows.push((key, n));
+                if n % 3 == 0 {
+                    let (key, _) = rows.remove(n / 2);
+                    original_window.pull(key).unwrap();
+                }
+            }
+
+            let mut restored_window = restored.window();
+            for (key, n) in rows.iter().rev() {
+                restored_window
+                    .restore(
+                        *key,
+                        Data {
+                            imm_data: *n,
+                            mut_data: *n,
+                        },
+                    )
+                    .unwrap();
+            }
+            assert_eq!(restored_window.count(), rows.len());
+            for (key, n) in &rows {
+                let Entry {
+                    data: Data { imm_data, mut_data },
+                    ..
+                } = restored_window.brw(*key).unwrap();
+                assert_eq!((*imm_data, *mut_data), (*n, *n));
+            }
+            let (key, _) = rows[0];
+            assert!(restored_window
+                .restore(
+                    key,
+                    Data {
+                        imm_data: 0,
+                        mut_data: 0,
+                    },
+                )
+                .is_err());
+        }
+
         macro_rules! test_pull_impl {
             ($name:ident => $col:ty) => {
                 #[test]
@@ -762,6 +983,68 @@ mod verif {
         test_pull_impl!(thunderdome_trans => PrimaryThunderDomeTrans<usize, usize>);
 
         test_app_impl!(assoc_blocks => AssocBlocks<usize, usize, 16>);
+
+        #[test]
+        fn thunderdome_restore() {
+            check_primary_restore::<PrimaryThunderDome<usize, usize>>();
+        }
+
+        #[test]
+        fn thunderdome_trans_restore() {
+            check_primary_restore::<PrimaryThunderDomeTrans<usize, usize>>();
+        }
+
+        macro_rules! test_pull_take_impl {
+            ($name:ident => $col:ty) => {
+                #[test]
+                fn $name() {
+                    check_primary_pull_take::<$col>();
+                }
+            };
+        }
+
+        macro_rules! test_app_take_impl {
+            ($name:ident => $col:ty) => {
+                #[test]
+                fn $name() {
+                    check_primary_app_take::<$col>();
+                }
+            };
+        }
+
+        test_pull_take_impl!(primary_retain_take => PrimaryRetain<String, String, 16>);
+        test_pull_take_impl!(gen_arena_take => PrimaryGenerationalArena<String, String>);
+        test_pull_take_impl!(thunderdome_take => PrimaryThunderDome<String, String>);
+        test_pull_take_impl!(thunderdome_trans_take => PrimaryThunderDomeTrans<String, String>);
+
+        test_app_take_impl!(assoc_blocks_take => AssocBlocks<String, String, 16>);
+        test_app_take_impl!(assoc_app_vec_take => AssocAppVec<String, String>);
+
+        #[test]
+        fn assoc_pull_blocks_take() {
+            let row = |name: &str| Data {
+                imm_data: format!("imm {name}"),
+                mut_data: format!("mut {name}"),
+            };
+            let mut col = AssocPullBlocks::<String, String, 2>::new(4);
+            let mut window = col.window();
+            for n in 0..4 {
+                window.assoc_append(row(&n.to_string()));
+            }
+            unsafe {
+                window.assoc_pull(1);
+                window.assoc_place(1, row("placed"));
+            }
+
+            let taken = col
+                .take()
+                .into_iter()
+                .map(|data| data.map(|Data { imm_data, mut_data }| (imm_data, mut_data)))
+                .collect::<Vec<_>>();
+            let expected = ["0", "placed", "2", "3"]
+                .map(|name| Some((format!("imm {name}"), format!("mut {name}"))));
+            assert_eq!(taken, expected);
+        }
     }
 
     #[cfg(kani)]
@@ -135,3 +135,13 @@ where
         pull
     }
 }
+
+impl<ImmData, MutData> ColumnTake<ImmData, MutData> for PrimaryGenerationalArena<ImmData, MutData> {
+    fn take(mut self) -> Vec<Option<Data<ImmData, MutData>>> {
+        let keys = self.arena.iter().map(|(key, _)| key).collect::<Vec<_>>();
+        utils::by_index(
+            keys.into_iter()
+                .filter_map(|key| Some((key.to_idx(), self.arena.remove(key)?))),
+        )
+    }
+}
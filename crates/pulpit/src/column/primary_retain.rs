@@ -44,14 +44,15 @@ union Slot<MutData> {
     next_free: EncodedNextFree,
 }
 
-struct MutEntry<ImmData, MutData> {
-    imm_ptr: PtrGen<ImmData>,
+struct MutEntry<MutData> {
+    imm_ptr: PtrGen,
     mut_data: Slot<MutData>,
 }
 
-impl<ImmData, MutData> Drop for MutEntry<ImmData, MutData> {
+impl<MutData> Drop for MutEntry<MutData> {
     fn drop(&mut self) {
-        if self.imm_ptr.0.is_null() {
+        // free slots (null pointer) contain the next free slot, not data
+        if !self.imm_ptr.0.is_null() {
             unsafe {
                 ManuallyDrop::drop(&mut self.mut_data.full);
             }
@@ -92,7 +93,7 @@ impl<ImmData, MutData> Drop for MutEntry<ImmData, MutData> {
 /// }
 /// ```  
 pub struct PrimaryRetain<ImmData, MutData, const BLOCK_SIZE: usize> {
-    mut_data: Vec<MutEntry<ImmData, MutData>>,
+    mut_data: Vec<MutEntry<MutData>>,
     visible_count: usize,
     next_free_mut: NextFree,
     imm_data: utils::Blocks<ImmData, BLOCK_SIZE>,
@@ -103,35 +104,22 @@ pub struct PrimaryRetain<ImmData, MutData, const BLOCK_SIZE: usize> {
 /// ## Concurrency Safe Marker for Key Type
 /// As we use the pointer as a generation counter, and only access using it once
 /// we have matched the generation, it is safe to share these.
-pub struct PtrGen<ImmData>(pub *const ImmData);
-
-impl<ImmData> Clone for PtrGen<ImmData> {
-    fn clone(&self) -> Self {
-        *self
-    }
-}
-impl<ImmData> Copy for PtrGen<ImmData> {}
-impl<ImmData> PartialEq for PtrGen<ImmData> {
-    fn eq(&self, other: &Self) -> bool {
-        self.0 == other.0
-    }
-}
-impl<ImmData> Eq for PtrGen<ImmData> {}
-impl<ImmData> Hash for PtrGen<ImmData> {
-    fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
-        self.0.hash(state);
-    }
-}
+///
+/// The pointer is untyped, so the key type does not depend on the data stored
+/// (and can be named without it, for example when a row of one table
+/// references a row in another).
+#[derive(Clone, Copy, PartialEq, Eq, Hash)]
+pub struct PtrGen(pub *const ());
 
 // Wrapping for concurrency (pointers are `!Send + !Sync` by default, we need to
 // explicitly mark this as concurrency safe)
-unsafe impl<ImmData> Send for PtrGen<ImmData> {}
-unsafe impl<ImmData> Sync for PtrGen<ImmData> {}
+unsafe impl Send for PtrGen {}
+unsafe impl Sync for PtrGen {}
 
 impl<ImmData, MutData, const BLOCK_SIZE: usize> Keyable
     for PrimaryRetain<ImmData, MutData, BLOCK_SIZE>
 {
-    type Key = GenKey<PtrGen<ImmData>>;
+    type Key = GenKey<PtrGen>;
 }
 
 impl<ImmData, MutData, const BLOCK_SIZE: usize> Column
@@ -297,7 +285,7 @@ where
 
                 self.inner.next_free_mut = NextFree::decode(mut_entry.mut_data.next_free);
                 *mut_entry = MutEntry {
-                    imm_ptr: PtrGen(imm_ptr),
+                    imm_ptr: PtrGen(imm_ptr.cast()),
                     mut_data: Slot {
                         full: ManuallyDrop::new(HiddenData {
                             hidden: false,
@@ -308,7 +296,7 @@ where
                 (
                     GenKey {
                         index: next_free,
-                        generation: PtrGen(imm_ptr),
+                        generation: PtrGen(imm_ptr.cast()),
                     },
                     InsertAction::Place(next_free),
                 )
@@ -316,7 +304,7 @@ where
         } else {
             let index = self.inner.mut_data.len();
             self.inner.mut_data.push(MutEntry {
-                imm_ptr: PtrGen(imm_ptr),
+                imm_ptr: PtrGen(imm_ptr.cast()),
                 mut_data: Slot {
                     full: ManuallyDrop::new(HiddenData {
                         hidden: false,
@@ -327,7 +315,7 @@ where
             (
                 GenKey {
                     index,
-                    generation: PtrGen(imm_ptr),
+                    generation: PtrGen(imm_ptr.cast()),
                 },
                 InsertAction::Append,
             )
@@ -348,12 +336,16 @@ where
                     if !pull_mut_data.hidden {
                         self.inner.visible_count -= 1;
                     }
-                    *mut_entry = MutEntry {
-                        imm_ptr: PtrGen(ptr::null()),
-                        mut_data: Slot {
-                            next_free: self.inner.next_free_mut.encode(),
+                    // the data was taken, so the entry must not be dropped
+                    ptr::write(
+                        mut_entry,
+                        MutEntry {
+                            imm_ptr: PtrGen(ptr::null()),
+                            mut_data: Slot {
+                                next_free: self.inner.next_free_mut.encode(),
+                            },
                         },
-                    };
+                    );
                     self.inner.next_free_mut = NextFree(Some(key.index));
                     Ok(Entry {
                         index: key.index,
@@ -433,3 +425,42 @@ impl<ImmData, MutData, const BLOCK_SIZE: usize> kani::Arbitrary
         }
     }
 }
+
+impl<ImmData, MutData, const BLOCK_SIZE: usize> ColumnTake<ImmData, MutData>
+    for PrimaryRetain<ImmData, MutData, BLOCK_SIZE>
+{
+    fn take(self) -> Vec<Option<Data<ImmData, MutData>>> {
+        let PrimaryRetain {
+            mut_data, imm_data, ..
+        } = self;
+        let (imm_ptrs, mut_data): (Vec<_>, Vec<_>) = mut_data
+            .into_iter()
+            .map(|entry| {
+                // the mutable data is moved out here, so must not be dropped with the entry
+                let mut entry = ManuallyDrop::new(entry);
+                if entry.imm_ptr.0.is_null() {
+                    (None, None)
+                } else {
+                    let HiddenData { hidden, data } =
+                        unsafe { ManuallyDrop::take(&mut entry.mut_data.full) };
+                    if hidden {
+                        (None, None)
+                    } else {
+                        (Some(entry.imm_ptr.0), Some(data))
+                    }
+                }
+            })
+            .unzip();
+        imm_data
+            .take_ptrs(imm_ptrs)
+            .into_iter()
+            .zip(mut_data)
+            .map(|(imm_data, mut_data)| {
+                Some(Data {
+                    imm_data: imm_data?,
+                    mut_data: mut_data?,
+                })
+            })
+            .collect()
+    }
+}
@@ -135,3 +135,32 @@ where
         pull
     }
 }
+
+impl<'imm, ImmData, MutData> PrimaryWindowRestore<'imm, ImmData, MutData>
+    for Window<'imm, PrimaryThunderDome<ImmData, MutData>>
+where
+    ImmData: Clone,
+    MutData: Clone,
+{
+    fn restore(
+        &mut self,
+        key: <Self::Col as Keyable>::Key,
+        val: Data<ImmData, MutData>,
+    ) -> Result<(), KeyError> {
+        if self.inner.arena.contains_slot(key.slot()).is_some() {
+            return Err(KeyError);
+        }
+        self.inner.arena.insert_at(key, val);
+        Ok(())
+    }
+}
+
+impl<ImmData, MutData> ColumnTake<ImmData, MutData> for PrimaryThunderDome<ImmData, MutData> {
+    fn take(self) -> Vec<Option<Data<ImmData, MutData>>> {
+        utils::by_index(
+            self.arena
+                .into_iter()
+                .map(|(key, data)| (key.slot() as usize, data)),
+        )
+    }
+}
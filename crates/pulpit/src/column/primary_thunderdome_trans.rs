@@ -150,22 +150,21 @@ where
 
     #[inline(always)]
     fn pull(&mut self, key: <Self::Col as Keyable>::Key) -> Access<Self::ImmPull, MutData> {
+        // hidden rows are pulled when a transaction deleting them is committed
         match self.inner.arena.remove(key) {
             Some(Data {
                 imm_data,
-                mut_data:
-                    TransData {
-                        visible: true,
-                        mut_data,
-                    },
+                mut_data: TransData { visible, mut_data },
             }) => {
-                self.inner.visible_size -= 1;
+                if visible {
+                    self.inner.visible_size -= 1;
+                }
                 Ok(Entry {
                     index: key.slot() as usize,
                     data: Data { imm_data, mut_data },
                 })
             }
-            _ => Err(KeyError),
+            None => Err(KeyError),
         }
     }
 
@@ -174,6 +173,35 @@ where
     }
 }
 
+impl<'imm, ImmData, MutData> PrimaryWindowRestore<'imm, ImmData, MutData>
+    for Window<'imm, PrimaryThunderDomeTrans<ImmData, MutData>>
+where
+    ImmData: Clone,
+    MutData: Clone,
+{
+    fn restore(
+        &mut self,
+        key: <Self::Col as Keyable>::Key,
+        Data { imm_data, mut_data }: Data<ImmData, MutData>,
+    ) -> Result<(), KeyError> {
+        if self.inner.arena.contains_slot(key.slot()).is_some() {
+            return Err(KeyError);
+        }
+        self.inner.arena.insert_at(
+            key,
+            Data {
+                imm_data,
+                mut_data: TransData {
+                    visible: true,
+                    mut_data,
+                },
+            },
+        );
+        self.inner.visible_size += 1;
+        Ok(())
+    }
+}
+
 impl<'imm, ImmData, MutData> PrimaryWindowHide<'imm, ImmData, MutData>
     for Window<'imm, PrimaryThunderDomeTrans<ImmData, MutData>>
 where
@@ -218,3 +246,17 @@ where
         }
     }
 }
+
+impl<ImmData, MutData> ColumnTake<ImmData, MutData> for PrimaryThunderDomeTrans<ImmData, MutData> {
+    fn take(self) -> Vec<Option<Data<ImmData, MutData>>> {
+        utils::by_index(self.arena.into_iter().filter_map(
+            |(
+                key,
+                Data {
+                    imm_data,
+                    mut_data: TransData { visible, mut_data },
+                },
+            )| visible.then_some((key.slot() as usize, Data { imm_data, mut_data })),
+        ))
+    }
+}
@@ -3,6 +3,7 @@
 
 pub mod access;
 pub mod column;
+pub mod persist;
 pub mod value;
 
 pub mod gen {
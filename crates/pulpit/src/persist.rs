@@ -0,0 +1,359 @@
+//! # Persisting Tables
+//! A minimal, serde-like trait for writing values to (and reading them from) a
+//! binary format, used by generated code to snapshot and restore tables.
+//!
+//! ## Format
+//! All values are little endian, with lengths as [`u64`]s.
+//!
+//! | Part         | Contents                                                        |
+//! |--------------|-----------------------------------------------------------------|
+//! | Header       | [`MAGIC`], then the [`VERSION`] as a [`u32`]                    |
+//! | Table header | The table name, its column names and the number of rows        |
+//! | Rows         | Each column value in order, as written by [`Persist::persist`]  |
+//!
+//! Table and column names are checked on restore, so a snapshot cannot be
+//! restored into a differently shaped table.
+//!
+//! ## Row Keys
+//! Rows are written in the order they are scanned. For tables without
+//! deletions, restoring inserts rows in the same order, which gives every row
+//! the same key as before the snapshot.
+//!
+//! Tables with deletions can have holes (and reused slots) that inserting does
+//! not recreate, so each row's values are preceded by its key, and the row is
+//! restored at that key (see [`crate::column::PrimaryWindowRestore`]). This
+//! requires a primary column with keys that can be written (e.g. the
+//! [`thunderdome::Index`] of [`crate::column::PrimaryThunderDomeTrans`], but
+//! not the pointers of [`crate::column::PrimaryRetain`]).
+//!
+//! The generations of free slots are not written, so a key to a row deleted
+//! before the snapshot may refer to a different row inserted into the same slot
+//! after restoring.
+
+use std::io::{Error, ErrorKind, Read, Result, Write};
+
+/// The bytes every snapshot starts with
+pub const MAGIC: [u8; 4] = *b"emDB";
+
+/// The current version of the format, snapshots from other versions cannot be
+/// restored.
+pub const VERSION: u32 = 1;
+
+/// A value that can be written to, and read back from a snapshot.
+///
+/// ```
+/// # use pulpit::persist::Persist;
+/// let mut buffer = Vec::new();
+/// (String::from("hello"), Some(3i32)).persist(&mut buffer).unwrap();
+/// let value = <(String, Option<i32>)>::restore(&mut buffer.as_slice()).unwrap();
+/// assert_eq!(value, (String::from("hello"), Some(3)));
+/// ```
+pub trait Persist: Sized {
+    fn persist<W: Write>(&self, writer: &mut W) -> Result<()>;
+    fn restore<R: Read>(reader: &mut R) -> Result<Self>;
+}
+
+/// An error for a snapshot that does not match what is being restored.
+pub fn invalid_data(message: impl Into<String>) -> Error {
+    Error::new(ErrorKind::InvalidData, message.into())
+}
+
+pub fn write_header<W: Write>(writer: &mut W) -> Result<()> {
+    writer.write_all(&MAGIC)?;
+    VERSION.persist(writer)
+}
+
+pub fn read_header<R: Read>(reader: &mut R) -> Result<()> {
+    let mut magic = [0; 4];
+    reader.read_exact(&mut magic)?;
+    if magic != MAGIC {
+        return Err(invalid_data("Not an emDB snapshot"));
+    }
+    let version = u32::restore(reader)?;
+    if version != VERSION {
+        return Err(invalid_data(format!(
+            "Snapshot has format version {version}, but only version {VERSION} is supported"
+        )));
+    }
+    Ok(())
+}
+
+pub fn write_table_header<W: Write>(
+    writer: &mut W,
+    table: &str,
+    columns: &[&str],
+    rows: usize,
+) -> Result<()> {
+    write_str(writer, table)?;
+    columns.len().persist(writer)?;
+    for column in columns {
+        write_str(writer, column)?;
+    }
+    rows.persist(writer)
+}
+
+/// Read the header for a table, checking it matches the table being restored,
+/// and get the number of rows.
+pub fn read_table_header<R: Read>(reader: &mut R, table: &str, columns: &[&str]) -> Result<usize> {
+    let name = String::restore(reader)?;
+    if name != table {
+        return Err(invalid_data(format!(
+            "Expected table `{table}`, but the snapshot contains `{name}`"
+        )));
+    }
+    let stored = Vec::<String>::restore(reader)?;
+    if stored.len() != columns.len() || stored.iter().zip(columns).any(|(s, c)| s != c) {
+        return Err(invalid_data(format!(
+            "Table `{table}` has columns ({}), but the snapshot has ({})",
+            columns.join(", "),
+            stored.join(", ")
+        )));
+    }
+    usize::restore(reader)
+}
+
+fn write_str<W: Write>(writer: &mut W, value: &str) -> Result<()> {
+    value.len().persist(writer)?;
+    writer.write_all(value.as_bytes())
+}
+
+fn read_len<R: Read>(reader: &mut R) -> Result<usize> {
+    usize::try_from(u64::restore(reader)?).map_err(|_| invalid_data("Length too large"))
+}
+
+macro_rules! persist_numbers {
+    ($($num:ty),*) => {
+        $(
+            impl Persist for $num {
+                fn persist<W: Write>(&self, writer: &mut W) -> Result<()> {
+                    writer.write_all(&self.to_le_bytes())
+                }
+
+                fn restore<R: Read>(reader: &mut R) -> Result<Self> {
+                    let mut bytes = [0; size_of::<$num>()];
+                    reader.read_exact(&mut bytes)?;
+                    Ok(<$num>::from_le_bytes(bytes))
+                }
+            }
+        )*
+    };
+}
+
+persist_numbers!(u8, u16, u32, u64, u128, i8, i16, i32, i64, i128, f32, f64);
+
+/// Platform sized integers are stored as 64 bits
+macro_rules! persist_sized {
+    ($($num:ty => $stored:ty),*) => {
+        $(
+            impl Persist for $num {
+                fn persist<W: Write>(&self, writer: &mut W) -> Result<()> {
+                    (*self as $stored).persist(writer)
+                }
+
+                fn restore<R: Read>(reader: &mut R) -> Result<Self> {
+                    <$num>::try_from(<$stored>::restore(reader)?)
+                        .map_err(|_| invalid_data(concat!("Value too large for ", stringify!($num))))
+                }
+            }
+        )*
+    };
+}
+
+persist_sized!(usize => u64, isize => i64);
+
+impl Persist for bool {
+    fn persist<W: Write>(&self, writer: &mut W) -> Result<()> {
+        u8::from(*self).persist(writer)
+    }
+
+    fn restore<R: Read>(reader: &mut R) -> Result<Self> {
+        match u8::restore(reader)? {
+            0 => Ok(false),
+            1 => Ok(true),
+            _ => Err(invalid_data("Invalid boolean")),
+        }
+    }
+}
+
+impl Persist for char {
+    fn persist<W: Write>(&self, writer: &mut W) -> Result<()> {
+        u32::from(*self).persist(writer)
+    }
+
+    fn restore<R: Read>(reader: &mut R) -> Result<Self> {
+        char::from_u32(u32::restore(reader)?).ok_or_else(|| invalid_data("Invalid character"))
+    }
+}
+
+impl Persist for String {
+    fn persist<W: Write>(&self, writer: &mut W) -> Result<()> {
+        write_str(writer, self)
+    }
+
+    fn restore<R: Read>(reader: &mut R) -> Result<Self> {
+        // the length is untrusted, so the buffer grows as it is filled
+        let len = read_len(reader)?;
+        let mut bytes = Vec::new();
+        reader.take(len as u64).read_to_end(&mut bytes)?;
+        if bytes.len() != len {
+            return Err(invalid_data("String is longer than the snapshot"));
+        }
+        String::from_utf8(bytes).map_err(|_| invalid_data("Invalid UTF-8 string"))
+    }
+}
+
+impl<T: Persist> Persist for Vec<T> {
+    fn persist<W: Write>(&self, writer: &mut W) -> Result<()> {
+        self.len().persist(writer)?;
+        self.iter().try_for_each(|value| value.persist(writer))
+    }
+
+    fn restore<R: Read>(reader: &mut R) -> Result<Self> {
+        // the length is untrusted, so the vector grows as values are read
+        let len = read_len(reader)?;
+        let mut values = Vec::new();
+        for _ in 0..len {
+            values.push(T::restore(reader)?);
+        }
+        Ok(values)
+    }
+}
+
+impl<T: Persist> Persist for Option<T> {
+    fn persist<W: Write>(&self, writer: &mut W) -> Result<()> {
+        match self {
+            None => false.persist(writer),
+            Some(value) => {
+                true.persist(writer)?;
+                value.persist(writer)
+            }
+        }
+    }
+
+    fn restore<R: Read>(reader: &mut R) -> Result<Self> {
+        if bool::restore(reader)? {
+            Ok(Some(T::restore(reader)?))
+        } else {
+            Ok(None)
+        }
+    }
+}
+
+impl<T: Persist> Persist for Box<T> {
+    fn persist<W: Write>(&self, writer: &mut W) -> Result<()> {
+        self.as_ref().persist(writer)
+    }
+
+    fn restore<R: Read>(reader: &mut R) -> Result<Self> {
+        Ok(Box::new(T::restore(reader)?))
+    }
+}
+
+impl Persist for thunderdome::Index {
+    fn persist<W: Write>(&self, writer: &mut W) -> Result<()> {
+        self.to_bits().persist(writer)
+    }
+
+    fn restore<R: Read>(reader: &mut R) -> Result<Self> {
+        thunderdome::Index::from_bits(u64::restore(reader)?)
+            .ok_or_else(|| invalid_data("Invalid key"))
+    }
+}
+
+macro_rules! persist_tuples {
+    ($(($($t:ident),*)),*) => {
+        $(
+            impl<$($t: Persist),*> Persist for ($($t,)*) {
+                #[allow(non_snake_case, unused_variables)]
+                fn persist<W: Write>(&self, writer: &mut W) -> Result<()> {
+                    let ($($t,)*) = self;
+                    $($t.persist(writer)?;)*
+                    Ok(())
+                }
+
+                #[allow(unused_variables)]
+                fn restore<R: Read>(reader: &mut R) -> Result<Self> {
+                    Ok(($($t::restore(reader)?,)*))
+                }
+            }
+        )*
+    };
+}
+
+persist_tuples!(
+    (),
+    (A),
+    (A, B),
+    (A, B, C),
+    (A, B, C, D),
+    (A, B, C, D, E),
+    (A, B, C, D, E, F)
+);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn roundtrip<T: Persist + PartialEq + std::fmt::Debug>(value: T) {
+        let mut buffer = Vec::new();
+        value.persist(&mut buffer).unwrap();
+        let mut reader = buffer.as_slice();
+        assert_eq!(T::restore(&mut reader).unwrap(), value);
+        assert!(reader.is_empty(), "Not all bytes were read");
+    }
+
+    #[test]
+    fn values_roundtrip() {
+        roundtrip(-3i32);
+        roundtrip(u128::MAX);
+        roundtrip(1.5f64);
+        roundtrip(usize::MAX);
+        roundtrip(true);
+        roundtrip('λ');
+        roundtrip(String::from("emDB"));
+        roundtrip(vec![Some(1u8), None, Some(3)]);
+        roundtrip(Box::new((String::new(), 2i64, false)));
+        roundtrip(());
+        roundtrip(thunderdome::Arena::new().insert(()));
+    }
+
+    #[test]
+    fn invalid_values() {
+        assert!(bool::restore(&mut [2u8].as_slice()).is_err());
+        assert!(String::restore(&mut [1u8, 0, 0, 0, 0, 0, 0, 0, 0xff].as_slice()).is_err());
+        assert!(u32::restore(&mut [1u8].as_slice()).is_err());
+    }
+
+    #[test]
+    fn huge_lengths() {
+        let huge = u64::MAX >> 1;
+        let mut buffer = Vec::new();
+        huge.persist(&mut buffer).unwrap();
+        buffer.extend_from_slice(b"emDB");
+        assert_eq!(
+            String::restore(&mut buffer.as_slice()).unwrap_err().kind(),
+            ErrorKind::InvalidData
+        );
+        assert!(Vec::<u64>::restore(&mut buffer.as_slice()).is_err());
+    }
+
+    #[test]
+    fn headers() {
+        let mut buffer = Vec::new();
+        write_header(&mut buffer).unwrap();
+        write_table_header(&mut buffer, "users", &["name", "age"], 3).unwrap();
+
+        let mut reader = buffer.as_slice();
+        read_header(&mut reader).unwrap();
+        assert_eq!(
+            read_table_header(&mut reader, "users", &["name", "age"]).unwrap(),
+            3
+        );
+
+        let mut reader = buffer.as_slice();
+        read_header(&mut reader).unwrap();
+        assert!(read_table_header(&mut reader, "users", &["name"]).is_err());
+
+        assert!(read_header(&mut [0u8; 8].as_slice()).is_err());
+    }
+}
@@ -0,0 +1,61 @@
+pulpit::macros::simple! {
+    fields {
+        name: String,
+        age: u8 @ index(same_age),
+    },
+    updates {
+        update_age: [age],
+    },
+    gets {
+        get_all: [name, age],
+    },
+    predicates {},
+    limit {
+        None
+    },
+    transactions: on,
+    deletions: on,
+    name: members
+}
+
+#[test]
+fn lookups_follow_mutations() {
+    let mut table = members::Table::new(16);
+    let mut window = table.window();
+    let member = |name: &str, age: u8| members::insert::Insert {
+        name: String::from(name),
+        age,
+    };
+    let same_age =
+        |window: &members::Window<'_>, age: u8| window.same_age().lookup(&age).collect::<Vec<_>>();
+
+    let alice = window.insert(member("alice", 30));
+    let bob = window.insert(member("bob", 30));
+    let carol = window.insert(member("carol", 40));
+    assert!(same_age(&window, 30) == vec![alice, bob]);
+    assert!(same_age(&window, 40) == vec![carol]);
+    assert!(same_age(&window, 50).is_empty());
+
+    // updates move the key to the new value
+    window
+        .update_age(members::updates::update_age::Update { age: 40 }, bob)
+        .unwrap();
+    assert!(same_age(&window, 30) == vec![alice]);
+    assert!(same_age(&window, 40) == vec![carol, bob]);
+
+    // updates to the same value leave the key in place
+    window
+        .update_age(members::updates::update_age::Update { age: 40 }, carol)
+        .unwrap();
+    assert!(same_age(&window, 40) == vec![carol, bob]);
+
+    window.delete(alice).unwrap();
+    assert!(same_age(&window, 30).is_empty());
+    assert_eq!(window.same_age().count(&30), 0);
+
+    window.commit();
+    window.delete(carol).unwrap();
+    window.commit();
+    assert!(same_age(&window, 40) == vec![bob]);
+    assert_eq!(window.same_age().count(&40), 1);
+}
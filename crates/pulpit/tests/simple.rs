@@ -31,4 +31,55 @@ pulpit::macros::simple! {
     name: bowling_club
 }
 
-// TODO: Write some tests (other than that the generated code compiles)
+#[test]
+fn errors_carry_context() {
+    let mut table = bowling_club::Table::new(16);
+    let mut window = table.window();
+    let member = |name: &str, id: usize, age: u8| bowling_club::insert::Insert {
+        name: String::from(name),
+        id,
+        age,
+        fav_rgb_colour: RGB::Red,
+    };
+
+    let alice = window.insert(member("alice", 1, 30)).unwrap();
+    let bob = window.insert(member("bob", 2, 40)).unwrap();
+
+    match window.insert(member("carol", 1, 50)) {
+        Err(err @ bowling_club::insert::Error::unique_reference_number { existing, value }) => {
+            assert!(existing == alice);
+            assert_eq!(value, 1);
+            assert_eq!(
+                err.to_string(),
+                "Cannot insert into `bowling_club`, a row with the same `id` already exists (unique constraint `unique_reference_number`)"
+            );
+        }
+        _ => panic!("expected a unique conflict"),
+    }
+
+    match window.insert(member("dave", 3, 12)) {
+        Err(bowling_club::insert::Error::adults_only { row }) => assert_eq!(row.name, "dave"),
+        _ => panic!("expected a predicate failure"),
+    }
+
+    match window.update_age(bowling_club::updates::update_age::Update { age: 120 }, bob) {
+        Err(bowling_club::updates::update_age::UpdateError::age_cap { key, update }) => {
+            assert!(key == bob);
+            assert_eq!(update.age, 120);
+        }
+        _ => panic!("expected a predicate failure"),
+    }
+
+    window.delete(bob).unwrap();
+    match window.update_age(bowling_club::updates::update_age::Update { age: 50 }, bob) {
+        Err(err @ bowling_club::updates::update_age::UpdateError::KeyError { key }) => {
+            assert!(key == bob);
+            let err: Box<dyn std::error::Error> = Box::new(err);
+            assert_eq!(
+                err.to_string(),
+                "Cannot update `bowling_club`, the key does not refer to a row"
+            );
+        }
+        _ => panic!("expected a key error"),
+    }
+}
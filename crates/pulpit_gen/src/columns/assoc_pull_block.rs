@@ -10,7 +10,7 @@ impl ColKind for AssocPullBlocks {
     fn derives(&self) -> MutImmut<Vec<Ident>> {
         MutImmut {
             imm_fields: vec![Ident::new("Clone", Span::call_site())],
-            mut_fields: vec![],
+            mut_fields: vec![Ident::new("Clone", Span::call_site())],
         }
     }
 
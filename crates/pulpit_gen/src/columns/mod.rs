@@ -56,6 +56,13 @@ pub trait ColKind {
     fn requires_get_lifetime(&self) -> bool {
         false
     }
+
+    /// If the column's window implements `PrimaryWindowRestore`, so rows can
+    /// be placed at a given key.
+    fn restores(&self) -> bool {
+        false
+    }
+
     fn convert_imm(&self, namer: &CodeNamer, imm_fields: &[Field]) -> ImmConversion {
         let field_defs = imm_fields.iter().map(|Field { name, ty }| {
             quote! {
@@ -16,6 +16,10 @@ impl ColKind for PrimaryThunderdome {
         quote! { #pulpit_path::column::PrimaryThunderDome }.into()
     }
 
+    fn restores(&self) -> bool {
+        true
+    }
+
     fn check_column_application(
         &self,
         error_span: Span,
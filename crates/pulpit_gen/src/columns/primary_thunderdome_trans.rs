@@ -16,6 +16,10 @@ impl ColKind for PrimaryThunderDomeTrans {
         quote! { #pulpit_path::column::PrimaryThunderDomeTrans }.into()
     }
 
+    fn restores(&self) -> bool {
+        true
+    }
+
     fn check_column_application(
         &self,
         _error_span: Span,
@@ -72,6 +72,16 @@ impl<Col: ColKind> Group<Col> {
         .get(index.field_num)
         .unwrap()
     }
+
+    fn get_field_mut(&mut self, index: &FieldIndexInner) -> &mut Field {
+        if index.imm {
+            &mut self.fields.imm_fields
+        } else {
+            &mut self.fields.mut_fields
+        }
+        .get_mut(index.field_num)
+        .unwrap()
+    }
 }
 
 pub struct GroupConfig {
@@ -126,6 +136,16 @@ impl Groups {
     pub fn get_typefield(&self, field: &FieldName) -> Option<&Tokens<Type>> {
         self.get_field_index(field).and_then(|f| self.get_type(f))
     }
+
+    /// Change the type of a field after selection, for types that depend on
+    /// the selection of other tables (e.g. their [`Groups::untyped_key_type`]).
+    pub fn set_type(&mut self, field: &FieldName, ty: Tokens<Type>) {
+        let field = match self.idents.get(field).unwrap() {
+            FieldIndex::Primary(inner) => self.primary.get_field_mut(inner),
+            FieldIndex::Assoc { assoc_ind, inner } => self.assoc[*assoc_ind].get_field_mut(inner),
+        };
+        field.ty = ty;
+    }
 }
 
 pub struct FieldIndexInner {
@@ -252,6 +272,20 @@ impl Groups {
         .into()
     }
 
+    /// The key type, without using the types of the table's fields, so it can
+    /// be named outside of the table's module (e.g. as the type of a field in
+    /// another table).
+    /// - Only valid for primary columns with keys that do not depend on the
+    ///   data stored (all but [`crate::columns::PrimaryGenerationalArena`])
+    pub fn untyped_key_type(&self, namer: &CodeNamer) -> Tokens<Type> {
+        let CodeNamer { pulpit_path, .. } = namer;
+        let primary_type =
+            self.primary
+                .col
+                .generate_column_type(namer, quote!(()).into(), quote!(()).into());
+        quote!(<#primary_type as #pulpit_path::column::Keyable>::Key).into()
+    }
+
     pub fn columns_definition(&self, namer: &CodeNamer) -> GroupsDef {
         let CodeNamer {
             mod_columns,
@@ -0,0 +1,75 @@
+use quote::quote;
+use quote_debug::Tokens;
+use syn::{Ident, ItemImpl, ItemStruct, Type};
+
+use super::{groups::Groups, namer::CodeNamer};
+
+/// The data structure used for a secondary (non-unique) index.
+pub enum IndexKind {
+    /// A `pulpit::access::Ordered` index, for range scans & ordered iteration.
+    Ordered,
+    /// A `pulpit::access::Hashed` index, for equality lookups.
+    Hashed,
+}
+
+pub struct Index {
+    pub alias: Ident,
+    pub field: Ident,
+    pub kind: IndexKind,
+}
+
+impl Index {
+    /// The type of the index, from the type of the column indexed.
+    pub fn index_type(&self, groups: &Groups, namer: &CodeNamer) -> Tokens<Type> {
+        let CodeNamer {
+            pulpit_path,
+            type_key,
+            ..
+        } = namer;
+        let ty = groups.get_typefield(&self.field).unwrap();
+        match self.kind {
+            IndexKind::Ordered => quote!(#pulpit_path::access::Ordered<#ty, #type_key>),
+            IndexKind::Hashed => quote!(#pulpit_path::access::Hashed<#ty, #type_key>),
+        }
+        .into()
+    }
+}
+
+pub struct IndexDec {
+    pub index_struct: Tokens<ItemStruct>,
+    pub index_impl: Tokens<ItemImpl>,
+}
+
+pub fn generate(indexes: &[Index], groups: &Groups, namer: &CodeNamer) -> IndexDec {
+    let CodeNamer { struct_indexes, .. } = namer;
+
+    let index_fields_def = indexes.iter().map(|index| {
+        let field = &index.field;
+        let ty = index.index_type(groups, namer);
+        quote!(#field: #ty)
+    });
+    let index_fields_impl = indexes.iter().map(|index| {
+        let field = &index.field;
+        let ty = index.index_type(groups, namer);
+        quote!(#field: <#ty>::new(size_hint))
+    });
+
+    IndexDec {
+        index_struct: quote! {
+            struct #struct_indexes {
+                #(#index_fields_def),*
+            }
+        }
+        .into(),
+        index_impl: quote! {
+            impl #struct_indexes {
+                fn new(size_hint: usize) -> Self {
+                    Self {
+                        #(#index_fields_impl),*
+                    }
+                }
+            }
+        }
+        .into(),
+    }
+}
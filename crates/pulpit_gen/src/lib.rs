@@ -1,5 +1,6 @@
 pub mod columns;
 pub mod groups;
+pub mod indexes;
 pub mod limit;
 pub mod macros;
 pub mod namer;
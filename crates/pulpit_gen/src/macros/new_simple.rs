@@ -5,6 +5,7 @@ use std::collections::{HashMap, HashSet, LinkedList};
 
 use combi::{
     core::{choice, mapall, mapsuc, nothing, recover, seq, seqdiff},
+    derived::many0,
     logical::or,
     macros::{choices, seqs},
     tokens::{
@@ -26,10 +27,11 @@ use syn::Ident;
 
 use crate::{
     groups::Field,
+    indexes::{Index, IndexKind},
     limit::{Limit, LimitKind},
     operations::{get::Get, update::Update},
     predicates::Predicate,
-    selector::SelectOperations,
+    selector::{Read, SelectOperations},
     uniques::Unique,
 };
 
@@ -47,13 +49,34 @@ pub fn on_off() -> impl TokenParser<bool> {
 }
 
 fn fields_parser() -> impl TokenParser<Vec<ASTField>> {
-    let unique_parse = mapsuc(
-        seqs!(
+    let index_parse = mapsuc(
+        seq(
             matchpunct('@'),
-            matchident("unique"),
-            recovgroup(proc_macro2::Delimiter::Parenthesis, getident())
+            choices!(
+                peekident("unique") => mapsuc(
+                    seq(
+                        matchident("unique"),
+                        recovgroup(proc_macro2::Delimiter::Parenthesis, getident())
+                    ),
+                    |(_, id)| ASTIndex::Unique(id)
+                ),
+                peekident("ordered") => mapsuc(
+                    seq(
+                        matchident("ordered"),
+                        recovgroup(proc_macro2::Delimiter::Parenthesis, getident())
+                    ),
+                    |(_, id)| ASTIndex::Ordered(id)
+                ),
+                otherwise => mapsuc(
+                    seq(
+                        matchident("index"),
+                        recovgroup(proc_macro2::Delimiter::Parenthesis, getident())
+                    ),
+                    |(_, id)| ASTIndex::Hashed(id)
+                )
+            ),
         ),
-        |(_, (_, id))| Some(id),
+        |(_, index)| index,
     );
     let inner = listseptrailing(
         ',',
@@ -62,14 +85,14 @@ fn fields_parser() -> impl TokenParser<Vec<ASTField>> {
                 getident(),
                 matchpunct(':'),
                 collectuntil(or(peekpunct(','), peekpunct('@'))),
-                choice(peekpunct('@'), unique_parse, mapsuc(nothing(), |()| None))
+                many0(peekpunct('@'), index_parse)
             ),
-            |(name, (_, (ty, unique)))| ASTField {
+            |(name, (_, (ty, indexes)))| ASTField {
                 field_kind: Field {
                     name,
                     ty: ty.into(),
                 },
-                unique,
+                indexes,
             },
         ),
     );
@@ -121,9 +144,15 @@ fn limit_parser() -> impl TokenParser<Limit> {
     )
 }
 
+enum ASTIndex {
+    Unique(Ident),
+    Ordered(Ident),
+    Hashed(Ident),
+}
+
 struct ASTField {
     field_kind: Field,
-    unique: Option<Ident>,
+    indexes: Vec<ASTIndex>,
 }
 
 #[allow(clippy::too_many_arguments)]
@@ -141,6 +170,7 @@ fn analyse(
     let mut seen_access_names: HashSet<Ident> = HashSet::new();
     let mut field_types = HashMap::new();
     let mut uniques = Vec::new();
+    let mut indexes = Vec::new();
     let mut errors = LinkedList::new();
 
     let mut add_duplicate = |curr_name: &Ident, prev_name: &Ident| {
@@ -166,17 +196,38 @@ fn analyse(
         }
     }
 
-    for ASTField { field_kind, unique } in fields {
-        if let Some(alias) = unique {
+    for ASTField {
+        field_kind,
+        indexes: field_indexes,
+    } in fields
+    {
+        for index in field_indexes {
+            let alias = match &index {
+                ASTIndex::Unique(alias) | ASTIndex::Ordered(alias) | ASTIndex::Hashed(alias) => {
+                    alias.clone()
+                }
+            };
             if let Some(name) = seen_access_names.get(&alias) {
                 add_duplicate(&alias, name);
             } else {
                 seen_access_names.insert(alias.clone());
             }
-            uniques.push(Unique {
-                alias,
-                field: field_kind.name.clone(),
-            })
+            match index {
+                ASTIndex::Unique(_) => uniques.push(Unique {
+                    alias,
+                    field: field_kind.name.clone(),
+                }),
+                ASTIndex::Ordered(_) => indexes.push(Index {
+                    alias,
+                    field: field_kind.name.clone(),
+                    kind: IndexKind::Ordered,
+                }),
+                ASTIndex::Hashed(_) => indexes.push(Index {
+                    alias,
+                    field: field_kind.name.clone(),
+                    kind: IndexKind::Hashed,
+                }),
+            }
         }
 
         if let Some(name) = seen_access_names.get(&field_kind.name) {
@@ -194,14 +245,26 @@ fn analyse(
             deletions,
             fields: field_types,
             uniques,
+            indexes,
             predicates,
+            reads: gets
+                .iter()
+                .map(|Access { fields, .. }| Read {
+                    fields: fields.clone(),
+                    scan: false,
+                })
+                .collect(),
             gets: gets
                 .into_iter()
                 .map(|Access { alias, fields }| Get { alias, fields })
                 .collect(),
             updates: updates
                 .into_iter()
-                .map(|Access { alias, fields }| Update { alias, fields })
+                .map(|Access { alias, fields }| Update {
+                    alias,
+                    fields,
+                    current: false,
+                })
                 .collect(),
             public,
             limit,
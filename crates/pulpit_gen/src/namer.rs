@@ -14,7 +14,9 @@ pub struct CodeNamer {
     pub mod_predicates: Ident,
     pub struct_uniques_holder: Ident,
     pub struct_table: Ident,
+    pub struct_table_method_take: Ident,
     pub struct_table_member_uniques: Ident,
+    pub struct_table_member_indexes: Ident,
     pub struct_table_member_transactions: Ident,
     pub struct_table_member_columns: Ident,
     pub mod_columns: Ident,
@@ -43,14 +45,20 @@ pub struct CodeNamer {
     pub mod_unique_struct_notfound: Ident,
     pub mod_insert: Ident,
     pub mod_insert_struct_insert: Ident,
+    pub mod_insert_struct_insert_method_borrow: Ident,
     pub mod_insert_enum_error: Ident,
+    pub mod_insert_enum_restore_error: Ident,
     pub struct_unique: Ident,
+    pub struct_indexes: Ident,
     pub struct_window_holder: Ident,
     pub struct_window: Ident,
     pub struct_window_method_commit: Ident,
     pub struct_window_method_abort: Ident,
+    pub struct_window_method_savepoint: Ident,
+    pub struct_window_method_abort_to: Ident,
     pub struct_window_method_borrow: Ident,
     pub struct_window_method_insert: Ident,
+    pub struct_window_method_restore: Ident,
     pub struct_window_method_delete: Ident,
     pub struct_window_method_scan_brw: Ident,
     pub struct_window_method_scan_get: Ident,
@@ -89,6 +97,7 @@ impl CodeNamer {
             mod_predicates: new_id("predicates"),
             struct_uniques_holder: new_id("Uniques"),
             struct_table_member_uniques: new_id("uniques"),
+            struct_table_member_indexes: new_id("indexes"),
             struct_table_member_transactions: new_id("transactions"),
             mod_transactions: new_id("transactions"),
             mod_transactions_enum_logitem: new_id("LogItem"),
@@ -97,13 +106,17 @@ impl CodeNamer {
             mod_borrow_struct_borrow: new_id("Borrows"),
             struct_window: new_id("Window"),
             struct_table: new_id("Table"),
+            struct_table_method_take: new_id("take"),
             mod_borrow: new_id("borrows"),
             mod_get: new_id("get"),
             mod_get_struct_get: new_id("Get"),
             mod_insert: new_id("insert"),
             mod_insert_struct_insert: new_id("Insert"),
+            mod_insert_struct_insert_method_borrow: new_id("borrow"),
             mod_insert_enum_error: new_id("Error"),
+            mod_insert_enum_restore_error: new_id("RestoreError"),
             struct_unique: new_id("Uniques"),
+            struct_indexes: new_id("Indexes"),
             mod_transactions_struct_data: new_id("Data"),
             mod_transactions_struct_data_member_log: new_id("log"),
             mod_transactions_struct_data_member_rollback: new_id("rollback"),
@@ -113,8 +126,11 @@ impl CodeNamer {
             mod_transactions_enum_logitem_variant_delete: new_id("Delete"),
             struct_window_method_commit: new_id("commit"),
             struct_window_method_abort: new_id("abort"),
+            struct_window_method_savepoint: new_id("savepoint"),
+            struct_window_method_abort_to: new_id("abort_to"),
             struct_window_method_borrow: new_id("borrow"),
             struct_window_method_insert: new_id("insert"),
+            struct_window_method_restore: new_id("restore"),
             struct_window_method_delete: new_id("delete"),
             struct_window_method_scan_brw: new_id("borrow_indices"),
             struct_window_method_scan_get: new_id("get_indices"),
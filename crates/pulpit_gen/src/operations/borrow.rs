@@ -29,6 +29,24 @@ fn generate_borrow_fields<'a>(
     })
 }
 
+/// Construct the borrow struct from the columns' entries (named as in
+/// [`generate`]).
+pub fn generate_borrow_struct(groups: &Groups, namer: &CodeNamer) -> TokenStream {
+    let CodeNamer {
+        mod_borrow,
+        mod_borrow_struct_borrow,
+        name_phantom_member,
+        ..
+    } = namer;
+    let borrowed_fields = if groups.idents.is_empty() {
+        quote!(#name_phantom_member: std::marker::PhantomData)
+    } else {
+        let borrowed_fields = generate_borrow_fields(groups, namer);
+        quote!(#(#borrowed_fields),*)
+    };
+    quote!(#mod_borrow::#mod_borrow_struct_borrow { #borrowed_fields })
+}
+
 pub fn generate(groups: &Groups, namer: &CodeNamer, op_attrs: &TokenStream) -> SingleOp {
     let CodeNamer {
         type_key,
@@ -44,19 +62,16 @@ pub fn generate(groups: &Groups, namer: &CodeNamer, op_attrs: &TokenStream) -> S
         ..
     } = namer;
 
-    let (struct_fields_def, borrowed_fields) = if groups.idents.is_empty() {
-        (
-            quote!(pub #name_phantom_member: std::marker::PhantomData<&'brw ()>),
-            quote!(#name_phantom_member: std::marker::PhantomData),
-        )
+    let struct_fields_def = if groups.idents.is_empty() {
+        quote!(pub #name_phantom_member: std::marker::PhantomData<&'brw ()>)
     } else {
-        let borrowed_fields = generate_borrow_fields(groups, namer);
         let struct_fields = groups.idents.iter().map(|(field_name, field_index)| {
             let field_ty = groups.get_type(field_index).unwrap();
             quote!(pub #field_name: &'brw #field_ty)
         });
-        (quote! {#(#struct_fields),*}, quote!(#(#borrowed_fields),*))
+        quote! {#(#struct_fields),*}
     };
+    let borrowed = generate_borrow_struct(groups, namer);
 
     let assoc_brws = (0..groups.assoc.len()).map(|ind| {
         let name = namer.name_assoc_column(ind);
@@ -81,9 +96,7 @@ pub fn generate(groups: &Groups, namer: &CodeNamer, op_attrs: &TokenStream) -> S
                     };
                     #(#assoc_brws;)*
 
-                    Ok(#mod_borrow::#mod_borrow_struct_borrow {
-                        #borrowed_fields
-                    })
+                    Ok(#borrowed)
                 }
             }
         }
@@ -1,5 +1,6 @@
 use crate::{
     groups::{FieldIndex, Groups},
+    indexes::Index,
     namer::CodeNamer,
     operations::SingleOpFn,
     uniques::Unique,
@@ -14,6 +15,7 @@ pub fn generate(
     namer: &CodeNamer,
     groups: &Groups,
     uniques: &[Unique],
+    indexes: &[Index],
     transactions: bool,
     op_attrs: &TokenStream,
 ) -> SingleOpFn {
@@ -35,6 +37,7 @@ pub fn generate(
         struct_window_method_delete_hidden,
         struct_window_method_restore_hidden,
         struct_table_member_uniques,
+        struct_table_member_indexes,
         struct_window_method_borrow,
         ..
     } = namer;
@@ -42,7 +45,7 @@ pub fn generate(
     let index_ident = Ident::new("index", Span::call_site());
     let brw_ident = Ident::new("brw_data", Span::call_site());
 
-    let unique_deletions = uniques.iter().map(|Unique { alias: _, field }| {
+    let pulled_field_access = |field: &Ident| {
         let field_index = groups.get_field_index(field).unwrap();
         let data = match field_index {
             FieldIndex::Primary(_) => namer.name_primary_column.clone(),
@@ -58,12 +61,21 @@ pub fn generate(
             quote!(mut_data)
         };
 
-        // POSSIBLE BUG: pulling values does not consider the transformations
-        //               that may need to be applied to immutable values
-        //               (`ImmPull`), or autodereference might take care of
-        //               this - not failing any tests for retain, would fail for
-        //               other wrappings?
-        quote!(self.#struct_table_member_uniques.#field.pull(&#data.#imm_access.#field).unwrap())
+        quote!(#data.#imm_access.#field)
+    };
+
+    // POSSIBLE BUG: pulling values does not consider the transformations
+    //               that may need to be applied to immutable values
+    //               (`ImmPull`), or autodereference might take care of
+    //               this - not failing any tests for retain, would fail for
+    //               other wrappings?
+    let unique_deletions = uniques.iter().map(|Unique { alias: _, field }| {
+        let access = pulled_field_access(field);
+        quote!(self.#struct_table_member_uniques.#field.pull(&#access).unwrap())
+    });
+    let index_deletions = indexes.iter().map(|Index { field, .. }| {
+        let access = pulled_field_access(field);
+        quote!(self.#struct_table_member_indexes.#field.pull(&#access, #key_ident).unwrap())
     });
 
     let assoc_cols = (0..groups.assoc.len())
@@ -78,6 +90,7 @@ pub fn generate(
                 Ok(#pulpit_path::column::Entry{ index: #index_ident, data: #name_primary_column }) => {
                     #(#assoc_cols;)*
                     #(#unique_deletions;)*
+                    #(#index_deletions;)*
                     Ok(())
                 },
                 Err(_) => Err(#type_key_error),
@@ -101,6 +114,31 @@ pub fn generate(
             quote!(self.#struct_table_member_uniques.#field.insert(#alias, #key_ident).unwrap())
         });
 
+        // Hidden rows are removed from secondary indexes immediately, so that
+        // range scans do not return keys that cannot be accessed.
+        let get_clone_of_indexes = indexes
+            .iter()
+            .map(|Index { alias, field, .. }| quote!(let #alias = #brw_ident.#field.clone()))
+            .collect::<Vec<_>>();
+        let restore_indexes_from_borrow = indexes.iter().map(|Index { alias, field, .. }| {
+            quote!(self.#struct_table_member_indexes.#field.insert(#alias, #key_ident))
+        });
+        let hide_from_indexes = if indexes.is_empty() {
+            quote!()
+        } else {
+            let index_pulls = indexes.iter().map(|Index { alias, field, .. }| {
+                quote!(self.#struct_table_member_indexes.#field.pull(&#alias, #key_ident).unwrap())
+            });
+            quote! {
+                let #brw_ident = match self.#struct_window_method_borrow(#key_ident) {
+                    Ok(brw) => brw,
+                    Err(_) => return Err(#type_key_error),
+                };
+                #(#get_clone_of_indexes;)*
+                #(#index_pulls;)*
+            }
+        };
+
         quote! {
             impl <'imm> #struct_window<'imm> {
                 fn #struct_window_method_reverse_insert(&mut self, #key_ident: #type_key) {
@@ -123,11 +161,14 @@ pub fn generate(
                     self.#table_member_columns.#name_primary_column.reveal(#key_ident).unwrap();
                     let #brw_ident = self.#struct_window_method_borrow(#key_ident).unwrap();
                     #(#get_clone_of_uniques;)*
+                    #(#get_clone_of_indexes;)*
                     #(#restore_unique_from_borrow;)*
+                    #(#restore_indexes_from_borrow;)*
                 }
 
                 #op_attrs
                 pub fn #method_delete(&mut self, #key_ident: #type_key) -> Result<(), #type_key_error> {
+                    #hide_from_indexes
                     match self.#table_member_columns.#name_primary_column.hide(#key_ident) {
                         Ok(()) => (),
                         Err(_) => return Err(#type_key_error),
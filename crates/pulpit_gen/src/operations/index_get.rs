@@ -0,0 +1,37 @@
+use super::SingleOpFn;
+use crate::{groups::Groups, indexes::Index, namer::CodeNamer};
+use proc_macro2::TokenStream;
+use quote::quote;
+
+pub fn generate(
+    groups: &Groups,
+    indexes: &[Index],
+    namer: &CodeNamer,
+    op_attrs: &TokenStream,
+) -> SingleOpFn {
+    let CodeNamer {
+        struct_window,
+        struct_table_member_indexes,
+        ..
+    } = namer;
+
+    let index_methods = indexes.iter().map(|index| {
+        let Index { alias, field, .. } = index;
+        let ty = index.index_type(groups, namer);
+        quote! {
+            #op_attrs
+            pub fn #alias(&self) -> &#ty {
+                &self.#struct_table_member_indexes.#field
+            }
+        }
+    });
+
+    SingleOpFn {
+        op_impl: quote! {
+            impl <'imm> #struct_window<'imm> {
+                #(#index_methods)*
+            }
+        }
+        .into(),
+    }
+}
@@ -1,9 +1,10 @@
 use std::iter::once;
 
-use super::SingleOp;
+use super::{generate_error_enum, ErrorVariant, SingleOp};
 use crate::{
     columns::ColKind,
     groups::{Field, Group, Groups},
+    indexes::Index,
     limit::Limit,
     namer::CodeNamer,
     predicates::Predicate,
@@ -52,13 +53,16 @@ pub fn generate_column_assignments<Col: ColKind>(
 
 #[allow(clippy::too_many_arguments)]
 pub fn generate(
+    name: &Ident,
     groups: &Groups,
     uniques: &[Unique],
+    indexes: &[Index],
     predicates: &[Predicate],
     namer: &CodeNamer,
     limit: &Option<Limit>,
     deletions: bool,
     transactions: bool,
+    restores: bool,
     op_attrs: &TokenStream,
 ) -> SingleOp {
     let CodeNamer {
@@ -66,11 +70,14 @@ pub fn generate(
         struct_window,
         mod_insert,
         mod_insert_struct_insert,
+        mod_insert_struct_insert_method_borrow,
         mod_insert_enum_error,
+        mod_insert_enum_restore_error,
         mod_borrow,
         mod_borrow_struct_borrow,
         mod_predicates,
         struct_table_member_uniques: table_member_uniques,
+        struct_table_member_indexes: table_member_indexes,
         struct_table_member_columns: table_member_columns,
         pulpit_path,
         name_primary_column,
@@ -82,6 +89,7 @@ pub fn generate(
         mod_transactions_struct_data_member_rollback,
         mod_transactions_struct_data_member_log,
         struct_window_method_insert: method_insert,
+        struct_window_method_restore: method_restore,
         name_phantom_member,
         struct_window_method_count,
         ..
@@ -95,6 +103,21 @@ pub fn generate(
         quote!(pub #field_name: #ty)
     });
 
+    let borrowed_fields = if groups.idents.is_empty() {
+        quote!(#name_phantom_member: std::marker::PhantomData)
+    } else {
+        let fields = groups.idents.keys().map(|name| quote!(#name: &self.#name));
+        quote!(#(#fields),*)
+    };
+    let insert_borrow = quote! {
+        impl #mod_insert_struct_insert {
+            /// Borrow the values of the row (as borrowed from a window).
+            pub fn #mod_insert_struct_insert_method_borrow(&self) -> super::#mod_borrow::#mod_borrow_struct_borrow<'_> {
+                super::#mod_borrow::#mod_borrow_struct_borrow { #borrowed_fields }
+            }
+        }
+    };
+
     let predicate_args_stream = groups
         .idents
         .keys()
@@ -107,53 +130,116 @@ pub fn generate(
         quote!(#(#predicate_args_stream),*)
     };
 
-    let predicate_checks = predicates.iter().map(|Predicate { alias, tokens: _ }| {
-        quote! {
-            if !#mod_predicates::#alias(#mod_borrow::#mod_borrow_struct_borrow{ #predicate_args }) {
-                return Err(#mod_insert::#mod_insert_enum_error::#alias);
-            }
-        }
-    });
+    // checks are shared by insert and restore, which wrap the error differently
+    let predicate_checks = |wrap: &dyn Fn(TokenStream) -> TokenStream| {
+        predicates
+            .iter()
+            .map(|Predicate { alias, tokens: _ }| {
+                let err = wrap(quote!(#mod_insert::#mod_insert_enum_error::#alias { row: #insert_val }));
+                quote! {
+                    if !#mod_predicates::#alias(#mod_borrow::#mod_borrow_struct_borrow{ #predicate_args }) {
+                        return Err(#err);
+                    }
+                }
+            })
+            .collect::<Vec<_>>()
+    };
 
+    // errors carry the rejected row, or the value and key of the row it
+    // conflicts with
+    let row_field = Ident::new("row", Span::call_site());
     let mut errors = uniques
         .iter()
-        .map(|Unique { alias, .. }| alias)
+        .map(|Unique { alias, field }| {
+            let ty = groups.get_typefield(field).unwrap();
+            ErrorVariant {
+                name: alias.clone(),
+                fields: vec![
+                    (Ident::new("existing", Span::call_site()), quote!(super::#type_key)),
+                    (Ident::new("value", Span::call_site()), quote!(#ty)),
+                ],
+                message: format!("Cannot insert into `{name}`, a row with the same `{field}` already exists (unique constraint `{alias}`)"),
+            }
+        })
         .chain(
             predicates
                 .iter()
-                .map(|Predicate { alias, tokens: _ }| alias),
+                .map(|Predicate { alias, tokens: _ }| ErrorVariant {
+                    name: alias.clone(),
+                    fields: vec![(row_field.clone(), quote!(#mod_insert_struct_insert))],
+                    message: format!("Cannot insert into `{name}`, the row does not satisfy the predicate `{alias}`"),
+                }),
         )
         .collect::<Vec<_>>();
 
-    let limit_cons = if let Some(limit) = limit {
+    if let Some(limit) = limit {
         let alias = &limit.alias;
-        errors.push(alias);
-        let value = limit.generate_check();
-        quote! {
-            {
-                if self.#struct_window_method_count() >= #value {
-                    return Err(#mod_insert::#mod_insert_enum_error::#alias);
+        errors.push(ErrorVariant {
+            name: alias.clone(),
+            fields: vec![(row_field, quote!(#mod_insert_struct_insert))],
+            message: format!("Cannot insert into `{name}`, the table is full (limit `{alias}`)"),
+        });
+    }
+    let limit_cons = |wrap: &dyn Fn(TokenStream) -> TokenStream| {
+        if let Some(limit) = limit {
+            let alias = &limit.alias;
+            let value = limit.generate_check();
+            let err =
+                wrap(quote!(#mod_insert::#mod_insert_enum_error::#alias { row: #insert_val }));
+            quote! {
+                {
+                    if self.#struct_window_method_count() >= #value {
+                        return Err(#err);
+                    }
                 }
             }
+        } else {
+            quote!()
         }
-    } else {
-        quote!()
     };
 
-    let unique_checks = uniques.iter().map(|Unique { alias, field }| {
-        quote! {
-            let #alias = match self.#table_member_uniques.#field.lookup(&#insert_val.#field) {
-                Ok(_) => return Err(#mod_insert::#mod_insert_enum_error::#alias),
-                Err(_) => #insert_val.#field.clone(),
-            };
-        }
-    });
+    let unique_checks = |wrap: &dyn Fn(TokenStream) -> TokenStream| {
+        uniques
+            .iter()
+            .map(|Unique { alias, field }| {
+                let err = wrap(quote!(#mod_insert::#mod_insert_enum_error::#alias { existing, value: #insert_val.#field }));
+                quote! {
+                    let #alias = match self.#table_member_uniques.#field.lookup(&#insert_val.#field) {
+                        Ok(existing) => return Err(#err),
+                        Err(_) => #insert_val.#field.clone(),
+                    };
+                }
+            })
+            .collect::<Vec<_>>()
+    };
 
-    let unique_updates = uniques.iter().map(|Unique { alias, field }| {
-        quote! {
-            self.#table_member_uniques.#field.insert(#alias, #key_var).unwrap();
-        }
-    });
+    let unique_updates = uniques
+        .iter()
+        .map(|Unique { alias, field }| {
+            quote! {
+                self.#table_member_uniques.#field.insert(#alias, #key_var).unwrap();
+            }
+        })
+        .collect::<Vec<_>>();
+
+    // secondary indexes cannot fail, so are only updated after the row is placed
+    let index_copies = indexes
+        .iter()
+        .map(|Index { alias, field, .. }| {
+            quote! {
+                let #alias = #insert_val.#field.clone();
+            }
+        })
+        .collect::<Vec<_>>();
+
+    let index_updates = indexes
+        .iter()
+        .map(|Index { alias, field, .. }| {
+            quote! {
+                self.#table_member_indexes.#field.insert(#alias, #key_var);
+            }
+        })
+        .collect::<Vec<_>>();
 
     let splitting = once(generate_column_assignments(
         &namer.name_primary_column.clone(),
@@ -168,7 +254,8 @@ pub fn generate(
             &groups.assoc[ind],
             namer,
         )
-    }));
+    }))
+    .collect::<Vec<_>>();
 
     let assoc_grps = (0..groups.assoc.len()).map(|ind| namer.name_assoc_column(ind));
     let appends = assoc_grps.clone().map(|grp| {
@@ -216,7 +303,7 @@ pub fn generate(
             if transactions {
                 quote! {
                     if !self.#table_member_transactions.#mod_transactions_struct_data_member_rollback {
-                        self.#table_member_transactions.#mod_transactions_struct_data_member_log.push(#mod_transactions::#mod_transactions_enum_logitem::#mod_transactions_enum_logitem_variant_append);
+                        self.#table_member_transactions.#mod_transactions_struct_data_member_log.push(#mod_transactions::#mod_transactions_enum_logitem::#mod_transactions_enum_logitem_variant_append(#key_var));
                     }
                 }
             } else {
@@ -225,6 +312,67 @@ pub fn generate(
         )
     };
 
+    let (restore_error, restore_fn) = if restores {
+        let wrap_insert = if errors.is_empty() {
+            quote!()
+        } else {
+            quote!(Insert(#mod_insert_enum_error),)
+        };
+        let display_insert = if errors.is_empty() {
+            quote!()
+        } else {
+            quote!(Self::Insert(e) => e.fmt(f),)
+        };
+        let occupied_message = format!("Cannot restore into `{name}`, the key is already in use");
+        let wrap =
+            |err: TokenStream| quote!(#mod_insert::#mod_insert_enum_restore_error::Insert(#err));
+        let restore_limit = limit_cons(&wrap);
+        let restore_predicates = predicate_checks(&wrap);
+        let restore_uniques = unique_checks(&wrap);
+        (
+            quote! {
+                /// An error from restoring a row at a given key.
+                #[derive(Debug)]
+                pub enum #mod_insert_enum_restore_error {
+                    Occupied,
+                    #wrap_insert
+                }
+
+                impl std::fmt::Display for #mod_insert_enum_restore_error {
+                    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+                        match self {
+                            Self::Occupied => f.write_str(#occupied_message),
+                            #display_insert
+                        }
+                    }
+                }
+
+                impl std::error::Error for #mod_insert_enum_restore_error {}
+            },
+            quote! {
+                /// Insert a row at a key, rather than the key chosen by the table.
+                /// - Used to restore rows with the keys they had when snapshot.
+                /// - Fails if the key is already in use.
+                #op_attrs
+                pub fn #method_restore(&mut self, #key_var: #type_key, #insert_val: #mod_insert::#mod_insert_struct_insert) -> Result<(), #mod_insert::#mod_insert_enum_restore_error> {
+                    #restore_limit
+                    #(#restore_predicates)*
+                    #(#restore_uniques)*
+                    #(#index_copies)*
+                    #(#splitting;)*
+                    self.#table_member_columns.#name_primary_column.restore(#key_var, #name_primary_column).map_err(|_| #mod_insert::#mod_insert_enum_restore_error::Occupied)?;
+                    #(#unique_updates)*
+                    #(#index_updates)*
+                    #add_trans
+
+                    Ok(())
+                }
+            },
+        )
+    } else {
+        (quote!(), quote!())
+    };
+
     if errors.is_empty() {
         SingleOp {
             op_mod: quote! {
@@ -232,6 +380,8 @@ pub fn generate(
                     pub struct #mod_insert_struct_insert {
                         #(#insert_struct_fields,)*
                     }
+                    #insert_borrow
+                    #restore_error
                 }
             }
             .into(),
@@ -239,26 +389,33 @@ pub fn generate(
                 impl <'imm> #struct_window<'imm> {
                     #op_attrs
                     pub fn #method_insert(&mut self, #insert_val: #mod_insert::#mod_insert_struct_insert) -> #type_key {
+                        #(#index_copies)*
                         #(#splitting;)*
                         #add_action
+                        #(#index_updates)*
                         #add_trans
                         key
                     }
+
+                    #restore_fn
                 }
             }
             .into(),
         }
     } else {
+        let error_enum = generate_error_enum(mod_insert_enum_error, &errors);
+        let limit_cons = limit_cons(&|err| err);
+        let predicate_checks = predicate_checks(&|err| err);
+        let unique_checks = unique_checks(&|err| err);
         SingleOp {
             op_mod: quote! {
                 pub mod #mod_insert {
                     pub struct #mod_insert_struct_insert {
                         #(#insert_struct_fields,)*
                     }
-                    #[derive(Debug)]
-                    pub enum #mod_insert_enum_error {
-                        #(#errors,)*
-                    }
+                    #insert_borrow
+                    #error_enum
+                    #restore_error
                 }
             }
             .into(),
@@ -269,13 +426,17 @@ pub fn generate(
                         #limit_cons
                         #(#predicate_checks)*
                         #(#unique_checks)*
+                        #(#index_copies)*
                         #(#splitting;)*
                         #add_action
                         #(#unique_updates)*
+                        #(#index_updates)*
                         #add_trans
 
                         Ok(#key_var)
                     }
+
+                    #restore_fn
                 }
             }
             .into(),
@@ -2,14 +2,18 @@ pub mod borrow;
 pub mod count;
 pub mod delete;
 pub mod get;
+pub mod index_get;
 pub mod insert;
 pub mod scan;
+pub mod take;
 pub mod transact;
 pub mod unique_get;
 pub mod update;
 
+use proc_macro2::TokenStream;
+use quote::quote;
 use quote_debug::Tokens;
-use syn::{ItemImpl, ItemMod};
+use syn::{Ident, ItemImpl, ItemMod};
 
 pub struct SingleOp {
     pub op_mod: Tokens<ItemMod>,
@@ -19,3 +23,52 @@ pub struct SingleOp {
 pub struct SingleOpFn {
     pub op_impl: Tokens<ItemImpl>,
 }
+
+/// A variant of an operation's error, carrying the values that caused it.
+pub struct ErrorVariant {
+    pub name: Ident,
+    pub fields: Vec<(Ident, TokenStream)>,
+    pub message: String,
+}
+
+/// Generate an operation's error enum, implementing [`std::fmt::Display`] and
+/// [`std::error::Error`].
+/// - [`std::fmt::Debug`] is implemented without the carried values, as keys and
+///   field types are not required to implement it.
+pub fn generate_error_enum(name: &Ident, variants: &[ErrorVariant]) -> TokenStream {
+    let defs = variants.iter().map(|ErrorVariant { name, fields, .. }| {
+        let fields = fields.iter().map(|(field, ty)| quote!(#field: #ty));
+        quote!(#name { #(#fields),* })
+    });
+    let debugs = variants.iter().map(|ErrorVariant { name, .. }| {
+        let name_str = name.to_string();
+        quote!(Self::#name { .. } => f.debug_struct(#name_str).finish_non_exhaustive())
+    });
+    let displays = variants.iter().map(
+        |ErrorVariant { name, message, .. }| quote!(Self::#name { .. } => f.write_str(#message)),
+    );
+
+    quote! {
+        pub enum #name {
+            #(#defs,)*
+        }
+
+        impl std::fmt::Debug for #name {
+            fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+                match self {
+                    #(#debugs,)*
+                }
+            }
+        }
+
+        impl std::fmt::Display for #name {
+            fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+                match self {
+                    #(#displays,)*
+                }
+            }
+        }
+
+        impl std::error::Error for #name {}
+    }
+}
@@ -0,0 +1,71 @@
+use super::SingleOpFn;
+use crate::{
+    groups::{Field, Groups},
+    namer::CodeNamer,
+};
+use proc_macro2::TokenStream;
+use quote::quote;
+
+/// Generates a consuming `take` for the table, to move its rows (e.g. into a
+/// table of another version of the schema) without copying.
+pub fn generate(groups: &Groups, namer: &CodeNamer) -> SingleOpFn {
+    let CodeNamer {
+        pulpit_path,
+        struct_table,
+        struct_table_method_take,
+        struct_table_member_columns,
+        struct_column_holder,
+        name_primary_column,
+        mod_insert,
+        mod_insert_struct_insert,
+        ..
+    } = namer;
+
+    let assoc_names = (0..groups.assoc.len())
+        .map(|ind| namer.name_assoc_column(ind))
+        .collect::<Vec<_>>();
+
+    let fields = |column: &TokenStream, fields: &[Field], imm_access: TokenStream| {
+        fields
+            .iter()
+            .map(|Field { name, .. }| quote!(#name: #column.#imm_access.#name))
+            .collect::<Vec<_>>()
+    };
+    let insert_fields = std::iter::once((quote!(#name_primary_column), &groups.primary.fields))
+        .chain(
+            assoc_names
+                .iter()
+                .zip(&groups.assoc)
+                .map(|(name, group)| (quote!(#name), &group.fields)),
+        )
+        .flat_map(|(column, group_fields)| {
+            fields(&column, &group_fields.imm_fields, quote!(imm_data))
+                .into_iter()
+                .chain(fields(&column, &group_fields.mut_fields, quote!(mut_data)))
+        });
+
+    SingleOpFn {
+        op_impl: quote! {
+            impl #struct_table {
+                /// Consume the table, moving out each row.
+                pub fn #struct_table_method_take(self) -> impl Iterator<Item = #mod_insert::#mod_insert_struct_insert> {
+                    let #struct_column_holder { #name_primary_column, #(#assoc_names),* } = self.#struct_table_member_columns;
+                    #(let mut #assoc_names = #pulpit_path::column::ColumnTake::take(#assoc_names).into_iter();)*
+                    #pulpit_path::column::ColumnTake::take(#name_primary_column)
+                        .into_iter()
+                        .filter_map(move |#name_primary_column| {
+                            // associated columns are advanced for every index, including those with no row
+                            #(let #assoc_names = #assoc_names.next().flatten();)*
+                            let #name_primary_column = #name_primary_column?;
+                            #(let #assoc_names = #assoc_names.expect("Associated columns have a value for each row");)*
+                            Some(#mod_insert::#mod_insert_struct_insert {
+                                #(#insert_fields),*
+                            })
+                        })
+                }
+            }
+
+        }
+        .into(),
+    }
+}
@@ -1,11 +1,12 @@
 use super::{update::Update, SingleOp};
-use crate::{groups::Groups, namer::CodeNamer};
+use crate::{groups::Groups, indexes::Index, namer::CodeNamer};
 use proc_macro2::TokenStream;
 use quote::quote;
 
 pub fn generate(
     groups: &Groups,
     updates: &[Update],
+    indexes: &[Index],
     namer: &CodeNamer,
     deletions: bool,
     _transactions: bool,
@@ -31,14 +32,18 @@ pub fn generate(
         name_primary_column,
         struct_window_method_commit: method_commit,
         struct_window_method_abort: method_abort,
+        struct_window_method_savepoint: method_savepoint,
+        struct_window_method_abort_to: method_abort_to,
         struct_window_method_delete_hidden,
         struct_window_method_reverse_insert,
         struct_window_method_restore_hidden,
+        struct_window_method_borrow,
+        struct_table_member_indexes,
         ..
     } = namer;
 
     let updates_variants = updates.iter().map(
-        |Update { fields: _, alias }| quote!(#alias(super::#mod_update::#alias::#mod_update_struct_update)),
+        |Update { alias, .. }| quote!(#alias(super::#mod_update::#alias::#mod_update_struct_update)),
     );
 
     let log_variants = if deletions {
@@ -50,14 +55,20 @@ pub fn generate(
     } else {
         quote! {
             #mod_transactions_enum_logitem_variant_update(super::#type_key, #mod_transactions_enum_update),
-            #mod_transactions_enum_logitem_variant_append,
+            #mod_transactions_enum_logitem_variant_append(super::#type_key),
         }
     };
 
-    let abort_update = updates.iter().map(|Update { fields: _, alias }| {
+    let abort_update = updates.iter().map(|Update { alias, current, .. }| {
+        // the logged update contains the previous values to restore
+        let restore = if *current {
+            quote!(|_| update)
+        } else {
+            quote!(update)
+        };
         quote! {
             #mod_transactions::#mod_transactions_enum_update::#alias(update) => {
-                self.#alias(update, key).unwrap();
+                self.#alias(#restore, key).unwrap();
             }
         }
     });
@@ -67,6 +78,17 @@ pub fn generate(
         }
     }};
 
+    // the log is only appended to (and popped by aborts), so its length marks
+    // the transactions to keep
+    let savepoint = quote! {
+        /// A savepoint that can be aborted to, keeping the transactions
+        /// applied before it
+        #op_attrs
+        pub fn #method_savepoint(&self) -> usize {
+            self.#table_member_transactions.#mod_transactions_struct_data_member_log.len()
+        }
+    };
+
     let op_impl = if deletions {
         quote! {
             impl <'imm> #struct_window<'imm> {
@@ -91,9 +113,18 @@ pub fn generate(
                 ///   (deletes' keys are actually just hidden until commit or abort)
                 #op_attrs
                 pub fn #method_abort(&mut self) {
+                    self.#method_abort_to(0)
+                }
+
+                #savepoint
+
+                /// Undo the transactions applied since the `savepoint`, keeping
+                /// those before it
+                #op_attrs
+                pub fn #method_abort_to(&mut self, savepoint: usize) {
                     self.#table_member_transactions.#mod_transactions_struct_data_member_rollback = true;
-                    while let Some(entry) = self.#table_member_transactions.#mod_transactions_struct_data_member_log.pop() {
-                        match entry {
+                    while self.#table_member_transactions.#mod_transactions_struct_data_member_log.len() > savepoint {
+                        match self.#table_member_transactions.#mod_transactions_struct_data_member_log.pop().unwrap() {
                             #mod_transactions::#mod_transactions_enum_logitem::#mod_transactions_enum_logitem_variant_delete(key) => {
                                 self.#struct_window_method_restore_hidden(key);
                             },
@@ -114,6 +145,24 @@ pub fn generate(
             quote!(self.#table_member_columns.#name.assoc_unppend())
         });
 
+        // The appended row is still borrowable, so we can get the values to
+        // remove from the secondary indexes before unppending.
+        let index_removal = if indexes.is_empty() {
+            quote!()
+        } else {
+            let index_copies = indexes
+                .iter()
+                .map(|Index { alias, field, .. }| quote!(let #alias = brw_data.#field.clone()));
+            let index_pulls = indexes.iter().map(|Index { alias, field, .. }| {
+                quote!(self.#struct_table_member_indexes.#field.pull(&#alias, key).unwrap())
+            });
+            quote! {
+                let brw_data = self.#struct_window_method_borrow(key).unwrap();
+                #(#index_copies;)*
+                #(#index_pulls;)*
+            }
+        };
+
         quote! {
             impl <'imm> #struct_window<'imm> {
                 /// Commit all current changes
@@ -129,10 +178,20 @@ pub fn generate(
                 ///   (deletes' keys are actually just hidden until commit or abort)
                 #op_attrs
                 pub fn #method_abort(&mut self) {
+                    self.#method_abort_to(0)
+                }
+
+                #savepoint
+
+                /// Undo the transactions applied since the `savepoint`, keeping
+                /// those before it
+                #op_attrs
+                pub fn #method_abort_to(&mut self, savepoint: usize) {
                     self.#table_member_transactions.#mod_transactions_struct_data_member_rollback = true;
-                    while let Some(entry) = self.#table_member_transactions.#mod_transactions_struct_data_member_log.pop() {
-                        match entry {
-                            #mod_transactions::#mod_transactions_enum_logitem::#mod_transactions_enum_logitem_variant_append => {
+                    while self.#table_member_transactions.#mod_transactions_struct_data_member_log.len() > savepoint {
+                        match self.#table_member_transactions.#mod_transactions_struct_data_member_log.pop().unwrap() {
+                            #mod_transactions::#mod_transactions_enum_logitem::#mod_transactions_enum_logitem_variant_append(key) => {
+                                #index_removal
                                 unsafe{
                                     self.#table_member_columns.#name_primary_column.unppend();
                                     #(#assoc_cols;)*
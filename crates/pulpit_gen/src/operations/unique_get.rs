@@ -1,9 +1,10 @@
 use super::SingleOp;
 use crate::{groups::Groups, namer::CodeNamer, uniques::Unique};
-use proc_macro2::TokenStream;
+use proc_macro2::{Ident, TokenStream};
 use quote::quote;
 
 pub fn generate(
+    name: &Ident,
     groups: &Groups,
     uniques: &[Unique],
     namer: &CodeNamer,
@@ -31,11 +32,21 @@ pub fn generate(
         }
     });
 
+    let not_found_message = format!("No row in `{name}` has the unique value");
+
     SingleOp {
         op_mod: quote! {
             pub mod #mod_unique {
                 #[derive(Debug)]
                 pub struct #mod_unique_struct_notfound;
+
+                impl std::fmt::Display for #mod_unique_struct_notfound {
+                    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+                        f.write_str(#not_found_message)
+                    }
+                }
+
+                impl std::error::Error for #mod_unique_struct_notfound {}
             }
         }
         .into(),
@@ -1,28 +1,39 @@
+use std::iter::once;
+
 use proc_macro2::{Span, TokenStream};
 use quote::quote;
 use quote_debug::Tokens;
-use syn::{ExprLet, ExprMethodCall, Ident, ImplItemFn, ItemMod, Variant};
+use syn::{ExprLet, ExprMethodCall, Ident, ImplItemFn, ItemMod};
 
 use crate::{
     groups::{FieldIndex, Groups},
+    indexes::Index,
     namer::CodeNamer,
+    operations::borrow::generate_borrow_struct,
     predicates::{generate_update_predicate_access, Predicate},
     uniques::Unique,
 };
 
-use super::SingleOp;
+use super::{generate_error_enum, ErrorVariant, SingleOp};
 
 /// An update operation, replacing [`Update::fields`] with new values.
 /// - Named for the user by [`Update::alias`]
+/// - If [`Update::current`], the new values are computed by a closure from a
+///   borrow of the row's current values (taken from the same `brw_mut` used to
+///   update the row).
 pub struct Update {
     pub fields: Vec<Ident>,
     pub alias: Ident,
+    pub current: bool,
 }
 
+#[allow(clippy::too_many_arguments)]
 pub fn generate(
+    name: &Ident,
     updates: &[Update],
     groups: &Groups,
     uniques: &[Unique],
+    indexes: &[Index],
     predicates: &[Predicate],
     namer: &CodeNamer,
     transactions: bool,
@@ -36,9 +47,17 @@ pub fn generate(
 
     let modules = updates
         .iter()
-        .map(|update| update.generate_mod(groups, uniques, predicates, namer));
+        .map(|update| update.generate_mod(name, groups, uniques, predicates, namer));
     let impl_fns = updates.iter().map(|update| {
-        update.generate_trait_impl_fn(namer, groups, uniques, predicates, transactions, op_attrs)
+        update.generate_trait_impl_fn(
+            namer,
+            groups,
+            uniques,
+            indexes,
+            predicates,
+            transactions,
+            op_attrs,
+        )
     });
 
     SingleOp {
@@ -60,72 +79,67 @@ pub fn generate(
 impl Update {
     fn generate_mod(
         &self,
+        name: &Ident,
         groups: &Groups,
         uniques: &[Unique],
         predicates: &[Predicate],
         namer: &CodeNamer,
     ) -> Tokens<ItemMod> {
-        fn generate_unique_error_variants<'a>(
-            unique_indexes: impl Iterator<Item = &'a Unique>,
-        ) -> Vec<Tokens<Variant>> {
-            unique_indexes
-                .map(|unique| {
-                    let variant = &unique.alias;
-                    quote!(
-                        #variant
-                    )
-                    .into()
-                })
-                .collect()
-        }
-
-        fn generate_predicate_error_variants(predicates: &[Predicate]) -> Vec<Tokens<Variant>> {
-            predicates
-                .iter()
-                .map(|pred| {
-                    let variant = &pred.alias;
-                    quote!(
-                        #variant
-                    )
-                    .into()
-                })
-                .collect()
-        }
         let CodeNamer {
             mod_update_enum_error,
             type_key_error,
+            type_key,
             mod_update_struct_update,
             ..
         } = namer;
 
         let update_name = &self.alias;
+        let key_field = Ident::new("key", Span::call_site());
 
-        // get the unique error types
-        let unique_indexes = uniques
+        // errors carry the key of the updated row, and the update or the value
+        // and key of the row it conflicts with
+        let key_error = ErrorVariant {
+            name: type_key_error.clone(),
+            fields: vec![(key_field.clone(), quote!(super::super::#type_key))],
+            message: format!("Cannot update `{name}`, the key does not refer to a row"),
+        };
+        let unique_errors = uniques
             .iter()
-            .filter(|uniq| self.fields.contains(&uniq.field));
-        let unique_errors = generate_unique_error_variants(unique_indexes);
-        let predicate_errors = generate_predicate_error_variants(predicates);
+            .filter(|uniq| self.fields.contains(&uniq.field))
+            .map(|Unique { alias, field }| ErrorVariant {
+                name: alias.clone(),
+                fields: vec![
+                    (key_field.clone(), quote!(super::super::#type_key)),
+                    (Ident::new("existing", Span::call_site()), quote!(super::super::#type_key)),
+                    (Ident::new("value", Span::call_site()), {
+                        let ty = groups.get_typefield(field).unwrap();
+                        quote!(#ty)
+                    }),
+                ],
+                message: format!("Cannot update `{name}`, a row with the same `{field}` already exists (unique constraint `{alias}`)"),
+            });
+        let predicate_errors = predicates.iter().map(|Predicate { alias, .. }| ErrorVariant {
+            name: alias.clone(),
+            fields: vec![
+                (key_field.clone(), quote!(super::super::#type_key)),
+                (Ident::new("update", Span::call_site()), quote!(#mod_update_struct_update)),
+            ],
+            message: format!("Cannot update `{name}`, the updated row does not satisfy the predicate `{alias}`"),
+        });
+        let errors = once(key_error)
+            .chain(unique_errors)
+            .chain(predicate_errors)
+            .collect::<Vec<_>>();
+        let error_enum = generate_error_enum(mod_update_enum_error, &errors);
 
         let struct_fields = self.fields.iter().map(|f| {
             let ty = groups.get_type(groups.get_field_index(f).unwrap()).unwrap();
             quote!(#f : #ty)
         });
 
-        let extra_comma = if unique_errors.is_empty() || predicate_errors.is_empty() {
-            quote!()
-        } else {
-            quote!(,)
-        };
-
         quote! {
             pub mod #update_name {
-                #[derive(Debug)]
-                pub enum #mod_update_enum_error {
-                    #type_key_error,
-                    #(#unique_errors),* #extra_comma
-                    #(#predicate_errors),*
-                }
+                #error_enum
 
                 pub struct #mod_update_struct_update {
                     #(pub #struct_fields),*
@@ -135,11 +149,13 @@ impl Update {
         .into()
     }
 
+    #[allow(clippy::too_many_arguments)]
     fn generate_trait_impl_fn(
         &self,
         namer: &CodeNamer,
         groups: &Groups,
         uniques: &[Unique],
+        indexes: &[Index],
         predicates: &[Predicate],
         transactions: bool,
         op_attrs: &TokenStream,
@@ -148,6 +164,8 @@ impl Update {
             mod_update,
             mod_update_struct_update,
             mod_update_enum_error,
+            mod_borrow,
+            mod_borrow_struct_borrow,
             name_primary_column,
             struct_table_member_columns: table_member_columns,
             type_key,
@@ -162,6 +180,7 @@ impl Update {
             mod_transactions_struct_data_member_rollback,
             mod_transactions_struct_data_member_log,
             struct_table_member_uniques: table_member_uniques,
+            struct_table_member_indexes: table_member_indexes,
             ..
         } = namer;
 
@@ -176,11 +195,24 @@ impl Update {
         let table_access = quote! {
             let #pulpit_path::column::Entry { index, data: #name_primary_column } = match self.#table_member_columns.#name_primary_column.brw_mut(key) {
                 Ok(entry) => entry,
-                Err(_) => return Err(#mod_update::#update_name::#mod_update_enum_error::#type_key_error),
+                Err(_) => return Err(#mod_update::#update_name::#mod_update_enum_error::#type_key_error { key }),
             };
             #(#assoc_brw_muts;)*
         };
 
+        let (update_param, compute_update) = if self.current {
+            let borrowed = generate_borrow_struct(groups, namer);
+            (
+                quote!(impl FnOnce(#mod_borrow::#mod_borrow_struct_borrow<'_>) -> #mod_update::#update_name::#mod_update_struct_update),
+                quote!(let #update_var = #update_var(#borrowed);),
+            )
+        } else {
+            (
+                quote!(#mod_update::#update_name::#mod_update_struct_update),
+                quote!(),
+            )
+        };
+
         // Pass borrow of all fields to the predicate (check if it will be valid)
         // needs to include new updated values
         let predicate_args =
@@ -189,7 +221,7 @@ impl Update {
             let pred = &pred.alias;
             quote! {
                 if !#mod_predicates::#pred(#predicate_args) {
-                    return Err(#mod_update::#update_name::#mod_update_enum_error::#pred);
+                    return Err(#mod_update::#update_name::#mod_update_enum_error::#pred { key, update: #update_var });
                 }
             }
         });
@@ -215,9 +247,9 @@ impl Update {
             unique_updates.push(quote!{
                 let #alias = match self.#table_member_uniques.#field.replace(&update.#field, &#from_data.#mutability.#field, key) {
                     Ok(old_val) => old_val,
-                    Err(_) => {
+                    Err(#pulpit_path::access::UniqueConflict { existing }) => {
                         #(#undo_prev_fields;)*
-                        return Err(#mod_update::#update_name::#mod_update_enum_error::#alias)
+                        return Err(#mod_update::#update_name::#mod_update_enum_error::#alias { key, existing, value: update.#field })
                     },
                 }
             }.into());
@@ -230,6 +262,21 @@ impl Update {
             )
         }
 
+        // secondary indexes cannot fail, so are updated after all unique checks pass
+        let index_updates = indexes
+            .iter()
+            .filter(|ord| self.fields.contains(&ord.field))
+            .map(|Index { field, .. }| {
+                let field_index = groups.idents.get(field).unwrap();
+                let from_data = match field_index {
+                    FieldIndex::Primary(_) => namer.name_primary_column.clone(),
+                    FieldIndex::Assoc { assoc_ind, .. } => namer.name_assoc_column(*assoc_ind),
+                };
+                quote! {
+                    self.#table_member_indexes.#field.replace(&update.#field, &#from_data.mut_data.#field, key)
+                }
+            });
+
         let update_pairs = self.fields.iter().map(|field| {
             let field_index = groups.idents.get(field).unwrap();
             let name_id = match field_index {
@@ -267,10 +314,12 @@ impl Update {
 
         quote! {
             #op_attrs
-            pub fn #update_name(&mut self, #update_var: #mod_update::#update_name::#mod_update_struct_update, key: #type_key) -> Result<(), #mod_update::#update_name::#mod_update_enum_error> {
+            pub fn #update_name(&mut self, #update_var: #update_param, key: #type_key) -> Result<(), #mod_update::#update_name::#mod_update_enum_error> {
                 #table_access
+                #compute_update
                 #(#predicate_checks)*
                 #(#unique_updates;)*
+                #(#index_updates;)*
                 #commit_updates
                 Ok(())
             }
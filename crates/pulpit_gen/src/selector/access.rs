@@ -0,0 +1,247 @@
+use std::collections::BTreeMap;
+
+use crate::{
+    columns::{
+        AssocAppVec, AssocBlocks, AssocPullBlocks, AssocVec, PrimaryRetain,
+        PrimaryThunderDomeTrans, PrimaryThunderdome,
+    },
+    groups::{Field, FieldName, Group, GroupConfig, MutImmut},
+    table::Table,
+};
+
+use super::*;
+
+/// The fields read together by a single user of the table (e.g. a `get`, or
+/// the fields of a dereferenced row used by an emDB query).
+pub struct Read {
+    pub fields: Vec<FieldName>,
+
+    /// The read is done for each row while scanning the whole table, rather
+    /// than for a single row.
+    pub scan: bool,
+}
+
+/// Splits fields into groups by the [`Read`]s and [`Update`]s that access
+/// them.
+/// - Fields always read and updated together are placed in the same group.
+/// - The hottest group (most accessed by single row reads and updates) is
+///   placed in the primary column, all others (including fields only read
+///   while scanning, or never read) in associated columns.
+pub struct AccessSelector;
+
+/// The indices of the reads and updates that access a field
+type Signature = (Vec<usize>, Vec<usize>);
+
+impl SelectorImpl for AccessSelector {
+    fn select_table(
+        &self,
+        SelectOperations {
+            name,
+            transactions,
+            deletions,
+            fields,
+            uniques,
+            indexes,
+            gets,
+            predicates,
+            updates,
+            reads,
+            public,
+            limit,
+        }: SelectOperations,
+    ) -> Table {
+        let MutImmut {
+            imm_fields,
+            mut_fields,
+        } = utils::determine_mutability(&updates, fields);
+
+        let signature = |field: &Field| -> Signature {
+            (
+                reads
+                    .iter()
+                    .enumerate()
+                    .filter_map(|(i, read)| read.fields.contains(&field.name).then_some(i))
+                    .collect(),
+                updates
+                    .iter()
+                    .enumerate()
+                    .filter_map(|(i, update)| update.fields.contains(&field.name).then_some(i))
+                    .collect(),
+            )
+        };
+
+        // group by signature, sorting by name so the layout is deterministic
+        let mut grouped: BTreeMap<Signature, MutImmut<Vec<Field>>> = BTreeMap::new();
+        for (imm, mut fields) in [(true, imm_fields), (false, mut_fields)] {
+            fields.sort_by(|a, b| a.name.cmp(&b.name));
+            for field in fields {
+                let group = grouped.entry(signature(&field)).or_insert(MutImmut {
+                    imm_fields: vec![],
+                    mut_fields: vec![],
+                });
+                if imm {
+                    group.imm_fields.push(field);
+                } else {
+                    group.mut_fields.push(field);
+                }
+            }
+        }
+
+        let heat = |(read_idxs, update_idxs): &Signature| {
+            let scans = read_idxs.iter().filter(|i| reads[**i].scan).count();
+            (read_idxs.len() - scans + update_idxs.len(), scans)
+        };
+        let mut grouped = grouped.into_iter().collect::<Vec<_>>();
+        let primary_fields = grouped
+            .iter()
+            .enumerate()
+            .max_by(|(i, (a, _)), (j, (b, _))| heat(a).cmp(&heat(b)).then(j.cmp(i)))
+            .map(|(i, _)| i)
+            .map_or(
+                MutImmut {
+                    imm_fields: vec![],
+                    mut_fields: vec![],
+                },
+                |i| grouped.remove(i).1,
+            );
+
+        let prim_col = if deletions {
+            if primary_fields.imm_fields.is_empty() {
+                if transactions {
+                    PrimaryThunderDomeTrans.into()
+                } else {
+                    PrimaryThunderdome.into()
+                }
+            } else {
+                PrimaryRetain { block_size: 1024 }.into()
+            }
+        } else {
+            AssocBlocks { block_size: 1024 }.into()
+        };
+
+        let assoc = grouped
+            .into_iter()
+            .map(|(_, fields)| Group {
+                col: match (deletions, fields.imm_fields.is_empty()) {
+                    (true, false) => AssocPullBlocks { block_size: 1024 }.into(),
+                    (true, true) => AssocVec.into(),
+                    (false, false) => AssocBlocks { block_size: 1024 }.into(),
+                    (false, true) => AssocAppVec.into(),
+                },
+                fields,
+            })
+            .collect();
+
+        Table {
+            groups: GroupConfig {
+                primary: Group {
+                    col: prim_col,
+                    fields: primary_fields,
+                },
+                assoc,
+            }
+            .into(),
+            uniques,
+            indexes,
+            predicates,
+            updates,
+            gets,
+            limit,
+            name,
+            transactions,
+            deletions,
+            public,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::columns::Primary;
+    use proc_macro2::Span;
+    use quote::quote;
+
+    fn ident(name: &str) -> Ident {
+        Ident::new(name, Span::call_site())
+    }
+
+    fn idents(names: &[&str]) -> Vec<Ident> {
+        names.iter().map(|name| ident(name)).collect()
+    }
+
+    fn names(fields: &[Field]) -> Vec<String> {
+        fields.iter().map(|field| field.name.to_string()).collect()
+    }
+
+    /// `score` and `visits` are updated, and read by most single row reads, so
+    /// are the hot group. `name` (read by one single row read), `id` (only read
+    /// while scanning) and `bio` (never read) are each cold.
+    fn select(deletions: bool) -> Table {
+        AccessSelector.select_table(SelectOperations {
+            name: ident("players"),
+            transactions: true,
+            deletions,
+            fields: ["id", "name", "score", "bio", "visits"]
+                .into_iter()
+                .map(|field| (ident(field), quote!(i32).into()))
+                .collect(),
+            uniques: vec![],
+            indexes: vec![],
+            gets: vec![],
+            predicates: vec![],
+            updates: vec![Update {
+                fields: idents(&["score", "visits"]),
+                alias: ident("play"),
+                current: false,
+            }],
+            reads: vec![
+                Read {
+                    fields: idents(&["name", "score", "visits"]),
+                    scan: false,
+                },
+                Read {
+                    fields: idents(&["id"]),
+                    scan: true,
+                },
+                Read {
+                    fields: idents(&["score", "visits"]),
+                    scan: false,
+                },
+            ],
+            limit: None,
+            public: false,
+        })
+    }
+
+    #[test]
+    fn groups_by_access() {
+        let table = select(false);
+        let primary = &table.groups.primary;
+        assert!(matches!(primary.col, Primary::AssocBlocks(_)));
+        assert!(primary.fields.imm_fields.is_empty());
+        assert_eq!(names(&primary.fields.mut_fields), ["score", "visits"]);
+
+        // cold groups are ordered by the reads and updates accessing them
+        let assoc = table
+            .groups
+            .assoc
+            .iter()
+            .map(|group| {
+                assert!(group.fields.mut_fields.is_empty());
+                names(&group.fields.imm_fields)
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(assoc, [["bio"], ["name"], ["id"]]);
+    }
+
+    #[test]
+    fn hot_group_without_immutable_fields_uses_thunderdome() {
+        let table = select(true);
+        assert!(matches!(
+            table.groups.primary.col,
+            Primary::PrimaryThunderDomeTrans(_)
+        ));
+        assert_eq!(table.groups.assoc.len(), 3);
+    }
+}
@@ -19,9 +19,11 @@ impl SelectorImpl for ColumnarSelector {
             deletions,
             fields,
             uniques,
+            indexes,
             gets,
             predicates,
             updates,
+            reads: _,
             public,
             limit,
         }: SelectOperations,
@@ -127,6 +129,7 @@ impl SelectorImpl for ColumnarSelector {
         Table {
             groups,
             uniques,
+            indexes,
             predicates,
             updates,
             gets,
@@ -19,8 +19,10 @@ impl SelectorImpl for CopySelector {
             deletions,
             fields,
             uniques,
+            indexes,
             predicates,
             updates,
+            reads: _,
             gets,
             public,
             limit,
@@ -51,6 +53,7 @@ impl SelectorImpl for CopySelector {
             }
             .into(),
             uniques,
+            indexes,
             predicates,
             updates,
             gets,
@@ -2,6 +2,7 @@
 //! Provides functions for determining the structure of the [`crate::table::Table`] chosen.
 
 use crate::{
+    indexes::Index,
     limit::Limit,
     operations::{get::Get, update::Update},
     predicates::Predicate,
@@ -18,9 +19,11 @@ pub struct SelectOperations {
     pub deletions: bool,
     pub fields: HashMap<Ident, Tokens<Type>>,
     pub uniques: Vec<Unique>,
+    pub indexes: Vec<Index>,
     pub gets: Vec<Get>,
     pub predicates: Vec<Predicate>,
     pub updates: Vec<Update>,
+    pub reads: Vec<Read>,
     pub limit: Option<Limit>,
     pub public: bool,
 }
@@ -33,6 +36,8 @@ mod copy;
 pub use copy::*;
 mod columnar;
 pub use columnar::*;
+mod access;
+pub use access::*;
 
 #[enumtrait::store(selector_impl_trait)]
 pub trait SelectorImpl {
@@ -46,6 +51,7 @@ pub enum TableSelectors {
     MutabilitySelector,
     ThunderdomeSelector,
     ColumnarSelector,
+    AccessSelector,
 
     // For Benchmarks
     CopySelector,
@@ -79,7 +85,7 @@ mod utils {
         let mut mut_fields = HashMap::new();
         for Update {
             fields: update_fields,
-            alias: _,
+            ..
         } in updates
         {
             for field in update_fields {
@@ -21,9 +21,11 @@ impl SelectorImpl for MutabilitySelector {
             deletions,
             fields,
             uniques,
+            indexes,
             gets,
             predicates,
             updates,
+            reads: _,
             public,
             limit,
         }: SelectOperations,
@@ -54,6 +56,7 @@ impl SelectorImpl for MutabilitySelector {
             }
             .into(),
             uniques,
+            indexes,
             predicates,
             updates,
             gets,
@@ -19,8 +19,10 @@ impl SelectorImpl for ThunderdomeSelector {
             deletions: _,
             fields,
             uniques,
+            indexes,
             predicates,
             updates,
+            reads: _,
             gets,
             limit,
             public,
@@ -36,6 +38,7 @@ impl SelectorImpl for ThunderdomeSelector {
             }
             .into(),
             uniques,
+            indexes,
             predicates,
             updates,
             gets,
@@ -1,7 +1,9 @@
 use std::collections::HashMap;
 
 use crate::{
+    columns::ColKind,
     groups::FieldName,
+    indexes::IndexDec,
     limit::Limit,
     operations::{self, get::get_struct_fields, SingleOpFn},
     uniques::UniqueDec,
@@ -13,6 +15,7 @@ use syn::{Ident, ItemImpl, ItemMod, ItemStruct, Type};
 
 use super::{
     groups::{Groups, GroupsDef},
+    indexes::{self, Index},
     namer::CodeNamer,
     operations::{get::Get, update::Update, SingleOp},
     predicates::{self, Predicate},
@@ -40,6 +43,7 @@ impl AttrKinds {
 pub struct Table {
     pub groups: Groups,
     pub uniques: Vec<Unique>,
+    pub indexes: Vec<Index>,
     pub predicates: Vec<Predicate>,
     pub updates: Vec<Update>,
     pub gets: Vec<Get>,
@@ -65,6 +69,8 @@ fn generate_table_and_window(transactions: bool, namer: &CodeNamer) -> TableDec
         struct_window_holder,
         struct_table_member_uniques: table_member_uniques,
         struct_unique,
+        struct_table_member_indexes: table_member_indexes,
+        struct_indexes,
         mod_transactions,
         mod_transactions_struct_data,
         struct_table_member_transactions: table_member_transactions,
@@ -87,6 +93,7 @@ fn generate_table_and_window(transactions: bool, namer: &CodeNamer) -> TableDec
             pub struct #struct_table {
                 #table_member_columns: #struct_column_holder,
                 #table_member_uniques: #struct_unique,
+                #table_member_indexes: #struct_indexes,
                 #trans_table
             }
         }
@@ -97,6 +104,7 @@ fn generate_table_and_window(transactions: bool, namer: &CodeNamer) -> TableDec
                     Self {
                         #table_member_columns: #struct_column_holder::new(size_hint),
                         #table_member_uniques: #struct_unique::new(size_hint),
+                        #table_member_indexes: #struct_indexes::new(size_hint),
                         #trans_new
                     }
                 }
@@ -105,6 +113,7 @@ fn generate_table_and_window(transactions: bool, namer: &CodeNamer) -> TableDec
                     #struct_window {
                         #table_member_columns: self.#table_member_columns.window(),
                         #table_member_uniques: &mut self.#table_member_uniques,
+                        #table_member_indexes: &mut self.#table_member_indexes,
                         #trans_wind
                     }
                 }
@@ -115,6 +124,7 @@ fn generate_table_and_window(transactions: bool, namer: &CodeNamer) -> TableDec
             pub struct #struct_window<'imm> {
                 #table_member_columns: #struct_window_holder<'imm>,
                 #table_member_uniques: &'imm mut #struct_unique,
+                #table_member_indexes: &'imm mut #struct_indexes,
                 #trans_wind_def
             }
         }
@@ -129,10 +139,17 @@ impl Table {
     pub fn insert_can_error(&self) -> bool {
         !self.predicates.is_empty() || !self.uniques.is_empty() || self.limit.is_some()
     }
+    /// If rows can be restored at a given key (by the window's `restore`).
+    /// - Placing rows does not provide an `InsertAction`, so the table must not
+    ///   have associated columns.
+    pub fn restores(&self) -> bool {
+        self.deletions && self.groups.assoc.is_empty() && self.groups.primary.col.restores()
+    }
     pub fn generate(&self, namer: &CodeNamer, attrs: Vec<AttrKinds>) -> Tokens<ItemMod> {
         let Self {
             groups,
             uniques,
+            indexes,
             predicates,
             updates,
             gets,
@@ -154,6 +171,8 @@ impl Table {
             ..
         } = namer;
 
+        let key_error_message = format!("The key does not refer to a row in `{name}`");
+
         let column_types = groups.column_types(namer);
         let key_type = groups.key_type(namer);
 
@@ -168,35 +187,45 @@ impl Table {
             unique_struct,
             unique_impl,
         } = uniques::generate(uniques, groups, namer);
+        let IndexDec {
+            index_struct,
+            index_impl,
+        } = indexes::generate(indexes, groups, namer);
 
         let mut ops_mod_code = vec![
             operations::borrow::generate(groups, namer, &op_attrs),
             operations::get::generate(groups, namer, gets, &op_attrs),
             operations::update::generate(
+                name,
                 updates,
                 groups,
                 uniques,
+                indexes,
                 predicates,
                 namer,
                 *transactions,
                 &op_attrs,
             ),
             operations::insert::generate(
+                name,
                 groups,
                 uniques,
+                indexes,
                 predicates,
                 namer,
                 limit,
                 *deletions,
                 *transactions,
+                self.restores(),
                 &op_attrs,
             ),
-            operations::unique_get::generate(groups, uniques, namer, &op_attrs),
+            operations::unique_get::generate(name, groups, uniques, namer, &op_attrs),
         ];
         if *transactions {
             ops_mod_code.push(operations::transact::generate(
                 groups,
                 updates,
+                indexes,
                 namer,
                 *deletions,
                 *transactions,
@@ -207,6 +236,8 @@ impl Table {
         let mut ops_fn_code = vec![
             operations::count::generate(namer, &op_attrs),
             operations::scan::generate(namer, &op_attrs),
+            operations::index_get::generate(groups, indexes, namer, &op_attrs),
+            operations::take::generate(groups, namer),
         ];
 
         if *deletions {
@@ -214,6 +245,7 @@ impl Table {
                 namer,
                 groups,
                 uniques,
+                indexes,
                 *transactions,
                 &op_attrs,
             ))
@@ -250,6 +282,7 @@ impl Table {
                     PrimaryWindowApp,
                     PrimaryWindowPull,
                     PrimaryWindowHide,
+                    PrimaryWindowRestore,
                     AssocWindow,
                     AssocWindowPull,
                     Column,
@@ -258,6 +291,14 @@ impl Table {
                 #[derive(Debug)]
                 pub struct #type_key_error;
 
+                impl std::fmt::Display for #type_key_error {
+                    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+                        f.write_str(#key_error_message)
+                    }
+                }
+
+                impl std::error::Error for #type_key_error {}
+
                 #column_types
 
                 #(#ops_tokens)*
@@ -267,6 +308,8 @@ impl Table {
                 #predicate_mod
                 #unique_struct
                 #unique_impl
+                #index_struct
+                #index_impl
 
                 #columns_struct
                 #columns_impl